@@ -20,9 +20,21 @@ pub enum CapabilityKind {
     EmailSend,
     #[serde(rename = "wallet:spend")]
     WalletSpend,
+    #[serde(rename = "browser:read")]
+    BrowserRead,
 }
 
 impl CapabilityKind {
+    pub const ALL: [CapabilityKind; 7] = [
+        Self::Click,
+        Self::Scroll,
+        Self::Type,
+        Self::Navigate,
+        Self::EmailSend,
+        Self::WalletSpend,
+        Self::BrowserRead,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Click => "click",
@@ -31,6 +43,7 @@ impl CapabilityKind {
             Self::Navigate => "navigate",
             Self::EmailSend => "email:send",
             Self::WalletSpend => "wallet:spend",
+            Self::BrowserRead => "browser:read",
         }
     }
 
@@ -42,6 +55,7 @@ impl CapabilityKind {
             "navigate" => Some(Self::Navigate),
             "email:send" | "email" => Some(Self::EmailSend),
             "wallet:spend" => Some(Self::WalletSpend),
+            "browser:read" => Some(Self::BrowserRead),
             _ => None,
         }
     }
@@ -169,6 +183,7 @@ impl CapabilityRegistry {
         registry.grant(CapabilityKind::Navigate, CapabilityLimit::unlimited());
         registry.grant(CapabilityKind::EmailSend, CapabilityLimit::limited(3));
         registry.grant(CapabilityKind::WalletSpend, CapabilityLimit::limited(3));
+        registry.grant(CapabilityKind::BrowserRead, CapabilityLimit::unlimited());
         registry
     }
 
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use ai_agent::{McpTool, McpToolDescription, McpToolError, McpToolResult};
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::approvals::ApprovalHandler;
+use crate::capabilities::{CapabilityKind, CapabilityRegistry, ConsumeOutcome};
+
+/// Approval prompt followed by quota consumption, applied before any
+/// capability-bearing tool runs. Shared by the agent runtime and by hosts that
+/// expose tools to external clients so both paths enforce identical policy.
+#[derive(Clone)]
+pub struct CapabilityGate {
+    capabilities: Arc<Mutex<CapabilityRegistry>>,
+    approval: Option<Arc<dyn ApprovalHandler>>,
+}
+
+impl CapabilityGate {
+    pub fn new(
+        capabilities: CapabilityRegistry,
+        approval: Option<Arc<dyn ApprovalHandler>>,
+    ) -> Self {
+        Self {
+            capabilities: Arc::new(Mutex::new(capabilities)),
+            approval,
+        }
+    }
+
+    pub fn registry(&self) -> Arc<Mutex<CapabilityRegistry>> {
+        self.capabilities.clone()
+    }
+
+    pub async fn authorize(
+        &self,
+        capability: &CapabilityKind,
+        payload: &Value,
+    ) -> Result<ConsumeOutcome, McpToolError> {
+        let approved = match &self.approval {
+            Some(handler) => handler
+                .request_approval(capability, payload)
+                .await
+                .map_err(|err| {
+                    McpToolError::Invocation(format!("approval request failed: {}", err))
+                })?,
+            None => true,
+        };
+        if !approved {
+            return Err(McpToolError::Invocation(
+                "action rejected by user approval flow".into(),
+            ));
+        }
+
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities
+            .consume(capability.clone())
+            .map_err(|err| McpToolError::Invocation(format!("{}", err)))
+    }
+
+    /// Wraps `tool` so every invocation passes through [`Self::authorize`].
+    pub fn guard(&self, tool: Arc<dyn McpTool>, capability: CapabilityKind) -> Arc<dyn McpTool> {
+        Arc::new(CapabilityGuardTool {
+            description: tool.description().clone(),
+            capability,
+            inner: tool,
+            gate: self.clone(),
+        })
+    }
+}
+
+/// Host-supplied check on a tool's arguments that runs ahead of its
+/// capability gate, so a call the policy forbids is refused without prompting
+/// the user and is recorded in the run ledger.
+#[async_trait]
+pub trait ToolPolicy: Send + Sync {
+    /// Returns why the call is refused, if it is.
+    async fn check(&self, args: &Value) -> Result<(), String>;
+
+    /// Called after a permitted call succeeded, for policies that track usage
+    /// across calls.
+    async fn record(&self, _args: &Value, _result: &McpToolResult) {}
+}
+
+struct CapabilityGuardTool {
+    description: McpToolDescription,
+    capability: CapabilityKind,
+    inner: Arc<dyn McpTool>,
+    gate: CapabilityGate,
+}
+
+#[async_trait]
+impl McpTool for CapabilityGuardTool {
+    fn description(&self) -> &McpToolDescription {
+        &self.description
+    }
+
+    async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
+        self.gate.authorize(&self.capability, &args).await?;
+        self.inner.invoke(args).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CapabilityLimit;
+    use serde_json::json;
+
+    struct DenyAll;
+
+    #[async_trait]
+    impl ApprovalHandler for DenyAll {
+        async fn request_approval(
+            &self,
+            _capability: &CapabilityKind,
+            _payload: &Value,
+        ) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    #[tokio::test]
+    async fn authorize_consumes_quota_and_respects_rejections() {
+        let mut registry = CapabilityRegistry::new();
+        registry.grant(CapabilityKind::Navigate, CapabilityLimit::limited(1));
+        let gate = CapabilityGate::new(registry, None);
+
+        gate.authorize(&CapabilityKind::Navigate, &json!({}))
+            .await
+            .expect("first navigate should be allowed");
+        assert!(gate
+            .authorize(&CapabilityKind::Navigate, &json!({}))
+            .await
+            .is_err());
+
+        let denied = CapabilityGate::new(
+            CapabilityRegistry::with_browser_defaults(),
+            Some(Arc::new(DenyAll)),
+        );
+        let err = denied
+            .authorize(&CapabilityKind::Click, &json!({}))
+            .await
+            .unwrap_err();
+        assert!(format!("{err}").contains("rejected"));
+    }
+}
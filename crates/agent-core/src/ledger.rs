@@ -3,12 +3,15 @@ use crate::dom::{DomEvent, DomObservation};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Entry stored in the agent ledger. Each entry is hashed independently and
 /// contributes to the running root hash.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LedgerEntry {
-    pub event: DomEvent,
+    /// DOM event behind the entry; `None` for refused tool calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<DomEvent>,
     pub capability: CapabilityKind,
     pub message: String,
     pub hash: String,
@@ -36,7 +39,7 @@ impl AgentLedger {
 
         let hash = hash_json(&payload);
         let entry = LedgerEntry {
-            event: observation.event.clone(),
+            event: Some(observation.event.clone()),
             capability,
             message: observation.message.clone(),
             hash,
@@ -44,6 +47,29 @@ impl AgentLedger {
         self.entries.push(entry);
     }
 
+    /// Records a tool call that a host policy refused before it ran.
+    pub fn record_violation(&mut self, capability: CapabilityKind, message: impl Into<String>) {
+        let message = message.into();
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let payload = json!({
+            "position": self.entries.len(),
+            "timestamp_ms": timestamp_ms,
+            "capability": capability.as_str(),
+            "violation": message,
+        });
+
+        let hash = hash_json(&payload);
+        self.entries.push(LedgerEntry {
+            event: None,
+            capability,
+            message,
+            hash,
+        });
+    }
+
     pub fn entries(&self) -> &[LedgerEntry] {
         &self.entries
     }
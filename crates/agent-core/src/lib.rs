@@ -4,6 +4,7 @@
 pub mod approvals;
 pub mod capabilities;
 pub mod dom;
+pub mod gate;
 pub mod ledger;
 pub mod runtime;
 
@@ -14,5 +15,6 @@ pub use capabilities::{
 pub use dom::{
     DomAction, DomEvent, DomExecutionResult, DomExecutor, DomObservation, NoopDomExecutor,
 };
+pub use gate::{CapabilityGate, ToolPolicy};
 pub use ledger::{AgentLedger, LedgerEntry};
 pub use runtime::{AgentRuntime, AgentRuntimeBuilder, AgentRuntimeResult};
@@ -12,19 +12,19 @@ use serde_json::{json, Value};
 use tokio::sync::Mutex;
 
 use crate::approvals::ApprovalHandler;
-use crate::capabilities::{CapabilityError, CapabilityKind, CapabilityRegistry};
+use crate::capabilities::{CapabilityKind, CapabilityRegistry};
 use crate::dom::{DomAction, DomExecutor, DomInstrumentation, NoopDomExecutor};
+use crate::gate::{CapabilityGate, ToolPolicy};
 use crate::ledger::AgentLedger;
 
 const DOM_TOOL_NAME: &str = "dom_action";
 
 #[derive(Clone)]
 struct SharedState {
-    capabilities: Arc<Mutex<CapabilityRegistry>>,
+    gate: CapabilityGate,
     dom: Arc<Mutex<DomInstrumentation>>,
     dom_executor: Arc<dyn DomExecutor>,
     ledger: Arc<Mutex<AgentLedger>>,
-    approval: Option<Arc<dyn ApprovalHandler>>,
 }
 
 impl SharedState {
@@ -34,22 +34,10 @@ impl SharedState {
         dom_executor: Arc<dyn DomExecutor>,
     ) -> Self {
         Self {
-            capabilities: Arc::new(Mutex::new(capabilities)),
+            gate: CapabilityGate::new(capabilities, approval),
             dom: Arc::new(Mutex::new(DomInstrumentation::new())),
             dom_executor,
             ledger: Arc::new(Mutex::new(AgentLedger::new())),
-            approval,
-        }
-    }
-
-    async fn request_approval(
-        &self,
-        capability: &CapabilityKind,
-        payload: &Value,
-    ) -> anyhow::Result<bool> {
-        match &self.approval {
-            Some(handler) => handler.request_approval(capability, payload).await,
-            None => Ok(true),
         }
     }
 }
@@ -92,7 +80,8 @@ impl AgentRuntime {
     }
 
     pub async fn capability_snapshot(&self) -> HashMap<String, Option<u32>> {
-        let guard = self.state.capabilities.lock().await;
+        let registry = self.state.gate.registry();
+        let guard = registry.lock().await;
         guard
             .snapshot()
             .into_iter()
@@ -105,12 +94,14 @@ impl AgentRuntime {
     }
 
     pub async fn revoke_capability(&self, kind: CapabilityKind) {
-        let mut guard = self.state.capabilities.lock().await;
+        let registry = self.state.gate.registry();
+        let mut guard = registry.lock().await;
         guard.revoke(kind);
     }
 
     pub async fn capability_remaining(&self, kind: CapabilityKind) -> Option<u32> {
-        let guard = self.state.capabilities.lock().await;
+        let registry = self.state.gate.registry();
+        let guard = registry.lock().await;
         guard.remaining(kind)
     }
 }
@@ -119,7 +110,7 @@ pub struct AgentRuntimeBuilder {
     model: Arc<dyn LanguageModelClient>,
     config: AgentConfig,
     capabilities: CapabilityRegistry,
-    tools: Vec<(Arc<dyn McpTool>, Option<CapabilityKind>)>,
+    tools: Vec<RegisteredTool>,
     approval_handler: Option<Arc<dyn ApprovalHandler>>,
     dom_executor: Arc<dyn DomExecutor>,
     event_callback: Option<AgentEventCallback>,
@@ -175,7 +166,28 @@ impl AgentRuntimeBuilder {
         tool: Arc<dyn McpTool>,
         capability: Option<CapabilityKind>,
     ) -> Self {
-        self.tools.push((tool, capability));
+        self.tools.push(RegisteredTool {
+            tool,
+            capability,
+            policy: None,
+        });
+        self
+    }
+
+    /// Registers a capability-bearing tool whose arguments must pass `policy`
+    /// before the capability gate is consulted. Refusals are written to the
+    /// run ledger.
+    pub fn register_tool_with_policy(
+        mut self,
+        tool: Arc<dyn McpTool>,
+        capability: CapabilityKind,
+        policy: Arc<dyn ToolPolicy>,
+    ) -> Self {
+        self.tools.push(RegisteredTool {
+            tool,
+            capability: Some(capability),
+            policy: Some(policy),
+        });
         self
     }
 
@@ -193,12 +205,20 @@ impl AgentRuntimeBuilder {
             orchestrator.set_cancellation_check(check);
         }
         orchestrator.register_tool(Arc::new(DomTool::new(state.clone())));
-        for (tool, capability) in self.tools {
-            let tool: Arc<dyn McpTool> = match capability {
-                Some(capability) => {
-                    Arc::new(CapabilityGuardTool::new(tool, capability, state.clone()))
-                }
-                None => tool,
+        for registered in self.tools {
+            let tool = match &registered.capability {
+                Some(capability) => state.gate.guard(registered.tool, capability.clone()),
+                None => registered.tool,
+            };
+            let tool = match (registered.policy, registered.capability) {
+                (Some(policy), Some(capability)) => Arc::new(PolicyCheckedTool {
+                    description: tool.description().clone(),
+                    capability,
+                    inner: tool,
+                    policy,
+                    ledger: state.ledger.clone(),
+                }),
+                _ => tool,
             };
             orchestrator.register_tool(tool);
         }
@@ -209,61 +229,48 @@ impl AgentRuntimeBuilder {
     }
 }
 
-#[derive(Debug)]
-pub struct AgentRuntimeResult {
-    pub agent: AgentResult,
-    pub ledger_root: Option<String>,
+struct RegisteredTool {
+    tool: Arc<dyn McpTool>,
+    capability: Option<CapabilityKind>,
+    policy: Option<Arc<dyn ToolPolicy>>,
 }
 
-struct CapabilityGuardTool {
+/// Runs a [`ToolPolicy`] ahead of the gated tool and logs refusals.
+struct PolicyCheckedTool {
     description: McpToolDescription,
     capability: CapabilityKind,
     inner: Arc<dyn McpTool>,
-    state: SharedState,
-}
-
-impl CapabilityGuardTool {
-    fn new(inner: Arc<dyn McpTool>, capability: CapabilityKind, state: SharedState) -> Self {
-        let description = inner.description().clone();
-        Self {
-            description,
-            capability,
-            inner,
-            state,
-        }
-    }
+    policy: Arc<dyn ToolPolicy>,
+    ledger: Arc<Mutex<AgentLedger>>,
 }
 
 #[async_trait]
-impl McpTool for CapabilityGuardTool {
+impl McpTool for PolicyCheckedTool {
     fn description(&self) -> &McpToolDescription {
         &self.description
     }
 
     async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
-        let approval_payload = args.clone();
-        let approved = self
-            .state
-            .request_approval(&self.capability, &approval_payload)
-            .await
-            .map_err(|err| McpToolError::Invocation(format!("approval request failed: {}", err)))?;
-        if !approved {
-            return Err(McpToolError::Invocation(
-                "action rejected by user approval flow".into(),
-            ));
-        }
-
-        {
-            let mut capabilities = self.state.capabilities.lock().await;
-            capabilities
-                .consume(self.capability.clone())
-                .map_err(capability_error_to_mcp)?;
+        if let Err(reason) = self.policy.check(&args).await {
+            let message = format!("refused `{}`: {reason}", self.description.name);
+            self.ledger
+                .lock()
+                .await
+                .record_violation(self.capability.clone(), message.clone());
+            return Err(McpToolError::Invocation(message));
         }
-
-        self.inner.invoke(args).await
+        let result = self.inner.invoke(args.clone()).await?;
+        self.policy.record(&args, &result).await;
+        Ok(result)
     }
 }
 
+#[derive(Debug)]
+pub struct AgentRuntimeResult {
+    pub agent: AgentResult,
+    pub ledger_root: Option<String>,
+}
+
 struct DomTool {
     description: McpToolDescription,
     state: SharedState,
@@ -297,23 +304,11 @@ impl McpTool for DomTool {
         let capability = map_action_to_capability(&action);
         let approval_payload =
             serde_json::to_value(&action).unwrap_or_else(|_| json!({ "action": "unknown" }));
-        let approved = self
+        let outcome = self
             .state
-            .request_approval(&capability, &approval_payload)
-            .await
-            .map_err(|err| McpToolError::Invocation(format!("approval request failed: {}", err)))?;
-        if !approved {
-            return Err(McpToolError::Invocation(
-                "action rejected by user approval flow".into(),
-            ));
-        }
-
-        let outcome = {
-            let mut capabilities = self.state.capabilities.lock().await;
-            capabilities
-                .consume(capability.clone())
-                .map_err(capability_error_to_mcp)?
-        };
+            .gate
+            .authorize(&capability, &approval_payload)
+            .await?;
 
         let execution = self
             .state
@@ -440,15 +435,14 @@ fn map_action_to_capability(action: &DomAction) -> CapabilityKind {
     }
 }
 
-fn capability_error_to_mcp(err: CapabilityError) -> McpToolError {
-    McpToolError::Invocation(format!("{}", err))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{CapabilityKind, CapabilityLimit, CapabilityRegistry};
-    use ai_agent::{FoundationModelOptions, LanguageModelResponse};
+    use ai_agent::{
+        Cassette, FoundationModelOptions, LanguageModelResponse, PromptMatch, RecordingModelClient,
+        ReplayModelClient,
+    };
     use serde_json::json;
     use std::collections::VecDeque;
     use tokio::sync::Mutex as TokioMutex;
@@ -601,4 +595,123 @@ mod tests {
         assert_eq!(runtime.ledger_root_hash().await.unwrap(), root);
         assert_ne!(original_hash, "tampered");
     }
+
+    struct EchoTool(McpToolDescription);
+
+    #[async_trait]
+    impl McpTool for EchoTool {
+        fn description(&self) -> &McpToolDescription {
+            &self.0
+        }
+
+        async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
+            Ok(McpToolResult {
+                content: args,
+                metadata: Default::default(),
+            })
+        }
+    }
+
+    struct MaxAmount(u64);
+
+    #[async_trait]
+    impl ToolPolicy for MaxAmount {
+        async fn check(&self, args: &Value) -> Result<(), String> {
+            match args["amount"].as_u64() {
+                Some(amount) if amount <= self.0 => Ok(()),
+                _ => Err(format!("amount exceeds {}", self.0)),
+            }
+        }
+    }
+
+    struct PanicOnApproval;
+
+    #[async_trait]
+    impl ApprovalHandler for PanicOnApproval {
+        async fn request_approval(
+            &self,
+            _capability: &CapabilityKind,
+            _payload: &Value,
+        ) -> anyhow::Result<bool> {
+            panic!("refused calls must not reach the approval prompt");
+        }
+    }
+
+    #[tokio::test]
+    async fn policy_refusals_skip_the_gate_and_are_logged() {
+        let model = ScriptedModel::new(vec![json!({
+            "type": "tool",
+            "name": "wallet.spend",
+            "args": { "amount": 10 }
+        })
+        .to_string()]);
+
+        let mut runtime = AgentRuntime::builder(model)
+            .with_approval_handler(Arc::new(PanicOnApproval))
+            .register_tool_with_policy(
+                Arc::new(EchoTool(McpToolDescription::new(
+                    "wallet.spend",
+                    "Spend",
+                    json!({ "type": "object" }),
+                ))),
+                CapabilityKind::WalletSpend,
+                Arc::new(MaxAmount(5)),
+            )
+            .build();
+
+        let err = runtime.run("Spend ten").await.unwrap_err();
+        assert!(format!("{err}").contains("amount exceeds 5"));
+
+        let entries = runtime.ledger_entries().await;
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].event.is_none());
+        assert_eq!(entries[0].capability, CapabilityKind::WalletSpend);
+        assert!(entries[0].message.contains("refused `wallet.spend`"));
+        assert!(runtime.ledger_root_hash().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn replayed_run_matches_recorded_run() {
+        let path = std::env::temp_dir()
+            .join("agent-core-runtime-tests")
+            .join(format!("cassette-{}.json", std::process::id()));
+        let recorder = Arc::new(RecordingModelClient::new(
+            ScriptedModel::new(agent_responses_for_order_test()),
+            &path,
+        ));
+        let task = "Sign into the dashboard and scroll to analytics.";
+
+        let mut recorded = AgentRuntime::builder(recorder.clone()).build();
+        let recorded_result = recorded.run(task).await.unwrap();
+        assert_eq!(recorder.entries().len(), 3);
+
+        // Observations carry wall-clock timestamps, so later prompts drift
+        // slightly between runs and need fuzzy matching.
+        let replay = Arc::new(ReplayModelClient::new(
+            Cassette::load(&path).unwrap(),
+            PromptMatch::fuzzy(),
+        ));
+        let mut replayed = AgentRuntime::builder(replay.clone()).build();
+        let replayed_result = replayed.run(task).await.unwrap();
+
+        assert_eq!(replay.remaining(), 0);
+        assert_eq!(
+            replayed_result.agent.final_answer,
+            recorded_result.agent.final_answer
+        );
+        let recorded_actions: Vec<DomAction> = recorded
+            .dom_events()
+            .await
+            .into_iter()
+            .map(|event| event.action)
+            .collect();
+        let replayed_actions: Vec<DomAction> = replayed
+            .dom_events()
+            .await
+            .into_iter()
+            .map(|event| event.action)
+            .collect();
+        assert_eq!(replayed_actions, recorded_actions);
+        let _ = std::fs::remove_file(&path);
+    }
 }
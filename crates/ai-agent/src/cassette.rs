@@ -0,0 +1,362 @@
+//! Record/replay wrappers around [`LanguageModelClient`].
+//!
+//! A [`RecordingModelClient`] forwards completions to a real model and appends
+//! every prompt/options/response tuple to a cassette file. A
+//! [`ReplayModelClient`] serves those tuples back without touching the
+//! network, which keeps agent tests deterministic on hosts that lack the
+//! platform foundation model.
+
+use crate::foundation::FoundationModelOptions;
+use crate::language_model::{LanguageModelClient, LanguageModelResponse};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const CASSETTE_VERSION: u32 = 1;
+const DEFAULT_FUZZY_THRESHOLD: f32 = 0.8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    pub prompt: String,
+    pub options: FoundationModelOptions,
+    pub response: LanguageModelResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cassette {
+    pub version: u32,
+    #[serde(default)]
+    pub entries: Vec<CassetteEntry>,
+}
+
+impl Default for Cassette {
+    fn default() -> Self {
+        Self {
+            version: CASSETTE_VERSION,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Cassette {
+    pub fn new(entries: Vec<CassetteEntry>) -> Self {
+        Self {
+            version: CASSETTE_VERSION,
+            entries,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read cassette {}", path.display()))?;
+        let cassette: Cassette = serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse cassette {}", path.display()))?;
+        if cassette.version != CASSETTE_VERSION {
+            return Err(anyhow!(
+                "unsupported cassette version {} in {}",
+                cassette.version,
+                path.display()
+            ));
+        }
+        Ok(cassette)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("failed to create cassette directory {}", parent.display())
+                })?;
+            }
+        }
+        let payload = serde_json::to_string_pretty(self)?;
+        fs::write(path, payload)
+            .with_context(|| format!("failed to write cassette {}", path.display()))
+    }
+}
+
+/// Wraps a live model and persists each completion to a cassette file.
+pub struct RecordingModelClient {
+    inner: Arc<dyn LanguageModelClient>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingModelClient {
+    /// Starts a fresh cassette at `path`, replacing any existing recording on
+    /// the first completion.
+    pub fn new(inner: Arc<dyn LanguageModelClient>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> Vec<CassetteEntry> {
+        self.cassette
+            .lock()
+            .map(|cassette| cassette.entries.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl LanguageModelClient for RecordingModelClient {
+    async fn complete(
+        &self,
+        prompt: &str,
+        options: &FoundationModelOptions,
+    ) -> Result<LanguageModelResponse> {
+        let response = self.inner.complete(prompt, options).await?;
+        let mut cassette = self
+            .cassette
+            .lock()
+            .map_err(|_| anyhow!("cassette lock poisoned"))?;
+        cassette.entries.push(CassetteEntry {
+            prompt: prompt.to_string(),
+            options: options.clone(),
+            response: response.clone(),
+        });
+        cassette.save(&self.path)?;
+        Ok(response)
+    }
+}
+
+/// How the replay client pairs incoming prompts with recorded entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptMatch {
+    /// Prompt text and options must match the recording exactly.
+    Strict,
+    /// Prompts are compared after whitespace/case normalisation, falling back
+    /// to token overlap at or above `threshold` (0.0..=1.0). Options are
+    /// ignored.
+    Fuzzy { threshold: f32 },
+}
+
+impl PromptMatch {
+    pub fn fuzzy() -> Self {
+        PromptMatch::Fuzzy {
+            threshold: DEFAULT_FUZZY_THRESHOLD,
+        }
+    }
+}
+
+/// Serves recorded completions back in place of a live model.
+pub struct ReplayModelClient {
+    entries: Vec<CassetteEntry>,
+    mode: PromptMatch,
+    used: Mutex<HashSet<usize>>,
+}
+
+impl ReplayModelClient {
+    pub fn new(cassette: Cassette, mode: PromptMatch) -> Self {
+        Self {
+            entries: cassette.entries,
+            mode,
+            used: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn from_path(path: &Path, mode: PromptMatch) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?, mode))
+    }
+
+    /// Number of recorded entries that have not been served yet.
+    pub fn remaining(&self) -> usize {
+        let used = self.used.lock().map(|used| used.len()).unwrap_or(0);
+        self.entries.len().saturating_sub(used)
+    }
+
+    fn find_entry(
+        &self,
+        prompt: &str,
+        options: &FoundationModelOptions,
+        used: &HashSet<usize>,
+    ) -> Option<usize> {
+        let unused = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !used.contains(index));
+
+        match self.mode {
+            PromptMatch::Strict => unused
+                .filter(|(_, entry)| {
+                    entry.prompt == prompt && options_match(&entry.options, options)
+                })
+                .map(|(index, _)| index)
+                .next(),
+            PromptMatch::Fuzzy { threshold } => {
+                let normalized = normalize_prompt(prompt);
+                let mut best: Option<(usize, f32)> = None;
+                for (index, entry) in unused {
+                    let candidate = normalize_prompt(&entry.prompt);
+                    if candidate == normalized {
+                        return Some(index);
+                    }
+                    let score = token_similarity(&candidate, &normalized);
+                    if score >= threshold && !matches!(best, Some((_, current)) if current >= score)
+                    {
+                        best = Some((index, score));
+                    }
+                }
+                best.map(|(index, _)| index)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModelClient for ReplayModelClient {
+    async fn complete(
+        &self,
+        prompt: &str,
+        options: &FoundationModelOptions,
+    ) -> Result<LanguageModelResponse> {
+        let mut used = self
+            .used
+            .lock()
+            .map_err(|_| anyhow!("replay lock poisoned"))?;
+        let index = self.find_entry(prompt, options, &used).ok_or_else(|| {
+            anyhow!(
+                "no recorded completion matches prompt ({} of {} entries unused)",
+                self.entries.len() - used.len(),
+                self.entries.len()
+            )
+        })?;
+        used.insert(index);
+        Ok(self.entries[index].response.clone())
+    }
+}
+
+fn options_match(recorded: &FoundationModelOptions, actual: &FoundationModelOptions) -> bool {
+    recorded.temperature.to_bits() == actual.temperature.to_bits()
+        && recorded.max_tokens == actual.max_tokens
+        && recorded.system_prompt == actual.system_prompt
+}
+
+fn normalize_prompt(prompt: &str) -> String {
+    prompt
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn token_similarity(left: &str, right: &str) -> f32 {
+    let left: HashSet<&str> = left.split(' ').filter(|token| !token.is_empty()).collect();
+    let right: HashSet<&str> = right.split(' ').filter(|token| !token.is_empty()).collect();
+    if left.is_empty() && right.is_empty() {
+        return 1.0;
+    }
+    let shared = left.intersection(&right).count() as f32;
+    let total = left.union(&right).count() as f32;
+    shared / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct EchoModel {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LanguageModelClient for EchoModel {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _options: &FoundationModelOptions,
+        ) -> Result<LanguageModelResponse> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(LanguageModelResponse::new(format!("{call}:{prompt}")))
+        }
+    }
+
+    fn temp_cassette_path() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        std::env::temp_dir()
+            .join("ai-agent-cassette-tests")
+            .join(format!("{}-{nanos}.json", std::process::id()))
+    }
+
+    fn entry(prompt: &str, response: &str) -> CassetteEntry {
+        CassetteEntry {
+            prompt: prompt.to_string(),
+            options: FoundationModelOptions::default(),
+            response: LanguageModelResponse::new(response.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn recording_round_trips_through_replay() {
+        let path = temp_cassette_path();
+        let recorder = RecordingModelClient::new(
+            Arc::new(EchoModel {
+                calls: AtomicUsize::new(0),
+            }),
+            &path,
+        );
+        let options = FoundationModelOptions::default();
+        recorder.complete("first", &options).await.unwrap();
+        recorder.complete("second", &options).await.unwrap();
+
+        let replay = ReplayModelClient::from_path(&path, PromptMatch::Strict).unwrap();
+        let second = replay.complete("second", &options).await.unwrap();
+        let first = replay.complete("first", &options).await.unwrap();
+        assert_eq!(first.text, "0:first");
+        assert_eq!(second.text, "1:second");
+        assert_eq!(replay.remaining(), 0);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn strict_replay_rejects_changed_options() {
+        let replay = ReplayModelClient::new(
+            Cassette::new(vec![entry("plan the task", "ok")]),
+            PromptMatch::Strict,
+        );
+        let options = FoundationModelOptions {
+            temperature: 0.1,
+            ..FoundationModelOptions::default()
+        };
+        assert!(replay.complete("plan the task", &options).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fuzzy_replay_tolerates_whitespace_and_small_drift() {
+        let replay = ReplayModelClient::new(
+            Cassette::new(vec![
+                entry("Open the docs page and summarise the intro section", "a"),
+                entry("unrelated prompt", "b"),
+            ]),
+            PromptMatch::fuzzy(),
+        );
+        let options = FoundationModelOptions::default();
+        let response = replay
+            .complete(
+                "open the  docs page and summarise the\nintro section please",
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.text, "a");
+        assert!(replay.complete("something else", &options).await.is_err());
+    }
+}
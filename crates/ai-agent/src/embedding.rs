@@ -0,0 +1,164 @@
+//! Text embedding primitives used for local semantic recall.
+//!
+//! [`EmbeddingClient`] mirrors [`crate::LanguageModelClient`] for vector
+//! embeddings. [`HashingEmbeddingClient`] is a dependency-free local
+//! implementation based on feature hashing of word and character n-grams; it
+//! never leaves the device and produces stable vectors across runs, so indexes
+//! persisted with it remain valid after restarts.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+pub const DEFAULT_EMBEDDING_DIMENSIONS: usize = 256;
+
+#[async_trait]
+pub trait EmbeddingClient: Send + Sync {
+    /// Stable identifier persisted alongside vectors so stale indexes can be
+    /// detected when the embedder changes.
+    fn model_id(&self) -> &str;
+
+    fn dimensions(&self) -> usize;
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+pub struct HashingEmbeddingClient {
+    model_id: String,
+    dimensions: usize,
+}
+
+impl HashingEmbeddingClient {
+    pub fn new(dimensions: usize) -> Result<Self> {
+        if dimensions == 0 {
+            return Err(anyhow!("embedding dimensions must be greater than zero"));
+        }
+        Ok(Self {
+            model_id: format!("local-hashing-v1-{dimensions}"),
+            dimensions,
+        })
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        let normalized = text
+            .chars()
+            .map(|ch| {
+                if ch.is_alphanumeric() {
+                    ch.to_lowercase().next().unwrap_or(ch)
+                } else {
+                    ' '
+                }
+            })
+            .collect::<String>();
+
+        for word in normalized.split_whitespace() {
+            self.accumulate(&mut vector, word.as_bytes(), 1.0);
+            let padded: Vec<char> = format!(" {word} ").chars().collect();
+            for trigram in padded.windows(3) {
+                let gram: String = trigram.iter().collect();
+                self.accumulate(&mut vector, gram.as_bytes(), 0.5);
+            }
+        }
+
+        let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for value in &mut vector {
+                *value /= norm;
+            }
+        }
+        vector
+    }
+
+    fn accumulate(&self, vector: &mut [f32], feature: &[u8], weight: f32) {
+        let hash = fnv1a(feature);
+        let bucket = (hash % self.dimensions as u64) as usize;
+        let sign = if (hash >> 63) & 1 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
+
+impl Default for HashingEmbeddingClient {
+    fn default() -> Self {
+        Self {
+            model_id: format!("local-hashing-v1-{DEFAULT_EMBEDDING_DIMENSIONS}"),
+            dimensions: DEFAULT_EMBEDDING_DIMENSIONS,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for HashingEmbeddingClient {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// Cosine similarity between two vectors; returns 0.0 for mismatched or
+/// zero-length inputs.
+pub fn cosine_similarity(left: &[f32], right: &[f32]) -> f32 {
+    if left.len() != right.len() || left.is_empty() {
+        return 0.0;
+    }
+    let mut dot = 0.0f32;
+    let mut left_norm = 0.0f32;
+    let mut right_norm = 0.0f32;
+    for (l, r) in left.iter().zip(right) {
+        dot += l * r;
+        left_norm += l * l;
+        right_norm += r * r;
+    }
+    if left_norm == 0.0 || right_norm == 0.0 {
+        return 0.0;
+    }
+    dot / (left_norm.sqrt() * right_norm.sqrt())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hashing_embeddings_are_stable_and_normalised() {
+        let client = HashingEmbeddingClient::default();
+        let texts = vec!["Rust async runtime".to_string()];
+        let first = client.embed(&texts).await.unwrap();
+        let second = client.embed(&texts).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first[0].len(), DEFAULT_EMBEDDING_DIMENSIONS);
+        let norm = first[0].iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+    }
+
+    #[tokio::test]
+    async fn related_texts_score_higher_than_unrelated() {
+        let client = HashingEmbeddingClient::default();
+        let vectors = client
+            .embed(&[
+                "tokio async runtime tutorial".to_string(),
+                "async runtimes in tokio".to_string(),
+                "chocolate cake recipe".to_string(),
+            ])
+            .await
+            .unwrap();
+        let related = cosine_similarity(&vectors[0], &vectors[1]);
+        let unrelated = cosine_similarity(&vectors[0], &vectors[2]);
+        assert!(related > unrelated, "{related} <= {unrelated}");
+    }
+}
@@ -3,11 +3,18 @@
 //! the macOS Foundation Model runtime (when available) and execute MCP-style
 //! tools provided by the host application.
 
+pub mod cassette;
+pub mod embedding;
 pub mod foundation;
 pub mod language_model;
 pub mod mcp;
 pub mod orchestrator;
+pub mod output;
 
+pub use cassette::{Cassette, CassetteEntry, PromptMatch, RecordingModelClient, ReplayModelClient};
+pub use embedding::{
+    cosine_similarity, EmbeddingClient, HashingEmbeddingClient, DEFAULT_EMBEDDING_DIMENSIONS,
+};
 pub use foundation::{FoundationModelClient, FoundationModelOptions, PlatformModelClient};
 pub use language_model::{LanguageModelClient, LanguageModelResponse};
 pub use mcp::{McpTool, McpToolDescription, McpToolError, McpToolResult};
@@ -15,5 +22,6 @@ pub use orchestrator::{
     AgentCancellationCheck, AgentConfig, AgentEvent, AgentEventCallback, AgentOrchestrator,
     AgentResult, PlanStep, ToolInvocation,
 };
+pub use output::{OutputContract, DEFAULT_OUTPUT_REPAIR_ATTEMPTS};
 
 pub const DEFAULT_AGENT_MAX_STEPS: usize = 8;
@@ -9,12 +9,19 @@ use tracing::warn;
 use crate::foundation::FoundationModelOptions;
 use crate::language_model::{LanguageModelClient, LanguageModelResponse};
 use crate::mcp::{McpTool, McpToolDescription};
+use crate::output::{OutputContract, DEFAULT_OUTPUT_REPAIR_ATTEMPTS};
 
 #[derive(Debug, Clone)]
 pub struct AgentConfig {
     pub system_prompt: String,
     pub max_steps: usize,
     pub model_options: FoundationModelOptions,
+    /// JSON Schema the final answer must match. The answer is then JSON and
+    /// is also returned parsed as [`AgentResult::structured_output`].
+    pub output_schema: Option<Value>,
+    /// Times a non-matching final answer is sent back for repair before the
+    /// run fails.
+    pub output_repair_attempts: usize,
 }
 
 impl Default for AgentConfig {
@@ -28,6 +35,8 @@ impl Default for AgentConfig {
             system_prompt: DEFAULT_SYSTEM_PROMPT.trim().to_string(),
             max_steps: crate::DEFAULT_AGENT_MAX_STEPS,
             model_options: options,
+            output_schema: None,
+            output_repair_attempts: DEFAULT_OUTPUT_REPAIR_ATTEMPTS,
         }
     }
 }
@@ -53,15 +62,32 @@ pub enum PlanStep {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AgentEvent {
-    ModelResponse { raw: String },
-    ToolCall { name: String, args: Value },
-    ToolResult { name: String, result: Value },
-    Cancelled { reason: String },
+    ModelResponse {
+        raw: String,
+    },
+    ToolCall {
+        name: String,
+        args: Value,
+    },
+    ToolResult {
+        name: String,
+        result: Value,
+    },
+    /// The final answer did not match the output schema and was sent back.
+    OutputRejected {
+        errors: Vec<String>,
+    },
+    Cancelled {
+        reason: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentResult {
     pub final_answer: Option<String>,
+    /// The final answer parsed and validated against the output schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_output: Option<Value>,
     pub steps: Vec<PlanStep>,
     pub events: Vec<AgentEvent>,
     pub halted: bool,
@@ -124,7 +150,7 @@ impl AgentOrchestrator {
             .collect()
     }
 
-    fn build_prompt(&self, task: &str) -> String {
+    fn build_prompt(&self, task: &str, contract: Option<&OutputContract>) -> String {
         let mut prompt = String::new();
         prompt.push_str("You must decide the next best action given the conversation so far.\n\n");
         prompt.push_str("<<TOOLS>>\n");
@@ -172,12 +198,28 @@ impl AgentOrchestrator {
         }
         prompt.push_str("<<TASK>>\n");
         prompt.push_str(task);
+        if let Some(contract) = contract {
+            prompt.push_str("\n\n<<OUTPUT>>\n");
+            prompt
+                .push_str("When finishing, `answer` must be a JSON value matching this schema:\n");
+            prompt.push_str(
+                &serde_json::to_string_pretty(contract.schema()).unwrap_or("{}".to_string()),
+            );
+        }
         prompt.push_str("\n\nRespond ONLY with JSON matching this schema:\n");
         prompt.push_str(JSON_SCHEMA_DESCRIPTION.trim());
         prompt
     }
 
     pub async fn run_task(&mut self, task: &str) -> Result<AgentResult> {
+        let contract = self
+            .config
+            .output_schema
+            .clone()
+            .map(OutputContract::compile)
+            .transpose()?;
+        let mut repairs_left = self.config.output_repair_attempts;
+
         self.history.push(DialogueTurn::User(task.to_string()));
         let mut steps = Vec::new();
         self.events.clear();
@@ -190,7 +232,7 @@ impl AgentOrchestrator {
                 return Ok(self.cancelled_result(steps));
             }
 
-            let prompt = self.build_prompt(task);
+            let prompt = self.build_prompt(task, contract.as_ref());
             let mut options = self.config.model_options.clone();
             options.system_prompt = Some(self.config.system_prompt.clone());
 
@@ -255,13 +297,39 @@ impl AgentOrchestrator {
                     });
                 }
                 ModelDirective::Finish { summary, answer } => {
+                    let checked = contract.as_ref().map(|c| c.check(&answer));
+                    let answer = match answer {
+                        Value::String(text) => text,
+                        other => other.to_string(),
+                    };
+                    self.history.push(DialogueTurn::Agent(answer.clone()));
+                    let structured_output = match checked {
+                        Some(Err(errors)) if repairs_left > 0 => {
+                            repairs_left -= 1;
+                            self.history.push(DialogueTurn::User(format!(
+                                "Your answer does not match the output schema: {}. \
+                                 Finish again with a corrected answer.",
+                                errors.join("; ")
+                            )));
+                            self.emit_event(AgentEvent::OutputRejected { errors });
+                            continue;
+                        }
+                        Some(Err(errors)) => {
+                            return Err(anyhow!(
+                                "final answer does not match the output schema: {}",
+                                errors.join("; ")
+                            ));
+                        }
+                        Some(Ok(value)) => Some(value),
+                        None => None,
+                    };
                     steps.push(PlanStep::Finish {
                         summary,
                         answer: answer.clone(),
                     });
-                    self.history.push(DialogueTurn::Agent(answer.clone()));
                     return Ok(AgentResult {
                         final_answer: Some(answer),
+                        structured_output,
                         steps,
                         events: self.events.clone(),
                         halted: false,
@@ -276,6 +344,7 @@ impl AgentOrchestrator {
 
         Ok(AgentResult {
             final_answer: None,
+            structured_output: None,
             steps,
             events: self.events.clone(),
             halted: true,
@@ -299,6 +368,7 @@ impl AgentOrchestrator {
     fn cancelled_result(&self, steps: Vec<PlanStep>) -> AgentResult {
         AgentResult {
             final_answer: None,
+            structured_output: None,
             steps,
             events: self.events.clone(),
             halted: true,
@@ -338,7 +408,7 @@ enum ModelDirective {
     },
     Finish {
         summary: Option<String>,
-        answer: String,
+        answer: Value,
     },
 }
 
@@ -382,3 +452,222 @@ Rules:
 - Always respond with strict JSON matching the provided schema. Do not include any extra text, code fencing, or commentary.
 - Tool arguments must be valid JSON objects; omit null keys.
 "#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cassette::{PromptMatch, RecordingModelClient, ReplayModelClient};
+    use crate::mcp::{McpToolError, McpToolResult};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Replies with the queued responses in order.
+    struct ScriptedModel {
+        responses: Mutex<Vec<&'static str>>,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedModel {
+        fn new(mut responses: Vec<&'static str>) -> Arc<Self> {
+            responses.reverse();
+            Arc::new(Self {
+                responses: Mutex::new(responses),
+                prompts: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LanguageModelClient for ScriptedModel {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _options: &FoundationModelOptions,
+        ) -> Result<LanguageModelResponse> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            let response = self
+                .responses
+                .lock()
+                .unwrap()
+                .pop()
+                .ok_or_else(|| anyhow!("script exhausted"))?;
+            Ok(LanguageModelResponse::new(response.to_string()))
+        }
+    }
+
+    fn config(repairs: usize) -> AgentConfig {
+        AgentConfig {
+            output_schema: Some(json!({
+                "type": "object",
+                "required": ["total"],
+                "properties": { "total": { "type": "number" } }
+            })),
+            output_repair_attempts: repairs,
+            ..AgentConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn non_matching_answers_are_sent_back_for_repair() {
+        let model = ScriptedModel::new(vec![
+            r#"{"type": "finish", "answer": "The total is 12."}"#,
+            r#"{"type": "finish", "answer": {"total": 12}}"#,
+        ]);
+        let mut agent = AgentOrchestrator::new(model.clone(), config(1));
+        let result = agent.run_task("Add up the cart").await.unwrap();
+
+        assert_eq!(result.structured_output, Some(json!({ "total": 12 })));
+        assert_eq!(result.final_answer.as_deref(), Some(r#"{"total":12}"#));
+        assert_eq!(result.steps.len(), 1);
+        assert!(result
+            .events
+            .iter()
+            .any(|event| matches!(event, AgentEvent::OutputRejected { .. })));
+        let prompts = model.prompts.lock().unwrap();
+        assert!(prompts[0].contains("<<OUTPUT>>"));
+        assert!(prompts[1].contains("does not match the output schema"));
+    }
+
+    #[tokio::test]
+    async fn runs_fail_once_repairs_are_exhausted() {
+        let model = ScriptedModel::new(vec![
+            r#"{"type": "finish", "answer": "{\"total\": \"12\"}"}"#,
+        ]);
+        let mut agent = AgentOrchestrator::new(model, config(0));
+        let err = agent.run_task("Add up the cart").await.unwrap_err();
+        assert!(err.to_string().contains("/total"));
+    }
+
+    #[tokio::test]
+    async fn string_answers_satisfy_string_schemas() {
+        let model = ScriptedModel::new(vec![r#"{"type": "finish", "answer": "Lisbon"}"#]);
+        let mut agent = AgentOrchestrator::new(
+            model,
+            AgentConfig {
+                output_schema: Some(json!({ "type": "string" })),
+                output_repair_attempts: 0,
+                ..AgentConfig::default()
+            },
+        );
+        let result = agent.run_task("Name the capital").await.unwrap();
+
+        assert_eq!(result.structured_output, Some(json!("Lisbon")));
+        assert_eq!(result.final_answer.as_deref(), Some("Lisbon"));
+    }
+
+    /// Prices SKUs from a fixed list and records the arguments of each call.
+    struct PriceTool {
+        description: McpToolDescription,
+        prices: Value,
+        calls: Mutex<Vec<Value>>,
+    }
+
+    impl PriceTool {
+        fn new(prices: Value) -> Arc<Self> {
+            Arc::new(Self {
+                description: McpToolDescription::new(
+                    "shop.price",
+                    "Look up the price of a SKU",
+                    json!({
+                        "type": "object",
+                        "properties": { "sku": { "type": "string" } },
+                        "required": ["sku"]
+                    }),
+                ),
+                prices,
+                calls: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl McpTool for PriceTool {
+        fn description(&self) -> &McpToolDescription {
+            &self.description
+        }
+
+        async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
+            self.calls.lock().unwrap().push(args.clone());
+            let price = args["sku"]
+                .as_str()
+                .and_then(|sku| self.prices.get(sku))
+                .ok_or_else(|| McpToolError::InvalidInput("unknown sku".into()))?;
+            Ok(McpToolResult {
+                content: json!({ "sku": args["sku"], "price": price }),
+                metadata: Default::default(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn replayed_cassettes_drive_the_recorded_tool_calls() {
+        let path = std::env::temp_dir()
+            .join("ai-agent-orchestrator-tests")
+            .join(format!("cassette-{}.json", std::process::id()));
+        let task = "Add up the cart";
+        let prices = json!({ "MBA13": 1299, "PIXEL9": 799 });
+
+        let recorder = Arc::new(RecordingModelClient::new(
+            ScriptedModel::new(vec![
+                r#"{"type": "tool", "thought": "price the laptop", "name": "shop.price", "args": {"sku": "MBA13"}}"#,
+                r#"{"type": "tool", "name": "shop.price", "args": {"sku": "PIXEL9"}}"#,
+                r#"{"type": "finish", "summary": "two items", "answer": {"total": 2098}}"#,
+            ]),
+            &path,
+        ));
+        let mut agent = AgentOrchestrator::new(recorder.clone(), config(0));
+        agent.register_tool(PriceTool::new(prices.clone()));
+        let recorded = agent.run_task(task).await.unwrap();
+        assert_eq!(recorder.entries().len(), 3);
+
+        let replay = Arc::new(ReplayModelClient::from_path(&path, PromptMatch::Strict).unwrap());
+        let tool = PriceTool::new(prices);
+        let mut agent = AgentOrchestrator::new(replay.clone(), config(0));
+        agent.register_tool(tool.clone());
+        let replayed = agent.run_task(task).await.unwrap();
+
+        assert_eq!(replay.remaining(), 0);
+        assert_eq!(
+            *tool.calls.lock().unwrap(),
+            vec![json!({ "sku": "MBA13" }), json!({ "sku": "PIXEL9" })]
+        );
+        let actions: Vec<(&str, Option<&Value>)> = replayed
+            .steps
+            .iter()
+            .map(|step| match step {
+                PlanStep::Tool { call, .. } => (call.name.as_str(), call.observation.as_ref()),
+                PlanStep::Finish { answer, .. } => (answer.as_str(), None),
+            })
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (
+                    "shop.price",
+                    Some(&json!({ "sku": "MBA13", "price": 1299 }))
+                ),
+                (
+                    "shop.price",
+                    Some(&json!({ "sku": "PIXEL9", "price": 799 }))
+                ),
+                (r#"{"total":2098}"#, None),
+            ]
+        );
+        assert_eq!(replayed.structured_output, Some(json!({ "total": 2098 })));
+        assert_eq!(
+            serde_json::to_value(&replayed.events).unwrap(),
+            serde_json::to_value(&recorded.events).unwrap()
+        );
+
+        // A tool that now observes something else changes the next prompt,
+        // which the strict cassette no longer matches.
+        let replay = Arc::new(ReplayModelClient::from_path(&path, PromptMatch::Strict).unwrap());
+        let mut agent = AgentOrchestrator::new(replay, config(0));
+        agent.register_tool(PriceTool::new(json!({ "MBA13": 999, "PIXEL9": 799 })));
+        let err = agent.run_task(task).await.unwrap_err();
+        assert!(err.to_string().contains("no recorded completion"), "{err}");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
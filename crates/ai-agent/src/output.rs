@@ -0,0 +1,153 @@
+//! Output contracts: a JSON Schema the agent's final answer must satisfy.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+/// How many times the orchestrator asks the model to fix a final answer that
+/// does not match the output schema before failing the run.
+pub const DEFAULT_OUTPUT_REPAIR_ATTEMPTS: usize = 2;
+
+/// A compiled output schema.
+#[derive(Debug, Clone)]
+pub struct OutputContract {
+    schema: Value,
+    compiled: Arc<JSONSchema>,
+}
+
+impl OutputContract {
+    pub fn compile(schema: Value) -> Result<Self> {
+        let compiled =
+            JSONSchema::compile(&schema).map_err(|err| anyhow!("invalid output schema: {err}"))?;
+        Ok(Self {
+            schema,
+            compiled: Arc::new(compiled),
+        })
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Validates the model's final `answer` as given. A string that does not
+    /// match on its own is parsed as JSON, tolerating a surrounding code
+    /// fence, since models often serialise structured answers into text. On
+    /// failure returns every problem found, phrased for the model to act on.
+    pub fn check(&self, answer: &Value) -> Result<Value, Vec<String>> {
+        let Value::String(text) = answer else {
+            self.validate(answer)?;
+            return Ok(answer.clone());
+        };
+        if self.validate(answer).is_ok() {
+            return Ok(answer.clone());
+        }
+        let value = parse_answer(text)
+            .map_err(|err| vec![format!("the answer is not valid JSON: {err}")])?;
+        self.validate(&value)?;
+        Ok(value)
+    }
+
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<String>> {
+        self.compiled.validate(value).map_err(|errors| {
+            errors
+                .map(|error| {
+                    let path = error.instance_path.to_string();
+                    if path.is_empty() {
+                        error.to_string()
+                    } else {
+                        format!("{path}: {error}")
+                    }
+                })
+                .collect()
+        })
+    }
+}
+
+fn parse_answer(answer: &str) -> serde_json::Result<Value> {
+    let trimmed = answer.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|body| body.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract() -> OutputContract {
+        OutputContract::compile(json!({
+            "type": "object",
+            "required": ["flights"],
+            "properties": {
+                "flights": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["carrier", "price"],
+                        "properties": {
+                            "carrier": { "type": "string" },
+                            "price": { "type": "number", "minimum": 0 }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("schema")
+    }
+
+    #[test]
+    fn accepts_fenced_json_that_matches() {
+        let value = contract()
+            .check(&json!(
+                "```json\n{\"flights\": [{\"carrier\": \"TAP\", \"price\": 412}]}\n```"
+            ))
+            .expect("valid answer");
+        assert_eq!(value["flights"][0]["carrier"], "TAP");
+    }
+
+    #[test]
+    fn reports_problems_with_their_location() {
+        let errors = contract()
+            .check(&json!(
+                r#"{"flights": [{"carrier": "TAP", "price": -1}, {"price": 10}]}"#
+            ))
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .any(|error| error.starts_with("/flights/0/price")));
+        assert!(errors
+            .iter()
+            .any(|error| error.starts_with("/flights/1") && error.contains("carrier")));
+
+        let errors = contract().check(&json!("Found two flights.")).unwrap_err();
+        assert!(errors[0].contains("not valid JSON"));
+    }
+
+    #[test]
+    fn validates_structured_answers_as_returned() {
+        let value = contract()
+            .check(&json!({ "flights": [{ "carrier": "TAP", "price": 412 }] }))
+            .expect("valid answer");
+        assert_eq!(value["flights"][0]["price"], 412);
+
+        let strings = OutputContract::compile(json!({ "type": "string" })).expect("schema");
+        assert_eq!(strings.check(&json!("Lisbon")).unwrap(), json!("Lisbon"));
+        assert_eq!(strings.check(&json!("42")).unwrap(), json!("42"));
+        assert!(strings.check(&json!(42)).is_err());
+
+        let numbers = OutputContract::compile(json!({ "type": "number" })).expect("schema");
+        assert_eq!(numbers.check(&json!("42")).unwrap(), json!(42));
+    }
+
+    #[test]
+    fn rejects_invalid_schemas() {
+        assert!(OutputContract::compile(json!({ "type": "nope" })).is_err());
+    }
+}
@@ -108,16 +108,20 @@ impl ApprovalHandler for GuiApprovalHandler {
 mod tests {
     use super::*;
     use crate::agent::McpServerRegistry;
+    use crate::agent_app_pipelines::AgentAppPipelineRegistry;
     use crate::agent_app_schedules::AgentAppScheduleRegistry;
+    use crate::agent_app_triggers::AgentAppTriggerHub;
     use crate::agent_apps::AgentAppRegistry;
     use crate::app_state::AppState;
     use crate::browser_engine::BrowserEngine;
     use crate::mcp_profiles::McpConfigService;
     use crate::protocol_handlers::ProtocolHandler;
     use crate::security::SecurityManager;
+    use crate::semantic_index::SemanticIndex;
     use crate::telemetry::TelemetryManager;
     use crate::wallet_store::WalletStore;
     use afm_node::AfmNodeConfig;
+    use ai_agent::HashingEmbeddingClient;
     use anyhow::Result as TestResult;
     use serde_json::Value;
     use std::sync::{Arc, Mutex};
@@ -141,6 +145,8 @@ mod tests {
             content_bounds: Mutex::new(None),
             last_content_bounds: Mutex::new(None),
             content_webview_visible: Mutex::new(false),
+            agent_run_tabs: Mutex::new(std::collections::HashMap::new()),
+            agent_panel_bounds: Mutex::new(None),
             browser_engine: Arc::new(BrowserEngine::new()),
             protocol_handler: Arc::new(Mutex::new(ProtocolHandler::new())),
             security_manager: Arc::new(Mutex::new(SecurityManager::new())),
@@ -154,8 +160,13 @@ mod tests {
             mcp_registry: Arc::new(mcp_registry),
             mcp_config,
             agent_apps: Arc::new(AgentAppRegistry::empty()),
+            agent_app_pipelines: Arc::new(AgentAppPipelineRegistry::empty()),
             agent_app_schedules: Arc::new(AgentAppScheduleRegistry::empty()),
+            agent_app_triggers: Arc::new(AgentAppTriggerHub::new()),
             download_controls: Arc::new(AsyncMutex::new(std::collections::HashMap::new())),
+            semantic_index: Arc::new(SemanticIndex::in_memory(Arc::new(
+                HashingEmbeddingClient::default(),
+            ))),
         };
         let broker = state.approval_broker.clone();
         let app = Builder::default()
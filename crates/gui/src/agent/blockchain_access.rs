@@ -0,0 +1,319 @@
+//! Runtime enforcement of an agent app's [`BlockchainAccessContract`].
+//!
+//! The contract is compiled into capability grants for the run and into a
+//! [`ToolPolicy`] on `wallet.spend`, which refuses calls the contract does not
+//! cover before the approval prompt. Refusals land in the run ledger.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use agent_core::{CapabilityKind, CapabilityRegistry, ToolPolicy};
+use ai_agent::McpToolResult;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::agent_apps::BlockchainAccessContract;
+
+/// EVM chain ids of the chain refs `wallet.spend` may broadcast through an
+/// Ethereum JSON-RPC endpoint.
+const EVM_CHAIN_IDS: &[(&str, u64)] = &[
+    ("ethereum-mainnet", 1),
+    ("ethereum-sepolia", 11_155_111),
+    ("ethereum-holesky", 17_000),
+    ("base-mainnet", 8_453),
+    ("base-sepolia", 84_532),
+    ("polygon-mainnet", 137),
+    ("polygon-amoy", 80_002),
+];
+
+/// The EVM chain id a chain ref such as `ethereum-sepolia` names.
+pub fn evm_chain_id(chain_ref: &str) -> Option<u64> {
+    let chain_ref = chain_ref.trim().to_ascii_lowercase();
+    EVM_CHAIN_IDS
+        .iter()
+        .find(|(name, _)| *name == chain_ref)
+        .map(|(_, id)| *id)
+}
+
+#[derive(Debug)]
+pub struct BlockchainAccessPolicy {
+    read_wallet_state: bool,
+    request_signing: bool,
+    request_broadcast: bool,
+    allowed_chain_refs: Vec<String>,
+    /// Caps on what the run may spend per chain ref, in the chain's
+    /// smallest unit. Chains without an entry may not be spent on when any
+    /// cap is set.
+    spend_limits: BTreeMap<String, u128>,
+    spent: Mutex<BTreeMap<String, u128>>,
+}
+
+impl BlockchainAccessPolicy {
+    pub fn from_contract(contract: &BlockchainAccessContract) -> Result<Self> {
+        Ok(Self {
+            read_wallet_state: contract.read_wallet_state,
+            request_signing: contract.request_signing,
+            request_broadcast: contract.request_broadcast,
+            allowed_chain_refs: contract
+                .allowed_chain_refs
+                .iter()
+                .map(|chain| chain.trim().to_ascii_lowercase())
+                .collect(),
+            spend_limits: contract.spend_limits()?,
+            spent: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Whether `wallet.info` may be exposed to the run.
+    pub fn allows_wallet_state(&self) -> bool {
+        self.read_wallet_state || self.request_signing
+    }
+
+    /// Narrows the run's grants: without signing, `wallet:spend` is revoked
+    /// outright so the gate refuses it even if the policy were bypassed.
+    pub fn apply_to(&self, capabilities: &mut CapabilityRegistry) {
+        if !self.request_signing {
+            capabilities.revoke(CapabilityKind::WalletSpend);
+        }
+    }
+
+    /// Chain refs are matched exactly: `eth` does not stand in for
+    /// `ethereum-sepolia`, since the network it reaches depends on the RPC.
+    fn chain_allowed(&self, chain: &str) -> bool {
+        self.allowed_chain_refs.is_empty()
+            || self
+                .allowed_chain_refs
+                .iter()
+                .any(|allowed| *allowed == chain)
+    }
+
+    fn spent(&self, chain: &str) -> u128 {
+        self.spent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(chain)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+fn spend_chain(args: &Value) -> Option<String> {
+    args.get("chain")
+        .and_then(Value::as_str)
+        .map(|chain| chain.trim().to_ascii_lowercase())
+}
+
+/// `wallet.spend`'s amount, which is a whole number of the chain's smallest
+/// unit.
+fn spend_amount(args: &Value) -> Option<u128> {
+    let amount = args.get("amount")?;
+    amount.as_u64().map(u128::from).or_else(|| {
+        amount
+            .as_f64()
+            .filter(|value| value.is_finite() && *value >= 0.0 && value.fract() == 0.0)
+            .map(|value| value as u128)
+    })
+}
+
+#[async_trait]
+impl ToolPolicy for BlockchainAccessPolicy {
+    async fn check(&self, args: &Value) -> Result<(), String> {
+        if !self.request_signing {
+            return Err("the app's blockchain access contract does not allow signing".into());
+        }
+        let broadcast = args
+            .get("broadcast")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        if broadcast && !self.request_broadcast {
+            return Err(
+                "the app's blockchain access contract does not allow broadcasting; \
+                 set broadcast to false to sign only"
+                    .into(),
+            );
+        }
+        let chain = spend_chain(args).unwrap_or_default();
+        if !self.chain_allowed(&chain) {
+            return Err(format!(
+                "chain `{chain}` is outside the app's allowed chains ({})",
+                self.allowed_chain_refs.join(", ")
+            ));
+        }
+        if !self.spend_limits.is_empty() {
+            let Some(limit) = self.spend_limits.get(&chain).copied() else {
+                return Err(format!(
+                    "the app's blockchain access contract sets no spend limit for chain `{chain}`"
+                ));
+            };
+            let Some(amount) = spend_amount(args) else {
+                return Err(
+                    "amount must be a whole number of the chain's smallest unit".to_string()
+                );
+            };
+            let spent = self.spent(&chain);
+            if spent.saturating_add(amount) > limit {
+                return Err(format!(
+                    "spending {amount} on {chain} would exceed the app's spend limit of {limit} \
+                     ({spent} already spent this run)"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    async fn record(&self, args: &Value, _result: &McpToolResult) {
+        if let (Some(chain), Some(amount)) = (spend_chain(args), spend_amount(args)) {
+            let mut spent = self
+                .spent
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let total = spent.entry(chain).or_default();
+            *total = total.saturating_add(amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(contract: Value) -> BlockchainAccessPolicy {
+        let contract: BlockchainAccessContract =
+            serde_json::from_value(contract).expect("contract");
+        BlockchainAccessPolicy::from_contract(&contract).expect("policy")
+    }
+
+    async fn record_spend(policy: &BlockchainAccessPolicy, args: &Value) {
+        let result = McpToolResult {
+            content: json!({ "status": "signed" }),
+            metadata: Default::default(),
+        };
+        policy.record(args, &result).await;
+    }
+
+    #[tokio::test]
+    async fn refuses_undeclared_signing_and_broadcast() {
+        let read_only = policy(json!({ "readChainData": true }));
+        let args = json!({ "to": "0xabc", "amount": 1, "chain": "eth" });
+        assert!(read_only
+            .check(&args)
+            .await
+            .unwrap_err()
+            .contains("signing"));
+
+        let mut capabilities = CapabilityRegistry::with_browser_defaults();
+        read_only.apply_to(&mut capabilities);
+        assert!(capabilities.consume(CapabilityKind::WalletSpend).is_err());
+
+        let sign_only = policy(json!({ "requestSigning": true }));
+        assert!(sign_only
+            .check(&args)
+            .await
+            .unwrap_err()
+            .contains("broadcasting"));
+        let mut sign_args = args.clone();
+        sign_args["broadcast"] = json!(false);
+        assert!(sign_only.check(&sign_args).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn refuses_chains_outside_the_contract() {
+        let policy = policy(json!({
+            "requestSigning": true,
+            "requestBroadcast": true,
+            "allowedChainRefs": ["ethereum-sepolia", "base-sepolia"]
+        }));
+        for chain in ["ethereum-sepolia", "Base-Sepolia"] {
+            let args = json!({ "to": "0xabc", "amount": 1, "chain": chain });
+            assert!(
+                policy.check(&args).await.is_ok(),
+                "{chain} should be allowed"
+            );
+        }
+        for chain in ["eth", "ethereum", "ethereum-mainnet", "polygon"] {
+            let args = json!({ "to": "0xabc", "amount": 1, "chain": chain });
+            assert!(
+                policy.check(&args).await.unwrap_err().contains(chain),
+                "{chain} should be refused"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn spend_limits_are_tracked_per_chain_in_smallest_units() {
+        let policy = policy(json!({
+            "requestSigning": true,
+            "requestBroadcast": true,
+            "spendLimit": { "ethereum-sepolia": "25 wei", "bitcoin-mainnet": "0.0000003 BTC" }
+        }));
+        let twenty = json!({ "to": "0xabc", "amount": 20, "chain": "ethereum-sepolia" });
+        assert!(policy.check(&twenty).await.is_ok());
+        record_spend(&policy, &twenty).await;
+
+        let ten = json!({ "to": "0xabc", "amount": 10, "chain": "ethereum-sepolia" });
+        assert!(policy
+            .check(&ten)
+            .await
+            .unwrap_err()
+            .contains("spend limit of 25"));
+        let five = json!({ "to": "0xabc", "amount": 5, "chain": "ethereum-sepolia" });
+        assert!(policy.check(&five).await.is_ok());
+
+        // Bitcoin has its own total: 30 sat.
+        let sats = json!({ "to": "bc1q", "amount": 30, "chain": "bitcoin-mainnet" });
+        assert!(policy.check(&sats).await.is_ok());
+        let fraction = json!({ "to": "0xabc", "amount": 1.5, "chain": "ethereum-sepolia" });
+        assert!(policy
+            .check(&fraction)
+            .await
+            .unwrap_err()
+            .contains("whole number"));
+        let unlisted = json!({ "to": "0xabc", "amount": 1, "chain": "solana-mainnet" });
+        assert!(policy
+            .check(&unlisted)
+            .await
+            .unwrap_err()
+            .contains("no spend limit"));
+    }
+
+    #[tokio::test]
+    async fn bundled_contracts_cap_spends_in_native_units() {
+        let manifest: Value =
+            serde_json::from_str(include_str!("../../../../configs/agent_apps.json"))
+                .expect("bundled manifest");
+        let contract = manifest["apps"]
+            .as_array()
+            .expect("apps")
+            .iter()
+            .find_map(|app| app.get("blockchainAccess"))
+            .cloned()
+            .expect("bundled blockchain contract");
+        let policy = policy(contract);
+
+        let wei = |eth_thousandths: u64| json!(eth_thousandths * 1_000_000_000_000_000u64);
+        let spend = json!({ "to": "0xabc", "amount": wei(6), "chain": "ethereum-mainnet" });
+        assert!(policy.check(&spend).await.is_ok());
+        record_spend(&policy, &spend).await;
+        assert!(policy
+            .check(&spend)
+            .await
+            .unwrap_err()
+            .contains("spend limit"));
+
+        let base = json!({ "to": "0xabc", "amount": wei(6), "chain": "base-mainnet" });
+        assert!(policy.check(&base).await.is_ok());
+        let sats = json!({ "to": "bc1q", "amount": 20_000, "chain": "bitcoin-mainnet" });
+        assert!(policy.check(&sats).await.is_ok());
+        let eth = json!({ "to": "0xabc", "amount": 1, "chain": "eth" });
+        assert!(policy.check(&eth).await.is_err());
+    }
+
+    #[test]
+    fn evm_chain_refs_name_their_chain_id() {
+        assert_eq!(evm_chain_id("ethereum-sepolia"), Some(11_155_111));
+        assert_eq!(evm_chain_id("Ethereum-Mainnet"), Some(1));
+        assert_eq!(evm_chain_id("eth"), None);
+    }
+}
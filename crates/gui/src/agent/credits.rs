@@ -1,52 +1,368 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+const DEFAULT_LEDGER_PATH: &str = "configs/agent_credit_ledger.jsonl";
+const DEFAULT_LEDGER_LIMIT: usize = 100;
+const MAX_LEDGER_LIMIT: usize = 1_000;
+const DAY_MS: i64 = 86_400_000;
+
 #[derive(Debug, Serialize, Clone)]
 pub struct CreditSnapshot {
     pub balance_tokens: i64,
     pub total_spent_tokens: u64,
+    /// Tokens charged by runs that are still in progress and not yet in the
+    /// ledger.
+    pub reserved_tokens: u64,
+}
+
+/// Tokens one provider/model pair consumed during a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditUsage {
+    pub provider: String,
+    pub model: String,
+    pub calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// At least one call reported no usage and was estimated from text length.
+    #[serde(default)]
+    pub estimated: bool,
 }
 
+impl CreditUsage {
+    fn absorb(&mut self, other: &CreditUsage) {
+        self.calls = self.calls.saturating_add(other.calls);
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self
+            .completion_tokens
+            .saturating_add(other.completion_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+        self.estimated |= other.estimated;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CreditLedgerKind {
+    /// Allowance granted when the ledger is first created.
+    Grant {
+        tokens: u64,
+    },
+    TopUp {
+        tokens: u64,
+    },
+    RunCharge {
+        run_id: String,
+        tokens: u64,
+        #[serde(default)]
+        usage: Vec<CreditUsage>,
+    },
+    Refund {
+        run_id: String,
+        tokens: u64,
+        reason: String,
+    },
+}
+
+impl CreditLedgerKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CreditLedgerKind::Grant { .. } => "grant",
+            CreditLedgerKind::TopUp { .. } => "top_up",
+            CreditLedgerKind::RunCharge { .. } => "run_charge",
+            CreditLedgerKind::Refund { .. } => "refund",
+        }
+    }
+
+    /// Signed effect on the balance.
+    fn delta(&self) -> i64 {
+        match self {
+            CreditLedgerKind::Grant { tokens }
+            | CreditLedgerKind::TopUp { tokens }
+            | CreditLedgerKind::Refund { tokens, .. } => *tokens as i64,
+            CreditLedgerKind::RunCharge { tokens, .. } => -(*tokens as i64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditLedgerEntry {
+    pub seq: u64,
+    pub recorded_at_ms: u64,
+    #[serde(flatten)]
+    pub kind: CreditLedgerKind,
+    pub balance_after: i64,
+}
+
+/// Ledger activity for one calendar month (UTC).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditStatement {
+    pub year: i32,
+    pub month: u32,
+    pub opening_balance: i64,
+    pub closing_balance: i64,
+    pub granted_tokens: u64,
+    pub topped_up_tokens: u64,
+    pub charged_tokens: u64,
+    pub refunded_tokens: u64,
+    pub usage: Vec<CreditUsage>,
+    pub entries: Vec<CreditLedgerEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CreditStatementFormat {
+    Json,
+    Csv,
+}
+
+/// Credit balance backed by an append-only ledger. Model calls reserve
+/// tokens as they happen; the run's charge is written to the ledger once it
+/// finishes, so the balance rebuilt on startup only reflects settled runs.
 #[derive(Debug)]
 pub struct CreditAccount {
-    balance_tokens: i64,
+    path: Option<PathBuf>,
+    entries: Vec<CreditLedgerEntry>,
+    settled_balance: i64,
+    reserved_tokens: u64,
     total_spent_tokens: u64,
 }
 
 impl CreditAccount {
+    /// An account that is not persisted, starting from `initial_balance`.
     pub fn new(initial_balance: i64) -> Self {
+        let mut account = Self::with_entries(None, Vec::new());
+        if initial_balance > 0 {
+            let _ = account.append(CreditLedgerKind::Grant {
+                tokens: initial_balance as u64,
+            });
+        } else {
+            account.settled_balance = initial_balance;
+        }
+        account
+    }
+
+    pub fn load_default(initial_grant: u64) -> Result<Self> {
+        Self::from_path(DEFAULT_LEDGER_PATH, initial_grant)
+    }
+
+    /// Replays the ledger at `path`, creating it with `initial_grant` when it
+    /// does not exist yet.
+    pub fn from_path(path: impl AsRef<Path>, initial_grant: u64) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (entries, tail) = read_ledger(&path)?;
+        repair_tail(&path, tail)?;
+        let fresh = entries.is_empty();
+        let mut account = Self::with_entries(Some(path), entries);
+        if fresh && initial_grant > 0 {
+            account.append(CreditLedgerKind::Grant {
+                tokens: initial_grant,
+            })?;
+        }
+        Ok(account)
+    }
+
+    fn with_entries(path: Option<PathBuf>, entries: Vec<CreditLedgerEntry>) -> Self {
+        let mut settled_balance = 0i64;
+        let mut total_spent_tokens = 0u64;
+        for entry in &entries {
+            settled_balance += entry.kind.delta();
+            match &entry.kind {
+                CreditLedgerKind::RunCharge { tokens, .. } => {
+                    total_spent_tokens = total_spent_tokens.saturating_add(*tokens);
+                }
+                CreditLedgerKind::Refund { tokens, .. } => {
+                    total_spent_tokens = total_spent_tokens.saturating_sub(*tokens);
+                }
+                _ => {}
+            }
+        }
         Self {
-            balance_tokens: initial_balance,
-            total_spent_tokens: 0,
+            path,
+            entries,
+            settled_balance,
+            reserved_tokens: 0,
+            total_spent_tokens,
         }
     }
 
     pub fn snapshot(&self) -> CreditSnapshot {
         CreditSnapshot {
-            balance_tokens: self.balance_tokens,
+            balance_tokens: self.balance(),
             total_spent_tokens: self.total_spent_tokens,
+            reserved_tokens: self.reserved_tokens,
         }
     }
 
+    /// Reserves tokens for a model call made by an in-progress run. The
+    /// reservation is kept even when it overdraws the balance, since the
+    /// call has already been made.
     pub fn charge(&mut self, tokens: u32) -> Result<(), CreditError> {
-        let tokens = tokens as i64;
-        self.balance_tokens -= tokens;
+        self.reserved_tokens = self.reserved_tokens.saturating_add(tokens as u64);
         self.total_spent_tokens = self.total_spent_tokens.saturating_add(tokens as u64);
-        if self.balance_tokens < 0 {
+        let balance = self.balance();
+        if balance < 0 {
             Err(CreditError::InsufficientBalance {
-                deficit: (-self.balance_tokens) as u64,
+                deficit: balance.unsigned_abs(),
             })
         } else {
             Ok(())
         }
     }
 
-    pub fn top_up(&mut self, tokens: u32) {
-        self.balance_tokens += tokens as i64;
+    pub fn top_up(&mut self, tokens: u32) -> Result<CreditLedgerEntry> {
+        if tokens == 0 {
+            return Err(anyhow!("top-up must be at least 1 token"));
+        }
+        self.append(CreditLedgerKind::TopUp {
+            tokens: tokens as u64,
+        })
+    }
+
+    /// Moves a finished run's reservation into the ledger as a charge with
+    /// its usage breakdown. When `refund_reason` is set the charge is
+    /// refunded straight away, as for runs that failed.
+    pub fn settle_run(
+        &mut self,
+        run_id: &str,
+        reserved_tokens: u64,
+        usage: Vec<CreditUsage>,
+        refund_reason: Option<String>,
+    ) -> Result<()> {
+        if reserved_tokens == 0 {
+            return Ok(());
+        }
+        self.reserved_tokens = self.reserved_tokens.saturating_sub(reserved_tokens);
+        self.append(CreditLedgerKind::RunCharge {
+            run_id: run_id.to_string(),
+            tokens: reserved_tokens,
+            usage,
+        })?;
+        if let Some(reason) = refund_reason {
+            self.total_spent_tokens = self.total_spent_tokens.saturating_sub(reserved_tokens);
+            self.append(CreditLedgerKind::Refund {
+                run_id: run_id.to_string(),
+                tokens: reserved_tokens,
+                reason,
+            })?;
+        }
+        Ok(())
     }
 
     pub fn balance(&self) -> i64 {
-        self.balance_tokens
+        self.settled_balance - self.reserved_tokens as i64
+    }
+
+    /// Ledger entries, newest first.
+    pub fn entries(&self, limit: Option<usize>, offset: Option<usize>) -> Vec<CreditLedgerEntry> {
+        let limit = limit
+            .unwrap_or(DEFAULT_LEDGER_LIMIT)
+            .clamp(1, MAX_LEDGER_LIMIT);
+        self.entries
+            .iter()
+            .rev()
+            .skip(offset.unwrap_or(0))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn statement(&self, year: i32, month: u32) -> Result<CreditStatement> {
+        let (start_ms, end_ms) = month_bounds_ms(year, month)?;
+        let mut statement = CreditStatement {
+            year,
+            month,
+            opening_balance: 0,
+            closing_balance: 0,
+            granted_tokens: 0,
+            topped_up_tokens: 0,
+            charged_tokens: 0,
+            refunded_tokens: 0,
+            usage: Vec::new(),
+            entries: Vec::new(),
+        };
+        let mut usage: BTreeMap<(String, String), CreditUsage> = BTreeMap::new();
+        let mut balance = 0i64;
+        for entry in &self.entries {
+            if entry.recorded_at_ms >= end_ms {
+                break;
+            }
+            if entry.recorded_at_ms < start_ms {
+                balance += entry.kind.delta();
+                statement.opening_balance = balance;
+                continue;
+            }
+            balance += entry.kind.delta();
+            match &entry.kind {
+                CreditLedgerKind::Grant { tokens } => statement.granted_tokens += tokens,
+                CreditLedgerKind::TopUp { tokens } => statement.topped_up_tokens += tokens,
+                CreditLedgerKind::RunCharge {
+                    tokens,
+                    usage: run_usage,
+                    ..
+                } => {
+                    statement.charged_tokens += tokens;
+                    for item in run_usage {
+                        usage
+                            .entry((item.provider.clone(), item.model.clone()))
+                            .or_insert_with(|| CreditUsage {
+                                provider: item.provider.clone(),
+                                model: item.model.clone(),
+                                ..CreditUsage::default()
+                            })
+                            .absorb(item);
+                    }
+                }
+                CreditLedgerKind::Refund { tokens, .. } => statement.refunded_tokens += tokens,
+            }
+            statement.entries.push(entry.clone());
+        }
+        statement.closing_balance = balance;
+        statement.usage = usage.into_values().collect();
+        Ok(statement)
+    }
+
+    pub fn export_statement(
+        &self,
+        year: i32,
+        month: u32,
+        format: CreditStatementFormat,
+    ) -> Result<String> {
+        let statement = self.statement(year, month)?;
+        Ok(match format {
+            CreditStatementFormat::Json => serde_json::to_string_pretty(&statement)?,
+            CreditStatementFormat::Csv => render_csv(&statement),
+        })
+    }
+
+    fn append(&mut self, kind: CreditLedgerKind) -> Result<CreditLedgerEntry> {
+        let entry = CreditLedgerEntry {
+            seq: self.entries.last().map(|entry| entry.seq + 1).unwrap_or(1),
+            recorded_at_ms: now_ms().max(
+                self.entries
+                    .last()
+                    .map(|entry| entry.recorded_at_ms)
+                    .unwrap_or(0),
+            ),
+            balance_after: self.settled_balance + kind.delta(),
+            kind,
+        };
+        if let Some(path) = &self.path {
+            append_line(path, &entry)?;
+        }
+        self.settled_balance = entry.balance_after;
+        self.entries.push(entry.clone());
+        Ok(entry)
     }
 }
 
@@ -55,3 +371,379 @@ pub enum CreditError {
     #[error("insufficient credits (deficit {deficit} tokens)")]
     InsufficientBalance { deficit: u64 },
 }
+
+/// What has to happen to the end of the ledger file before it is appended
+/// to again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LedgerTail {
+    Clean,
+    /// The last entry is intact but its newline never made it to disk.
+    MissingNewline,
+    /// A torn entry follows the first `len` bytes.
+    Truncate {
+        len: u64,
+    },
+}
+
+/// Reads every entry in the ledger. A torn final line, left by a crash
+/// mid-write, is skipped and reported so it can be cut off before the next
+/// append; damage anywhere else is an error.
+fn read_ledger(path: &Path) -> Result<(Vec<CreditLedgerEntry>, LedgerTail)> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((Vec::new(), LedgerTail::Clean));
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    let mut lines = Vec::new();
+    let mut offset = 0usize;
+    for (number, line) in raw.split_inclusive('\n').enumerate() {
+        if !line.trim().is_empty() {
+            lines.push((number, offset, line));
+        }
+        offset += line.len();
+    }
+    let mut entries = Vec::with_capacity(lines.len());
+    let mut tail = if raw.ends_with('\n') || raw.trim().is_empty() {
+        LedgerTail::Clean
+    } else {
+        LedgerTail::MissingNewline
+    };
+    for (position, (number, offset, line)) in lines.iter().enumerate() {
+        match serde_json::from_str::<CreditLedgerEntry>(line.trim_end()) {
+            Ok(entry) => entries.push(entry),
+            Err(err) if position + 1 == lines.len() => {
+                tracing::warn!(
+                    path = %path.display(),
+                    line = number + 1,
+                    error = %err,
+                    "ignoring incomplete trailing credit ledger entry"
+                );
+                tail = LedgerTail::Truncate {
+                    len: *offset as u64,
+                };
+            }
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "invalid credit ledger entry at {}:{}",
+                        path.display(),
+                        number + 1
+                    )
+                });
+            }
+        }
+    }
+    Ok((entries, tail))
+}
+
+/// Cuts a torn entry off the ledger, or finishes an unterminated last line,
+/// so the next append starts on a line of its own.
+fn repair_tail(path: &Path, tail: LedgerTail) -> Result<()> {
+    match tail {
+        LedgerTail::Clean => Ok(()),
+        LedgerTail::MissingNewline => OpenOptions::new()
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(b"\n").and_then(|_| file.sync_data()))
+            .with_context(|| format!("failed to terminate {}", path.display())),
+        LedgerTail::Truncate { len } => OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_len(len).and_then(|_| file.sync_data()))
+            .with_context(|| format!("failed to truncate {}", path.display())),
+    }
+}
+
+fn append_line(path: &Path, entry: &CreditLedgerEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .and_then(|_| file.sync_data())
+        .with_context(|| format!("failed to append to {}", path.display()))
+}
+
+fn render_csv(statement: &CreditStatement) -> String {
+    let mut out = String::from("seq,recorded_at_ms,kind,run_id,tokens,balance_after,detail\n");
+    for entry in &statement.entries {
+        let (run_id, tokens, detail) = match &entry.kind {
+            CreditLedgerKind::Grant { tokens } | CreditLedgerKind::TopUp { tokens } => {
+                ("", *tokens, String::new())
+            }
+            CreditLedgerKind::RunCharge {
+                run_id,
+                tokens,
+                usage,
+            } => (
+                run_id.as_str(),
+                *tokens,
+                usage
+                    .iter()
+                    .map(|item| format!("{}/{}:{}", item.provider, item.model, item.total_tokens))
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            ),
+            CreditLedgerKind::Refund {
+                run_id,
+                tokens,
+                reason,
+            } => (run_id.as_str(), *tokens, reason.clone()),
+        };
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            entry.seq,
+            entry.recorded_at_ms,
+            entry.kind.as_str(),
+            csv_field(run_id),
+            tokens,
+            entry.balance_after,
+            csv_field(&detail)
+        );
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Start (inclusive) and end (exclusive) of a UTC calendar month in unix ms.
+fn month_bounds_ms(year: i32, month: u32) -> Result<(u64, u64)> {
+    if !(1..=12).contains(&month) {
+        return Err(anyhow!("month must be between 1 and 12"));
+    }
+    if year < 1970 {
+        return Err(anyhow!("year must be 1970 or later"));
+    }
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let start = days_from_civil(year, month) * DAY_MS;
+    let end = days_from_civil(next_year, next_month) * DAY_MS;
+    Ok((start as u64, end as u64))
+}
+
+/// Days from 1970-01-01 to the first day of `month` in `year` (proleptic
+/// Gregorian calendar).
+fn days_from_civil(year: i32, month: u32) -> i64 {
+    let year = i64::from(if month <= 2 { year - 1 } else { year });
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_ledger_path() -> PathBuf {
+        std::env::temp_dir()
+            .join("dbrowser-credit-ledger-tests")
+            .join(Uuid::new_v4().to_string())
+            .join("ledger.jsonl")
+    }
+
+    fn usage(tokens: u64) -> Vec<CreditUsage> {
+        vec![CreditUsage {
+            provider: "apple_foundation".into(),
+            model: "system".into(),
+            calls: 2,
+            prompt_tokens: tokens / 2,
+            completion_tokens: tokens - tokens / 2,
+            total_tokens: tokens,
+            estimated: false,
+        }]
+    }
+
+    #[test]
+    fn balance_is_rebuilt_from_the_ledger() {
+        let path = temp_ledger_path();
+        {
+            let mut account = CreditAccount::from_path(&path, 1_000).expect("create ledger");
+            account.top_up(500).expect("top up");
+            account.charge(120).expect("charge");
+            account
+                .settle_run("run-1", 120, usage(120), None)
+                .expect("settle run");
+            assert_eq!(account.balance(), 1_380);
+        }
+
+        let reloaded = CreditAccount::from_path(&path, 1_000).expect("reload ledger");
+        assert_eq!(reloaded.balance(), 1_380);
+        assert_eq!(reloaded.snapshot().total_spent_tokens, 120);
+        let kinds = reloaded
+            .entries(None, None)
+            .into_iter()
+            .map(|entry| entry.kind.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["run_charge", "top_up", "grant"]);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn failed_runs_are_refunded_and_reservations_do_not_persist() {
+        let path = temp_ledger_path();
+        {
+            let mut account = CreditAccount::from_path(&path, 200).expect("create ledger");
+            account.charge(80).expect("charge");
+            account
+                .settle_run("run-1", 80, usage(80), Some("model error".into()))
+                .expect("settle failed run");
+            assert_eq!(account.balance(), 200);
+            assert_eq!(account.snapshot().total_spent_tokens, 0);
+
+            // An unsettled reservation lowers the live balance only.
+            account.charge(50).expect("charge");
+            assert_eq!(account.snapshot().reserved_tokens, 50);
+            assert_eq!(account.balance(), 150);
+        }
+
+        let reloaded = CreditAccount::from_path(&path, 200).expect("reload ledger");
+        assert_eq!(reloaded.balance(), 200);
+        assert_eq!(reloaded.entries(None, None).len(), 3);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn torn_trailing_entry_is_ignored() {
+        let path = temp_ledger_path();
+        {
+            let mut account = CreditAccount::from_path(&path, 100).expect("create ledger");
+            account.top_up(25).expect("top up");
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":3,"recordedAtMs":1,"kind":"top_"#)
+            .unwrap();
+
+        let reloaded = CreditAccount::from_path(&path, 100).expect("reload ledger");
+        assert_eq!(reloaded.balance(), 125);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn appends_after_a_torn_entry_survive_a_reload() {
+        let path = temp_ledger_path();
+        {
+            let mut account = CreditAccount::from_path(&path, 100).expect("create ledger");
+            account.top_up(25).expect("top up");
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":3,"recordedAtMs":1,"kind":"top_"#)
+            .unwrap();
+
+        {
+            let mut account = CreditAccount::from_path(&path, 100).expect("reload torn ledger");
+            account.top_up(10).expect("top up after torn entry");
+        }
+        let reloaded = CreditAccount::from_path(&path, 100).expect("reload repaired ledger");
+        assert_eq!(reloaded.balance(), 135);
+        assert_eq!(reloaded.entries(None, None).len(), 3);
+
+        // An intact last entry that lost its newline is kept and terminated.
+        let raw = fs::read_to_string(&path).unwrap();
+        fs::write(&path, raw.trim_end()).unwrap();
+        {
+            let mut account = CreditAccount::from_path(&path, 100).expect("reload unterminated");
+            account.top_up(5).expect("top up after unterminated entry");
+        }
+        let reloaded = CreditAccount::from_path(&path, 100).expect("reload terminated ledger");
+        assert_eq!(reloaded.balance(), 140);
+        assert_eq!(reloaded.entries(None, None).len(), 4);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn monthly_statement_splits_opening_balance_and_aggregates_usage() {
+        let january = month_bounds_ms(2026, 1).unwrap();
+        let february = month_bounds_ms(2026, 2).unwrap();
+        assert_eq!(january.1, february.0);
+        assert_eq!(february.0, 1_769_904_000_000);
+
+        let mut account = CreditAccount::with_entries(None, Vec::new());
+        for (at, kind) in [
+            (january.0 + 1, CreditLedgerKind::Grant { tokens: 1_000 }),
+            (
+                february.0 + 10,
+                CreditLedgerKind::RunCharge {
+                    run_id: "run-1".into(),
+                    tokens: 100,
+                    usage: usage(100),
+                },
+            ),
+            (february.0 + 20, CreditLedgerKind::TopUp { tokens: 300 }),
+            (
+                february.0 + 30,
+                CreditLedgerKind::RunCharge {
+                    run_id: "run-2".into(),
+                    tokens: 40,
+                    usage: usage(40),
+                },
+            ),
+            (
+                february.0 + 40,
+                CreditLedgerKind::Refund {
+                    run_id: "run-2".into(),
+                    tokens: 40,
+                    reason: "failed, retry later".into(),
+                },
+            ),
+            (february.1 + 1, CreditLedgerKind::TopUp { tokens: 5 }),
+        ] {
+            let mut entry = account.append(kind).unwrap();
+            entry.recorded_at_ms = at;
+            *account.entries.last_mut().unwrap() = entry;
+        }
+
+        let statement = account.statement(2026, 2).expect("statement");
+        assert_eq!(statement.opening_balance, 1_000);
+        assert_eq!(statement.closing_balance, 1_200);
+        assert_eq!(statement.charged_tokens, 140);
+        assert_eq!(statement.refunded_tokens, 40);
+        assert_eq!(statement.topped_up_tokens, 300);
+        assert_eq!(statement.entries.len(), 4);
+        assert_eq!(statement.usage.len(), 1);
+        assert_eq!(statement.usage[0].total_tokens, 140);
+        assert_eq!(statement.usage[0].calls, 4);
+
+        let csv = account
+            .export_statement(2026, 2, CreditStatementFormat::Csv)
+            .expect("csv export");
+        assert!(csv.starts_with("seq,recorded_at_ms,kind"));
+        assert!(csv.contains("refund,run-2,40,1200,\"failed, retry later\""));
+        assert!(account.statement(2026, 13).is_err());
+    }
+}
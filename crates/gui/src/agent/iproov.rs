@@ -3,12 +3,17 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine as _;
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
-use p256::ecdsa::signature::Signer;
-use p256::ecdsa::{Signature, SigningKey};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
 use p256::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+use p256::EncodedPoint;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -16,6 +21,127 @@ use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use super::iproov_http::{HttpIdentityGateway, HttpMerchantCartService};
+use super::spent_mandates::SpentMandateStore;
+
+/// Base URL of an external identity gateway; unset uses the in-process mock.
+pub const IDENTITY_GATEWAY_URL_ENV: &str = "ADVATAR_IDENTITY_GATEWAY_URL";
+/// Expected `iss` of the external gateway's decision tokens; required with
+/// [`IDENTITY_GATEWAY_URL_ENV`].
+pub const IDENTITY_GATEWAY_ISSUER_ENV: &str = "ADVATAR_IDENTITY_GATEWAY_ISSUER";
+/// Expected `aud` of the external gateway's decision tokens; required with
+/// [`IDENTITY_GATEWAY_URL_ENV`].
+pub const IDENTITY_GATEWAY_AUDIENCE_ENV: &str = "ADVATAR_IDENTITY_GATEWAY_AUDIENCE";
+/// Base URL of an external merchant cart service; unset uses the mock.
+pub const MERCHANT_URL_ENV: &str = "ADVATAR_MERCHANT_URL";
+
+/// The presentation/decision gateway: asks the user to approve agent
+/// actions, issues decision tokens and signs cart mandates.
+#[async_trait]
+pub trait IdentityGateway: Send + Sync {
+    /// The JSON Web Key Set that verifies the gateway's signatures.
+    async fn jwks(&self) -> Result<Value>;
+
+    async fn create_presentation(
+        &self,
+        policy: String,
+        amount_cents: u64,
+        sku: Option<String>,
+        metadata: Value,
+    ) -> Result<PresentationInfo>;
+
+    async fn approve_presentation(
+        &self,
+        request_id: &str,
+        agent_id: &str,
+        audience: &str,
+    ) -> Result<DecisionToken>;
+
+    async fn await_decision(&self, request_id: &str) -> Result<Option<DecisionToken>>;
+
+    /// Verifies a decision token and returns its claims.
+    async fn introspect_decision(&self, token: &str) -> Result<Value>;
+
+    /// Asks the user to approve the merchant's `quote` and returns the
+    /// signed mandate.
+    async fn approve_cart(&self, quote: &CartQuote) -> Result<CartMandate>;
+
+    async fn fetch_mandate(&self, cart_id: &str) -> Result<Option<CartMandate>>;
+}
+
+/// The merchant side of the cart flow.
+#[async_trait]
+pub trait MerchantCartService: Send + Sync {
+    async fn quote_cart(
+        &self,
+        term: Option<String>,
+        sku: Option<String>,
+        quantity: Option<u32>,
+    ) -> Result<CartQuote>;
+
+    async fn start_cart(&self, cart_id: &str) -> Result<CartQuote>;
+
+    async fn place_order(
+        &self,
+        cart_id: &str,
+        mandate: Option<CartMandate>,
+        decision: Option<String>,
+    ) -> Result<OrderConfirmation>;
+}
+
+/// The gateway and merchant behind the `gateway.*` and `merchant.*` tools.
+#[derive(Clone)]
+pub struct IproovBackends {
+    pub gateway: Arc<dyn IdentityGateway>,
+    pub merchant: Arc<dyn MerchantCartService>,
+    /// Nonces of mandates already used by `merchant.place_order`.
+    pub spent_mandates: Arc<SpentMandateStore>,
+}
+
+impl IproovBackends {
+    /// Both sides served by one in-process [`IproovServices`], with spent
+    /// mandates kept in memory.
+    pub fn mock(threshold_cents: u64) -> Result<Self> {
+        let services = Arc::new(IproovServices::new(threshold_cents)?);
+        Ok(Self {
+            gateway: services.clone(),
+            merchant: services,
+            spent_mandates: Arc::new(SpentMandateStore::empty()),
+        })
+    }
+
+    /// HTTP clients for the sides configured through
+    /// [`IDENTITY_GATEWAY_URL_ENV`] and [`MERCHANT_URL_ENV`], the mock for
+    /// the rest. Spent mandates are persisted.
+    pub fn from_env(threshold_cents: u64) -> Result<Self> {
+        let env = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let mut backends = Self::mock(threshold_cents)?;
+        backends.spent_mandates = Arc::new(SpentMandateStore::load_default()?);
+        if let Some(url) = env(IDENTITY_GATEWAY_URL_ENV) {
+            let required = |name: &str| {
+                env(name)
+                    .ok_or_else(|| anyhow!("{name} must be set with {IDENTITY_GATEWAY_URL_ENV}"))
+            };
+            backends.gateway = Arc::new(HttpIdentityGateway::new(
+                &url,
+                required(IDENTITY_GATEWAY_ISSUER_ENV)?,
+                required(IDENTITY_GATEWAY_AUDIENCE_ENV)?,
+            )?);
+        }
+        if let Some(url) = env(MERCHANT_URL_ENV) {
+            backends.merchant = Arc::new(HttpMerchantCartService::new(&url)?);
+        }
+        Ok(backends)
+    }
+}
+
+/// In-process mock of both the identity gateway and the merchant, signing
+/// with a P-256 key generated at startup.
 #[derive(Clone)]
 pub struct IproovServices {
     inner: Arc<Inner>,
@@ -110,15 +236,74 @@ enum CartStatus {
     Rejected,
 }
 
+/// A gateway's approval of one quoted cart. The signature covers every other
+/// field, so the approved total, currency and merchant cannot be changed
+/// without invalidating it, and the nonce lets merchants refuse reuse.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CartMandate {
     pub cart_id: String,
     pub cart_hash: String,
+    pub merchant_id: String,
+    pub total_cents: u64,
+    pub currency: String,
+    pub nonce: String,
     pub approved_at: u64,
     pub approval_expires_at: u64,
+    /// Gateway key the mandate was signed with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
     pub signature: String,
 }
 
+impl CartMandate {
+    /// Canonical text the gateway signs.
+    pub fn signing_payload(&self) -> String {
+        canonicalize(&json!({
+            "cart_id": self.cart_id,
+            "cart_hash": self.cart_hash,
+            "merchant_id": self.merchant_id,
+            "total_cents": self.total_cents,
+            "currency": self.currency,
+            "nonce": self.nonce,
+            "approved_at": self.approved_at,
+            "approval_expires_at": self.approval_expires_at,
+            "kid": self.kid,
+        }))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        now_ts() >= self.approval_expires_at
+    }
+
+    /// Checks that the mandate approves exactly `quote` and has not expired.
+    /// The signature is checked separately with [`GatewayJwks::verify_mandate`].
+    pub fn check_quote(&self, quote: &CartQuote) -> Result<()> {
+        if self.cart_id != quote.cart_id || self.cart_hash != quote.cart_hash {
+            return Err(anyhow!("mandate cart hash mismatch"));
+        }
+        if self.merchant_id != quote.merchant_id {
+            return Err(anyhow!(
+                "mandate is for merchant {}, not {}",
+                self.merchant_id,
+                quote.merchant_id
+            ));
+        }
+        if self.total_cents != quote.total_cents || self.currency != quote.currency {
+            return Err(anyhow!(
+                "mandate approves {} {} but the cart totals {} {}",
+                self.total_cents,
+                self.currency,
+                quote.total_cents,
+                quote.currency
+            ));
+        }
+        if self.is_expired() {
+            return Err(anyhow!("mandate expired"));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct OrderConfirmation {
     pub order_id: String,
@@ -140,11 +325,20 @@ impl IproovServices {
         })
     }
 
-    pub async fn jwks(&self) -> Value {
-        self.inner.state.read().await.keys.jwk.clone()
+    fn sign_decision(&self, keys: &GatewayKeys, claims: &DecisionClaims) -> Result<String> {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(keys.kid.clone());
+        encode(&header, claims, &keys.encoding).map_err(|err| anyhow!(err))
+    }
+}
+
+#[async_trait]
+impl IdentityGateway for IproovServices {
+    async fn jwks(&self) -> Result<Value> {
+        Ok(self.inner.state.read().await.keys.jwk.clone())
     }
 
-    pub async fn create_presentation(
+    async fn create_presentation(
         &self,
         policy: String,
         amount_cents: u64,
@@ -185,7 +379,7 @@ impl IproovServices {
         Ok(info)
     }
 
-    pub async fn approve_presentation(
+    async fn approve_presentation(
         &self,
         request_id: &str,
         agent_id: &str,
@@ -214,7 +408,7 @@ impl IproovServices {
         Ok(decision)
     }
 
-    pub async fn await_decision(&self, request_id: &str) -> Result<Option<DecisionToken>> {
+    async fn await_decision(&self, request_id: &str) -> Result<Option<DecisionToken>> {
         let state = self.inner.state.read().await;
         Ok(state
             .presentations
@@ -222,7 +416,7 @@ impl IproovServices {
             .and_then(|entry| entry.decision.clone()))
     }
 
-    pub async fn introspect_decision(&self, token: &str) -> Result<Value> {
+    async fn introspect_decision(&self, token: &str) -> Result<Value> {
         let state = self.inner.state.read().await;
         let mut validation = Validation::new(Algorithm::ES256);
         validation.set_audience::<&str>(&[]);
@@ -234,7 +428,47 @@ impl IproovServices {
         }))
     }
 
-    pub async fn quote_cart(
+    async fn approve_cart(&self, quote: &CartQuote) -> Result<CartMandate> {
+        let mut state = self.inner.state.write().await;
+        let now = now_ts();
+        let keys = state.keys.clone();
+        let session = state
+            .carts
+            .get_mut(&quote.cart_id)
+            .ok_or_else(|| anyhow!("cart not found"))?;
+        if session.quote.cart_hash != quote.cart_hash {
+            return Err(anyhow!("cart changed since it was quoted"));
+        }
+        let mut mandate = CartMandate {
+            cart_id: session.quote.cart_id.clone(),
+            cart_hash: session.quote.cart_hash.clone(),
+            merchant_id: session.quote.merchant_id.clone(),
+            total_cents: session.quote.total_cents,
+            currency: session.quote.currency.clone(),
+            nonce: Uuid::new_v4().to_string(),
+            approved_at: now,
+            approval_expires_at: (now + 300).min(session.quote.expires_at),
+            kid: Some(keys.kid.clone()),
+            signature: String::new(),
+        };
+        mandate.signature = sign_text(&keys, &mandate.signing_payload())?;
+        session.mandate = Some(mandate.clone());
+        session.status = CartStatus::Approved;
+        Ok(mandate)
+    }
+
+    async fn fetch_mandate(&self, cart_id: &str) -> Result<Option<CartMandate>> {
+        let state = self.inner.state.read().await;
+        Ok(state
+            .carts
+            .get(cart_id)
+            .and_then(|session| session.mandate.clone()))
+    }
+}
+
+#[async_trait]
+impl MerchantCartService for IproovServices {
+    async fn quote_cart(
         &self,
         term: Option<String>,
         sku: Option<String>,
@@ -287,7 +521,7 @@ impl IproovServices {
         Ok(quote)
     }
 
-    pub async fn start_cart(&self, cart_id: &str) -> Result<CartQuote> {
+    async fn start_cart(&self, cart_id: &str) -> Result<CartQuote> {
         let state = self.inner.state.read().await;
         let session = state
             .carts
@@ -296,36 +530,7 @@ impl IproovServices {
         Ok(session.quote.clone())
     }
 
-    pub async fn approve_cart(&self, cart_id: &str) -> Result<CartMandate> {
-        let mut state = self.inner.state.write().await;
-        let now = now_ts();
-        let keys = state.keys.clone();
-        let session = state
-            .carts
-            .get_mut(cart_id)
-            .ok_or_else(|| anyhow!("cart not found"))?;
-        let signature = sign_text(&keys, &session.quote.cart_hash)?;
-        let mandate = CartMandate {
-            cart_id: session.quote.cart_id.clone(),
-            cart_hash: session.quote.cart_hash.clone(),
-            approved_at: now,
-            approval_expires_at: now + 300,
-            signature,
-        };
-        session.mandate = Some(mandate.clone());
-        session.status = CartStatus::Approved;
-        Ok(mandate)
-    }
-
-    pub async fn fetch_mandate(&self, cart_id: &str) -> Result<Option<CartMandate>> {
-        let state = self.inner.state.read().await;
-        Ok(state
-            .carts
-            .get(cart_id)
-            .and_then(|session| session.mandate.clone()))
-    }
-
-    pub async fn place_order(
+    async fn place_order(
         &self,
         cart_id: &str,
         mandate: Option<CartMandate>,
@@ -337,11 +542,12 @@ impl IproovServices {
             .get(cart_id)
             .ok_or_else(|| anyhow!("cart not found"))?;
 
-        if session.quote.user_signature_required {
-            let mandate = mandate.ok_or_else(|| anyhow!("mandate required"))?;
-            if mandate.cart_hash != session.quote.cart_hash {
-                return Err(anyhow!("mandate cart hash mismatch"));
+        match mandate {
+            Some(mandate) => mandate.check_quote(&session.quote)?,
+            None if session.quote.user_signature_required => {
+                return Err(anyhow!("mandate required"));
             }
+            None => {}
         }
 
         if let Some(token) = decision {
@@ -359,11 +565,126 @@ impl IproovServices {
             pickup_code: random_code(4),
         })
     }
+}
 
-    fn sign_decision(&self, keys: &GatewayKeys, claims: &DecisionClaims) -> Result<String> {
-        let mut header = Header::new(Algorithm::ES256);
-        header.kid = Some(keys.kid.clone());
-        encode(&header, claims, &keys.encoding).map_err(|err| anyhow!(err))
+/// Public keys of a gateway, parsed from its JWKS. Only P-256 keys are used.
+#[derive(Clone)]
+pub struct GatewayJwks {
+    keys: Vec<GatewayPublicKey>,
+}
+
+#[derive(Clone)]
+struct GatewayPublicKey {
+    kid: Option<String>,
+    decoding: DecodingKey,
+    verifying: VerifyingKey,
+}
+
+impl GatewayJwks {
+    pub fn from_value(jwks: &Value) -> Result<Self> {
+        let keys = jwks
+            .get("keys")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("JWKS has no `keys` array"))?
+            .iter()
+            .filter(|jwk| {
+                jwk.get("kty").and_then(Value::as_str) == Some("EC")
+                    && jwk.get("crv").and_then(Value::as_str) == Some("P-256")
+            })
+            .map(GatewayPublicKey::from_jwk)
+            .collect::<Result<Vec<_>>>()?;
+        if keys.is_empty() {
+            return Err(anyhow!("JWKS has no P-256 keys"));
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn has_key(&self, kid: &str) -> bool {
+        self.keys.iter().any(|key| key.kid.as_deref() == Some(kid))
+    }
+
+    /// Verifies an ES256 decision token with the key named by its `kid` or,
+    /// without one, each of the gateway's keys in turn, checking expiry,
+    /// issuer and audience. Returns the claims.
+    pub fn verify_decision(&self, token: &str, issuer: &str, audience: &str) -> Result<Value> {
+        let header =
+            decode_header(token).map_err(|err| anyhow!("decision verify failed: {err}"))?;
+        let mut validation = Validation::new(Algorithm::ES256);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        let mut last_error = None;
+        for key in self
+            .keys
+            .iter()
+            .filter(|key| header.kid.is_none() || key.kid == header.kid)
+        {
+            match decode::<Value>(token, &key.decoding, &validation) {
+                Ok(decoded) => return Ok(decoded.claims),
+                // Claims are only checked once the signature matches, so any
+                // other failure is final.
+                Err(err) if *err.kind() != ErrorKind::InvalidSignature => {
+                    return Err(anyhow!("decision verify failed: {err}"));
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(match last_error {
+            Some(err) => anyhow!("decision verify failed: {err}"),
+            None => anyhow!("decision signed with an unknown key"),
+        })
+    }
+
+    /// Checks the mandate's signature over its signing payload, with the key
+    /// named by its `kid` or, without one, any of the gateway's keys.
+    pub fn verify_mandate(&self, mandate: &CartMandate) -> Result<()> {
+        let signature = URL_SAFE_NO_PAD
+            .decode(&mandate.signature)
+            .ok()
+            .and_then(|der| Signature::from_der(&der).ok())
+            .ok_or_else(|| anyhow!("mandate signature is malformed"))?;
+        let payload = mandate.signing_payload();
+        if self
+            .keys
+            .iter()
+            .filter(|key| mandate.kid.is_none() || key.kid == mandate.kid)
+            .any(|key| key.verifying.verify(payload.as_bytes(), &signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(anyhow!("mandate signature does not match the gateway keys"))
+        }
+    }
+}
+
+impl GatewayPublicKey {
+    fn from_jwk(jwk: &Value) -> Result<Self> {
+        let coordinate = |name: &str| {
+            jwk.get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("P-256 JWK is missing `{name}`"))
+        };
+        let (x, y) = (coordinate("x")?, coordinate("y")?);
+        let decode_coordinate = |value: &str| {
+            URL_SAFE_NO_PAD
+                .decode(value)
+                .ok()
+                .filter(|bytes| bytes.len() == 32)
+                .ok_or_else(|| anyhow!("P-256 JWK has a malformed coordinate"))
+        };
+        let point = EncodedPoint::from_affine_coordinates(
+            decode_coordinate(x)?.as_slice().into(),
+            decode_coordinate(y)?.as_slice().into(),
+            false,
+        );
+        let verifying = VerifyingKey::from_encoded_point(&point)
+            .map_err(|err| anyhow!("P-256 JWK is not a valid key: {err}"))?;
+        let decoding = DecodingKey::from_ec_components(x, y)
+            .map_err(|err| anyhow!("P-256 JWK is not a valid key: {err}"))?;
+        Ok(Self {
+            kid: jwk.get("kid").and_then(Value::as_str).map(str::to_string),
+            decoding,
+            verifying,
+        })
     }
 }
 
@@ -596,7 +917,7 @@ mod tests {
         let session = services.start_cart(&quote.cart_id).await?;
         assert_eq!(session.cart_id, quote.cart_id);
 
-        let mandate = services.approve_cart(&quote.cart_id).await?;
+        let mandate = services.approve_cart(&session).await?;
         assert_eq!(mandate.cart_id, quote.cart_id);
 
         let fetched = services.fetch_mandate(&quote.cart_id).await?;
@@ -611,6 +932,113 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn mandates_and_decisions_verify_against_the_jwks() -> Result<()> {
+        let services = IproovServices::new(10_000)?;
+        let jwks = GatewayJwks::from_value(&services.jwks().await?)?;
+
+        let quote = services
+            .quote_cart(Some("pixel".into()), None, None)
+            .await?;
+        let mut mandate = services.approve_cart(&quote).await?;
+        jwks.verify_mandate(&mandate)?;
+        let mut tampered = mandate.clone();
+        tampered.total_cents -= 1;
+        assert!(jwks.verify_mandate(&tampered).is_err());
+        mandate.cart_hash = hash_base64("another cart");
+        assert!(jwks.verify_mandate(&mandate).is_err());
+
+        let info = services
+            .create_presentation("policy-1".into(), 4_500, None, Value::Null)
+            .await?;
+        let decision = services
+            .approve_presentation(&info.request_id, "agent-9", "vendor.example")
+            .await?;
+        let issuer = "https://gateway.advatar.local";
+        let claims = jwks.verify_decision(&decision.decision_jwt, issuer, "vendor.example")?;
+        assert_eq!(claims["request_id"], info.request_id);
+        assert!(jwks
+            .verify_decision(
+                &decision.decision_jwt,
+                "https://elsewhere.example",
+                "vendor.example"
+            )
+            .is_err());
+        assert!(jwks
+            .verify_decision(&decision.decision_jwt, issuer, "elsewhere.example")
+            .is_err());
+
+        let other_services = IproovServices::new(0)?;
+        let other = GatewayJwks::from_value(&other_services.jwks().await?)?;
+        assert!(other
+            .verify_decision(&decision.decision_jwt, issuer, "vendor.example")
+            .is_err());
+
+        // Without a `kid` every key is tried, not only the first.
+        let state = services.inner.state.read().await;
+        let mut validation = Validation::new(Algorithm::ES256);
+        validation.set_audience(&["vendor.example"]);
+        let claims =
+            decode::<Value>(&decision.decision_jwt, &state.keys.decoding, &validation)?.claims;
+        let unnamed = encode(
+            &Header::new(Algorithm::ES256),
+            &claims,
+            &state.keys.encoding,
+        )?;
+        let mut both = other_services.jwks().await?;
+        both["keys"]
+            .as_array_mut()
+            .unwrap()
+            .extend(state.keys.jwk["keys"].as_array().unwrap().iter().cloned());
+        let both = GatewayJwks::from_value(&both)?;
+        assert_eq!(
+            both.verify_decision(&unnamed, issuer, "vendor.example")?["request_id"],
+            info.request_id
+        );
+        assert!(other
+            .verify_decision(&unnamed, issuer, "vendor.example")
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mandates_are_bound_to_the_quoted_amount_and_expire() -> Result<()> {
+        let services = IproovServices::new(10_000)?;
+        let quote = services
+            .quote_cart(None, Some("MBA13-2025".into()), Some(1))
+            .await?;
+        let mandate = services.approve_cart(&quote).await?;
+        assert_eq!(mandate.total_cents, quote.total_cents);
+        assert_eq!(mandate.currency, quote.currency);
+        assert_eq!(mandate.merchant_id, quote.merchant_id);
+        mandate.check_quote(&quote)?;
+
+        let mut other_amount = quote.clone();
+        other_amount.total_cents += 100;
+        let err = mandate
+            .check_quote(&other_amount)
+            .expect_err("mandate is bound to the approved total");
+        assert!(err.to_string().contains("approves"), "{err}");
+
+        let mut other_merchant = quote.clone();
+        other_merchant.merchant_id = "merchant.example:elsewhere".into();
+        assert!(mandate.check_quote(&other_merchant).is_err());
+
+        let other = services.approve_cart(&quote).await?;
+        assert_ne!(other.nonce, mandate.nonce);
+
+        let mut expired = mandate.clone();
+        expired.approval_expires_at = now_ts() - 1;
+        match services
+            .place_order(&quote.cart_id, Some(expired), None)
+            .await
+        {
+            Ok(_) => panic!("expired mandates are refused"),
+            Err(err) => assert!(err.to_string().contains("expired")),
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn placing_high_value_cart_without_mandate_fails() -> Result<()> {
         let services = IproovServices::new(10_000)?;
@@ -0,0 +1,619 @@
+//! HTTP clients for an external identity gateway and merchant cart service.
+//!
+//! The gateway publishes its keys at `/.well-known/jwks.json`; decision
+//! tokens and cart mandates it returns are verified against them before the
+//! agent sees them. Paths are relative to the configured base URL:
+//!
+//! | Gateway                              | Merchant             |
+//! |--------------------------------------|----------------------|
+//! | `POST presentations`                 | `POST carts`         |
+//! | `POST presentations/{id}/approve`    | `GET carts/{id}`     |
+//! | `GET presentations/{id}/decision`    | `POST orders`        |
+//! | `POST carts/{id}/approve`            |                      |
+//! | `GET carts/{id}/mandate`             |                      |
+//!
+//! A `404` from a lookup means "not yet" rather than an error.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+use url::Url;
+
+use super::iproov::{
+    CartMandate, CartQuote, DecisionToken, GatewayJwks, IdentityGateway, MerchantCartService,
+    OrderConfirmation, PresentationInfo,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long fetched gateway keys are trusted before they are fetched again.
+/// A token naming an unknown key refetches them early.
+const JWKS_TTL: Duration = Duration::from_secs(5 * 60);
+/// Minimum age of the cached keys before an unknown key or a failed
+/// signature refetches them, so tokens naming made-up keys cannot turn every
+/// verification into a request to the gateway.
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct HttpIdentityGateway {
+    client: Client,
+    base_url: Url,
+    issuer: String,
+    audience: String,
+    jwks: RwLock<Option<CachedJwks>>,
+}
+
+struct CachedJwks {
+    fetched_at: Instant,
+    raw: Value,
+    keys: Arc<GatewayJwks>,
+}
+
+impl HttpIdentityGateway {
+    /// Every decision token must carry `issuer` as its `iss` and `audience`
+    /// as its `aud`.
+    pub fn new(base_url: &str, issuer: String, audience: String) -> Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            base_url: parse_base_url(base_url)?,
+            issuer,
+            audience,
+            jwks: RwLock::new(None),
+        })
+    }
+
+    /// The gateway's keys, refetched when stale or when `kid` is not among
+    /// them and they were not fetched within [`JWKS_REFETCH_INTERVAL`].
+    async fn keys(&self, kid: Option<&str>) -> Result<Arc<GatewayJwks>> {
+        if let Some(cached) = self.jwks.read().await.as_ref() {
+            let age = cached.fetched_at.elapsed();
+            let known = kid.is_none_or(|kid| cached.keys.has_key(kid));
+            if age < JWKS_TTL && (known || age < JWKS_REFETCH_INTERVAL) {
+                return Ok(cached.keys.clone());
+            }
+        }
+        self.refresh_jwks().await
+    }
+
+    /// Refetched keys after a signature failed to verify, or `None` when the
+    /// cached ones are younger than [`JWKS_REFETCH_INTERVAL`].
+    async fn refetched_keys(&self) -> Result<Option<Arc<GatewayJwks>>> {
+        let recent = self
+            .jwks
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|cached| cached.fetched_at.elapsed() < JWKS_REFETCH_INTERVAL);
+        if recent {
+            return Ok(None);
+        }
+        self.refresh_jwks().await.map(Some)
+    }
+
+    async fn refresh_jwks(&self) -> Result<Arc<GatewayJwks>> {
+        let raw: Value = send(
+            self.client
+                .get(endpoint(&self.base_url, ".well-known/jwks.json")?),
+            "gateway JWKS",
+        )
+        .await?
+        .ok_or_else(|| anyhow!("gateway JWKS not found"))?;
+        let keys = Arc::new(GatewayJwks::from_value(&raw).context("gateway JWKS")?);
+        *self.jwks.write().await = Some(CachedJwks {
+            fetched_at: Instant::now(),
+            raw,
+            keys: keys.clone(),
+        });
+        Ok(keys)
+    }
+
+    async fn verified_decision(&self, decision: DecisionToken) -> Result<DecisionToken> {
+        self.introspect_decision(&decision.decision_jwt).await?;
+        Ok(decision)
+    }
+
+    async fn verified_mandate(&self, mandate: CartMandate) -> Result<CartMandate> {
+        if let Err(err) = self
+            .keys(mandate.kid.as_deref())
+            .await?
+            .verify_mandate(&mandate)
+        {
+            // The gateway may have rotated its key since the last fetch.
+            match self.refetched_keys().await? {
+                Some(keys) => keys.verify_mandate(&mandate)?,
+                None => return Err(err),
+            }
+        }
+        Ok(mandate)
+    }
+}
+
+#[async_trait]
+impl IdentityGateway for HttpIdentityGateway {
+    async fn jwks(&self) -> Result<Value> {
+        self.keys(None).await?;
+        let cache = self.jwks.read().await;
+        Ok(cache
+            .as_ref()
+            .map(|cached| cached.raw.clone())
+            .unwrap_or_default())
+    }
+
+    async fn create_presentation(
+        &self,
+        policy: String,
+        amount_cents: u64,
+        sku: Option<String>,
+        metadata: Value,
+    ) -> Result<PresentationInfo> {
+        let request = self
+            .client
+            .post(endpoint(&self.base_url, "presentations")?)
+            .json(&json!({
+                "policy": policy,
+                "amount_cents": amount_cents,
+                "sku": sku,
+                "metadata": metadata,
+            }));
+        send(request, "gateway presentation")
+            .await?
+            .ok_or_else(|| anyhow!("gateway presentation endpoint not found"))
+    }
+
+    async fn approve_presentation(
+        &self,
+        request_id: &str,
+        agent_id: &str,
+        audience: &str,
+    ) -> Result<DecisionToken> {
+        let path = format!("presentations/{}/approve", urlencoding::encode(request_id));
+        let request = self
+            .client
+            .post(endpoint(&self.base_url, &path)?)
+            .json(&json!({ "agent_id": agent_id, "audience": audience }));
+        let decision = send(request, "gateway approval")
+            .await?
+            .ok_or_else(|| anyhow!("presentation not found"))?;
+        self.verified_decision(decision).await
+    }
+
+    async fn await_decision(&self, request_id: &str) -> Result<Option<DecisionToken>> {
+        let path = format!("presentations/{}/decision", urlencoding::encode(request_id));
+        let decision: Option<Option<DecisionToken>> = send(
+            self.client.get(endpoint(&self.base_url, &path)?),
+            "gateway decision",
+        )
+        .await?;
+        match decision.flatten() {
+            Some(decision) => Ok(Some(self.verified_decision(decision).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn introspect_decision(&self, token: &str) -> Result<Value> {
+        let kid = jsonwebtoken::decode_header(token)
+            .map_err(|err| anyhow!("decision verify failed: {err}"))?
+            .kid;
+        let claims = self.keys(kid.as_deref()).await?.verify_decision(
+            token,
+            &self.issuer,
+            &self.audience,
+        )?;
+        Ok(json!({ "claims": claims }))
+    }
+
+    async fn approve_cart(&self, quote: &CartQuote) -> Result<CartMandate> {
+        let path = format!("carts/{}/approve", urlencoding::encode(&quote.cart_id));
+        let request = self
+            .client
+            .post(endpoint(&self.base_url, &path)?)
+            .json(&json!({ "quote": quote }));
+        let mandate: CartMandate = send(request, "gateway cart approval")
+            .await?
+            .ok_or_else(|| anyhow!("cart not found"))?;
+        mandate
+            .check_quote(quote)
+            .context("gateway mandate does not match the quoted cart")?;
+        self.verified_mandate(mandate).await
+    }
+
+    async fn fetch_mandate(&self, cart_id: &str) -> Result<Option<CartMandate>> {
+        let path = format!("carts/{}/mandate", urlencoding::encode(cart_id));
+        let mandate: Option<Option<CartMandate>> = send(
+            self.client.get(endpoint(&self.base_url, &path)?),
+            "gateway mandate",
+        )
+        .await?;
+        match mandate.flatten() {
+            Some(mandate) => Ok(Some(self.verified_mandate(mandate).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+pub struct HttpMerchantCartService {
+    client: Client,
+    base_url: Url,
+}
+
+impl HttpMerchantCartService {
+    pub fn new(base_url: &str) -> Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            base_url: parse_base_url(base_url)?,
+        })
+    }
+}
+
+#[async_trait]
+impl MerchantCartService for HttpMerchantCartService {
+    async fn quote_cart(
+        &self,
+        term: Option<String>,
+        sku: Option<String>,
+        quantity: Option<u32>,
+    ) -> Result<CartQuote> {
+        let request = self
+            .client
+            .post(endpoint(&self.base_url, "carts")?)
+            .json(&json!({ "term": term, "sku": sku, "quantity": quantity }));
+        send(request, "merchant quote")
+            .await?
+            .ok_or_else(|| anyhow!("merchant cart endpoint not found"))
+    }
+
+    async fn start_cart(&self, cart_id: &str) -> Result<CartQuote> {
+        let path = format!("carts/{}", urlencoding::encode(cart_id));
+        send(
+            self.client.get(endpoint(&self.base_url, &path)?),
+            "merchant cart",
+        )
+        .await?
+        .ok_or_else(|| anyhow!("cart not found"))
+    }
+
+    async fn place_order(
+        &self,
+        cart_id: &str,
+        mandate: Option<CartMandate>,
+        decision: Option<String>,
+    ) -> Result<OrderConfirmation> {
+        let request = self
+            .client
+            .post(endpoint(&self.base_url, "orders")?)
+            .json(&json!({
+                "cart_id": cart_id,
+                "mandate": mandate,
+                "decision_jwt": decision,
+            }));
+        send(request, "merchant order")
+            .await?
+            .ok_or_else(|| anyhow!("cart not found"))
+    }
+}
+
+fn http_client() -> Result<Client> {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("building gateway HTTP client")
+}
+
+fn parse_base_url(base_url: &str) -> Result<Url> {
+    let mut url =
+        Url::parse(base_url.trim()).with_context(|| format!("invalid base URL `{base_url}`"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("base URL `{base_url}` must be http or https"));
+    }
+    // Without a trailing slash `join` would replace the last path segment.
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+fn endpoint(base_url: &Url, path: &str) -> Result<Url> {
+    base_url
+        .join(path)
+        .with_context(|| format!("invalid endpoint `{path}`"))
+}
+
+/// Sends `request` and parses the JSON response; `Ok(None)` on `404`.
+async fn send<T: DeserializeOwned>(request: RequestBuilder, what: &str) -> Result<Option<T>> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("{what} request failed"))?;
+    let status = response.status();
+    if status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let body = body.trim();
+        return Err(if body.is_empty() {
+            anyhow!("{what} returned {status}")
+        } else {
+            anyhow!("{what} returned {status}: {body}")
+        });
+    }
+    response
+        .json::<T>()
+        .await
+        .map(Some)
+        .with_context(|| format!("{what} returned an invalid response"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::iproov::IproovServices;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine as _;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const ISSUER: &str = "https://gateway.advatar.local";
+
+    /// Serves the gateway and merchant endpoints from an in-process
+    /// [`IproovServices`]. With `forge_mandates` every mandate it returns
+    /// carries a corrupted signature.
+    async fn spawn_stand_in(services: Arc<IproovServices>, forge_mandates: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let services = services.clone();
+                tokio::spawn(serve(socket, services, forge_mandates));
+            }
+        });
+        format!("http://{address}/api")
+    }
+
+    async fn serve(mut socket: TcpStream, services: Arc<IproovServices>, forge_mandates: bool) {
+        let mut raw = Vec::new();
+        let mut buffer = [0_u8; 4096];
+        let (head, body) = loop {
+            let read = socket.read(&mut buffer).await.unwrap_or(0);
+            if read == 0 {
+                return;
+            }
+            raw.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&raw).to_string();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if body.len() >= length {
+                break (head.to_string(), body.to_string());
+            }
+        };
+        let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+        let body: Value = serde_json::from_str(&body).unwrap_or_default();
+
+        let response = route(&services, method, path, &body)
+            .await
+            .map(|mut value| {
+                if forge_mandates && value.get("cart_hash").is_some() {
+                    value["signature"] = json!("MEQCIA");
+                }
+                value
+            });
+        let (status, body) = match response {
+            Ok(value) => ("200 OK", value.to_string()),
+            Err(err) if err.to_string().contains("not found") => ("404 Not Found", String::new()),
+            Err(err) => ("422 Unprocessable Entity", err.to_string()),
+        };
+        let reply = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = socket.write_all(reply.as_bytes()).await;
+        let _ = socket.shutdown().await;
+    }
+
+    async fn route(
+        services: &IproovServices,
+        method: &str,
+        path: &str,
+        body: &Value,
+    ) -> Result<Value> {
+        let text = |name: &str| body[name].as_str().map(str::to_string);
+        let segments: Vec<&str> = path.trim_start_matches("/api/").split('/').collect();
+        Ok(match (method, segments.as_slice()) {
+            ("GET", [".well-known", "jwks.json"]) => services.jwks().await?,
+            ("POST", ["presentations"]) => json!(
+                services
+                    .create_presentation(
+                        text("policy").unwrap_or_default(),
+                        body["amount_cents"].as_u64().unwrap_or_default(),
+                        text("sku"),
+                        body["metadata"].clone(),
+                    )
+                    .await?
+            ),
+            ("POST", ["presentations", id, "approve"]) => json!(
+                services
+                    .approve_presentation(
+                        id,
+                        &text("agent_id").unwrap_or_default(),
+                        &text("audience").unwrap_or_default(),
+                    )
+                    .await?
+            ),
+            ("GET", ["presentations", id, "decision"]) => {
+                json!(services.await_decision(id).await?)
+            }
+            ("POST", ["carts", _, "approve"]) => {
+                let quote: CartQuote = serde_json::from_value(body["quote"].clone())?;
+                json!(services.approve_cart(&quote).await?)
+            }
+            ("GET", ["carts", id, "mandate"]) => json!(services.fetch_mandate(id).await?),
+            ("POST", ["carts"]) => json!(
+                services
+                    .quote_cart(
+                        text("term"),
+                        text("sku"),
+                        body["quantity"].as_u64().map(|quantity| quantity as u32),
+                    )
+                    .await?
+            ),
+            ("GET", ["carts", id]) => json!(services.start_cart(id).await?),
+            ("POST", ["orders"]) => json!(
+                services
+                    .place_order(
+                        &text("cart_id").unwrap_or_default(),
+                        serde_json::from_value(body["mandate"].clone())?,
+                        text("decision_jwt"),
+                    )
+                    .await?
+            ),
+            _ => return Err(anyhow!("route not found")),
+        })
+    }
+
+    #[tokio::test]
+    async fn cart_and_decision_flows_work_over_http() -> Result<()> {
+        let base_url = spawn_stand_in(Arc::new(IproovServices::new(50_000)?), false).await;
+        let gateway = HttpIdentityGateway::new(&base_url, ISSUER.into(), "vendor.example".into())?;
+        let merchant = HttpMerchantCartService::new(&base_url)?;
+
+        let quote = merchant
+            .quote_cart(None, Some("MBA13-2025".into()), Some(1))
+            .await?;
+        assert!(quote.user_signature_required);
+        assert_eq!(
+            merchant.start_cart(&quote.cart_id).await?.cart_hash,
+            quote.cart_hash
+        );
+        assert!(gateway.fetch_mandate(&quote.cart_id).await?.is_none());
+
+        let mandate = gateway.approve_cart(&quote).await?;
+        assert_eq!(mandate.cart_hash, quote.cart_hash);
+        assert!(gateway.fetch_mandate(&quote.cart_id).await?.is_some());
+        let err = merchant
+            .place_order(&quote.cart_id, None, None)
+            .await
+            .err()
+            .expect("mandate is required");
+        assert!(err.to_string().contains("mandate required"), "{err}");
+        let confirmation = merchant
+            .place_order(&quote.cart_id, Some(mandate), None)
+            .await?;
+        assert!(confirmation.order_id.starts_with("order-"));
+
+        let info = gateway
+            .create_presentation("policy-1".into(), 4_500, None, Value::Null)
+            .await?;
+        assert!(gateway.await_decision(&info.request_id).await?.is_none());
+        let decision = gateway
+            .approve_presentation(&info.request_id, "agent-9", "vendor.example")
+            .await?;
+        let introspected = gateway.introspect_decision(&decision.decision_jwt).await?;
+        assert_eq!(introspected["claims"]["sub"], "agent-9");
+        assert!(gateway.await_decision(&info.request_id).await?.is_some());
+        assert!(gateway.jwks().await?["keys"].is_array());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn forged_mandates_and_foreign_issuers_are_rejected() -> Result<()> {
+        let services = Arc::new(IproovServices::new(0)?);
+        let base_url = spawn_stand_in(services.clone(), true).await;
+        let gateway = HttpIdentityGateway::new(&base_url, ISSUER.into(), "vendor.example".into())?;
+        let quote = services.quote_cart(None, None, None).await?;
+        let err = gateway
+            .approve_cart(&quote)
+            .await
+            .err()
+            .expect("forged mandate is rejected");
+        assert!(err.to_string().contains("mandate signature"), "{err}");
+
+        let base_url = spawn_stand_in(services.clone(), false).await;
+        let gateway = HttpIdentityGateway::new(
+            &base_url,
+            "https://other.example".into(),
+            "vendor.example".into(),
+        )?;
+        let info = services
+            .create_presentation("policy-1".into(), 0, None, Value::Null)
+            .await?;
+        let err = gateway
+            .approve_presentation(&info.request_id, "agent-9", "vendor.example")
+            .await
+            .err()
+            .expect("issuer is checked");
+        assert!(err.to_string().contains("InvalidIssuer"), "{err}");
+
+        let gateway = HttpIdentityGateway::new(&base_url, ISSUER.into(), "vendor.example".into())?;
+        let info = services
+            .create_presentation("policy-1".into(), 0, None, Value::Null)
+            .await?;
+        let err = gateway
+            .approve_presentation(&info.request_id, "agent-9", "elsewhere.example")
+            .await
+            .err()
+            .expect("audience is checked");
+        assert!(err.to_string().contains("InvalidAudience"), "{err}");
+        Ok(())
+    }
+
+    async fn jwks_fetched_at(gateway: &HttpIdentityGateway) -> Option<Instant> {
+        gateway
+            .jwks
+            .read()
+            .await
+            .as_ref()
+            .map(|cached| cached.fetched_at)
+    }
+
+    #[tokio::test]
+    async fn unknown_keys_refetch_the_jwks_at_most_once_per_interval() -> Result<()> {
+        let services = Arc::new(IproovServices::new(0)?);
+        let base_url = spawn_stand_in(services.clone(), false).await;
+        let gateway = HttpIdentityGateway::new(&base_url, ISSUER.into(), "vendor.example".into())?;
+        let info = services
+            .create_presentation("policy-1".into(), 0, None, Value::Null)
+            .await?;
+        let decision = services
+            .approve_presentation(&info.request_id, "agent-9", "vendor.example")
+            .await?;
+        gateway.introspect_decision(&decision.decision_jwt).await?;
+        let fetched_at = jwks_fetched_at(&gateway).await;
+        assert!(fetched_at.is_some());
+
+        let (_, rest) = decision.decision_jwt.split_once('.').unwrap();
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","typ":"JWT","kid":"rotated"}"#);
+        let unknown = format!("{header}.{rest}");
+        for _ in 0..3 {
+            let err = gateway
+                .introspect_decision(&unknown)
+                .await
+                .expect_err("unknown key is rejected");
+            assert!(err.to_string().contains("unknown key"), "{err}");
+        }
+        assert_eq!(jwks_fetched_at(&gateway).await, fetched_at);
+
+        if let Some(cached) = gateway.jwks.write().await.as_mut() {
+            cached.fetched_at -= JWKS_REFETCH_INTERVAL;
+        }
+        let aged = jwks_fetched_at(&gateway).await;
+        assert!(gateway.introspect_decision(&unknown).await.is_err());
+        assert_ne!(jwks_fetched_at(&gateway).await, aged);
+        Ok(())
+    }
+}
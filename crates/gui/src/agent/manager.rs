@@ -1,41 +1,67 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 use afm_node::{AfmNodeHandle, AgentRuntimeAfmExt};
 use agent_core::{
-    AgentRuntime, AgentRuntimeResult, CapabilityKind, DomExecutionResult, DomExecutor, LedgerEntry,
+    AgentRuntime, AgentRuntimeResult, ApprovalHandler, CapabilityKind, DomExecutionResult,
+    DomExecutor, LedgerEntry,
 };
 use ai_agent::{
     AgentCancellationCheck, AgentConfig, AgentEvent, AgentEventCallback, AgentResult,
     FoundationModelOptions, LanguageModelClient, LanguageModelResponse, McpTool,
-    McpToolDescription,
+    McpToolDescription, PlanStep,
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use llm_router::{LlmRouter, RoutingPolicy};
+use llm_router::{LlmRouter, Provider, RoutingPolicy};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Wry};
 use tokio::sync::Mutex as AsyncMutex;
+use uuid::Uuid;
 
 use super::approvals::{ApprovalBroker, GuiApprovalHandler};
-use super::credits::{CreditAccount, CreditSnapshot};
-use super::iproov::IproovServices;
-use super::mcp_client::McpServerRegistry;
-use super::skills::{SkillDefinition, SkillRegistry};
+use super::blockchain_access::BlockchainAccessPolicy;
+use super::credits::{
+    CreditAccount, CreditLedgerEntry, CreditSnapshot, CreditStatement, CreditStatementFormat,
+    CreditUsage,
+};
+use super::iproov::IproovBackends;
+use super::mcp_client::{McpPromptRef, McpResourceRef, McpServerRegistry};
+use super::mcp_server::{
+    serve_http, serve_stdio, BrowserMcpServer, McpHttpServerHandle, McpHttpServerInfo,
+    DEFAULT_MCP_SERVER_PORT,
+};
+use super::run_history::{
+    AgentRunExportFormat, AgentRunHistory, AgentRunOutputFormat, AgentRunQuery, AgentRunRetention,
+    AgentRunTranscript,
+};
+use super::run_queue::{
+    AgentRunPriority, AgentRunQueue, AgentRunQueueLimits, AgentRunQueueListener,
+    AgentRunQueueSnapshot, AgentRunTicket,
+};
+use super::skills::{
+    SkillDefinition, SkillLoadIssue, SkillRegistry, SkillSource, SKILL_SCHEMA_VERSION,
+};
 use super::tools::{
     DomQueryTool, GatewayApproveCartTool, GatewayApprovePresentationTool, GatewayAwaitDecisionTool,
     GatewayCreatePresentationTool, GatewayFetchMandateTool, GatewayIntrospectTool,
-    MerchantPlaceOrderTool, MerchantQuoteCartTool, NavigateTool, PageSnapshotTool, TabsTool,
-    WalletInfoTool, WalletSpendTool,
+    MerchantPlaceOrderTool, MerchantQuoteCartTool, NavigateTool, PageSnapshotTool, RecallTool,
+    TabsTool, WalletInfoTool, WalletSpendTool,
 };
-use crate::app_state::AppState;
+use crate::agent_app_pipelines::{PipelineStepOutcome, PipelineStepRunner};
+use crate::agent_apps::AgentAppRegistry;
+use crate::app_state::{AppState, RunTabSurface};
 use crate::browser_engine::BrowserEngine;
+use crate::semantic_index::SemanticIndex;
 use crate::wallet_store::{WalletOwner, WalletStore};
-use crate::webview_automation::execute_active_dom_action;
+use crate::webview_automation::{execute_dom_action, TabTarget, TabWebviewHost};
 
 const DEFAULT_INITIAL_CREDITS: i64 = 50_000;
 const MAX_RUN_SUMMARIES: usize = 24;
+const MCP_SERVER_AGENT_ID: &str = "mcp-server";
+const RUN_TAB_INITIAL_URL: &str = "about:blank";
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -51,6 +77,18 @@ pub struct AgentRunRequest {
     pub app_id: Option<String>,
     #[serde(default)]
     pub schedule_id: Option<String>,
+    #[serde(default)]
+    pub mcp_resources: Vec<McpResourceRef>,
+    #[serde(default)]
+    pub mcp_prompts: Vec<McpPromptRef>,
+    #[serde(default)]
+    pub priority: Option<AgentRunPriority>,
+    #[serde(default)]
+    pub surface: Option<AgentRunSurface>,
+    /// Leave the run's tab open and focused for the user when the run ends
+    /// instead of closing it. Ignored for cancelled runs.
+    #[serde(default)]
+    pub hand_over: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,9 +114,29 @@ pub struct AgentRunResponse {
     pub schedule_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerModeStatus {
+    pub running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<McpHttpServerInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    pub tools: Vec<String>,
+    pub active_sessions: usize,
+    pub capabilities: HashMap<String, Option<u32>>,
+}
+
+struct McpServerModeState {
+    server: Arc<BrowserMcpServer>,
+    http: McpHttpServerHandle,
+    auth_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentRunStatus {
+    Queued,
     Running,
     CancelRequested,
     Completed,
@@ -86,7 +144,33 @@ pub enum AgentRunStatus {
     Failed,
 }
 
-#[derive(Debug, Serialize, Clone)]
+impl AgentRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentRunStatus::Queued => "queued",
+            AgentRunStatus::Running => "running",
+            AgentRunStatus::CancelRequested => "cancel_requested",
+            AgentRunStatus::Completed => "completed",
+            AgentRunStatus::Cancelled => "cancelled",
+            AgentRunStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Where a run does its browsing.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentRunSurface {
+    /// A hidden tab owned by the run.
+    #[default]
+    Background,
+    /// A run-owned tab shown in the agent side panel.
+    SidePanel,
+    /// The user's active tab.
+    ActiveTab,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentRunSummary {
     pub run_id: String,
@@ -99,6 +183,12 @@ pub struct AgentRunSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub schedule_id: Option<String>,
     pub status: AgentRunStatus,
+    #[serde(default)]
+    pub priority: AgentRunPriority,
+    #[serde(default)]
+    pub surface: AgentRunSurface,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_id: Option<String>,
     pub started_at_ms: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub finished_at_ms: Option<u64>,
@@ -108,6 +198,11 @@ pub struct AgentRunSummary {
     pub cancelled: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub final_answer: Option<String>,
+    /// The final answer as JSON, for runs of apps with an output schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -117,6 +212,18 @@ pub struct AgentSkillSummary {
     pub name: String,
     pub description: String,
     pub tags: Vec<String>,
+    pub source: SkillSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSkillStatus {
+    pub schema_version: u32,
+    pub skills: Vec<AgentSkillSummary>,
+    pub issues: Vec<SkillLoadIssue>,
+    pub user_dirs: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -127,7 +234,7 @@ pub struct AgentRunEventEnvelope {
     pub payload: AgentRunEventPayload,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "stage", rename_all = "snake_case")]
 pub enum AgentRunEventPayload {
     Started {
@@ -140,6 +247,13 @@ pub enum AgentRunEventPayload {
         #[serde(skip_serializing_if = "Option::is_none")]
         schedule_id: Option<String>,
     },
+    Admitted {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tab_id: Option<String>,
+    },
+    HandedOver {
+        tab_id: String,
+    },
     Activity {
         event: AgentEvent,
     },
@@ -149,6 +263,8 @@ pub enum AgentRunEventPayload {
         cancelled: bool,
         #[serde(skip_serializing_if = "Option::is_none")]
         final_answer: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        structured_output: Option<Value>,
         tokens_used: u64,
         tokens_estimated: bool,
         credits_spent: u64,
@@ -165,9 +281,67 @@ struct RunControl {
     cancelled: AtomicBool,
 }
 
+/// Every event published for a run, kept so the finished transcript can be
+/// written to [`AgentRunHistory`].
+type RunEventLog = Arc<Mutex<Vec<AgentRunEventPayload>>>;
+
 struct ActiveRunState {
     control: Arc<RunControl>,
     summary: AgentRunSummary,
+    events: RunEventLog,
+    hand_over: bool,
+}
+
+/// Per-run limits on what the runtime may do: the skill's tools and
+/// capabilities, narrowed further by the launching app's blockchain contract,
+/// and the schema the app requires its final answer to match.
+#[derive(Default)]
+struct RunGrants<'a> {
+    skill: Option<&'a SkillDefinition>,
+    blockchain_access: Option<Arc<BlockchainAccessPolicy>>,
+    output_schema: Option<Value>,
+}
+
+/// Final state of a run, folded into its summary and transcript by
+/// [`AgentManager::finish_run`].
+struct RunOutcome {
+    status: AgentRunStatus,
+    tokens_used: u64,
+    tokens_estimated: bool,
+    credits_spent: u64,
+    cancelled: bool,
+    final_answer: Option<String>,
+    structured_output: Option<Value>,
+    error: Option<String>,
+    steps: Vec<PlanStep>,
+    ledger_root: Option<String>,
+    ledger_entries: Vec<LedgerEntry>,
+    usage: Vec<CreditUsage>,
+}
+
+impl RunOutcome {
+    fn failed(error: String, tokens_used: u64, tokens_estimated: bool) -> Self {
+        Self {
+            status: AgentRunStatus::Failed,
+            tokens_used,
+            tokens_estimated,
+            credits_spent: tokens_used,
+            cancelled: false,
+            final_answer: None,
+            structured_output: None,
+            error: Some(error),
+            steps: Vec::new(),
+            ledger_root: None,
+            ledger_entries: Vec::new(),
+            usage: Vec::new(),
+        }
+    }
+}
+
+fn record_run_event(events: &RunEventLog, payload: &AgentRunEventPayload) {
+    if let Ok(mut log) = events.lock() {
+        log.push(payload.clone());
+    }
 }
 
 impl RunControl {
@@ -189,18 +363,19 @@ impl RunControl {
 #[derive(Clone)]
 struct GuiDomExecutor {
     app_handle: AppHandle<Wry>,
+    target: TabTarget,
 }
 
 impl GuiDomExecutor {
-    fn new(app_handle: AppHandle<Wry>) -> Self {
-        Self { app_handle }
+    fn new(app_handle: AppHandle<Wry>, target: TabTarget) -> Self {
+        Self { app_handle, target }
     }
 }
 
 #[async_trait]
 impl DomExecutor for GuiDomExecutor {
     async fn execute(&self, action: &agent_core::DomAction) -> Result<DomExecutionResult> {
-        execute_active_dom_action(&self.app_handle, action)
+        execute_dom_action(&self.app_handle, &self.target, action)
             .await
             .map_err(anyhow::Error::msg)
     }
@@ -211,17 +386,22 @@ pub struct AgentManager {
     router: LlmRouter,
     browser_engine: Arc<BrowserEngine>,
     wallet_store: Arc<std::sync::Mutex<WalletStore>>,
-    iproov: Arc<IproovServices>,
+    iproov: IproovBackends,
     approval_handler: Arc<GuiApprovalHandler>,
     skills: SkillRegistry,
+    agent_apps: Arc<AgentAppRegistry>,
     credit_account: Arc<AsyncMutex<CreditAccount>>,
     model_available: bool,
     no_egress: AtomicBool,
     afm_node_handle: Arc<Mutex<Option<AfmNodeHandle>>>,
     mcp_registry: Arc<McpServerRegistry>,
+    semantic_index: Arc<SemanticIndex>,
     run_seq: AtomicU64,
     active_runs: AsyncMutex<HashMap<String, ActiveRunState>>,
-    recent_runs: AsyncMutex<VecDeque<AgentRunSummary>>,
+    history: AgentRunHistory,
+    run_queue: Arc<AgentRunQueue>,
+    tab_host: Mutex<Option<Arc<dyn TabWebviewHost>>>,
+    mcp_server_mode: AsyncMutex<Option<McpServerModeState>>,
 }
 
 impl AgentManager {
@@ -231,10 +411,29 @@ impl AgentManager {
         broker: Arc<ApprovalBroker>,
     ) -> Result<Self> {
         let router = LlmRouter::new()?;
-        let iproov = Arc::new(IproovServices::new(100_000)?);
+        let iproov = IproovBackends::from_env(100_000)?;
         let approval_handler = GuiApprovalHandler::new(app_handle.clone(), broker);
-        let credit_account = Arc::new(AsyncMutex::new(CreditAccount::new(DEFAULT_INITIAL_CREDITS)));
+        // A ledger that cannot be replayed must not be swapped for a fresh
+        // grant: that would hand out credits and drop the recorded spend.
+        let credit_account = CreditAccount::load_default(DEFAULT_INITIAL_CREDITS as u64)
+            .context("failed to load credit ledger")?;
+        let credit_account = Arc::new(AsyncMutex::new(credit_account));
         let skills = SkillRegistry::load()?;
+        let history = AgentRunHistory::load_default().unwrap_or_else(|err| {
+            tracing::warn!(error = %err, "failed to load agent run history; starting empty");
+            AgentRunHistory::empty()
+        });
+        let queue_listener: AgentRunQueueListener = {
+            let app_handle = app_handle.clone();
+            Arc::new(move |snapshot: &AgentRunQueueSnapshot| {
+                let _ = app_handle.emit("agent://run-queue", snapshot);
+            })
+        };
+        let run_queue =
+            AgentRunQueue::load_default(Some(queue_listener.clone())).unwrap_or_else(|err| {
+                tracing::warn!(error = %err, "failed to load agent run queue limits; using defaults");
+                AgentRunQueue::new(AgentRunQueueLimits::default(), Some(queue_listener))
+            });
         let mcp_registry = state.mcp_registry.clone();
         let wallet_store = state.wallet_store.clone();
         // Ensure the primary user wallet exists up front.
@@ -254,13 +453,18 @@ impl AgentManager {
             iproov,
             approval_handler,
             skills,
+            agent_apps: state.agent_apps.clone(),
             credit_account,
             no_egress: AtomicBool::new(false),
             afm_node_handle: state.afm_node_handle.clone(),
             mcp_registry,
-            run_seq: AtomicU64::new(1),
+            semantic_index: state.semantic_index.clone(),
+            run_seq: AtomicU64::new(history.last_sequence() + 1),
             active_runs: AsyncMutex::new(HashMap::new()),
-            recent_runs: AsyncMutex::new(VecDeque::new()),
+            history,
+            run_queue,
+            tab_host: Mutex::new(None),
+            mcp_server_mode: AsyncMutex::new(None),
         })
     }
 
@@ -271,25 +475,88 @@ impl AgentManager {
     pub fn list_skills(&self) -> Vec<AgentSkillSummary> {
         self.skills
             .list()
-            .iter()
+            .into_iter()
             .map(|skill| AgentSkillSummary {
-                id: skill.id.clone(),
-                name: skill.name.clone(),
-                description: skill.description.clone(),
-                tags: skill.tags.clone(),
+                id: skill.id,
+                name: skill.name,
+                description: skill.description,
+                tags: skill.tags,
+                source: skill.source,
+                extends: skill.extends,
             })
             .collect()
     }
 
+    pub fn skill_status(&self) -> AgentSkillStatus {
+        AgentSkillStatus {
+            schema_version: SKILL_SCHEMA_VERSION,
+            skills: self.list_skills(),
+            issues: self.skills.issues(),
+            user_dirs: self
+                .skills
+                .user_dirs()
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect(),
+        }
+    }
+
+    /// Re-reads the bundled manifest and user skill directories. Errors are
+    /// reported through `AgentSkillStatus::issues`; the last good skill set
+    /// stays active.
+    pub fn reload_skills(&self) -> AgentSkillStatus {
+        if let Err(err) = self.skills.reload() {
+            tracing::warn!(error = %err, "skill reload failed; keeping previous skills");
+        }
+        let status = self.skill_status();
+        let _ = self.app_handle.emit("agent://skills-updated", &status);
+        status
+    }
+
+    /// Polled by the skill watcher; reloads and notifies the UI only when a
+    /// skill file was added, removed or modified.
+    pub fn reload_skills_if_changed(&self) -> Option<AgentSkillStatus> {
+        if let Err(err) = self.skills.reload_if_changed()? {
+            tracing::warn!(error = %err, "skill reload failed; keeping previous skills");
+        }
+        let status = self.skill_status();
+        let _ = self.app_handle.emit("agent://skills-updated", &status);
+        Some(status)
+    }
+
     pub async fn credit_snapshot(&self) -> CreditSnapshot {
         let account = self.credit_account.lock().await;
         account.snapshot()
     }
 
-    pub async fn top_up_credits(&self, tokens: u32) -> CreditSnapshot {
+    pub async fn top_up_credits(&self, tokens: u32) -> Result<CreditSnapshot> {
         let mut account = self.credit_account.lock().await;
-        account.top_up(tokens);
-        account.snapshot()
+        account.top_up(tokens)?;
+        Ok(account.snapshot())
+    }
+
+    pub async fn credit_ledger(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Vec<CreditLedgerEntry> {
+        self.credit_account.lock().await.entries(limit, offset)
+    }
+
+    pub async fn credit_statement(&self, year: i32, month: u32) -> Result<CreditStatement> {
+        self.credit_account.lock().await.statement(year, month)
+    }
+
+    pub async fn export_credit_statement(
+        &self,
+        year: i32,
+        month: u32,
+        format: CreditStatementFormat,
+    ) -> Result<String> {
+        self.credit_account
+            .lock()
+            .await
+            .export_statement(year, month, format)
     }
 
     pub fn set_no_egress(&self, value: bool) {
@@ -305,10 +572,10 @@ impl AgentManager {
                 .map(|run| run.summary.clone())
                 .collect::<Vec<_>>()
         };
-        let recent = {
-            let recent_runs = self.recent_runs.lock().await;
-            recent_runs.iter().cloned().collect::<Vec<_>>()
-        };
+        let recent = self.history.search(&AgentRunQuery {
+            limit: Some(limit),
+            ..AgentRunQuery::default()
+        });
 
         let mut combined = active;
         combined.extend(recent);
@@ -322,31 +589,178 @@ impl AgentManager {
         combined
     }
 
+    /// Installs the host that backs run-owned tabs with webviews. Without one,
+    /// only runs on the user's active tab can start.
+    pub fn set_tab_host(&self, host: Arc<dyn TabWebviewHost>) {
+        if let Ok(mut slot) = self.tab_host.lock() {
+            *slot = Some(host);
+        }
+    }
+
+    pub fn run_queue_snapshot(&self) -> AgentRunQueueSnapshot {
+        self.run_queue.snapshot()
+    }
+
+    pub fn set_run_queue_limits(
+        &self,
+        limits: AgentRunQueueLimits,
+    ) -> Result<AgentRunQueueSnapshot> {
+        self.run_queue.set_limits(limits)
+    }
+
+    pub fn search_runs(&self, query: &AgentRunQuery) -> Vec<AgentRunSummary> {
+        self.history.search(query)
+    }
+
+    pub fn open_run(&self, run_id: &str) -> Result<Option<AgentRunTranscript>> {
+        self.history.get(run_id)
+    }
+
+    pub fn export_run(&self, run_id: &str, format: AgentRunExportFormat) -> Result<Option<String>> {
+        self.history.export(run_id, format)
+    }
+
+    /// Structured output of a finished run, exported as JSON or CSV.
+    pub fn export_run_output(
+        &self,
+        run_id: &str,
+        format: AgentRunOutputFormat,
+    ) -> Result<Option<String>> {
+        self.history.export_output(run_id, format)
+    }
+
+    /// Structured output of a finished run, for piping into another app.
+    pub fn run_output(&self, run_id: &str) -> Option<Value> {
+        self.history.output(run_id)
+    }
+
+    pub fn delete_run(&self, run_id: &str) -> Result<bool> {
+        self.history.delete(run_id)
+    }
+
+    pub fn run_retention(&self) -> AgentRunRetention {
+        self.history.retention()
+    }
+
+    pub fn set_run_retention(&self, retention: AgentRunRetention) -> Result<AgentRunRetention> {
+        self.history.set_retention(retention)
+    }
+
     pub async fn tool_descriptions(&self) -> Result<Vec<McpToolDescription>> {
         let policy = RoutingPolicy {
             no_egress: self.no_egress.load(Ordering::SeqCst),
             ..RoutingPolicy::default()
         };
         let (runtime, _) = self
-            .build_runtime(None, policy, WalletOwner::User, None, None)
+            .build_runtime(
+                RunGrants::default(),
+                policy,
+                WalletOwner::User,
+                TabTarget::Active,
+                None,
+                None,
+            )
             .await?;
         Ok(runtime.tool_descriptions())
     }
 
+    /// Starts the loopback MCP endpoint that lets external hosts drive the
+    /// browser. Calls are gated by a fresh capability registry and the GUI
+    /// approval flow; quotas reset each time the server is started.
+    pub async fn start_mcp_server_mode(&self, port: Option<u16>) -> Result<McpServerModeStatus> {
+        let mut slot = self.mcp_server_mode.lock().await;
+        if slot.is_none() {
+            let server = self.build_mcp_server()?;
+            let auth_token = Uuid::new_v4().simple().to_string();
+            let bind =
+                SocketAddr::from((Ipv4Addr::LOCALHOST, port.unwrap_or(DEFAULT_MCP_SERVER_PORT)));
+            let http = serve_http(server.clone(), bind, auth_token.clone()).await?;
+            *slot = Some(McpServerModeState {
+                server,
+                http,
+                auth_token,
+            });
+        }
+        Ok(Self::mcp_server_status(slot.as_ref()).await)
+    }
+
+    pub async fn stop_mcp_server_mode(&self) -> McpServerModeStatus {
+        let state = self.mcp_server_mode.lock().await.take();
+        if let Some(state) = state {
+            state.http.shutdown().await;
+        }
+        Self::mcp_server_status(None).await
+    }
+
+    pub async fn mcp_server_mode_status(&self) -> McpServerModeStatus {
+        let slot = self.mcp_server_mode.lock().await;
+        Self::mcp_server_status(slot.as_ref()).await
+    }
+
+    /// Serves MCP on the process stdin/stdout until the host closes the pipe.
+    pub async fn serve_mcp_stdio(&self) -> Result<()> {
+        let server = self.build_mcp_server()?;
+        serve_stdio(server, tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    async fn mcp_server_status(state: Option<&McpServerModeState>) -> McpServerModeStatus {
+        match state {
+            Some(state) => McpServerModeStatus {
+                running: true,
+                http: Some(state.http.info()),
+                auth_token: Some(state.auth_token.clone()),
+                tools: state.server.tool_names(),
+                active_sessions: state.http.session_count().await,
+                capabilities: state.server.capability_snapshot().await,
+            },
+            None => McpServerModeStatus {
+                running: false,
+                http: None,
+                auth_token: None,
+                tools: Vec::new(),
+                active_sessions: 0,
+                capabilities: HashMap::new(),
+            },
+        }
+    }
+
+    fn build_mcp_server(&self) -> Result<Arc<BrowserMcpServer>> {
+        {
+            let mut store = self
+                .wallet_store
+                .lock()
+                .map_err(|_| anyhow!("wallet store mutex poisoned"))?;
+            store.ensure_agent_profile(MCP_SERVER_AGENT_ID)?;
+        }
+        let tools = self.browser_tools(
+            WalletOwner::Agent(MCP_SERVER_AGENT_ID.to_string()),
+            TabTarget::Active,
+        );
+        let approval: Arc<dyn ApprovalHandler> = self.approval_handler.clone();
+        Ok(Arc::new(BrowserMcpServer::new(
+            tools,
+            self.skills.build_capabilities(None)?,
+            Some(approval),
+        )))
+    }
+
     pub async fn cancel_run(&self, run_id: &str) -> Result<bool> {
-        let cancelled = {
+        let events = {
             let mut active_runs = self.active_runs.lock().await;
-            if let Some(run) = active_runs.get_mut(run_id) {
+            active_runs.get_mut(run_id).map(|run| {
                 run.control.cancel();
                 run.summary.status = AgentRunStatus::CancelRequested;
-                true
-            } else {
-                false
-            }
+                run.events.clone()
+            })
         };
 
-        if cancelled {
-            self.emit_run_event(run_id, AgentRunEventPayload::CancelRequested, false)?;
+        if let Some(events) = events {
+            // A run still waiting for a slot is dropped from the queue; its
+            // `run_task` call then finishes it as cancelled.
+            self.run_queue.cancel(run_id);
+            let payload = AgentRunEventPayload::CancelRequested;
+            record_run_event(&events, &payload);
+            self.emit_run_event(run_id, payload, false)?;
             Ok(true)
         } else {
             Ok(false)
@@ -367,10 +781,29 @@ impl AgentManager {
             }
         }
 
-        let skill_ref = request
+        let run_input = match self
+            .mcp_registry
+            .render_run_context(&request.mcp_prompts, &request.mcp_resources)
+            .await?
+        {
+            Some(context) => format!("{}\n\n{}", context, request.task.trim()),
+            None => request.task.clone(),
+        };
+
+        let skill = request
             .skill_id
             .as_ref()
             .and_then(|id| self.skills.find(id));
+        let app = request
+            .app_id
+            .as_deref()
+            .and_then(|id| self.agent_apps.find(id));
+        let blockchain_access = app
+            .as_ref()
+            .and_then(|app| app.blockchain_access.as_ref())
+            .map(|contract| BlockchainAccessPolicy::from_contract(contract).map(Arc::new))
+            .transpose()?;
+        let output_schema = app.and_then(|app| app.output_schema);
 
         let no_egress = request
             .no_egress
@@ -400,13 +833,18 @@ impl AgentManager {
             store.ensure_agent_profile(&agent_id)?;
         }
 
+        let priority = request.priority.unwrap_or_default();
+        let surface = request.surface.unwrap_or_default();
         let run_control = RunControl::new();
+        let run_events: RunEventLog = Arc::new(Mutex::new(Vec::new()));
         {
             let mut active_runs = self.active_runs.lock().await;
             active_runs.insert(
                 run_id.clone(),
                 ActiveRunState {
                     control: run_control.clone(),
+                    events: run_events.clone(),
+                    hand_over: request.hand_over,
                     summary: AgentRunSummary {
                         run_id: run_id.clone(),
                         task: request.task.clone(),
@@ -414,7 +852,10 @@ impl AgentManager {
                         skill_id: request.skill_id.clone(),
                         app_id: request.app_id.clone(),
                         schedule_id: request.schedule_id.clone(),
-                        status: AgentRunStatus::Running,
+                        status: AgentRunStatus::Queued,
+                        priority,
+                        surface,
+                        tab_id: None,
                         started_at_ms: now_ms(),
                         finished_at_ms: None,
                         tokens_used: 0,
@@ -422,84 +863,166 @@ impl AgentManager {
                         credits_spent: 0,
                         cancelled: false,
                         final_answer: None,
+                        structured_output: None,
+                        error: None,
                     },
                 },
             );
         }
 
-        self.emit_run_event(
-            &run_id,
-            AgentRunEventPayload::Started {
-                task: request.task.clone(),
-                label: label.clone(),
-                skill_id: request.skill_id.clone(),
-                app_id: request.app_id.clone(),
-                schedule_id: request.schedule_id.clone(),
-            },
-            false,
-        )?;
+        let started = AgentRunEventPayload::Started {
+            task: request.task.clone(),
+            label: label.clone(),
+            skill_id: request.skill_id.clone(),
+            app_id: request.app_id.clone(),
+            schedule_id: request.schedule_id.clone(),
+        };
+        record_run_event(&run_events, &started);
+        self.emit_run_event(&run_id, started, false)?;
+
+        let ticket = AgentRunTicket {
+            run_id: run_id.clone(),
+            label: label.clone(),
+            app_id: request.app_id.clone(),
+            priority,
+        };
+        // Held until this function returns so the slot is freed however the
+        // run ends.
+        let _permit = match self.run_queue.acquire(ticket, now_ms()).await {
+            Ok(permit) => permit,
+            Err(err) => {
+                let _ = self
+                    .finish_run(
+                        &run_id,
+                        &run_events,
+                        RunOutcome {
+                            status: AgentRunStatus::Cancelled,
+                            cancelled: true,
+                            ..RunOutcome::failed(err.to_string(), 0, false)
+                        },
+                        AgentRunEventPayload::Finished {
+                            halted: true,
+                            cancelled: true,
+                            final_answer: None,
+                            structured_output: None,
+                            tokens_used: 0,
+                            tokens_estimated: false,
+                            credits_spent: 0,
+                        },
+                    )
+                    .await;
+                return Err(err);
+            }
+        };
+
+        let run_tab_surface = match surface {
+            AgentRunSurface::Background => Some(RunTabSurface::Hidden),
+            AgentRunSurface::SidePanel => Some(RunTabSurface::SidePanel),
+            AgentRunSurface::ActiveTab => None,
+        };
+        // A run that asked for its own tab must never fall back to driving
+        // the user's active tab.
+        let tab_id = match run_tab_surface
+            .map(|surface| self.open_run_tab(&run_id, surface))
+            .transpose()
+        {
+            Ok(tab_id) => tab_id,
+            Err(err) => {
+                let _ = self
+                    .finish_run(
+                        &run_id,
+                        &run_events,
+                        RunOutcome::failed(err.to_string(), 0, false),
+                        AgentRunEventPayload::Failed {
+                            error: err.to_string(),
+                            tokens_used: 0,
+                            tokens_estimated: false,
+                            credits_spent: 0,
+                        },
+                    )
+                    .await;
+                return Err(err);
+            }
+        };
+        {
+            let mut active_runs = self.active_runs.lock().await;
+            if let Some(run) = active_runs.get_mut(&run_id) {
+                if run.summary.status == AgentRunStatus::Queued {
+                    run.summary.status = AgentRunStatus::Running;
+                }
+                run.summary.tab_id = tab_id.clone();
+            }
+        }
+        let admitted = AgentRunEventPayload::Admitted {
+            tab_id: tab_id.clone(),
+        };
+        record_run_event(&run_events, &admitted);
+        self.emit_run_event(&run_id, admitted, false)?;
+        let target = tab_id.map(TabTarget::Tab).unwrap_or_default();
 
         let runtime_result = self
             .build_runtime(
-                skill_ref,
+                RunGrants {
+                    skill: skill.as_ref(),
+                    blockchain_access,
+                    output_schema,
+                },
                 policy,
                 wallet_owner,
-                Some(self.build_run_event_callback(&run_id)),
+                target,
+                Some(self.build_run_event_callback(&run_id, run_events.clone())),
                 Some(self.build_cancellation_check(run_control)),
             )
             .await;
         let (mut runtime, metered_model) = match runtime_result {
             Ok(result) => result,
             Err(err) => {
-                self.finish_run_summary(&run_id, AgentRunStatus::Failed, 0, false, 0, false, None)
+                let _ = self
+                    .finish_run(
+                        &run_id,
+                        &run_events,
+                        RunOutcome::failed(err.to_string(), 0, false),
+                        AgentRunEventPayload::Failed {
+                            error: err.to_string(),
+                            tokens_used: 0,
+                            tokens_estimated: false,
+                            credits_spent: 0,
+                        },
+                    )
                     .await;
-                let _ = self.emit_run_event(
-                    &run_id,
-                    AgentRunEventPayload::Failed {
-                        error: err.to_string(),
-                        tokens_used: 0,
-                        tokens_estimated: false,
-                        credits_spent: 0,
-                    },
-                    true,
-                );
                 return Err(err);
             }
         };
         metered_model.reset();
 
-        let run_result = runtime.run(&request.task).await;
+        let run_result = runtime.run(&run_input).await;
 
         let ledger_entries = runtime.ledger_entries().await;
         let capabilities = runtime.capability_snapshot().await;
         let tokens_used = metered_model.tokens_used();
         let tokens_estimated = metered_model.used_estimated_tokens();
         let credits_spent = tokens_used;
-        let credit = self.credit_snapshot().await;
 
         let result = match run_result {
             Ok(result) => result,
             Err(err) => {
-                self.finish_run_summary(
-                    &run_id,
-                    AgentRunStatus::Failed,
-                    tokens_used,
-                    tokens_estimated,
-                    credits_spent,
-                    false,
-                    None,
-                )
-                .await;
-                let _ = self.emit_run_event(
-                    &run_id,
-                    AgentRunEventPayload::Failed {
-                        error: err.to_string(),
-                        tokens_used,
-                        tokens_estimated,
-                        credits_spent,
-                    },
-                    true,
-                );
+                let _ = self
+                    .finish_run(
+                        &run_id,
+                        &run_events,
+                        RunOutcome {
+                            ledger_entries,
+                            usage: metered_model.usage(),
+                            ..RunOutcome::failed(err.to_string(), tokens_used, tokens_estimated)
+                        },
+                        AgentRunEventPayload::Failed {
+                            error: err.to_string(),
+                            tokens_used,
+                            tokens_estimated,
+                            credits_spent,
+                        },
+                    )
+                    .await;
                 return Err(err);
             }
         };
@@ -515,29 +1038,35 @@ impl AgentManager {
             AgentRunStatus::Completed
         };
 
-        self.finish_run_summary(
-            &run_id,
-            final_status,
-            tokens_used,
-            tokens_estimated,
-            credits_spent,
-            cancelled,
-            agent.final_answer.clone(),
-        )
-        .await;
-
-        self.emit_run_event(
+        self.finish_run(
             &run_id,
+            &run_events,
+            RunOutcome {
+                status: final_status,
+                tokens_used,
+                tokens_estimated,
+                credits_spent,
+                cancelled,
+                final_answer: agent.final_answer.clone(),
+                structured_output: agent.structured_output.clone(),
+                error: None,
+                steps: agent.steps.clone(),
+                ledger_root: ledger_root.clone(),
+                ledger_entries: ledger_entries.clone(),
+                usage: metered_model.usage(),
+            },
             AgentRunEventPayload::Finished {
                 halted: agent.halted,
                 cancelled,
                 final_answer: agent.final_answer.clone(),
+                structured_output: agent.structured_output.clone(),
                 tokens_used,
                 tokens_estimated,
                 credits_spent,
             },
-            true,
-        )?;
+        )
+        .await?;
+        let credit = self.credit_snapshot().await;
 
         Ok(AgentRunResponse {
             run_id,
@@ -557,15 +1086,17 @@ impl AgentManager {
         })
     }
 
-    fn build_run_event_callback(&self, run_id: &str) -> AgentEventCallback {
+    fn build_run_event_callback(&self, run_id: &str, events: RunEventLog) -> AgentEventCallback {
         let app_handle = self.app_handle.clone();
         let run_id = run_id.to_string();
         Arc::new(move |event: AgentEvent| {
+            let payload = AgentRunEventPayload::Activity { event };
+            record_run_event(&events, &payload);
             let _ = app_handle.emit(
                 "agent://run-event",
                 AgentRunEventEnvelope {
                     run_id: run_id.clone(),
-                    payload: AgentRunEventPayload::Activity { event },
+                    payload,
                 },
             );
         })
@@ -575,38 +1106,115 @@ impl AgentManager {
         Arc::new(move || run_control.is_cancelled())
     }
 
-    async fn finish_run_summary(
+    /// Moves a run out of `active_runs`, writes its transcript to the run
+    /// history and only then announces `terminal`, so listeners reloading the
+    /// run list on `agent://run-finished` already see the stored run.
+    async fn finish_run(
         &self,
         run_id: &str,
-        status: AgentRunStatus,
-        tokens_used: u64,
-        tokens_estimated: bool,
-        credits_spent: u64,
-        cancelled: bool,
-        final_answer: Option<String>,
-    ) {
-        let summary = {
+        events: &RunEventLog,
+        outcome: RunOutcome,
+        terminal: AgentRunEventPayload,
+    ) -> Result<()> {
+        let active = {
             let mut active_runs = self.active_runs.lock().await;
-            active_runs.remove(run_id).map(|mut active| {
-                active.summary.status = status;
-                active.summary.finished_at_ms = Some(now_ms());
-                active.summary.tokens_used = tokens_used;
-                active.summary.tokens_estimated = tokens_estimated;
-                active.summary.credits_spent = credits_spent;
-                active.summary.cancelled = cancelled;
-                active.summary.final_answer = final_answer;
-                active.summary
-            })
+            active_runs.remove(run_id)
         };
 
+        let refund_reason = (outcome.status == AgentRunStatus::Failed).then(|| {
+            outcome
+                .error
+                .clone()
+                .unwrap_or_else(|| "run failed".to_string())
+        });
+        if let Err(err) = self.credit_account.lock().await.settle_run(
+            run_id,
+            outcome.credits_spent,
+            outcome.usage,
+            refund_reason,
+        ) {
+            tracing::warn!(run_id, error = %err, "failed to record agent run charge");
+        }
+
+        if let Some(active) = &active {
+            if let Some(tab_id) = active.summary.tab_id.clone() {
+                if active.hand_over && !outcome.cancelled && self.hand_over_run_tab(&tab_id) {
+                    let handed_over = AgentRunEventPayload::HandedOver { tab_id };
+                    record_run_event(events, &handed_over);
+                    let _ = self.emit_run_event(run_id, handed_over, false);
+                } else {
+                    self.close_run_tab(&tab_id);
+                }
+            }
+        }
+
+        record_run_event(events, &terminal);
+        let summary = active.map(|mut active| {
+            active.summary.status = outcome.status;
+            active.summary.finished_at_ms = Some(now_ms());
+            active.summary.tokens_used = outcome.tokens_used;
+            active.summary.tokens_estimated = outcome.tokens_estimated;
+            active.summary.credits_spent = outcome.credits_spent;
+            active.summary.cancelled = outcome.cancelled;
+            active.summary.final_answer = outcome.final_answer;
+            active.summary.structured_output = outcome.structured_output;
+            active.summary.error = outcome.error;
+            active.summary
+        });
+
         if let Some(summary) = summary {
-            let mut recent_runs = self.recent_runs.lock().await;
-            recent_runs.retain(|entry| entry.run_id != summary.run_id);
-            recent_runs.push_front(summary);
-            while recent_runs.len() > MAX_RUN_SUMMARIES {
-                recent_runs.pop_back();
+            let transcript = AgentRunTranscript {
+                summary,
+                events: events.lock().map(|log| log.clone()).unwrap_or_default(),
+                steps: outcome.steps,
+                ledger_root: outcome.ledger_root,
+                ledger_entries: outcome.ledger_entries,
+            };
+            if let Err(err) = self.history.record(&transcript) {
+                tracing::warn!(run_id, error = %err, "failed to persist agent run transcript");
             }
         }
+
+        self.emit_run_event(run_id, terminal, true)
+    }
+
+    /// Opens a tab owned by the run so it does not act on the user's page.
+    /// Fails when no tab host is installed or the tab could not be created.
+    fn open_run_tab(&self, run_id: &str, surface: RunTabSurface) -> Result<String> {
+        let host = self
+            .tab_host()
+            .ok_or_else(|| anyhow!("no tab host is available to open the agent run tab"))?;
+        let tab_id = format!("agent-{run_id}");
+        host.open_tab(&tab_id, RUN_TAB_INITIAL_URL, surface)
+            .context("failed to open agent run tab")?;
+        Ok(tab_id)
+    }
+
+    fn close_run_tab(&self, tab_id: &str) {
+        if let Some(host) = self.tab_host() {
+            if let Err(err) = host.close_tab(tab_id) {
+                tracing::warn!(tab_id, error = %err, "failed to close agent run tab");
+            }
+        }
+    }
+
+    /// Returns whether the tab now belongs to the user. On failure the tab is
+    /// left for the caller to close.
+    fn hand_over_run_tab(&self, tab_id: &str) -> bool {
+        let Some(host) = self.tab_host() else {
+            return false;
+        };
+        match host.hand_over_tab(tab_id) {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::warn!(tab_id, error = %err, "failed to hand agent run tab to the user");
+                false
+            }
+        }
+    }
+
+    fn tab_host(&self) -> Option<Arc<dyn TabWebviewHost>> {
+        self.tab_host.lock().ok().and_then(|slot| slot.clone())
     }
 
     fn emit_run_event(
@@ -628,15 +1236,24 @@ impl AgentManager {
 
     async fn build_runtime(
         &self,
-        skill: Option<&SkillDefinition>,
+        grants: RunGrants<'_>,
         policy: RoutingPolicy,
         wallet_owner: WalletOwner,
+        target: TabTarget,
         event_callback: Option<AgentEventCallback>,
         cancellation_check: Option<AgentCancellationCheck>,
     ) -> Result<(AgentRuntime, Arc<MeteredModel>)> {
-        let capabilities = self
+        let RunGrants {
+            skill,
+            blockchain_access,
+            output_schema,
+        } = grants;
+        let mut capabilities = self
             .skills
-            .build_capabilities(skill.map(|skill| skill.id.as_str()));
+            .build_capabilities(skill.map(|skill| skill.id.as_str()))?;
+        if let Some(access) = &blockchain_access {
+            access.apply_to(&mut capabilities);
+        }
 
         let mut config = AgentConfig::default();
         if let Some(skill) = skill {
@@ -647,9 +1264,11 @@ impl AgentManager {
                 config.max_steps = max_steps;
             }
         }
+        config.output_schema = output_schema;
 
+        let provider = self.router.provider_for(&policy);
         let base_model = self.router.route(policy)?;
-        let metered_model = MeteredModel::new(base_model, self.credit_account.clone());
+        let metered_model = MeteredModel::new(base_model, self.credit_account.clone(), provider);
         let model: Arc<dyn LanguageModelClient> = metered_model.clone();
 
         let mut builder = AgentRuntime::builder(model)
@@ -659,8 +1278,10 @@ impl AgentManager {
 
         #[cfg(target_os = "macos")]
         {
-            builder =
-                builder.with_dom_executor(Arc::new(GuiDomExecutor::new(self.app_handle.clone())));
+            builder = builder.with_dom_executor(Arc::new(GuiDomExecutor::new(
+                self.app_handle.clone(),
+                target.clone(),
+            )));
         }
 
         if let Some(callback) = event_callback {
@@ -671,8 +1292,21 @@ impl AgentManager {
             builder = builder.with_cancellation_check(check);
         }
 
-        for (tool, capability) in self.build_tools(wallet_owner.clone()).await {
-            builder = builder.register_tool(tool, capability);
+        let allowed_tools = skill.and_then(|skill| skill.tools.as_ref());
+        for (tool, capability) in self.build_tools(wallet_owner.clone(), target).await {
+            let name = tool.description().name.clone();
+            if allowed_tools.is_some_and(|allowed| !allowed.contains(&name)) {
+                continue;
+            }
+            builder = match (&blockchain_access, capability) {
+                (Some(access), _) if name == "wallet.info" && !access.allows_wallet_state() => {
+                    continue
+                }
+                (Some(access), Some(capability)) if name == "wallet.spend" => {
+                    builder.register_tool_with_policy(tool, capability, access.clone())
+                }
+                (_, capability) => builder.register_tool(tool, capability),
+            };
         }
 
         if let Ok(slot) = self.afm_node_handle.lock() {
@@ -684,21 +1318,27 @@ impl AgentManager {
         Ok((builder.build(), metered_model))
     }
 
-    async fn build_tools(
+    /// Navigation, page inspection, tab and wallet tools shared by agent runs
+    /// and MCP server mode.
+    fn browser_tools(
         &self,
         wallet_owner: WalletOwner,
+        target: TabTarget,
     ) -> Vec<(Arc<dyn McpTool>, Option<CapabilityKind>)> {
         let mut tools: Vec<(Arc<dyn McpTool>, Option<CapabilityKind>)> = Vec::new();
 
         tools.push((
-            Arc::new(NavigateTool::new(self.app_handle.clone())),
+            Arc::new(NavigateTool::new(self.app_handle.clone(), target.clone())),
             Some(CapabilityKind::Navigate),
         ));
         #[cfg(target_os = "macos")]
         {
-            tools.push((Arc::new(DomQueryTool::new(self.app_handle.clone())), None));
             tools.push((
-                Arc::new(PageSnapshotTool::new(self.app_handle.clone())),
+                Arc::new(DomQueryTool::new(self.app_handle.clone(), target.clone())),
+                None,
+            ));
+            tools.push((
+                Arc::new(PageSnapshotTool::new(self.app_handle.clone(), target)),
                 None,
             ));
         }
@@ -713,29 +1353,65 @@ impl AgentManager {
         tools.push((
             Arc::new(WalletSpendTool::new(
                 self.wallet_store.clone(),
-                wallet_owner.clone(),
+                wallet_owner,
             )),
             Some(CapabilityKind::WalletSpend),
         ));
+        tools
+    }
 
-        let iproov = self.iproov.clone();
+    async fn build_tools(
+        &self,
+        wallet_owner: WalletOwner,
+        target: TabTarget,
+    ) -> Vec<(Arc<dyn McpTool>, Option<CapabilityKind>)> {
+        let mut tools = self.browser_tools(wallet_owner, target);
+        tools.push((
+            Arc::new(RecallTool::new(
+                self.browser_engine.clone(),
+                self.semantic_index.clone(),
+            )),
+            None,
+        ));
+
+        let IproovBackends {
+            gateway,
+            merchant,
+            spent_mandates,
+        } = self.iproov.clone();
         tools.push((
-            Arc::new(GatewayCreatePresentationTool::new(iproov.clone())),
+            Arc::new(GatewayCreatePresentationTool::new(gateway.clone())),
             None,
         ));
         tools.push((
-            Arc::new(GatewayApprovePresentationTool::new(iproov.clone())),
+            Arc::new(GatewayApprovePresentationTool::new(gateway.clone())),
             None,
         ));
         tools.push((
-            Arc::new(GatewayAwaitDecisionTool::new(iproov.clone())),
+            Arc::new(GatewayAwaitDecisionTool::new(gateway.clone())),
+            None,
+        ));
+        tools.push((Arc::new(GatewayIntrospectTool::new(gateway.clone())), None));
+        tools.push((Arc::new(MerchantQuoteCartTool::new(merchant.clone())), None));
+        tools.push((
+            Arc::new(GatewayApproveCartTool::new(
+                gateway.clone(),
+                merchant.clone(),
+            )),
+            None,
+        ));
+        tools.push((
+            Arc::new(GatewayFetchMandateTool::new(gateway.clone())),
+            None,
+        ));
+        tools.push((
+            Arc::new(MerchantPlaceOrderTool::new(
+                gateway,
+                merchant,
+                spent_mandates,
+            )),
             None,
         ));
-        tools.push((Arc::new(GatewayIntrospectTool::new(iproov.clone())), None));
-        tools.push((Arc::new(MerchantQuoteCartTool::new(iproov.clone())), None));
-        tools.push((Arc::new(GatewayApproveCartTool::new(iproov.clone())), None));
-        tools.push((Arc::new(GatewayFetchMandateTool::new(iproov.clone())), None));
-        tools.push((Arc::new(MerchantPlaceOrderTool::new(iproov)), None));
 
         let remote_tools = self.mcp_registry.remote_tools().await;
         tools.extend(remote_tools);
@@ -744,29 +1420,73 @@ impl AgentManager {
     }
 }
 
+#[async_trait]
+impl PipelineStepRunner for AgentManager {
+    async fn run_step(&self, request: AgentRunRequest) -> Result<PipelineStepOutcome> {
+        let response = self.run_task(request).await?;
+        let output = response
+            .agent
+            .structured_output
+            .or(response.agent.final_answer.map(serde_json::Value::String))
+            .unwrap_or_default();
+        Ok(PipelineStepOutcome {
+            run_id: response.run_id,
+            output,
+            cancelled: response.cancelled,
+            ledger_entries: response.ledger_entries,
+        })
+    }
+}
+
 struct MeteredModel {
     inner: Arc<dyn LanguageModelClient>,
     credits: Arc<AsyncMutex<CreditAccount>>,
+    provider: Provider,
     tokens_used: AtomicU64,
     estimated_tokens_used: AtomicU64,
+    usage: Mutex<CreditUsage>,
 }
 
 impl MeteredModel {
     fn new(
         inner: Arc<dyn LanguageModelClient>,
         credits: Arc<AsyncMutex<CreditAccount>>,
+        provider: Provider,
     ) -> Arc<Self> {
         Arc::new(Self {
             inner,
             credits,
+            provider,
             tokens_used: AtomicU64::new(0),
             estimated_tokens_used: AtomicU64::new(0),
+            usage: Mutex::new(Self::empty_usage(provider)),
         })
     }
 
+    fn empty_usage(provider: Provider) -> CreditUsage {
+        CreditUsage {
+            provider: provider.as_str().to_string(),
+            model: provider.model().to_string(),
+            ..CreditUsage::default()
+        }
+    }
+
     fn reset(&self) {
         self.tokens_used.store(0, Ordering::SeqCst);
         self.estimated_tokens_used.store(0, Ordering::SeqCst);
+        if let Ok(mut usage) = self.usage.lock() {
+            *usage = Self::empty_usage(self.provider);
+        }
+    }
+
+    /// Per-model breakdown of the tokens charged since the last reset.
+    fn usage(&self) -> Vec<CreditUsage> {
+        self.usage
+            .lock()
+            .map(|usage| usage.clone())
+            .into_iter()
+            .filter(|usage| usage.calls > 0)
+            .collect()
     }
 
     fn tokens_used(&self) -> u64 {
@@ -787,6 +1507,21 @@ impl LanguageModelClient for MeteredModel {
     ) -> anyhow::Result<LanguageModelResponse> {
         let mut response = self.inner.complete(prompt, options).await?;
         let (tokens, estimated) = usage_tokens(prompt, &response);
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.calls = usage.calls.saturating_add(1);
+            usage.prompt_tokens += response
+                .usage
+                .prompt_tokens
+                .unwrap_or_else(|| estimate_tokens(prompt))
+                as u64;
+            usage.completion_tokens += response
+                .usage
+                .completion_tokens
+                .unwrap_or_else(|| estimate_tokens(&response.text))
+                as u64;
+            usage.total_tokens += tokens as u64;
+            usage.estimated |= estimated;
+        }
         if response.usage.total_tokens.is_none() && tokens > 0 {
             response.usage.total_tokens = Some(tokens);
         }
@@ -859,7 +1594,9 @@ fn default_run_label(task: &str) -> String {
 mod tests {
     use super::*;
     use ai_agent::language_model::LanguageModelUsage;
-    use ai_agent::LanguageModelResponse;
+    use ai_agent::{
+        Cassette, CassetteEntry, LanguageModelResponse, PromptMatch, ReplayModelClient,
+    };
     use async_trait::async_trait;
     use std::sync::{Arc, Mutex};
     use tokio::sync::Mutex as AsyncMutex;
@@ -900,7 +1637,11 @@ mod tests {
             calls: Arc::new(Mutex::new(Vec::new())),
         };
         let credits = Arc::new(AsyncMutex::new(CreditAccount::new(100)));
-        let metered = MeteredModel::new(Arc::new(stub.clone()), credits.clone());
+        let metered = MeteredModel::new(
+            Arc::new(stub.clone()),
+            credits.clone(),
+            Provider::AppleFoundation,
+        );
 
         let result = metered
             .complete("summarise", &FoundationModelOptions::default())
@@ -915,6 +1656,14 @@ mod tests {
         let balance = credits.lock().await.balance();
         assert_eq!(balance, 58);
         assert_eq!(metered.tokens_used(), 42);
+
+        let usage = metered.usage();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].provider, "apple_foundation");
+        assert_eq!(usage[0].calls, 1);
+        assert_eq!(usage[0].prompt_tokens, 20);
+        assert_eq!(usage[0].completion_tokens, 22);
+        assert_eq!(usage[0].total_tokens, 42);
     }
 
     #[tokio::test]
@@ -932,7 +1681,11 @@ mod tests {
             calls: Arc::new(Mutex::new(Vec::new())),
         };
         let credits = Arc::new(AsyncMutex::new(CreditAccount::new(50)));
-        let metered = MeteredModel::new(Arc::new(stub.clone()), credits.clone());
+        let metered = MeteredModel::new(
+            Arc::new(stub.clone()),
+            credits.clone(),
+            Provider::AppleFoundation,
+        );
 
         let err = metered
             .complete("exhaust", &FoundationModelOptions::default())
@@ -958,7 +1711,7 @@ mod tests {
             calls: Arc::new(Mutex::new(Vec::new())),
         };
         let credits = Arc::new(AsyncMutex::new(CreditAccount::new(100)));
-        let metered = MeteredModel::new(Arc::new(stub), credits.clone());
+        let metered = MeteredModel::new(Arc::new(stub), credits.clone(), Provider::AppleFoundation);
 
         let result = metered
             .complete("estimate this prompt", &FoundationModelOptions::default())
@@ -971,4 +1724,33 @@ mod tests {
         assert!(metered.used_estimated_tokens());
         assert_eq!(credits.lock().await.balance(), 100 - expected as i64);
     }
+
+    /// Unit coverage for `MeteredModel` over a replayed cassette; it does not
+    /// build an `AgentManager`, which needs a live Tauri app handle.
+    #[tokio::test]
+    async fn metered_model_charges_usage_reported_by_a_replay_client() {
+        let cassette = Cassette::new(vec![CassetteEntry {
+            prompt: "plan the checkout".into(),
+            options: FoundationModelOptions::default(),
+            response: LanguageModelResponse {
+                text: "{\"type\":\"finish\",\"answer\":\"done\"}".into(),
+                usage: LanguageModelUsage {
+                    total_tokens: Some(30),
+                    prompt_tokens: None,
+                    completion_tokens: None,
+                },
+            },
+        }]);
+        let replay = Arc::new(ReplayModelClient::new(cassette, PromptMatch::Strict));
+        let credits = Arc::new(AsyncMutex::new(CreditAccount::new(100)));
+        let metered = MeteredModel::new(replay.clone(), credits.clone(), Provider::AppleFoundation);
+
+        metered
+            .complete("plan the checkout", &FoundationModelOptions::default())
+            .await
+            .expect("replayed completion should succeed");
+        assert_eq!(replay.remaining(), 0);
+        assert_eq!(metered.tokens_used(), 30);
+        assert_eq!(credits.lock().await.balance(), 70);
+    }
 }
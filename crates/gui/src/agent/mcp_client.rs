@@ -1,9 +1,16 @@
 use std::collections::VecDeque;
+use std::fmt;
 use std::process::Stdio;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use super::mcp_health::{McpCircuitBreakerConfig, McpToolHealth, ToolHealthTracker};
+use super::mcp_oauth::{
+    McpAuthorizationRequired, McpOAuthClient, McpOAuthConfig, McpOAuthPending, McpOAuthStatus,
+    McpOAuthTokenStore,
+};
+use super::mcp_sandbox::{McpSandboxConfig, McpSandboxViolation, McpSandboxViolationKind};
 use crate::mcp_profiles::McpConfigService;
 use agent_core::CapabilityKind;
 use ai_agent::{McpTool, McpToolDescription, McpToolError, McpToolResult};
@@ -11,14 +18,16 @@ use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
 use indexmap::IndexMap;
-use reqwest::Client;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::process::{Child, ChildStdin, ChildStdout, Command};
 use tokio::sync::Mutex;
-use tokio::time::timeout;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::tungstenite::http::header::{HeaderName, HeaderValue};
 use tokio_tungstenite::tungstenite::Message;
@@ -27,8 +36,17 @@ use tracing::warn;
 
 const TOOL_CACHE_TTL: Duration = Duration::from_secs(120);
 const MAX_LOG_ENTRIES: usize = 20;
-
-fn now_millis() -> u64 {
+const MAX_LIST_PAGES: usize = 20;
+/// How long to wait for the user to finish authorizing in the browser.
+const OAUTH_CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_ATTACHED_RESOURCE_CHARS: usize = 16_000;
+pub(super) const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+const MCP_SESSION_HEADER: &str = "mcp-session-id";
+const MCP_PROTOCOL_HEADER: &str = "mcp-protocol-version";
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+pub(super) fn now_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|dur| dur.as_millis() as u64)
@@ -72,6 +90,9 @@ pub struct McpResolvedServerConfig {
     pub program: Option<String>,
     pub args: Vec<String>,
     pub env: IndexMap<String, String>,
+    pub sandbox: Option<McpSandboxConfig>,
+    pub circuit_breaker: Option<McpCircuitBreakerConfig>,
+    pub oauth: Option<McpOAuthConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,6 +119,15 @@ pub struct McpServerConfig {
     pub args: Vec<String>,
     #[serde(default)]
     pub env: IndexMap<String, McpConfigValue>,
+    /// Confinement for stdio servers; ignored by network transports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<McpSandboxConfig>,
+    /// Per-tool error budget; defaults apply when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<McpCircuitBreakerConfig>,
+    /// OAuth 2.1 authorization for HTTP transports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<McpOAuthConfig>,
 }
 
 fn default_enabled() -> bool {
@@ -112,6 +142,10 @@ fn default_transport() -> McpTransportKind {
 #[serde(rename_all = "lowercase")]
 pub enum McpTransportKind {
     Http,
+    /// MCP streamable HTTP: JSON or SSE responses, session ids and a
+    /// server-initiated notification stream.
+    #[serde(rename = "streamable-http", alias = "streamableHttp", alias = "sse")]
+    StreamableHttp,
     Websocket,
     Stdio,
 }
@@ -147,6 +181,16 @@ pub struct McpRuntimeStatus {
     pub error_count: u64,
     #[serde(default)]
     pub recent_logs: Vec<McpLogEntry>,
+    #[serde(default)]
+    pub subscriptions: Vec<McpResourceSubscription>,
+    #[serde(default)]
+    pub sandboxed: bool,
+    #[serde(default)]
+    pub sandbox_violations: Vec<McpSandboxViolation>,
+    #[serde(default)]
+    pub tool_health: Vec<McpToolHealth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<McpOAuthStatus>,
 }
 
 impl McpRuntimeStatus {
@@ -159,6 +203,11 @@ impl McpRuntimeStatus {
             success_count: 0,
             error_count: 0,
             recent_logs: Vec::new(),
+            subscriptions: Vec::new(),
+            sandboxed: false,
+            sandbox_violations: Vec::new(),
+            tool_health: Vec::new(),
+            oauth: None,
         }
     }
 }
@@ -177,6 +226,101 @@ pub struct McpLogEntry {
     pub timestamp_ms: u64,
     pub level: McpLogLevel,
     pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_violation: Option<McpSandboxViolationKind>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceSubscription {
+    pub uri: String,
+    pub subscribed_at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_updated_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    #[serde(default)]
+    pub server_id: String,
+    pub uri: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Base64-encoded payload for binary resources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpPromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpPrompt {
+    #[serde(default)]
+    pub server_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpPromptMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpPromptResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<McpPromptMessage>,
+}
+
+/// Server-provided document to attach to an agent run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceRef {
+    pub server_id: String,
+    pub uri: String,
+}
+
+/// Server-provided prompt template to expand in front of an agent run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpPromptRef {
+    pub server_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: IndexMap<String, String>,
 }
 
 #[derive(Clone)]
@@ -195,7 +339,7 @@ impl McpServerRegistry {
 
     pub fn from_config_service(config_service: Arc<McpConfigService>) -> Result<Self> {
         let resolved = config_service.load_active_resolved_servers()?;
-        let clients = Self::build_clients(resolved);
+        let clients = Self::build_clients(resolved, config_service.secret_store());
         Ok(Self {
             clients: Arc::new(RwLock::new(clients)),
             config_service,
@@ -219,7 +363,7 @@ impl McpServerRegistry {
         for client in clients {
             match client.list_tools().await {
                 Ok(descriptions) => {
-                    for description in descriptions {
+                    for description in client.available_tools(descriptions).await {
                         tools.push((
                             Arc::new(RemoteMcpTool::new(client.clone(), description))
                                 as Arc<dyn McpTool>,
@@ -240,9 +384,104 @@ impl McpServerRegistry {
         tools
     }
 
+    pub async fn list_resources(&self) -> Vec<McpResource> {
+        let mut resources = Vec::new();
+        for client in self.clients_snapshot() {
+            match client.list_resources().await {
+                Ok(listed) => resources.extend(listed),
+                Err(err) => warn!(
+                    target: "mcp_client",
+                    server_id = client.id(),
+                    error = %err,
+                    "failed to list MCP resources"
+                ),
+            }
+        }
+        resources
+    }
+
+    pub async fn read_resource(
+        &self,
+        server_id: &str,
+        uri: &str,
+    ) -> Result<Vec<McpResourceContents>> {
+        self.client(server_id)?.read_resource(uri).await
+    }
+
+    pub async fn subscribe_resource(&self, server_id: &str, uri: &str) -> Result<()> {
+        self.client(server_id)?.subscribe_resource(uri).await
+    }
+
+    pub async fn unsubscribe_resource(&self, server_id: &str, uri: &str) -> Result<()> {
+        self.client(server_id)?.unsubscribe_resource(uri).await
+    }
+
+    pub async fn list_prompts(&self) -> Vec<McpPrompt> {
+        let mut prompts = Vec::new();
+        for client in self.clients_snapshot() {
+            match client.list_prompts().await {
+                Ok(listed) => prompts.extend(listed),
+                Err(err) => warn!(
+                    target: "mcp_client",
+                    server_id = client.id(),
+                    error = %err,
+                    "failed to list MCP prompts"
+                ),
+            }
+        }
+        prompts
+    }
+
+    pub async fn get_prompt(
+        &self,
+        server_id: &str,
+        name: &str,
+        arguments: &IndexMap<String, String>,
+    ) -> Result<McpPromptResult> {
+        self.client(server_id)?.get_prompt(name, arguments).await
+    }
+
+    /// Expands prompt templates and reads resources into a context block that
+    /// is prepended to an agent task. Returns `None` when nothing is attached.
+    pub async fn render_run_context(
+        &self,
+        prompts: &[McpPromptRef],
+        resources: &[McpResourceRef],
+    ) -> Result<Option<String>> {
+        let mut expanded_prompts = Vec::with_capacity(prompts.len());
+        for reference in prompts {
+            let result = self
+                .get_prompt(&reference.server_id, &reference.name, &reference.arguments)
+                .await
+                .with_context(|| {
+                    format!(
+                        "expanding MCP prompt `{}` from {}",
+                        reference.name, reference.server_id
+                    )
+                })?;
+            expanded_prompts.push(result);
+        }
+
+        let mut read_resources = Vec::with_capacity(resources.len());
+        for reference in resources {
+            let contents = self
+                .read_resource(&reference.server_id, &reference.uri)
+                .await
+                .with_context(|| {
+                    format!(
+                        "reading MCP resource {} from {}",
+                        reference.uri, reference.server_id
+                    )
+                })?;
+            read_resources.push((reference, contents));
+        }
+
+        Ok(render_attachments(&expanded_prompts, &read_resources))
+    }
+
     pub async fn reload_active(&self) -> Result<()> {
         let resolved = self.config_service.load_active_resolved_servers()?;
-        let clients = Self::build_clients(resolved);
+        let clients = Self::build_clients(resolved, self.config_service.secret_store());
         let mut guard = self.clients.write().expect("mcp clients poisoned");
         *guard = clients;
         Ok(())
@@ -280,15 +519,58 @@ impl McpServerRegistry {
             return Ok(existing.runtime_status().await);
         }
 
-        let client = Arc::new(McpServerClient::new(config)?);
+        let client = Arc::new(McpServerClient::new(
+            config,
+            self.config_service.secret_store(),
+        )?);
         let _ = client.list_tools().await;
         Ok(client.runtime_status().await)
     }
 
-    fn build_clients(configs: Vec<McpResolvedServerConfig>) -> Vec<Arc<McpServerClient>> {
+    /// Starts an OAuth authorization for the server; the caller sends the
+    /// user to the returned URL and then awaits [`complete_oauth`](Self::complete_oauth).
+    pub async fn begin_oauth(&self, server_id: &str) -> Result<McpOAuthPending> {
+        self.client(server_id)?.oauth()?.begin_authorization().await
+    }
+
+    pub async fn complete_oauth(&self, server_id: &str, pending: McpOAuthPending) -> Result<()> {
+        let client = self.client(server_id)?;
+        client
+            .oauth()?
+            .complete_authorization(pending, OAUTH_CALLBACK_TIMEOUT)
+            .await?;
+        client.reset_connection("OAuth authorization granted").await;
+        Ok(())
+    }
+
+    pub async fn sign_out_oauth(&self, server_id: &str) -> Result<()> {
+        let client = self.client(server_id)?;
+        client.oauth()?.sign_out().await?;
+        client.reset_connection("OAuth tokens removed").await;
+        Ok(())
+    }
+
+    fn clients_snapshot(&self) -> Vec<Arc<McpServerClient>> {
+        self.clients.read().expect("mcp clients poisoned").clone()
+    }
+
+    fn client(&self, server_id: &str) -> Result<Arc<McpServerClient>> {
+        self.clients
+            .read()
+            .expect("mcp clients poisoned")
+            .iter()
+            .find(|client| client.id() == server_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("MCP server `{server_id}` is not active"))
+    }
+
+    fn build_clients(
+        configs: Vec<McpResolvedServerConfig>,
+        token_store: Arc<dyn McpOAuthTokenStore>,
+    ) -> Vec<Arc<McpServerClient>> {
         let mut clients = Vec::new();
         for config in configs.into_iter().filter(|cfg| cfg.enabled) {
-            match McpServerClient::new(config) {
+            match McpServerClient::new(config, token_store.clone()) {
                 Ok(client) => clients.push(Arc::new(client)),
                 Err(err) => warn!(
                     target: "mcp_client",
@@ -308,8 +590,9 @@ struct McpServerClient {
     request_timeout: Duration,
     transport: McpTransport,
     request_id: AtomicU64,
-    state: Mutex<McpClientState>,
+    state: Arc<Mutex<McpClientState>>,
     init_lock: Mutex<()>,
+    oauth: Option<Arc<McpOAuthClient>>,
 }
 
 #[derive(Debug, Clone)]
@@ -320,20 +603,24 @@ struct ToolCache {
 
 struct McpClientState {
     initialised: bool,
+    server_capabilities: Value,
     cache_dirty: bool,
     cache: Option<ToolCache>,
     status: McpRuntimeStatus,
     logs: VecDeque<McpLogEntry>,
+    tool_health: ToolHealthTracker,
 }
 
 impl McpClientState {
-    fn new() -> Self {
+    fn new(circuit_breaker: McpCircuitBreakerConfig) -> Self {
         Self {
             initialised: false,
+            server_capabilities: Value::Null,
             cache_dirty: false,
             cache: None,
             status: McpRuntimeStatus::new(McpServerState::Idle),
             logs: VecDeque::with_capacity(MAX_LOG_ENTRIES),
+            tool_health: ToolHealthTracker::new(circuit_breaker),
         }
     }
 
@@ -345,6 +632,29 @@ impl McpClientState {
             timestamp_ms: now_millis(),
             level,
             message,
+            sandbox_violation: None,
+        });
+        self.refresh_recent_logs();
+    }
+
+    fn push_sandbox_violation(&mut self, kind: McpSandboxViolationKind, message: String) {
+        let timestamp_ms = now_millis();
+        if self.status.sandbox_violations.len() == MAX_LOG_ENTRIES {
+            self.status.sandbox_violations.remove(0);
+        }
+        self.status.sandbox_violations.push(McpSandboxViolation {
+            timestamp_ms,
+            kind,
+            message: message.clone(),
+        });
+        if self.logs.len() == MAX_LOG_ENTRIES {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(McpLogEntry {
+            timestamp_ms,
+            level: McpLogLevel::Error,
+            message: format!("sandbox violation: {message}"),
+            sandbox_violation: Some(kind),
         });
         self.refresh_recent_logs();
     }
@@ -362,6 +672,7 @@ impl McpClientState {
 
 enum McpTransport {
     Http(HttpTransport),
+    StreamableHttp(StreamableHttpTransport),
     Websocket(WebSocketTransport),
     Stdio(StdioTransport),
 }
@@ -370,8 +681,41 @@ struct HttpTransport {
     client: Client,
     endpoint: String,
     headers: IndexMap<String, String>,
+    oauth: Option<Arc<McpOAuthClient>>,
+}
+
+struct StreamableHttpTransport {
+    client: Client,
+    /// Client without an overall timeout for the long-lived notification stream.
+    stream_client: Client,
+    endpoint: String,
+    headers: IndexMap<String, String>,
+    oauth: Option<Arc<McpOAuthClient>>,
+    session: Arc<std::sync::Mutex<StreamableSession>>,
+    listener: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Debug, Default)]
+struct StreamableSession {
+    id: Option<String>,
+    last_event_id: Option<String>,
+}
+
+/// The server no longer recognises our `Mcp-Session-Id`; the client must
+/// re-initialise before retrying.
+#[derive(Debug)]
+struct McpSessionExpired {
+    server_id: String,
+}
+
+impl fmt::Display for McpSessionExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MCP session for server {} expired", self.server_id)
+    }
 }
 
+impl std::error::Error for McpSessionExpired {}
+
 struct WebSocketTransport {
     endpoint: String,
     headers: IndexMap<String, String>,
@@ -379,9 +723,11 @@ struct WebSocketTransport {
 }
 
 struct StdioTransport {
+    server_id: String,
     program: String,
     args: Vec<String>,
     env: IndexMap<String, String>,
+    sandbox: Option<McpSandboxConfig>,
     state: Mutex<Option<StdioProcess>>,
 }
 
@@ -389,21 +735,45 @@ struct StdioProcess {
     child: Child,
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
+    wall_clock_expired: Arc<AtomicBool>,
+    watchdog: Option<JoinHandle<()>>,
 }
 
 impl Drop for StdioProcess {
     fn drop(&mut self) {
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
         let _ = self.child.start_kill();
     }
 }
 
 impl McpServerClient {
-    fn new(config: McpResolvedServerConfig) -> Result<Self> {
+    fn new(
+        config: McpResolvedServerConfig,
+        token_store: Arc<dyn McpOAuthTokenStore>,
+    ) -> Result<Self> {
         let timeout = Duration::from_millis(config.timeout_ms.unwrap_or(20_000));
         let capability = config
             .default_capability
             .as_deref()
             .and_then(CapabilityKind::parse);
+        let oauth = match &config.oauth {
+            Some(oauth)
+                if matches!(
+                    config.transport,
+                    McpTransportKind::Http | McpTransportKind::StreamableHttp
+                ) =>
+            {
+                Some(Arc::new(McpOAuthClient::new(
+                    &config.id,
+                    &config.endpoint,
+                    oauth.clone(),
+                    token_store,
+                )?))
+            }
+            _ => None,
+        };
         let transport = match config.transport {
             McpTransportKind::Http => {
                 let endpoint = ensure_endpoint(&config, "http")?;
@@ -415,6 +785,27 @@ impl McpServerClient {
                     client,
                     endpoint,
                     headers: config.headers.clone(),
+                    oauth: oauth.clone(),
+                })
+            }
+            McpTransportKind::StreamableHttp => {
+                let endpoint = ensure_endpoint(&config, "streamable-http")?;
+                let client = Client::builder()
+                    .timeout(timeout)
+                    .build()
+                    .context("building HTTP client for MCP server")?;
+                let stream_client = Client::builder()
+                    .connect_timeout(timeout)
+                    .build()
+                    .context("building streaming HTTP client for MCP server")?;
+                McpTransport::StreamableHttp(StreamableHttpTransport {
+                    client,
+                    stream_client,
+                    endpoint,
+                    headers: config.headers.clone(),
+                    oauth: oauth.clone(),
+                    session: Arc::new(std::sync::Mutex::new(StreamableSession::default())),
+                    listener: std::sync::Mutex::new(None),
                 })
             }
             McpTransportKind::Websocket => {
@@ -442,9 +833,11 @@ impl McpServerClient {
                         )
                     })?;
                 McpTransport::Stdio(StdioTransport {
+                    server_id: config.id.clone(),
                     program,
                     args: config.args.clone(),
                     env: config.env.clone(),
+                    sandbox: config.sandbox.clone().filter(|sandbox| sandbox.enabled),
                     state: Mutex::new(None),
                 })
             }
@@ -457,8 +850,11 @@ impl McpServerClient {
             request_timeout: timeout,
             transport,
             request_id: AtomicU64::new(1),
-            state: Mutex::new(McpClientState::new()),
+            state: Arc::new(Mutex::new(McpClientState::new(
+                config.circuit_breaker.unwrap_or_default(),
+            ))),
             init_lock: Mutex::new(()),
+            oauth,
         })
     }
 
@@ -466,12 +862,40 @@ impl McpServerClient {
         &self.id
     }
 
+    fn oauth(&self) -> Result<&McpOAuthClient> {
+        self.oauth
+            .as_deref()
+            .ok_or_else(|| anyhow!("MCP server {} is not configured for OAuth", self.id))
+    }
+
+    /// Forces the next request to initialise a fresh session, e.g. after the
+    /// credentials changed.
+    async fn reset_connection(&self, reason: &str) {
+        let mut state = self.state.lock().await;
+        state.initialised = false;
+        state.status.state = McpServerState::Idle;
+        state.status.last_error = None;
+        state.status.last_updated_ms = now_millis();
+        state.push_log(McpLogLevel::Info, reason.to_string());
+    }
+
     fn capability(&self) -> Option<CapabilityKind> {
         self.default_capability.clone()
     }
 
     async fn runtime_status(&self) -> McpRuntimeStatus {
-        self.state.lock().await.status.clone()
+        let mut status = {
+            let mut state = self.state.lock().await;
+            let mut status = state.status.clone();
+            status.tool_health = state.tool_health.snapshot(Instant::now());
+            status
+        };
+        status.sandboxed =
+            matches!(&self.transport, McpTransport::Stdio(stdio) if stdio.sandbox.is_some());
+        if let Some(oauth) = &self.oauth {
+            status.oauth = Some(oauth.status().await);
+        }
+        status
     }
 
     async fn list_tools(&self) -> Result<Vec<McpToolDescription>> {
@@ -482,7 +906,7 @@ impl McpServerClient {
         }
 
         let result = self
-            .send_request("tools/list", json!({}))
+            .request("tools/list", json!({}))
             .await
             .context("requesting MCP tool list")?;
         let response: ToolListResponse =
@@ -503,7 +927,55 @@ impl McpServerClient {
         Ok(tools)
     }
 
+    /// Filters out tools whose circuit breaker is currently open.
+    async fn available_tools(&self, tools: Vec<McpToolDescription>) -> Vec<McpToolDescription> {
+        let mut state = self.state.lock().await;
+        let now = Instant::now();
+        tools
+            .into_iter()
+            .filter(|tool| state.tool_health.is_available(&tool.name, now))
+            .collect()
+    }
+
     async fn call_tool(&self, name: &str, args: Value) -> Result<McpToolResult, McpToolError> {
+        let admitted = self
+            .state
+            .lock()
+            .await
+            .tool_health
+            .admit(name, Instant::now());
+        if let Err(retry_in) = admitted {
+            return Err(McpToolError::Invocation(format!(
+                "MCP tool {name} on {} is temporarily disabled after repeated failures; retry in {} s",
+                self.id,
+                retry_in.as_secs().max(1)
+            )));
+        }
+
+        let start = Instant::now();
+        let outcome = self.invoke_tool(name, args).await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+        let mut state = self.state.lock().await;
+        match &outcome {
+            Ok(_) => state.tool_health.record_success(name, latency_ms),
+            Err(McpToolError::InvalidInput(_)) => state.tool_health.record_invalid_input(name),
+            Err(err) => {
+                let message = err.to_string();
+                if state
+                    .tool_health
+                    .record_failure(name, latency_ms, &message, Instant::now())
+                {
+                    state.push_log(
+                        McpLogLevel::Warn,
+                        format!("circuit opened for tool {name}: {}", message.trim()),
+                    );
+                }
+            }
+        }
+        outcome
+    }
+
+    async fn invoke_tool(&self, name: &str, args: Value) -> Result<McpToolResult, McpToolError> {
         self.ensure_initialised()
             .await
             .map_err(|err| McpToolError::Invocation(err.to_string()))?;
@@ -512,7 +984,7 @@ impl McpServerClient {
             "arguments": args
         });
         let result = self
-            .send_request("tools/call", payload)
+            .request("tools/call", payload)
             .await
             .map_err(|err| McpToolError::Invocation(err.to_string()))?;
         let response: ToolCallResponse = serde_json::from_value(result).map_err(|err| {
@@ -524,6 +996,131 @@ impl McpServerClient {
         })
     }
 
+    async fn list_resources(&self) -> Result<Vec<McpResource>> {
+        self.ensure_initialised().await?;
+        let mut resources = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let result = self
+                .request("resources/list", pagination_params(cursor.as_deref()))
+                .await
+                .context("requesting MCP resource list")?;
+            let page: ResourceListResponse =
+                serde_json::from_value(result).context("decoding MCP resource list response")?;
+            resources.extend(page.resources.into_iter().map(|mut resource| {
+                resource.server_id = self.id.clone();
+                resource
+            }));
+            match page.next_cursor {
+                Some(next) if !next.is_empty() => cursor = Some(next),
+                _ => break,
+            }
+        }
+        Ok(resources)
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<McpResourceContents>> {
+        self.ensure_initialised().await?;
+        let result = self
+            .request("resources/read", json!({ "uri": uri }))
+            .await
+            .with_context(|| format!("reading MCP resource {uri}"))?;
+        let response: ResourceReadResponse =
+            serde_json::from_value(result).context("decoding MCP resource contents")?;
+        Ok(response.contents)
+    }
+
+    async fn subscribe_resource(&self, uri: &str) -> Result<()> {
+        self.ensure_initialised().await?;
+        {
+            let state = self.state.lock().await;
+            let supported = state
+                .server_capabilities
+                .pointer("/resources/subscribe")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            if !supported {
+                return Err(anyhow!(
+                    "MCP server {} does not support resource subscriptions",
+                    self.id
+                ));
+            }
+            if state.status.subscriptions.iter().any(|sub| sub.uri == uri) {
+                return Ok(());
+            }
+        }
+
+        self.request("resources/subscribe", json!({ "uri": uri }))
+            .await
+            .with_context(|| format!("subscribing to MCP resource {uri}"))?;
+        let mut state = self.state.lock().await;
+        if !state.status.subscriptions.iter().any(|sub| sub.uri == uri) {
+            state.status.subscriptions.push(McpResourceSubscription {
+                uri: uri.to_string(),
+                subscribed_at_ms: now_millis(),
+                last_updated_ms: None,
+            });
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<()> {
+        {
+            let state = self.state.lock().await;
+            if !state.status.subscriptions.iter().any(|sub| sub.uri == uri) {
+                return Ok(());
+            }
+        }
+        self.request("resources/unsubscribe", json!({ "uri": uri }))
+            .await
+            .with_context(|| format!("unsubscribing from MCP resource {uri}"))?;
+        let mut state = self.state.lock().await;
+        state.status.subscriptions.retain(|sub| sub.uri != uri);
+        Ok(())
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<McpPrompt>> {
+        self.ensure_initialised().await?;
+        let mut prompts = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let result = self
+                .request("prompts/list", pagination_params(cursor.as_deref()))
+                .await
+                .context("requesting MCP prompt list")?;
+            let page: PromptListResponse =
+                serde_json::from_value(result).context("decoding MCP prompt list response")?;
+            prompts.extend(page.prompts.into_iter().map(|mut prompt| {
+                prompt.server_id = self.id.clone();
+                prompt
+            }));
+            match page.next_cursor {
+                Some(next) if !next.is_empty() => cursor = Some(next),
+                _ => break,
+            }
+        }
+        Ok(prompts)
+    }
+
+    async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &IndexMap<String, String>,
+    ) -> Result<McpPromptResult> {
+        self.ensure_initialised().await?;
+        let result = self
+            .request(
+                "prompts/get",
+                json!({
+                    "name": name,
+                    "arguments": arguments,
+                }),
+            )
+            .await
+            .with_context(|| format!("requesting MCP prompt `{name}`"))?;
+        serde_json::from_value(result).context("decoding MCP prompt response")
+    }
+
     async fn ensure_initialised(&self) -> Result<()> {
         {
             let state = self.state.lock().await;
@@ -542,6 +1139,7 @@ impl McpServerClient {
 
         self.update_status(McpServerState::Connecting, None).await;
         let params = json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
             "clientInfo": {
                 "name": "Advatar Browser",
                 "version": env!("CARGO_PKG_VERSION"),
@@ -550,17 +1148,58 @@ impl McpServerClient {
                 "tools": { "listChanged": true }
             }
         });
-        self.send_request("initialize", params)
+        let result = self
+            .send_request("initialize", params)
             .await
             .context("initialising MCP connection")?;
 
-        let mut state = self.state.lock().await;
-        state.initialised = true;
-        state.cache_dirty = true;
-        state.status = McpRuntimeStatus::new(McpServerState::Ready);
+        if let McpTransport::StreamableHttp(transport) = &self.transport {
+            transport
+                .notify("notifications/initialized")
+                .await
+                .with_context(|| format!("acknowledging MCP initialisation for {}", self.id))?;
+            transport.start_listener(self.id.clone(), self.state.clone());
+        }
+
+        let subscriptions = {
+            let mut state = self.state.lock().await;
+            state.initialised = true;
+            state.server_capabilities = result.get("capabilities").cloned().unwrap_or(Value::Null);
+            state.cache_dirty = true;
+            let sandbox_violations = std::mem::take(&mut state.status.sandbox_violations);
+            let subscriptions = std::mem::take(&mut state.status.subscriptions);
+            state.status = McpRuntimeStatus::new(McpServerState::Ready);
+            state.status.sandbox_violations = sandbox_violations;
+            subscriptions
+        };
+        self.restore_subscriptions(subscriptions).await;
         Ok(())
     }
 
+    /// Re-subscribes after a fresh session: the server forgot our
+    /// subscriptions along with the old session. Subscriptions it refuses are
+    /// dropped so the status does not claim updates that will never arrive.
+    async fn restore_subscriptions(&self, subscriptions: Vec<McpResourceSubscription>) {
+        for subscription in subscriptions {
+            // `send_request` rather than `request`: we already hold the init
+            // lock, so a second expiry must not try to re-initialise here.
+            let restored = self
+                .send_request("resources/subscribe", json!({ "uri": subscription.uri }))
+                .await;
+            let mut state = self.state.lock().await;
+            match restored {
+                Ok(_) => state.status.subscriptions.push(subscription),
+                Err(err) => state.push_log(
+                    McpLogLevel::Warn,
+                    format!(
+                        "dropped subscription to {} after reconnecting: {err:#}",
+                        subscription.uri
+                    ),
+                ),
+            }
+        }
+    }
+
     async fn cached_tools(&self) -> Option<Vec<McpToolDescription>> {
         let state = self.state.lock().await;
         if state.cache_dirty {
@@ -582,11 +1221,6 @@ impl McpServerClient {
         state.cache_dirty = false;
     }
 
-    async fn mark_cache_dirty(&self) {
-        let mut state = self.state.lock().await;
-        state.cache_dirty = true;
-    }
-
     async fn record_success(&self, method: &str, latency_ms: u64) {
         let mut state = self.state.lock().await;
         state.status.state = McpServerState::Ready;
@@ -622,6 +1256,17 @@ impl McpServerClient {
         }
     }
 
+    /// Sends a request, re-initialising once if the server dropped our session.
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        match self.send_request(method, params.clone()).await {
+            Err(err) if err.is::<McpSessionExpired>() => {
+                self.ensure_initialised().await?;
+                self.send_request(method, params).await
+            }
+            other => other,
+        }
+    }
+
     async fn send_request(&self, method: &str, params: Value) -> Result<Value> {
         let id = self.request_id.fetch_add(1, Ordering::SeqCst);
         let body = json!({
@@ -634,6 +1279,9 @@ impl McpServerClient {
         let start = Instant::now();
         let result = match &self.transport {
             McpTransport::Http(transport) => self.send_via_http(transport, &body).await,
+            McpTransport::StreamableHttp(transport) => {
+                self.send_via_streamable_http(transport, &body, id).await
+            }
             McpTransport::Websocket(transport) => {
                 self.send_via_websocket(transport, body.clone(), id).await
             }
@@ -668,12 +1316,22 @@ impl McpServerClient {
         for (key, value) in &transport.headers {
             request = request.header(key, value);
         }
+        let request = authorize_request(transport.oauth.as_deref(), request).await?;
 
         let response = request
             .send()
             .await
             .with_context(|| format!("request to MCP server {} failed", self.id))?;
         let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            if let Some(oauth) = &transport.oauth {
+                oauth.invalidate().await;
+                return Err(McpAuthorizationRequired {
+                    server_id: self.id.clone(),
+                }
+                .into());
+            }
+        }
         let payload: Value = response
             .json()
             .await
@@ -691,6 +1349,110 @@ impl McpServerClient {
         Ok(payload)
     }
 
+    async fn send_via_streamable_http(
+        &self,
+        transport: &StreamableHttpTransport,
+        body: &Value,
+        request_id: u64,
+    ) -> Result<Value> {
+        let had_session = transport.session_id().is_some();
+        let response = transport
+            .post(body)
+            .await?
+            .send()
+            .await
+            .with_context(|| format!("request to MCP server {} failed", self.id))?;
+        let status = response.status();
+
+        if status == StatusCode::UNAUTHORIZED {
+            if let Some(oauth) = &transport.oauth {
+                oauth.invalidate().await;
+                return Err(McpAuthorizationRequired {
+                    server_id: self.id.clone(),
+                }
+                .into());
+            }
+        }
+        if status == StatusCode::NOT_FOUND && had_session {
+            transport.reset_session();
+            self.state.lock().await.initialised = false;
+            return Err(McpSessionExpired {
+                server_id: self.id.clone(),
+            }
+            .into());
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "MCP server {} returned HTTP {}: {}",
+                self.id,
+                status,
+                text.trim()
+            ));
+        }
+
+        if let Some(session_id) = response
+            .headers()
+            .get(MCP_SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            transport.set_session_id(session_id);
+        }
+
+        let is_event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        if !is_event_stream {
+            let payload: Value = response
+                .json()
+                .await
+                .with_context(|| format!("invalid MCP response from {}", self.id))?;
+            let messages = match payload {
+                Value::Array(items) => items,
+                other => vec![other],
+            };
+            let mut matched = None;
+            for message in messages {
+                if matched.is_none() && Self::matches_request(&message, request_id) {
+                    matched = Some(message);
+                } else {
+                    self.handle_notification(&message).await;
+                }
+            }
+            return matched.ok_or_else(|| anyhow!("MCP server {} returned no response", self.id));
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut decoder = SseDecoder::default();
+        loop {
+            let chunk = timeout(self.request_timeout, stream.next())
+                .await
+                .map_err(|_| anyhow!("MCP server {} timed out waiting for response", self.id))?;
+            let chunk = match chunk {
+                Some(chunk) => {
+                    chunk.with_context(|| format!("reading MCP event stream from {}", self.id))?
+                }
+                None => {
+                    return Err(anyhow!(
+                        "MCP server {} closed the event stream before responding",
+                        self.id
+                    ))
+                }
+            };
+            for event in decoder.push(&chunk) {
+                let Ok(message) = serde_json::from_str::<Value>(&event.data) else {
+                    continue;
+                };
+                if Self::matches_request(&message, request_id) {
+                    return Ok(message);
+                }
+                self.handle_notification(&message).await;
+            }
+        }
+    }
+
     async fn send_via_websocket(
         &self,
         transport: &WebSocketTransport,
@@ -801,32 +1563,65 @@ impl McpServerClient {
         let process = guard
             .as_mut()
             .ok_or_else(|| anyhow!("missing MCP stdio process for {}", self.id))?;
-        process.send(&body).await?;
-
-        loop {
-            match process.read_message(self.request_timeout).await {
-                Ok(value) => {
-                    if Self::matches_request(&value, request_id) {
-                        return Ok(value);
+        let outcome = match process.send(&body).await {
+            Ok(()) => loop {
+                match process.read_message(self.request_timeout).await {
+                    Ok(value) => {
+                        if Self::matches_request(&value, request_id) {
+                            break Ok(value);
+                        }
+                        self.handle_notification(&value).await;
                     }
-                    self.handle_notification(&value).await;
+                    Err(err) => break Err(err),
                 }
-                Err(err) => {
-                    guard.take();
-                    return Err(err);
+            },
+            Err(err) => Err(err),
+        };
+
+        match outcome {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                if let Some(mut process) = guard.take() {
+                    self.record_stdio_exit(transport, &mut process).await;
                 }
+                Err(err)
             }
         }
     }
 
-    async fn handle_notification(&self, payload: &Value) {
-        if let Some(method) = payload.get("method").and_then(|m| m.as_str()) {
-            if method.eq_ignore_ascii_case("tools/listChanged") {
-                self.mark_cache_dirty().await;
+    /// Called once a stdio server stops answering: the replacement process
+    /// needs a fresh `initialize`, and a sandboxed exit may reveal which limit
+    /// the server hit.
+    async fn record_stdio_exit(&self, transport: &StdioTransport, process: &mut StdioProcess) {
+        let violation = match &transport.sandbox {
+            Some(sandbox) => {
+                let expired = process.wall_clock_expired.load(Ordering::SeqCst);
+                let status = timeout(Duration::from_millis(500), process.child.wait()).await;
+                match status {
+                    Ok(Ok(status)) => sandbox.classify_exit(status, expired),
+                    _ => None,
+                }
             }
+            None => None,
+        };
+
+        let mut state = self.state.lock().await;
+        state.initialised = false;
+        if let Some((kind, message)) = violation {
+            warn!(
+                target: "mcp_client",
+                server_id = self.id.as_str(),
+                kind = ?kind,
+                "{message}"
+            );
+            state.push_sandbox_violation(kind, message);
         }
     }
 
+    async fn handle_notification(&self, payload: &Value) {
+        apply_notification(&self.state, payload).await;
+    }
+
     fn matches_request(payload: &Value, request_id: u64) -> bool {
         match payload.get("id") {
             Some(Value::Number(num)) => num.as_u64() == Some(request_id),
@@ -852,6 +1647,299 @@ impl McpServerClient {
     }
 }
 
+impl StreamableHttpTransport {
+    async fn post(&self, body: &Value) -> Result<reqwest::RequestBuilder> {
+        let request = self
+            .client
+            .post(&self.endpoint)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(body);
+        authorize_request(self.oauth.as_deref(), self.apply_headers(request)).await
+    }
+
+    async fn notify(&self, method: &str) -> Result<()> {
+        let body = json!({ "jsonrpc": "2.0", "method": method });
+        let response = self.post(&body).await?.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "notification {method} rejected with HTTP {}",
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+
+    fn apply_headers(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request = request.header(MCP_PROTOCOL_HEADER, MCP_PROTOCOL_VERSION);
+        if let Some(session_id) = self.session_id() {
+            request = request.header(MCP_SESSION_HEADER, session_id);
+        }
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        request
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.session
+            .lock()
+            .expect("mcp session poisoned")
+            .id
+            .clone()
+    }
+
+    fn set_session_id(&self, session_id: &str) {
+        let mut session = self.session.lock().expect("mcp session poisoned");
+        if session.id.as_deref() != Some(session_id) {
+            session.id = Some(session_id.to_string());
+            session.last_event_id = None;
+        }
+    }
+
+    fn reset_session(&self) {
+        *self.session.lock().expect("mcp session poisoned") = StreamableSession::default();
+    }
+
+    /// Opens the server-initiated notification stream (GET on the endpoint),
+    /// replacing any listener left over from a previous session.
+    fn start_listener(&self, server_id: String, state: Arc<Mutex<McpClientState>>) {
+        let listener = NotificationListener {
+            server_id,
+            client: self.stream_client.clone(),
+            endpoint: self.endpoint.clone(),
+            headers: self.headers.clone(),
+            oauth: self.oauth.clone(),
+            session: self.session.clone(),
+            state,
+        };
+        let handle = tokio::spawn(listener.run());
+        if let Some(previous) = self
+            .listener
+            .lock()
+            .expect("mcp listener poisoned")
+            .replace(handle)
+        {
+            previous.abort();
+        }
+    }
+}
+
+impl Drop for StreamableHttpTransport {
+    fn drop(&mut self) {
+        if let Ok(mut listener) = self.listener.lock() {
+            if let Some(handle) = listener.take() {
+                handle.abort();
+            }
+        }
+    }
+}
+
+struct NotificationListener {
+    server_id: String,
+    client: Client,
+    endpoint: String,
+    headers: IndexMap<String, String>,
+    oauth: Option<Arc<McpOAuthClient>>,
+    session: Arc<std::sync::Mutex<StreamableSession>>,
+    state: Arc<Mutex<McpClientState>>,
+}
+
+enum ListenerExit {
+    /// Stream ended after delivering events; reconnect promptly.
+    Closed,
+    /// Server does not offer a GET stream (HTTP 405).
+    Unsupported,
+    /// Session was dropped by the server; the next request re-initialises.
+    SessionExpired,
+    /// Credentials were rejected; the stream restarts after re-authorization.
+    Unauthorized,
+}
+
+impl NotificationListener {
+    async fn run(self) {
+        let mut attempt = 0u32;
+        loop {
+            match self.listen_once().await {
+                Ok(ListenerExit::Unsupported) => return,
+                Ok(ListenerExit::Unauthorized) => {
+                    if let Some(oauth) = &self.oauth {
+                        oauth.invalidate().await;
+                    }
+                    return;
+                }
+                Err(err) if err.is::<McpAuthorizationRequired>() => return,
+                Ok(ListenerExit::SessionExpired) => {
+                    *self.session.lock().expect("mcp session poisoned") =
+                        StreamableSession::default();
+                    self.state.lock().await.initialised = false;
+                    return;
+                }
+                Ok(ListenerExit::Closed) => attempt = 0,
+                Err(err) => {
+                    let mut state = self.state.lock().await;
+                    state.push_log(
+                        McpLogLevel::Warn,
+                        format!("notification stream error: {}", err),
+                    );
+                    attempt = attempt.saturating_add(1);
+                }
+            }
+            sleep(reconnect_delay(attempt)).await;
+        }
+    }
+
+    async fn listen_once(&self) -> Result<ListenerExit> {
+        let (session_id, last_event_id) = {
+            let session = self.session.lock().expect("mcp session poisoned");
+            (session.id.clone(), session.last_event_id.clone())
+        };
+        let mut request = self
+            .client
+            .get(&self.endpoint)
+            .header(ACCEPT, "text/event-stream")
+            .header(MCP_PROTOCOL_HEADER, MCP_PROTOCOL_VERSION);
+        if let Some(session_id) = &session_id {
+            request = request.header(MCP_SESSION_HEADER, session_id);
+        }
+        if let Some(last_event_id) = last_event_id {
+            request = request.header("last-event-id", last_event_id);
+        }
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        let request = authorize_request(self.oauth.as_deref(), request).await?;
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("opening MCP notification stream for {}", self.server_id))?;
+        match response.status() {
+            StatusCode::METHOD_NOT_ALLOWED => return Ok(ListenerExit::Unsupported),
+            StatusCode::UNAUTHORIZED => return Ok(ListenerExit::Unauthorized),
+            StatusCode::NOT_FOUND if session_id.is_some() => {
+                return Ok(ListenerExit::SessionExpired)
+            }
+            status if !status.is_success() => {
+                return Err(anyhow!("notification stream returned HTTP {status}"))
+            }
+            _ => {}
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut decoder = SseDecoder::default();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("reading MCP notification stream")?;
+            for event in decoder.push(&chunk) {
+                if let Some(id) = event.id {
+                    self.session
+                        .lock()
+                        .expect("mcp session poisoned")
+                        .last_event_id = Some(id);
+                }
+                if let Ok(message) = serde_json::from_str::<Value>(&event.data) {
+                    apply_notification(&self.state, &message).await;
+                }
+            }
+        }
+        Ok(ListenerExit::Closed)
+    }
+}
+
+/// Adds the OAuth bearer token, refreshing it first when it is about to expire.
+async fn authorize_request(
+    oauth: Option<&McpOAuthClient>,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::RequestBuilder> {
+    match oauth {
+        Some(oauth) => Ok(request.bearer_auth(oauth.bearer_token().await?)),
+        None => Ok(request),
+    }
+}
+
+/// Incremental `text/event-stream` parser. Only `data` and `id` fields matter
+/// for MCP; event names and retry hints are ignored.
+#[derive(Debug, Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+    last_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SseEvent {
+    id: Option<String>,
+    data: String,
+}
+
+impl SseDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        id: self.last_id.clone(),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "data" => self.data.push(value.to_string()),
+                "id" => self.last_id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(1u32 << attempt.min(16))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+async fn apply_notification(state: &Mutex<McpClientState>, payload: &Value) {
+    let Some(method) = payload.get("method").and_then(Value::as_str) else {
+        return;
+    };
+    match method {
+        "notifications/tools/list_changed" | "tools/listChanged" => {
+            state.lock().await.cache_dirty = true;
+        }
+        "notifications/resources/updated" => {
+            let Some(uri) = payload.pointer("/params/uri").and_then(Value::as_str) else {
+                return;
+            };
+            let mut state = state.lock().await;
+            let Some(subscription) = state
+                .status
+                .subscriptions
+                .iter_mut()
+                .find(|sub| sub.uri == uri)
+            else {
+                return;
+            };
+            subscription.last_updated_ms = Some(now_millis());
+            state.push_log(McpLogLevel::Info, format!("resource {uri} updated"));
+        }
+        _ => {}
+    }
+}
+
 impl WebSocketTransport {
     async fn connect(&self) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         let mut request = self
@@ -883,9 +1971,41 @@ impl StdioTransport {
         }
         command.stdin(Stdio::piped()).stdout(Stdio::piped());
 
+        // Keep the prepared sandbox alive until the child has exec'd; it owns
+        // the Landlock ruleset descriptor used by the pre-exec hook.
+        let sandbox = match &self.sandbox {
+            Some(config) => {
+                let prepared = config
+                    .prepare(&self.server_id, &self.program)
+                    .with_context(|| format!("preparing sandbox for {}", self.program))?;
+                prepared.configure(&mut command);
+                Some(prepared)
+            }
+            None => None,
+        };
+
         let mut child = command
             .spawn()
             .with_context(|| format!("spawning MCP stdio program {}", self.program))?;
+        let wall_clock_expired = Arc::new(AtomicBool::new(false));
+        let watchdog = sandbox
+            .as_ref()
+            .and_then(|prepared| prepared.wall_limit())
+            .zip(child.id())
+            .map(|(limit, pid)| {
+                let expired = wall_clock_expired.clone();
+                tokio::spawn(async move {
+                    sleep(limit).await;
+                    expired.store(true, Ordering::SeqCst);
+                    #[cfg(unix)]
+                    // SAFETY: the watchdog is aborted when the process handle is
+                    // dropped, so `pid` still names our child here.
+                    unsafe {
+                        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+                    }
+                })
+            });
+        drop(sandbox);
         let stdin = child
             .stdin
             .take()
@@ -898,6 +2018,8 @@ impl StdioTransport {
             child,
             stdin,
             stdout: BufReader::new(stdout),
+            wall_clock_expired,
+            watchdog,
         })
     }
 }
@@ -975,6 +2097,30 @@ struct ToolCallResponse {
     metadata: Option<IndexMap<String, Value>>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceListResponse {
+    #[serde(default)]
+    resources: Vec<McpResource>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceReadResponse {
+    #[serde(default)]
+    contents: Vec<McpResourceContents>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptListResponse {
+    #[serde(default)]
+    prompts: Vec<McpPrompt>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
 #[derive(Clone)]
 struct RemoteMcpTool {
     description: McpToolDescription,
@@ -1001,6 +2147,89 @@ impl McpTool for RemoteMcpTool {
     }
 }
 
+fn pagination_params(cursor: Option<&str>) -> Value {
+    match cursor {
+        Some(cursor) => json!({ "cursor": cursor }),
+        None => json!({}),
+    }
+}
+
+fn render_attachments(
+    prompts: &[McpPromptResult],
+    resources: &[(&McpResourceRef, Vec<McpResourceContents>)],
+) -> Option<String> {
+    let mut sections = Vec::new();
+
+    for prompt in prompts {
+        for message in &prompt.messages {
+            let text = prompt_content_text(&message.content);
+            if !text.trim().is_empty() {
+                sections.push(format!("[{}] {}", message.role, text.trim()));
+            }
+        }
+    }
+
+    for (reference, contents) in resources {
+        for content in contents {
+            let body = match (&content.text, &content.blob) {
+                (Some(text), _) => truncate_chars(text, MAX_ATTACHED_RESOURCE_CHARS),
+                (None, Some(_)) => format!(
+                    "(binary content omitted, {})",
+                    content
+                        .mime_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream")
+                ),
+                (None, None) => continue,
+            };
+            sections.push(format!(
+                "Resource {} (server {}):\n{}",
+                content.uri, reference.server_id, body
+            ));
+        }
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n\n"))
+    }
+}
+
+fn prompt_content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(prompt_content_text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Object(map) => match map.get("type").and_then(Value::as_str) {
+            Some("text") => map
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            Some("resource") => map
+                .get("resource")
+                .and_then(|resource| resource.get("text"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
 fn ensure_endpoint(config: &McpResolvedServerConfig, label: &str) -> Result<String> {
     if config.endpoint.trim().is_empty() {
         return Err(anyhow!(
@@ -1015,6 +2244,8 @@ fn ensure_endpoint(config: &McpResolvedServerConfig, label: &str) -> Result<Stri
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::McpCircuitState;
+    use crate::mcp_profiles::McpSecretStore;
 
     #[tokio::test]
     async fn probe_returns_disabled_for_inactive_server() {
@@ -1032,6 +2263,9 @@ mod tests {
                 program: None,
                 args: vec![],
                 env: IndexMap::new(),
+                sandbox: None,
+                circuit_breaker: None,
+                oauth: None,
             })
             .await
             .expect("should return status");
@@ -1054,8 +2288,287 @@ mod tests {
                 program: None,
                 args: vec![],
                 env: IndexMap::new(),
+                sandbox: None,
+                circuit_breaker: None,
+                oauth: None,
             })
             .await;
         assert!(result.is_err(), "expected error for missing endpoint");
     }
+
+    #[tokio::test]
+    async fn failing_tool_is_withheld_once_breaker_opens() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = McpServerClient::new(
+            McpResolvedServerConfig {
+                id: "flaky".into(),
+                name: None,
+                endpoint: format!("http://127.0.0.1:{port}/mcp"),
+                enabled: true,
+                headers: IndexMap::new(),
+                timeout_ms: Some(1_000),
+                default_capability: None,
+                transport: McpTransportKind::Http,
+                program: None,
+                args: vec![],
+                env: IndexMap::new(),
+                sandbox: None,
+                circuit_breaker: Some(McpCircuitBreakerConfig {
+                    failure_threshold: 2,
+                    ..McpCircuitBreakerConfig::default()
+                }),
+                oauth: None,
+            },
+            Arc::new(McpSecretStore::new()),
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            assert!(client.call_tool("search", json!({})).await.is_err());
+        }
+        let err = client.call_tool("search", json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("temporarily disabled"), "{err}");
+
+        let tools = vec![
+            McpToolDescription::new("search", "", json!({})),
+            McpToolDescription::new("fetch", "", json!({})),
+        ];
+        let available = client.available_tools(tools).await;
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].name, "fetch");
+
+        let status = client.runtime_status().await;
+        assert_eq!(status.tool_health.len(), 1);
+        assert_eq!(status.tool_health[0].circuit, McpCircuitState::Open);
+        assert_eq!(status.tool_health[0].rejected, 1);
+    }
+
+    #[test]
+    fn sse_decoder_reassembles_split_events() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"id: 7\r\ndata: {\"a\":").is_empty());
+        let events = decoder.push(b"1}\r\n\r\n: keep-alive\n\ndata: one\ndata: two\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    id: Some("7".into()),
+                    data: "{\"a\":1}".into(),
+                },
+                SseEvent {
+                    id: Some("7".into()),
+                    data: "one\ntwo".into(),
+                },
+            ]
+        );
+        assert_eq!(reconnect_delay(0), RECONNECT_BASE_DELAY);
+        assert_eq!(reconnect_delay(3), Duration::from_secs(4));
+        assert_eq!(reconnect_delay(30), RECONNECT_MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn list_changed_notification_invalidates_tool_cache() {
+        let state = Mutex::new(McpClientState::new(McpCircuitBreakerConfig::default()));
+        {
+            let mut guard = state.lock().await;
+            guard.cache = Some(ToolCache {
+                tools: Vec::new(),
+                fetched_at: Instant::now(),
+            });
+            guard.status.subscriptions.push(McpResourceSubscription {
+                uri: "file:///notes.md".into(),
+                subscribed_at_ms: 0,
+                last_updated_ms: None,
+            });
+        }
+
+        apply_notification(
+            &state,
+            &json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" }),
+        )
+        .await;
+        apply_notification(
+            &state,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": "file:///notes.md" }
+            }),
+        )
+        .await;
+
+        let guard = state.lock().await;
+        assert!(guard.cache_dirty);
+        assert!(guard.status.subscriptions[0].last_updated_ms.is_some());
+    }
+
+    /// Streamable HTTP server that expires the first session on its first
+    /// non-subscribe request and pushes a resource update alongside the
+    /// reply to the retried one. Records `(session, method)` per request.
+    async fn spawn_expiring_session_server() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::<(String, String)>::new()));
+        let log = seen.clone();
+        tokio::spawn(async move {
+            let mut sessions = 0;
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut reader = BufReader::new(&mut socket);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).await.unwrap();
+                let (mut session, mut length) = (String::new(), 0);
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await.unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(':').unwrap();
+                    match name.to_ascii_lowercase().as_str() {
+                        "content-length" => length = value.trim().parse().unwrap(),
+                        MCP_SESSION_HEADER => session = value.trim().to_string(),
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).await.unwrap();
+
+                let (status, headers, payload) = if request_line.starts_with("GET") {
+                    ("405 Method Not Allowed", String::new(), Value::Null)
+                } else {
+                    let message: Value = serde_json::from_slice(&body).unwrap();
+                    let method = message["method"].as_str().unwrap().to_string();
+                    log.lock().await.push((session.clone(), method.clone()));
+                    let id = message["id"].clone();
+                    match method.as_str() {
+                        "initialize" => {
+                            sessions += 1;
+                            (
+                                "200 OK",
+                                format!("{MCP_SESSION_HEADER}: s{sessions}\r\n"),
+                                json!({
+                                    "jsonrpc": "2.0",
+                                    "id": id,
+                                    "result": {
+                                        "capabilities": { "resources": { "subscribe": true } }
+                                    }
+                                }),
+                            )
+                        }
+                        "notifications/initialized" => ("202 Accepted", String::new(), Value::Null),
+                        "resources/subscribe" => (
+                            "200 OK",
+                            String::new(),
+                            json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+                        ),
+                        _ if session == "s1" => ("404 Not Found", String::new(), Value::Null),
+                        _ => (
+                            "200 OK",
+                            String::new(),
+                            json!([
+                                {
+                                    "jsonrpc": "2.0",
+                                    "method": "notifications/resources/updated",
+                                    "params": { "uri": "file:///notes.md" }
+                                },
+                                { "jsonrpc": "2.0", "id": id, "result": { "prompts": [] } }
+                            ]),
+                        ),
+                    }
+                };
+                let body = if payload.is_null() {
+                    String::new()
+                } else {
+                    payload.to_string()
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\n{headers}\
+                     content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (endpoint, seen)
+    }
+
+    #[tokio::test]
+    async fn subscriptions_survive_an_expired_session() {
+        let (endpoint, seen) = spawn_expiring_session_server().await;
+        let client = McpServerClient::new(
+            McpResolvedServerConfig {
+                id: "notes".into(),
+                name: None,
+                endpoint,
+                enabled: true,
+                headers: IndexMap::new(),
+                timeout_ms: Some(1_000),
+                default_capability: None,
+                transport: McpTransportKind::StreamableHttp,
+                program: None,
+                args: vec![],
+                env: IndexMap::new(),
+                sandbox: None,
+                circuit_breaker: None,
+                oauth: None,
+            },
+            Arc::new(McpSecretStore::new()),
+        )
+        .unwrap();
+
+        client.subscribe_resource("file:///notes.md").await.unwrap();
+        // The first session expires here; the client re-initialises and
+        // retries, and the retried reply carries the resource update.
+        assert!(client.list_prompts().await.unwrap().is_empty());
+
+        let status = client.runtime_status().await;
+        assert_eq!(status.subscriptions.len(), 1);
+        assert_eq!(status.subscriptions[0].uri, "file:///notes.md");
+        assert!(status.subscriptions[0].last_updated_ms.is_some());
+        let subscribes: Vec<String> = seen
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, method)| method == "resources/subscribe")
+            .map(|(session, _)| session.clone())
+            .collect();
+        assert_eq!(subscribes, vec!["s1".to_string(), "s2".to_string()]);
+    }
+
+    #[test]
+    fn attachments_render_prompt_messages_and_resource_text() {
+        let prompt: McpPromptResult = serde_json::from_value(json!({
+            "description": "Review",
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Review the policy." } },
+                { "role": "assistant", "content": { "type": "image", "data": "AAAA" } }
+            ]
+        }))
+        .unwrap();
+        let reference = McpResourceRef {
+            server_id: "docs".into(),
+            uri: "file:///policy.md".into(),
+        };
+        let contents: ResourceReadResponse = serde_json::from_value(json!({
+            "contents": [
+                { "uri": "file:///policy.md", "mimeType": "text/markdown", "text": "No refunds." },
+                { "uri": "file:///logo.png", "mimeType": "image/png", "blob": "iVBOR" }
+            ]
+        }))
+        .unwrap();
+
+        let rendered =
+            render_attachments(&[prompt], &[(&reference, contents.contents)]).expect("context");
+        assert!(rendered.contains("[user] Review the policy."));
+        assert!(!rendered.contains("[assistant]"));
+        assert!(rendered.contains("Resource file:///policy.md (server docs):\nNo refunds."));
+        assert!(rendered.contains("binary content omitted, image/png"));
+        assert!(render_attachments(&[], &[]).is_none());
+    }
 }
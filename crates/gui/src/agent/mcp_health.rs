@@ -0,0 +1,574 @@
+//! Per-tool health tracking and circuit breaking for remote MCP servers.
+//!
+//! Every `tools/call` is recorded against the tool it targeted: latency goes
+//! into a fixed-bucket histogram and the outcome into a sliding window used to
+//! compute an error rate. When a tool exhausts its error budget the breaker
+//! opens and the tool is withheld from the orchestrator until the cooldown
+//! elapses. The breaker then goes half-open and admits a single probe call;
+//! success closes it again, failure re-opens it with a doubled cooldown.
+//!
+//! The state machine follows the bitswap `CircuitBreaker` in the IPFS crate,
+//! but is keyed per tool and driven by an explicit clock so it stays testable.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::mcp_client::now_millis;
+
+/// Upper bounds (inclusive, in milliseconds) of the latency histogram buckets.
+/// Calls slower than the last bound land in an overflow bucket.
+const LATENCY_BUCKETS_MS: [u64; 10] = [10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+/// How long a half-open probe may stay outstanding before another call is
+/// admitted in its place, so a probe whose caller went away cannot wedge the
+/// breaker.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpCircuitBreakerConfig {
+    pub enabled: bool,
+    /// Consecutive failures that open the breaker regardless of error rate.
+    pub failure_threshold: u32,
+    /// Error rate over the sliding window (0.0–1.0) that opens the breaker.
+    pub error_rate_threshold: f64,
+    /// Calls required in the window before the error rate is considered.
+    pub min_calls: u32,
+    /// Number of recent outcomes kept per tool.
+    pub window_size: u32,
+    /// Initial time a tripped tool stays unavailable.
+    pub cooldown_ms: u64,
+    /// Ceiling for the cooldown after repeated failed probes.
+    pub max_cooldown_ms: u64,
+}
+
+impl Default for McpCircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 5,
+            error_rate_threshold: 0.5,
+            min_calls: 10,
+            window_size: 50,
+            cooldown_ms: 30_000,
+            max_cooldown_ms: 600_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum McpCircuitState {
+    Closed,
+    HalfOpen,
+    Open,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpLatencyBucket {
+    /// Inclusive upper bound; `None` for the overflow bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpLatencySummary {
+    pub buckets: Vec<McpLatencyBucket>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p50_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p95_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolHealth {
+    pub tool: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub rejected: u64,
+    /// Error rate over the sliding window.
+    pub error_rate: f64,
+    pub consecutive_failures: u32,
+    pub circuit: McpCircuitState,
+    /// Wall-clock time at which an open breaker will admit a probe.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub latency: McpLatencySummary,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    total_ms: u64,
+    max_ms: u64,
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, latency_ms: u64) {
+        let index = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[index] += 1;
+        self.total_ms = self.total_ms.saturating_add(latency_ms);
+        self.max_ms = self.max_ms.max(latency_ms);
+    }
+
+    fn samples(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Upper bound of the bucket containing the given quantile. The overflow
+    /// bucket reports the largest latency seen.
+    fn quantile(&self, quantile: f64) -> Option<u64> {
+        let samples = self.samples();
+        if samples == 0 {
+            return None;
+        }
+        let rank = ((samples as f64) * quantile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(
+                    LATENCY_BUCKETS_MS
+                        .get(index)
+                        .copied()
+                        .unwrap_or(self.max_ms)
+                        .min(self.max_ms),
+                );
+            }
+        }
+        Some(self.max_ms)
+    }
+
+    fn summary(&self) -> McpLatencySummary {
+        let samples = self.samples();
+        let buckets = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(index, count)| McpLatencyBucket {
+                le_ms: LATENCY_BUCKETS_MS.get(index).copied(),
+                count: *count,
+            })
+            .collect();
+        McpLatencySummary {
+            buckets,
+            mean_ms: (samples > 0).then(|| self.total_ms / samples),
+            p50_ms: self.quantile(0.5),
+            p95_ms: self.quantile(0.95),
+            max_ms: (samples > 0).then_some(self.max_ms),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Breaker {
+    Closed,
+    Open {
+        until: Instant,
+        cooldown: Duration,
+    },
+    HalfOpen {
+        cooldown: Duration,
+        /// When the outstanding probe is given up on, if one is in flight.
+        probe_deadline: Option<Instant>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct ToolHealth {
+    calls: u64,
+    errors: u64,
+    rejected: u64,
+    consecutive_failures: u32,
+    window: VecDeque<bool>,
+    latency: LatencyHistogram,
+    last_error: Option<String>,
+    breaker: Breaker,
+}
+
+impl ToolHealth {
+    fn new() -> Self {
+        Self {
+            calls: 0,
+            errors: 0,
+            rejected: 0,
+            consecutive_failures: 0,
+            window: VecDeque::new(),
+            latency: LatencyHistogram::default(),
+            last_error: None,
+            breaker: Breaker::Closed,
+        }
+    }
+
+    fn error_rate(&self) -> f64 {
+        if self.window.is_empty() {
+            return 0.0;
+        }
+        let failures = self.window.iter().filter(|ok| !**ok).count();
+        failures as f64 / self.window.len() as f64
+    }
+
+    /// Moves an open breaker whose cooldown elapsed into half-open.
+    fn refresh(&mut self, now: Instant) {
+        if let Breaker::Open { until, cooldown } = self.breaker {
+            if now >= until {
+                self.breaker = Breaker::HalfOpen {
+                    cooldown,
+                    probe_deadline: None,
+                };
+            }
+        }
+    }
+
+    fn circuit(&self) -> McpCircuitState {
+        match self.breaker {
+            Breaker::Closed => McpCircuitState::Closed,
+            Breaker::Open { .. } => McpCircuitState::Open,
+            Breaker::HalfOpen { .. } => McpCircuitState::HalfOpen,
+        }
+    }
+}
+
+/// Per-tool health and breaker state for a single MCP server.
+#[derive(Debug, Clone)]
+pub(crate) struct ToolHealthTracker {
+    config: McpCircuitBreakerConfig,
+    tools: HashMap<String, ToolHealth>,
+}
+
+impl ToolHealthTracker {
+    pub(crate) fn new(config: McpCircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            tools: HashMap::new(),
+        }
+    }
+
+    /// Whether the tool should be offered to the orchestrator. Half-open tools
+    /// stay listed so that a probe call can reach them.
+    pub(crate) fn is_available(&mut self, tool: &str, now: Instant) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        match self.tools.get_mut(tool) {
+            Some(health) => {
+                health.refresh(now);
+                !matches!(health.breaker, Breaker::Open { .. })
+            }
+            None => true,
+        }
+    }
+
+    /// Decides whether a call may proceed. A half-open breaker lets exactly
+    /// one probe through at a time, or another once the previous one has
+    /// been outstanding for `PROBE_TIMEOUT`; everything else is rejected
+    /// while open.
+    pub(crate) fn admit(&mut self, tool: &str, now: Instant) -> Result<(), Duration> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let Some(health) = self.tools.get_mut(tool) else {
+            return Ok(());
+        };
+        health.refresh(now);
+        let verdict = match &mut health.breaker {
+            Breaker::Closed => Ok(()),
+            Breaker::Open { until, .. } => Err(until.saturating_duration_since(now)),
+            Breaker::HalfOpen {
+                probe_deadline: Some(deadline),
+                ..
+            } if now < *deadline => Err(Duration::ZERO),
+            Breaker::HalfOpen { probe_deadline, .. } => {
+                *probe_deadline = Some(now + PROBE_TIMEOUT);
+                Ok(())
+            }
+        };
+        if verdict.is_err() {
+            health.rejected = health.rejected.saturating_add(1);
+        }
+        verdict
+    }
+
+    pub(crate) fn record_success(&mut self, tool: &str, latency_ms: u64) {
+        let health = self.entry(tool);
+        health.calls = health.calls.saturating_add(1);
+        health.consecutive_failures = 0;
+        health.latency.observe(latency_ms);
+        if matches!(health.breaker, Breaker::HalfOpen { .. }) {
+            // The probe succeeded: start over with a clean window so stale
+            // failures don't immediately trip the breaker again.
+            health.window.clear();
+            health.breaker = Breaker::Closed;
+        }
+        self.push_outcome(tool, true);
+    }
+
+    /// Records a call the tool refused because of its arguments. That says
+    /// nothing about the tool's health, so it is kept out of the error budget;
+    /// a probe it used is released for the next call.
+    pub(crate) fn record_invalid_input(&mut self, tool: &str) {
+        if let Some(health) = self.tools.get_mut(tool) {
+            if let Breaker::HalfOpen { probe_deadline, .. } = &mut health.breaker {
+                *probe_deadline = None;
+            }
+        }
+    }
+
+    /// Records a failed call and reports whether it opened the breaker.
+    pub(crate) fn record_failure(
+        &mut self,
+        tool: &str,
+        latency_ms: u64,
+        error: &str,
+        now: Instant,
+    ) -> bool {
+        let config = self.config.clone();
+        let health = self.entry(tool);
+        health.calls = health.calls.saturating_add(1);
+        health.errors = health.errors.saturating_add(1);
+        health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        health.latency.observe(latency_ms);
+        health.last_error = Some(error.to_string());
+        self.push_outcome(tool, false);
+
+        if !config.enabled {
+            return false;
+        }
+        let health = self.entry(tool);
+        let base = Duration::from_millis(config.cooldown_ms);
+        let max = Duration::from_millis(config.max_cooldown_ms.max(config.cooldown_ms));
+        let cooldown = match health.breaker {
+            Breaker::HalfOpen { cooldown, .. } => Some((cooldown * 2).min(max)),
+            Breaker::Open { .. } => None,
+            Breaker::Closed => {
+                let over_budget = health.window.len() as u32 >= config.min_calls.max(1)
+                    && health.error_rate() >= config.error_rate_threshold;
+                let too_many = config.failure_threshold > 0
+                    && health.consecutive_failures >= config.failure_threshold;
+                (over_budget || too_many).then_some(base)
+            }
+        };
+        match cooldown {
+            Some(cooldown) => {
+                health.breaker = Breaker::Open {
+                    until: now + cooldown,
+                    cooldown,
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn snapshot(&mut self, now: Instant) -> Vec<McpToolHealth> {
+        let wall_now = now_millis();
+        let mut tools: Vec<McpToolHealth> = self
+            .tools
+            .iter_mut()
+            .map(|(name, health)| {
+                health.refresh(now);
+                let retry_at_ms = match health.breaker {
+                    Breaker::Open { until, .. } => {
+                        Some(wall_now + until.saturating_duration_since(now).as_millis() as u64)
+                    }
+                    _ => None,
+                };
+                McpToolHealth {
+                    tool: name.clone(),
+                    calls: health.calls,
+                    errors: health.errors,
+                    rejected: health.rejected,
+                    error_rate: health.error_rate(),
+                    consecutive_failures: health.consecutive_failures,
+                    circuit: health.circuit(),
+                    retry_at_ms,
+                    last_error: health.last_error.clone(),
+                    latency: health.latency.summary(),
+                }
+            })
+            .collect();
+        tools.sort_by(|a, b| a.tool.cmp(&b.tool));
+        tools
+    }
+
+    fn entry(&mut self, tool: &str) -> &mut ToolHealth {
+        self.tools
+            .entry(tool.to_string())
+            .or_insert_with(ToolHealth::new)
+    }
+
+    fn push_outcome(&mut self, tool: &str, ok: bool) {
+        let window_size = self.config.window_size.max(1) as usize;
+        let health = self.entry(tool);
+        if health.window.len() == window_size {
+            health.window.pop_front();
+        }
+        health.window.push_back(ok);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> McpCircuitBreakerConfig {
+        McpCircuitBreakerConfig {
+            failure_threshold: 3,
+            error_rate_threshold: 0.5,
+            min_calls: 4,
+            window_size: 10,
+            cooldown_ms: 1_000,
+            max_cooldown_ms: 3_000,
+            ..McpCircuitBreakerConfig::default()
+        }
+    }
+
+    #[test]
+    fn consecutive_failures_open_and_probe_closes() {
+        let mut tracker = ToolHealthTracker::new(config());
+        let start = Instant::now();
+        for _ in 0..2 {
+            assert!(tracker.admit("search", start).is_ok());
+            assert!(!tracker.record_failure("search", 5, "boom", start));
+        }
+        assert!(tracker.record_failure("search", 5, "boom", start));
+        assert!(!tracker.is_available("search", start));
+        assert!(tracker.admit("search", start).is_err());
+        assert!(tracker.is_available("other", start));
+
+        let later = start + Duration::from_millis(1_000);
+        assert!(tracker.is_available("search", later));
+        assert!(tracker.admit("search", later).is_ok());
+        // Only one probe may be in flight while half-open.
+        assert!(tracker.admit("search", later).is_err());
+        tracker.record_success("search", 7);
+
+        let health = &tracker.snapshot(later)[0];
+        assert_eq!(health.circuit, McpCircuitState::Closed);
+        assert_eq!(health.calls, 4);
+        assert_eq!(health.errors, 3);
+        assert_eq!(health.rejected, 2);
+        assert_eq!(health.error_rate, 0.0);
+    }
+
+    #[test]
+    fn failed_probe_doubles_cooldown_up_to_cap() {
+        let mut tracker = ToolHealthTracker::new(McpCircuitBreakerConfig {
+            failure_threshold: 1,
+            ..config()
+        });
+        let mut now = Instant::now();
+        assert!(tracker.record_failure("fetch", 1, "down", now));
+
+        for expected_ms in [2_000, 3_000, 3_000] {
+            now += Duration::from_millis(3_000);
+            assert!(tracker.admit("fetch", now).is_ok());
+            assert!(tracker.record_failure("fetch", 1, "down", now));
+            let just_before = now + Duration::from_millis(expected_ms - 1);
+            assert!(!tracker.is_available("fetch", just_before));
+            assert!(tracker.is_available("fetch", now + Duration::from_millis(expected_ms)));
+        }
+    }
+
+    #[test]
+    fn abandoned_probe_is_replaced_after_the_timeout() {
+        let mut tracker = ToolHealthTracker::new(McpCircuitBreakerConfig {
+            failure_threshold: 1,
+            ..config()
+        });
+        let start = Instant::now();
+        assert!(tracker.record_failure("fetch", 1, "down", start));
+
+        // The probe's caller goes away without recording an outcome.
+        let half_open = start + Duration::from_millis(1_000);
+        assert!(tracker.admit("fetch", half_open).is_ok());
+        assert!(tracker
+            .admit("fetch", half_open + PROBE_TIMEOUT / 2)
+            .is_err());
+        let expired = half_open + PROBE_TIMEOUT;
+        assert!(tracker.admit("fetch", expired).is_ok());
+        tracker.record_success("fetch", 3);
+        assert_eq!(
+            tracker.snapshot(expired)[0].circuit,
+            McpCircuitState::Closed
+        );
+    }
+
+    #[test]
+    fn invalid_input_is_kept_out_of_the_error_budget() {
+        let mut tracker = ToolHealthTracker::new(McpCircuitBreakerConfig {
+            failure_threshold: 1,
+            ..config()
+        });
+        let start = Instant::now();
+        assert!(tracker.record_failure("fetch", 1, "down", start));
+
+        let half_open = start + Duration::from_millis(1_000);
+        assert!(tracker.admit("fetch", half_open).is_ok());
+        tracker.record_invalid_input("fetch");
+        // The probe slot is free again and nothing was counted.
+        assert!(tracker.admit("fetch", half_open).is_ok());
+        let health = &tracker.snapshot(half_open)[0];
+        assert_eq!(health.circuit, McpCircuitState::HalfOpen);
+        assert_eq!(health.errors, 1);
+        assert_eq!(health.consecutive_failures, 1);
+    }
+
+    #[test]
+    fn error_rate_opens_breaker_once_min_calls_reached() {
+        let mut tracker = ToolHealthTracker::new(McpCircuitBreakerConfig {
+            failure_threshold: 0,
+            ..config()
+        });
+        let now = Instant::now();
+        tracker.record_success("lookup", 10);
+        assert!(!tracker.record_failure("lookup", 10, "bad", now));
+        tracker.record_success("lookup", 10);
+        assert!(tracker.record_failure("lookup", 10, "bad", now));
+        assert_eq!(tracker.snapshot(now)[0].circuit, McpCircuitState::Open);
+    }
+
+    #[test]
+    fn disabled_breaker_only_collects_metrics() {
+        let mut tracker = ToolHealthTracker::new(McpCircuitBreakerConfig {
+            enabled: false,
+            ..config()
+        });
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(!tracker.record_failure("noop", 3, "err", now));
+        }
+        assert!(tracker.admit("noop", now).is_ok());
+        assert!(tracker.is_available("noop", now));
+        assert_eq!(tracker.snapshot(now)[0].errors, 10);
+    }
+
+    #[test]
+    fn latency_histogram_reports_quantiles() {
+        let mut histogram = LatencyHistogram::default();
+        for latency in [5, 8, 20, 40, 90, 120, 300, 700, 2_000, 12_000] {
+            histogram.observe(latency);
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.buckets.len(), LATENCY_BUCKETS_MS.len() + 1);
+        assert_eq!(summary.buckets.last().unwrap().count, 1);
+        assert_eq!(summary.p50_ms, Some(100));
+        assert_eq!(summary.p95_ms, Some(12_000));
+        assert_eq!(summary.max_ms, Some(12_000));
+        assert_eq!(summary.mean_ms, Some(1_528));
+    }
+}
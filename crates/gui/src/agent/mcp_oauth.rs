@@ -0,0 +1,1115 @@
+//! OAuth 2.1 authorization for remote MCP servers.
+//!
+//! Follows the MCP authorization flow: discover the authorization server
+//! (protected resource metadata, then RFC 8414 metadata with the spec's
+//! default endpoints as a fallback), register a public client dynamically
+//! when none is configured, run an authorization code + PKCE (S256) flow
+//! whose redirect lands on a loopback listener, and keep the resulting
+//! tokens in the secret store. Access tokens are refreshed shortly before
+//! they expire so transports can simply ask for a bearer token per request.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use url::Url;
+
+use super::mcp_client::now_millis;
+use crate::mcp_profiles::McpSecretStore;
+
+const PROTECTED_RESOURCE_PATH: &str = "/.well-known/oauth-protected-resource";
+const AUTHORIZATION_SERVER_PATH: &str = "/.well-known/oauth-authorization-server";
+const CALLBACK_PATH: &str = "/callback";
+const CLIENT_NAME: &str = "Advatar Browser";
+/// Refresh access tokens this long before they expire.
+const REFRESH_SKEW_MS: u64 = 60_000;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(20);
+const MAX_CALLBACK_REQUEST_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpOAuthConfig {
+    /// Pre-registered client id; dynamic registration is used when absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    pub scopes: Vec<String>,
+    /// Issuer to use instead of discovering it from the MCP server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_server: Option<String>,
+    /// Fixed loopback port for the redirect URI, for clients registered with
+    /// an exact redirect.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpOAuthStatus {
+    pub authorized: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// What the UI needs to send the user to the authorization server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpOAuthAuthorization {
+    pub server_id: String,
+    pub authorization_url: String,
+    pub redirect_uri: String,
+}
+
+/// The server rejected or lacks credentials; the user has to (re)authorize.
+#[derive(Debug)]
+pub struct McpAuthorizationRequired {
+    pub server_id: String,
+}
+
+impl fmt::Display for McpAuthorizationRequired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MCP server {} requires OAuth authorization",
+            self.server_id
+        )
+    }
+}
+
+impl std::error::Error for McpAuthorizationRequired {}
+
+/// The token endpoint answered with an error status.
+#[derive(Debug)]
+struct TokenEndpointError {
+    status: StatusCode,
+    /// The OAuth `error` code from the response body, when it had one.
+    error: Option<String>,
+    body: String,
+}
+
+impl TokenEndpointError {
+    /// Whether the grant itself was refused, as opposed to the server being
+    /// unavailable; only then are stored tokens worth discarding.
+    fn rejects_grant(&self) -> bool {
+        self.error.as_deref() == Some("invalid_grant")
+            || matches!(
+                self.status,
+                StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED
+            )
+    }
+}
+
+impl fmt::Display for TokenEndpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "OAuth token endpoint returned HTTP {}: {}",
+            self.status, self.body
+        )
+    }
+}
+
+impl std::error::Error for TokenEndpointError {}
+
+/// Persistence for OAuth client registrations and tokens.
+pub trait McpOAuthTokenStore: Send + Sync {
+    fn load(&self, key: &str) -> Result<Option<String>>;
+    fn save(&self, key: &str, value: &str) -> Result<()>;
+    fn remove(&self, key: &str) -> Result<()>;
+}
+
+impl McpOAuthTokenStore for McpSecretStore {
+    fn load(&self, key: &str) -> Result<Option<String>> {
+        self.try_read(key)
+    }
+
+    fn save(&self, key: &str, value: &str) -> Result<()> {
+        self.store(Some(key.to_string()), value).map(|_| ())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        McpSecretStore::remove(self, key)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AuthorizationServerMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    #[serde(default)]
+    registration_endpoint: Option<String>,
+    #[serde(default)]
+    code_challenge_methods_supported: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProtectedResourceMetadata {
+    #[serde(default)]
+    authorization_servers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisteredClient {
+    client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    redirect_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredTokens {
+    access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    token_endpoint: String,
+    client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+}
+
+impl StoredTokens {
+    fn needs_refresh(&self, now_ms: u64) -> bool {
+        self.expires_at_ms
+            .is_some_and(|expires| expires <= now_ms.saturating_add(REFRESH_SKEW_MS))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredOAuthState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client: Option<RegisteredClient>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tokens: Option<StoredTokens>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// An authorization in progress: the loopback listener waiting for the
+/// redirect plus the PKCE verifier needed to redeem the code.
+pub struct McpOAuthPending {
+    authorization: McpOAuthAuthorization,
+    listener: TcpListener,
+    state: String,
+    code_verifier: String,
+    client: RegisteredClient,
+    token_endpoint: String,
+}
+
+impl McpOAuthPending {
+    pub fn authorization(&self) -> &McpOAuthAuthorization {
+        &self.authorization
+    }
+}
+
+pub(crate) struct McpOAuthClient {
+    server_id: String,
+    resource: String,
+    config: McpOAuthConfig,
+    http: Client,
+    store: Arc<dyn McpOAuthTokenStore>,
+    store_key: String,
+    cached: Mutex<Option<StoredOAuthState>>,
+}
+
+impl McpOAuthClient {
+    pub(crate) fn new(
+        server_id: &str,
+        resource: &str,
+        config: McpOAuthConfig,
+        store: Arc<dyn McpOAuthTokenStore>,
+    ) -> Result<Self> {
+        let http = Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .context("building OAuth HTTP client")?;
+        Ok(Self {
+            server_id: server_id.to_string(),
+            resource: resource.to_string(),
+            config,
+            http,
+            store,
+            store_key: format!("mcp-oauth:{server_id}@{resource}"),
+            cached: Mutex::new(None),
+        })
+    }
+
+    fn authorization_required(&self) -> anyhow::Error {
+        McpAuthorizationRequired {
+            server_id: self.server_id.clone(),
+        }
+        .into()
+    }
+
+    /// Returns a usable access token, refreshing it first when it is about to
+    /// expire.
+    pub(crate) async fn bearer_token(&self) -> Result<String> {
+        let mut cached = self.cached.lock().await;
+        let mut state = self.load_state(&mut cached)?;
+        let Some(tokens) = state.tokens.clone() else {
+            return Err(self.authorization_required());
+        };
+        if !tokens.needs_refresh(now_millis()) {
+            return Ok(tokens.access_token);
+        }
+        let Some(refresh_token) = tokens.refresh_token.clone() else {
+            return Err(self.authorization_required());
+        };
+
+        let mut form = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.clone()),
+            ("client_id", tokens.client_id.clone()),
+            ("resource", self.resource.clone()),
+        ];
+        if let Some(secret) = &tokens.client_secret {
+            form.push(("client_secret", secret.clone()));
+        }
+        let response = match self.token_request(&tokens.token_endpoint, &form).await {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::warn!(
+                    target: "mcp_oauth",
+                    server_id = %self.server_id,
+                    error = %err,
+                    "refreshing MCP access token failed"
+                );
+                // Network errors, timeouts and server faults are transient:
+                // keep the refresh token so a later request can retry.
+                let rejected = err
+                    .downcast_ref::<TokenEndpointError>()
+                    .is_some_and(TokenEndpointError::rejects_grant);
+                if !rejected {
+                    return Err(err.context("refreshing MCP access token"));
+                }
+                state.tokens = None;
+                self.persist(&mut cached, state)?;
+                return Err(self.authorization_required());
+            }
+        };
+        let refreshed = StoredTokens {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token.or(Some(refresh_token)),
+            expires_at_ms: response
+                .expires_in
+                .map(|secs| now_millis().saturating_add(secs.saturating_mul(1_000))),
+            scope: response.scope.or(tokens.scope),
+            ..tokens
+        };
+        let access_token = refreshed.access_token.clone();
+        state.tokens = Some(refreshed);
+        self.persist(&mut cached, state)?;
+        Ok(access_token)
+    }
+
+    /// Marks the current access token as expired after the resource server
+    /// rejected it, so the next request refreshes or asks for authorization.
+    pub(crate) async fn invalidate(&self) {
+        let mut cached = self.cached.lock().await;
+        let Ok(mut state) = self.load_state(&mut cached) else {
+            return;
+        };
+        if let Some(tokens) = state.tokens.as_mut() {
+            tokens.expires_at_ms = Some(0);
+        }
+        let _ = self.persist(&mut cached, state);
+    }
+
+    pub(crate) async fn status(&self) -> McpOAuthStatus {
+        let mut cached = self.cached.lock().await;
+        let tokens = self
+            .load_state(&mut cached)
+            .ok()
+            .and_then(|state| state.tokens);
+        McpOAuthStatus {
+            authorized: tokens.is_some(),
+            expires_at_ms: tokens.as_ref().and_then(|tokens| tokens.expires_at_ms),
+            scope: tokens.and_then(|tokens| tokens.scope),
+        }
+    }
+
+    /// Forgets stored tokens while keeping the client registration.
+    pub(crate) async fn sign_out(&self) -> Result<()> {
+        let mut cached = self.cached.lock().await;
+        let mut state = self.load_state(&mut cached)?;
+        state.tokens = None;
+        self.persist(&mut cached, state)
+    }
+
+    /// Discovers the authorization server, registers a client if needed and
+    /// starts listening for the redirect.
+    pub(crate) async fn begin_authorization(&self) -> Result<McpOAuthPending> {
+        let metadata = self.discover().await?;
+        if let Some(methods) = &metadata.code_challenge_methods_supported {
+            if !methods.iter().any(|method| method == "S256") {
+                return Err(anyhow!(
+                    "authorization server for {} does not support PKCE S256",
+                    self.server_id
+                ));
+            }
+        }
+
+        let stored_client = {
+            let mut cached = self.cached.lock().await;
+            self.load_state(&mut cached)?.client
+        };
+        let preferred_port = self.config.redirect_port.or_else(|| {
+            stored_client
+                .as_ref()
+                .and_then(|client| Url::parse(&client.redirect_uri).ok())
+                .and_then(|url| url.port())
+        });
+        let listener = match preferred_port {
+            Some(port) => match TcpListener::bind(("127.0.0.1", port)).await {
+                Ok(listener) => listener,
+                Err(_) if self.config.redirect_port.is_none() => {
+                    TcpListener::bind("127.0.0.1:0").await?
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("binding OAuth redirect listener on port {port}"))
+                }
+            },
+            None => TcpListener::bind("127.0.0.1:0").await?,
+        };
+        let redirect_uri = format!(
+            "http://127.0.0.1:{}{CALLBACK_PATH}",
+            listener.local_addr()?.port()
+        );
+
+        let client = match (&self.config.client_id, stored_client) {
+            (Some(client_id), _) => RegisteredClient {
+                client_id: client_id.clone(),
+                client_secret: None,
+                redirect_uri: redirect_uri.clone(),
+            },
+            (None, Some(client)) if client.redirect_uri == redirect_uri => client,
+            (None, _) => {
+                let client = self.register(&metadata, &redirect_uri).await?;
+                let mut cached = self.cached.lock().await;
+                let mut state = self.load_state(&mut cached)?;
+                state.client = Some(client.clone());
+                self.persist(&mut cached, state)?;
+                client
+            }
+        };
+
+        let code_verifier = random_token(48);
+        let challenge = pkce_challenge(&code_verifier);
+        let state = random_token(24);
+        let mut authorization_url = Url::parse(&metadata.authorization_endpoint)
+            .context("parsing OAuth authorization endpoint")?;
+        {
+            let mut query = authorization_url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &client.client_id)
+                .append_pair("redirect_uri", &redirect_uri)
+                .append_pair("code_challenge", &challenge)
+                .append_pair("code_challenge_method", "S256")
+                .append_pair("state", &state)
+                .append_pair("resource", &self.resource);
+            if !self.config.scopes.is_empty() {
+                query.append_pair("scope", &self.config.scopes.join(" "));
+            }
+        }
+
+        Ok(McpOAuthPending {
+            authorization: McpOAuthAuthorization {
+                server_id: self.server_id.clone(),
+                authorization_url: authorization_url.to_string(),
+                redirect_uri,
+            },
+            listener,
+            state,
+            code_verifier,
+            client,
+            token_endpoint: metadata.token_endpoint,
+        })
+    }
+
+    /// Waits for the browser to hit the redirect URI and redeems the code.
+    pub(crate) async fn complete_authorization(
+        &self,
+        pending: McpOAuthPending,
+        wait: Duration,
+    ) -> Result<()> {
+        let code = timeout(wait, wait_for_callback(&pending.listener, &pending.state))
+            .await
+            .map_err(|_| anyhow!("timed out waiting for OAuth authorization"))??;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code),
+            ("redirect_uri", pending.authorization.redirect_uri.clone()),
+            ("client_id", pending.client.client_id.clone()),
+            ("code_verifier", pending.code_verifier.clone()),
+            ("resource", self.resource.clone()),
+        ];
+        if let Some(secret) = &pending.client.client_secret {
+            form.push(("client_secret", secret.clone()));
+        }
+        let response = self
+            .token_request(&pending.token_endpoint, &form)
+            .await
+            .context("exchanging OAuth authorization code")?;
+
+        let mut cached = self.cached.lock().await;
+        let mut state = self.load_state(&mut cached)?;
+        state.tokens = Some(StoredTokens {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at_ms: response
+                .expires_in
+                .map(|secs| now_millis().saturating_add(secs.saturating_mul(1_000))),
+            scope: response.scope,
+            token_endpoint: pending.token_endpoint,
+            client_id: pending.client.client_id,
+            client_secret: pending.client.client_secret,
+        });
+        self.persist(&mut cached, state)
+    }
+
+    async fn discover(&self) -> Result<AuthorizationServerMetadata> {
+        let resource = Url::parse(&self.resource).context("parsing MCP server endpoint")?;
+        let origin = resource.origin().ascii_serialization();
+        let issuer = match &self.config.authorization_server {
+            Some(issuer) => issuer.trim_end_matches('/').to_string(),
+            None => self
+                .fetch_json::<ProtectedResourceMetadata>(&format!(
+                    "{origin}{PROTECTED_RESOURCE_PATH}"
+                ))
+                .await
+                .ok()
+                .flatten()
+                .and_then(|metadata| metadata.authorization_servers.into_iter().next())
+                .map(|issuer| issuer.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| origin.clone()),
+        };
+
+        let metadata_url = well_known_url(&issuer, AUTHORIZATION_SERVER_PATH)?;
+        match self
+            .fetch_json::<AuthorizationServerMetadata>(&metadata_url)
+            .await?
+        {
+            Some(metadata) => Ok(metadata),
+            // Servers without metadata use the default endpoints relative to
+            // the issuer.
+            None => Ok(AuthorizationServerMetadata {
+                authorization_endpoint: format!("{issuer}/authorize"),
+                token_endpoint: format!("{issuer}/token"),
+                registration_endpoint: Some(format!("{issuer}/register")),
+                code_challenge_methods_supported: None,
+            }),
+        }
+    }
+
+    async fn register(
+        &self,
+        metadata: &AuthorizationServerMetadata,
+        redirect_uri: &str,
+    ) -> Result<RegisteredClient> {
+        let endpoint = metadata.registration_endpoint.as_deref().ok_or_else(|| {
+            anyhow!(
+                "authorization server for {} does not support dynamic client registration; set oauth.clientId",
+                self.server_id
+            )
+        })?;
+        let mut body = serde_json::json!({
+            "client_name": CLIENT_NAME,
+            "redirect_uris": [redirect_uri],
+            "grant_types": ["authorization_code", "refresh_token"],
+            "response_types": ["code"],
+            "token_endpoint_auth_method": "none",
+        });
+        if !self.config.scopes.is_empty() {
+            body["scope"] = Value::String(self.config.scopes.join(" "));
+        }
+        let response = self
+            .http
+            .post(endpoint)
+            .json(&body)
+            .send()
+            .await
+            .context("registering OAuth client")?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "OAuth client registration returned HTTP {status}: {}",
+                text.trim()
+            ));
+        }
+        let registered: Value = response
+            .json()
+            .await
+            .context("decoding OAuth registration response")?;
+        let client_id = registered
+            .get("client_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("OAuth registration response is missing client_id"))?;
+        Ok(RegisteredClient {
+            client_id: client_id.to_string(),
+            client_secret: registered
+                .get("client_secret")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            redirect_uri: redirect_uri.to_string(),
+        })
+    }
+
+    async fn token_request(
+        &self,
+        endpoint: &str,
+        form: &[(&str, String)],
+    ) -> Result<TokenResponse> {
+        let response = self
+            .http
+            .post(endpoint)
+            .form(form)
+            .send()
+            .await
+            .context("requesting OAuth token")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default().trim().to_string();
+            let error = serde_json::from_str::<Value>(&body).ok().and_then(|value| {
+                value
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+            return Err(TokenEndpointError {
+                status,
+                error,
+                body,
+            }
+            .into());
+        }
+        response
+            .json()
+            .await
+            .context("decoding OAuth token response")
+    }
+
+    /// Fetches JSON metadata, treating a 404 as "not published".
+    async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<Option<T>> {
+        let response = self
+            .http
+            .get(url)
+            .header(
+                "MCP-Protocol-Version",
+                super::mcp_client::MCP_PROTOCOL_VERSION,
+            )
+            .send()
+            .await
+            .with_context(|| format!("fetching {url}"))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .json()
+                .await
+                .map(Some)
+                .with_context(|| format!("decoding {url}")),
+            status => Err(anyhow!("{url} returned HTTP {status}")),
+        }
+    }
+
+    fn load_state(&self, cached: &mut Option<StoredOAuthState>) -> Result<StoredOAuthState> {
+        if let Some(state) = cached.as_ref() {
+            return Ok(state.clone());
+        }
+        let state = match self.store.load(&self.store_key)? {
+            Some(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+            None => StoredOAuthState::default(),
+        };
+        *cached = Some(state.clone());
+        Ok(state)
+    }
+
+    fn persist(
+        &self,
+        cached: &mut Option<StoredOAuthState>,
+        state: StoredOAuthState,
+    ) -> Result<()> {
+        if state.client.is_none() && state.tokens.is_none() {
+            self.store.remove(&self.store_key)?;
+        } else {
+            let raw = serde_json::to_string(&state).context("serialising OAuth state")?;
+            self.store.save(&self.store_key, &raw)?;
+        }
+        *cached = Some(state);
+        Ok(())
+    }
+}
+
+/// RFC 8414 inserts the well-known segment between host and issuer path.
+fn well_known_url(issuer: &str, well_known: &str) -> Result<String> {
+    let url = Url::parse(issuer).with_context(|| format!("parsing OAuth issuer {issuer}"))?;
+    let path = url.path().trim_end_matches('/');
+    Ok(format!(
+        "{}{well_known}{path}",
+        url.origin().ascii_serialization()
+    ))
+}
+
+fn random_token(bytes: usize) -> String {
+    let raw: Vec<u8> = (0..bytes).map(|_| rand::random::<u8>()).collect();
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Serves the loopback redirect until a request with the expected `state`
+/// arrives and returns its authorization code.
+async fn wait_for_callback(listener: &TcpListener, expected_state: &str) -> Result<String> {
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let Ok(target) = read_request_target(&mut socket).await else {
+            continue;
+        };
+        let Ok(url) = Url::parse(&format!("http://127.0.0.1{target}")) else {
+            respond(&mut socket, "400 Bad Request", "Malformed request.").await;
+            continue;
+        };
+        if url.path() != CALLBACK_PATH {
+            respond(&mut socket, "404 Not Found", "Not found").await;
+            continue;
+        }
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        if param("state").as_deref() != Some(expected_state) {
+            respond(
+                &mut socket,
+                "400 Bad Request",
+                "Authorization state mismatch.",
+            )
+            .await;
+            continue;
+        }
+        if let Some(error) = param("error") {
+            respond(&mut socket, "200 OK", "Authorization was not granted.").await;
+            let description = param("error_description").unwrap_or_default();
+            return Err(anyhow!("authorization denied: {error} {description}")
+                .context("OAuth authorization failed"));
+        }
+        let Some(code) = param("code") else {
+            respond(
+                &mut socket,
+                "400 Bad Request",
+                "Missing authorization code.",
+            )
+            .await;
+            continue;
+        };
+        respond(
+            &mut socket,
+            "200 OK",
+            "Authorization complete. You can close this tab.",
+        )
+        .await;
+        return Ok(code);
+    }
+}
+
+async fn read_request_target(socket: &mut TcpStream) -> Result<String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = timeout(Duration::from_secs(5), socket.read(&mut chunk)).await??;
+        if read == 0 || buffer.len() > MAX_CALLBACK_REQUEST_BYTES {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let head = String::from_utf8_lossy(&buffer);
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) if target.starts_with('/') => Ok(target.to_string()),
+        _ => Err(anyhow!("unexpected OAuth callback request")),
+    }
+}
+
+async fn respond(socket: &mut TcpStream, status: &str, message: &str) {
+    let body = format!("<!doctype html><html><body><p>{message}</p></body></html>");
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemoryStore(std::sync::Mutex<HashMap<String, String>>);
+
+    impl McpOAuthTokenStore for MemoryStore {
+        fn load(&self, key: &str) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn save(&self, key: &str, value: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn remove(&self, key: &str) -> Result<()> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockState {
+        challenge: Option<String>,
+        registrations: u32,
+        refreshes: u32,
+    }
+
+    /// Minimal authorization server: metadata, registration, an authorize
+    /// endpoint that immediately redirects, and a PKCE-checking token
+    /// endpoint that issues short-lived tokens.
+    async fn spawn_authorization_server() -> (String, Arc<std::sync::Mutex<MockState>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(std::sync::Mutex::new(MockState::default()));
+        let server_base = base.clone();
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let base = server_base.clone();
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    let (target, body) = read_request(&mut socket).await;
+                    let url = Url::parse(&format!("{base}{target}")).unwrap();
+                    let params: HashMap<String, String> = if url.path() == "/token" {
+                        url::form_urlencoded::parse(body.as_bytes())
+                            .into_owned()
+                            .collect()
+                    } else {
+                        url.query_pairs().into_owned().collect()
+                    };
+                    let (status, headers, payload) = match url.path() {
+                        "/.well-known/oauth-authorization-server" => (
+                            "200 OK",
+                            String::new(),
+                            serde_json::json!({
+                                "issuer": base,
+                                "authorization_endpoint": format!("{base}/authorize"),
+                                "token_endpoint": format!("{base}/token"),
+                                "registration_endpoint": format!("{base}/register"),
+                                "code_challenge_methods_supported": ["S256"],
+                            })
+                            .to_string(),
+                        ),
+                        "/register" => {
+                            state.lock().unwrap().registrations += 1;
+                            (
+                                "201 Created",
+                                String::new(),
+                                r#"{"client_id":"client-123"}"#.to_string(),
+                            )
+                        }
+                        "/authorize" => {
+                            assert_eq!(params["code_challenge_method"], "S256");
+                            assert_eq!(params["client_id"], "client-123");
+                            state.lock().unwrap().challenge =
+                                Some(params["code_challenge"].clone());
+                            let location = format!(
+                                "{}?code=auth-code&state={}",
+                                params["redirect_uri"], params["state"]
+                            );
+                            (
+                                "302 Found",
+                                format!("Location: {location}\r\n"),
+                                String::new(),
+                            )
+                        }
+                        "/token" => {
+                            let mut guard = state.lock().unwrap();
+                            match params["grant_type"].as_str() {
+                                "authorization_code" => {
+                                    assert_eq!(params["code"], "auth-code");
+                                    assert_eq!(
+                                        Some(pkce_challenge(&params["code_verifier"])),
+                                        guard.challenge
+                                    );
+                                    (
+                                        "200 OK",
+                                        String::new(),
+                                        r#"{"access_token":"at-1","refresh_token":"rt-1","expires_in":1,"token_type":"Bearer"}"#
+                                            .to_string(),
+                                    )
+                                }
+                                _ => {
+                                    assert_eq!(params["refresh_token"], "rt-1");
+                                    guard.refreshes += 1;
+                                    (
+                                        "200 OK",
+                                        String::new(),
+                                        r#"{"access_token":"at-2","expires_in":3600,"token_type":"Bearer"}"#
+                                            .to_string(),
+                                    )
+                                }
+                            }
+                        }
+                        _ => ("404 Not Found", String::new(), String::new()),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{payload}",
+                        payload.len()
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+        (base, state)
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> (String, String) {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let read = socket.read(&mut chunk).await.unwrap();
+            buffer.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&buffer).to_string();
+            if let Some(split) = text.find("\r\n\r\n") {
+                let length = text[..split]
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if buffer.len() >= split + 4 + length || read == 0 {
+                    let target = text.split_whitespace().nth(1).unwrap().to_string();
+                    return (target, text[split + 4..].to_string());
+                }
+            }
+            if read == 0 {
+                return (String::new(), String::new());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn authorization_code_flow_registers_exchanges_and_refreshes() {
+        let (base, mock) = spawn_authorization_server().await;
+        let store = Arc::new(MemoryStore::default());
+        let client = McpOAuthClient::new(
+            "remote",
+            &format!("{base}/mcp"),
+            McpOAuthConfig {
+                scopes: vec!["tools".into()],
+                ..McpOAuthConfig::default()
+            },
+            store.clone(),
+        )
+        .unwrap();
+
+        let err = client.bearer_token().await.unwrap_err();
+        assert!(err.is::<McpAuthorizationRequired>());
+
+        let pending = client.begin_authorization().await.unwrap();
+        let authorization_url = pending.authorization().authorization_url.clone();
+        assert!(authorization_url.contains("scope=tools"));
+
+        // Play the browser tab: follow the authorize redirect to the loopback.
+        let browser = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let completion = tokio::spawn(async move {
+            client
+                .complete_authorization(pending, Duration::from_secs(10))
+                .await
+                .map(|_| client)
+        });
+        let redirect = browser.get(&authorization_url).send().await.unwrap();
+        let location = redirect.headers()["location"].to_str().unwrap().to_string();
+        let landing = browser.get(&location).send().await.unwrap();
+        assert!(landing.status().is_success());
+        let client = completion.await.unwrap().unwrap();
+
+        // The issued token expires within the refresh skew, so the first
+        // bearer request refreshes it.
+        assert_eq!(client.bearer_token().await.unwrap(), "at-2");
+        assert_eq!(client.bearer_token().await.unwrap(), "at-2");
+        assert_eq!(mock.lock().unwrap().refreshes, 1);
+        assert_eq!(mock.lock().unwrap().registrations, 1);
+        assert!(client.status().await.authorized);
+
+        let persisted = store.load(&client.store_key).unwrap().unwrap();
+        assert!(persisted.contains("at-2") && persisted.contains("client-123"));
+
+        client.sign_out().await.unwrap();
+        assert!(!client.status().await.authorized);
+    }
+
+    /// Token endpoint that answers refresh requests with `responses` in turn.
+    async fn spawn_token_endpoint(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for (status, payload) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                read_request(&mut socket).await;
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{payload}",
+                    payload.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        endpoint
+    }
+
+    fn client_with_expired_tokens(token_endpoint: &str) -> (McpOAuthClient, Arc<MemoryStore>) {
+        let store = Arc::new(MemoryStore::default());
+        let client = McpOAuthClient::new(
+            "remote",
+            "http://127.0.0.1:9/mcp",
+            McpOAuthConfig::default(),
+            store.clone(),
+        )
+        .unwrap();
+        let state = StoredOAuthState {
+            client: None,
+            tokens: Some(StoredTokens {
+                access_token: "at-1".into(),
+                refresh_token: Some("rt-1".into()),
+                expires_at_ms: Some(0),
+                scope: None,
+                token_endpoint: token_endpoint.to_string(),
+                client_id: "client-123".into(),
+                client_secret: None,
+            }),
+        };
+        store
+            .save(&client.store_key, &serde_json::to_string(&state).unwrap())
+            .unwrap();
+        (client, store)
+    }
+
+    #[tokio::test]
+    async fn transient_refresh_failures_keep_the_refresh_token() {
+        let endpoint = spawn_token_endpoint(vec![
+            (
+                "503 Service Unavailable",
+                r#"{"error":"temporarily_unavailable"}"#,
+            ),
+            ("400 Bad Request", r#"{"error":"invalid_grant"}"#),
+        ])
+        .await;
+        let (client, store) = client_with_expired_tokens(&endpoint);
+
+        let err = client.bearer_token().await.unwrap_err();
+        assert!(!err.is::<McpAuthorizationRequired>(), "{err:#}");
+        assert!(store
+            .load(&client.store_key)
+            .unwrap()
+            .unwrap()
+            .contains("rt-1"));
+
+        let err = client.bearer_token().await.unwrap_err();
+        assert!(err.is::<McpAuthorizationRequired>(), "{err:#}");
+        assert!(store.load(&client.store_key).unwrap().is_none());
+
+        // An unreachable token endpoint is a network error, not a rejection.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = format!("http://{}/token", listener.local_addr().unwrap());
+        drop(listener);
+        let (client, store) = client_with_expired_tokens(&closed);
+        let err = client.bearer_token().await.unwrap_err();
+        assert!(!err.is::<McpAuthorizationRequired>(), "{err:#}");
+        assert!(store
+            .load(&client.store_key)
+            .unwrap()
+            .unwrap()
+            .contains("rt-1"));
+    }
+
+    #[tokio::test]
+    async fn callback_rejects_mismatched_state() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let waiter = tokio::spawn(async move { wait_for_callback(&listener, "expected").await });
+        // A malformed request does not stop the listener.
+        let mut garbage = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        garbage.write_all(b"BREW /pot\r\n\r\n").await.unwrap();
+        drop(garbage);
+        let http = Client::new();
+        let forged = http
+            .get(format!(
+                "http://127.0.0.1:{port}/callback?code=x&state=forged"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(forged.status(), StatusCode::BAD_REQUEST);
+        http.get(format!(
+            "http://127.0.0.1:{port}/callback?error=access_denied&state=expected"
+        ))
+        .send()
+        .await
+        .unwrap();
+        assert!(waiter.await.unwrap().is_err());
+    }
+
+    #[test]
+    fn well_known_url_inserts_suffix_before_issuer_path() {
+        assert_eq!(
+            well_known_url("https://auth.example.com/tenant", AUTHORIZATION_SERVER_PATH).unwrap(),
+            "https://auth.example.com/.well-known/oauth-authorization-server/tenant"
+        );
+        assert_eq!(
+            well_known_url("https://auth.example.com", AUTHORIZATION_SERVER_PATH).unwrap(),
+            "https://auth.example.com/.well-known/oauth-authorization-server"
+        );
+    }
+}
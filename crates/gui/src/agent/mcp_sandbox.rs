@@ -0,0 +1,864 @@
+//! Optional confinement for stdio MCP servers.
+//!
+//! On Linux a sandboxed server runs with Landlock filesystem rules, a private
+//! network namespace, a seccomp deny-list and rlimits, all installed between
+//! `fork` and `exec`. Everything that allocates (path resolution, rule
+//! creation, filter assembly) happens in the parent so the `pre_exec` hook only
+//! issues syscalls.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per-server sandbox declaration from the MCP profile manifest.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpSandboxConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Keeps the host network namespace. Without it the server only sees a
+    /// private loopback interface.
+    #[serde(default)]
+    pub allow_network: bool,
+    /// Restricts filesystem access to system directories, the program's own
+    /// install directory, a private scratch home and the listed paths.
+    #[serde(default = "default_true")]
+    pub restrict_filesystem: bool,
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+    #[serde(default)]
+    pub write_paths: Vec<String>,
+    /// Kills the server if it calls privileged syscalls (ptrace, mount, bpf,
+    /// io_uring, namespace creation, ...).
+    #[serde(default = "default_true")]
+    pub filter_syscalls: bool,
+    /// Address-space limit; runtimes that reserve large heaps need headroom.
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    #[serde(default)]
+    pub max_cpu_seconds: Option<u64>,
+    #[serde(default)]
+    pub max_wall_seconds: Option<u64>,
+    #[serde(default)]
+    pub max_open_files: Option<u64>,
+}
+
+impl Default for McpSandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allow_network: false,
+            restrict_filesystem: true,
+            read_paths: Vec::new(),
+            write_paths: Vec::new(),
+            filter_syscalls: true,
+            max_memory_mb: None,
+            max_cpu_seconds: None,
+            max_wall_seconds: None,
+            max_open_files: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum McpSandboxViolationKind {
+    CpuLimit,
+    MemoryLimit,
+    WallClockLimit,
+    BlockedSyscall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpSandboxViolation {
+    pub timestamp_ms: u64,
+    pub kind: McpSandboxViolationKind,
+    pub message: String,
+}
+
+/// Sandbox state resolved in the parent and ready to attach to a `Command`.
+pub(crate) struct PreparedSandbox {
+    scratch_dir: PathBuf,
+    wall_limit: Option<Duration>,
+    #[cfg(target_os = "linux")]
+    linux: linux::ChildSetup,
+}
+
+impl McpSandboxConfig {
+    pub(crate) fn prepare(&self, server_id: &str, program: &str) -> Result<PreparedSandbox> {
+        let scratch_dir = std::env::temp_dir()
+            .join("advatar-mcp-sandbox")
+            .join(sanitize_component(server_id));
+        std::fs::create_dir_all(&scratch_dir).with_context(|| {
+            format!(
+                "creating sandbox scratch directory {}",
+                scratch_dir.display()
+            )
+        })?;
+
+        #[cfg(target_os = "linux")]
+        {
+            let linux = linux::ChildSetup::new(self, program, &scratch_dir)?;
+            Ok(PreparedSandbox {
+                scratch_dir,
+                wall_limit: self.max_wall_seconds.map(Duration::from_secs),
+                linux,
+            })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = program;
+            Err(anyhow!(
+                "MCP server {server_id} declares a sandbox, which is only supported on Linux"
+            ))
+        }
+    }
+
+    /// Maps how a sandboxed server exited onto the limit it most likely hit.
+    pub(crate) fn classify_exit(
+        &self,
+        status: ExitStatus,
+        wall_clock_expired: bool,
+    ) -> Option<(McpSandboxViolationKind, String)> {
+        if wall_clock_expired {
+            return Some((
+                McpSandboxViolationKind::WallClockLimit,
+                format!(
+                    "exceeded wall-clock limit of {}s",
+                    self.max_wall_seconds.unwrap_or_default()
+                ),
+            ));
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // Wrappers such as `npx` or `sh -c` report a child's fatal signal
+            // as exit code 128 + signal.
+            let signal = status
+                .signal()
+                .or_else(|| status.code().and_then(|code| code.checked_sub(128)));
+            match signal {
+                Some(libc::SIGXCPU) => Some((
+                    McpSandboxViolationKind::CpuLimit,
+                    format!(
+                        "exceeded CPU limit of {}s",
+                        self.max_cpu_seconds.unwrap_or_default()
+                    ),
+                )),
+                Some(libc::SIGKILL) if self.max_cpu_seconds.is_some() => Some((
+                    McpSandboxViolationKind::CpuLimit,
+                    format!(
+                        "killed after exceeding CPU limit of {}s",
+                        self.max_cpu_seconds.unwrap_or_default()
+                    ),
+                )),
+                Some(libc::SIGSYS) if self.filter_syscalls => Some((
+                    McpSandboxViolationKind::BlockedSyscall,
+                    "attempted a blocked system call".to_string(),
+                )),
+                Some(libc::SIGSEGV | libc::SIGABRT) if self.max_memory_mb.is_some() => Some((
+                    McpSandboxViolationKind::MemoryLimit,
+                    format!(
+                        "crashed, probably after exhausting the {} MiB memory limit",
+                        self.max_memory_mb.unwrap_or_default()
+                    ),
+                )),
+                _ => None,
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = status;
+            None
+        }
+    }
+}
+
+impl PreparedSandbox {
+    pub(crate) fn wall_limit(&self) -> Option<Duration> {
+        self.wall_limit
+    }
+
+    /// Points the server's home and temp directories at its scratch space and
+    /// installs the confinement hook.
+    pub(crate) fn configure(&self, command: &mut Command) {
+        command
+            .env("HOME", &self.scratch_dir)
+            .env("TMPDIR", &self.scratch_dir)
+            .env("XDG_CACHE_HOME", self.scratch_dir.join(".cache"))
+            .env("XDG_CONFIG_HOME", self.scratch_dir.join(".config"));
+
+        #[cfg(target_os = "linux")]
+        self.linux.install(command);
+    }
+}
+
+fn sanitize_component(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.is_empty() {
+        "server".to_string()
+    } else {
+        sanitized
+    }
+}
+
+fn expand_path(raw: &str) -> Option<PathBuf> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if let Some(rest) = trimmed.strip_prefix("~/") {
+        return std::env::var_os("HOME").map(|home| Path::new(&home).join(rest));
+    }
+    Some(PathBuf::from(trimmed))
+}
+
+/// Resolves `program` through `PATH` the way `execvp` would.
+fn resolve_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return std::fs::canonicalize(program).ok();
+    }
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+            .and_then(|candidate| std::fs::canonicalize(candidate).ok())
+    })
+}
+
+/// Directory tree the program was installed into, e.g. `~/.nvm/.../v20` for
+/// `~/.nvm/.../v20/bin/node`.
+fn program_install_root(program: &Path) -> Option<PathBuf> {
+    let dir = program.parent()?;
+    if dir.file_name().is_some_and(|name| name == "bin") {
+        dir.parent().map(Path::to_path_buf)
+    } else {
+        Some(dir.to_path_buf())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::path::Path;
+
+    use super::*;
+
+    const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+    const LANDLOCK_RULE_PATH_BENEATH: u32 = 1;
+
+    const ACCESS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_READ_DIR: u64 = 1 << 3;
+    /// Every filesystem right defined by Landlock ABI v1.
+    const ACCESS_ALL_V1: u64 = (1 << 13) - 1;
+    /// Linking or renaming across directories (ABI v2).
+    const ACCESS_REFER: u64 = 1 << 13;
+    /// `truncate(2)` and `O_TRUNC` (ABI v3).
+    const ACCESS_TRUNCATE: u64 = 1 << 14;
+    const ACCESS_FILE_ONLY: u64 =
+        ACCESS_EXECUTE | ACCESS_WRITE_FILE | ACCESS_READ_FILE | ACCESS_TRUNCATE;
+    const ACCESS_READ: u64 = ACCESS_EXECUTE | ACCESS_READ_FILE | ACCESS_READ_DIR;
+
+    const SYSTEM_READ_PATHS: &[&str] = &[
+        "/usr",
+        "/lib",
+        "/lib32",
+        "/lib64",
+        "/bin",
+        "/sbin",
+        "/etc",
+        "/opt",
+        "/nix/store",
+        "/proc",
+        "/sys",
+        "/dev",
+    ];
+    const SYSTEM_WRITE_PATHS: &[&str] = &["/dev/null", "/dev/zero", "/dev/tty"];
+
+    #[repr(C)]
+    struct LandlockRulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct LandlockPathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_setns,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_userfaultfd,
+        libc::SYS_io_uring_setup,
+        libc::SYS_io_uring_enter,
+        libc::SYS_io_uring_register,
+    ];
+
+    /// Syscalls killed when their first argument asks for a new namespace.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const NAMESPACE_SYSCALLS: &[libc::c_long] = &[libc::SYS_clone, libc::SYS_unshare];
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const CLONE_NAMESPACE_FLAGS: u32 = (libc::CLONE_NEWNS
+        | libc::CLONE_NEWCGROUP
+        | libc::CLONE_NEWUTS
+        | libc::CLONE_NEWIPC
+        | libc::CLONE_NEWUSER
+        | libc::CLONE_NEWPID
+        | libc::CLONE_NEWNET) as u32;
+    /// Start of the x32 syscall range, which shares the x86_64 audit arch.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    /// Inputs for the post-fork hook; owned by the parent for the lifetime
+    /// of the spawn call.
+    pub(super) struct ChildSetup {
+        limits: Vec<(libc::__rlimit_resource_t, libc::rlimit)>,
+        isolate_network: bool,
+        id_maps: [(CString, Vec<u8>); 3],
+        ruleset: Option<OwnedFd>,
+        seccomp: Option<Vec<libc::sock_filter>>,
+    }
+
+    impl ChildSetup {
+        pub(super) fn new(
+            config: &McpSandboxConfig,
+            program: &str,
+            scratch_dir: &Path,
+        ) -> Result<Self> {
+            let mut limits = vec![(libc::RLIMIT_CORE, rlimit(0, 0))];
+            if let Some(mb) = config.max_memory_mb {
+                let bytes = mb.saturating_mul(1024 * 1024);
+                limits.push((libc::RLIMIT_AS, rlimit(bytes, bytes)));
+            }
+            if let Some(seconds) = config.max_cpu_seconds {
+                // SIGXCPU at the soft limit, SIGKILL one second later.
+                limits.push((libc::RLIMIT_CPU, rlimit(seconds, seconds.saturating_add(1))));
+            }
+            if let Some(files) = config.max_open_files {
+                limits.push((libc::RLIMIT_NOFILE, rlimit(files, files)));
+            }
+
+            // SAFETY: getuid/getgid cannot fail.
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            let id_maps = [
+                (
+                    CString::new("/proc/self/setgroups").expect("static path"),
+                    b"deny".to_vec(),
+                ),
+                (
+                    CString::new("/proc/self/uid_map").expect("static path"),
+                    format!("{uid} {uid} 1").into_bytes(),
+                ),
+                (
+                    CString::new("/proc/self/gid_map").expect("static path"),
+                    format!("{gid} {gid} 1").into_bytes(),
+                ),
+            ];
+
+            let ruleset = if config.restrict_filesystem {
+                Some(build_ruleset(config, program, scratch_dir)?)
+            } else {
+                None
+            };
+
+            let seccomp = if config.filter_syscalls {
+                Some(build_seccomp_filter()?)
+            } else {
+                None
+            };
+
+            Ok(Self {
+                limits,
+                isolate_network: !config.allow_network,
+                id_maps,
+                ruleset,
+                seccomp,
+            })
+        }
+
+        pub(super) fn install(&self, command: &mut Command) {
+            let limits = self.limits.clone();
+            let isolate_network = self.isolate_network;
+            let id_maps = self.id_maps.clone();
+            let ruleset_fd = self.ruleset.as_ref().map(AsRawFd::as_raw_fd);
+            let seccomp = self.seccomp.clone();
+
+            // SAFETY: the hook only performs raw syscalls on data prepared
+            // before fork; it does not allocate or take locks.
+            unsafe {
+                command.pre_exec(move || {
+                    for (resource, limit) in &limits {
+                        check(libc::setrlimit(*resource, limit))?;
+                    }
+
+                    if isolate_network {
+                        check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET))?;
+                        for (path, contents) in &id_maps {
+                            write_proc_file(path, contents)?;
+                        }
+                    }
+
+                    check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+
+                    if let Some(fd) = ruleset_fd {
+                        check(libc::syscall(libc::SYS_landlock_restrict_self, fd, 0u32) as i32)?;
+                    }
+
+                    if let Some(filter) = &seccomp {
+                        let program = libc::sock_fprog {
+                            len: filter.len() as libc::c_ushort,
+                            filter: filter.as_ptr() as *mut libc::sock_filter,
+                        };
+                        check(libc::prctl(
+                            libc::PR_SET_SECCOMP,
+                            libc::SECCOMP_MODE_FILTER,
+                            &program as *const libc::sock_fprog,
+                        ))?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+    }
+
+    fn rlimit(soft: u64, hard: u64) -> libc::rlimit {
+        libc::rlimit {
+            rlim_cur: soft as libc::rlim_t,
+            rlim_max: hard as libc::rlim_t,
+        }
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    unsafe fn write_proc_file(path: &CString, contents: &[u8]) -> io::Result<()> {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            // setgroups is missing on kernels older than 3.19; the maps still apply.
+            return if path.as_bytes().ends_with(b"setgroups") {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            };
+        }
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        let error = io::Error::last_os_error();
+        libc::close(fd);
+        if written < 0 {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(super) fn landlock_abi() -> Option<i64> {
+        // SAFETY: querying the ABI version takes no pointers.
+        let version = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<LandlockRulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        (version > 0).then_some(version as i64)
+    }
+
+    /// Every filesystem right the running kernel's Landlock ABI can restrict.
+    fn handled_access(abi: i64) -> u64 {
+        let mut access = ACCESS_ALL_V1;
+        if abi >= 2 {
+            access |= ACCESS_REFER;
+        }
+        if abi >= 3 {
+            access |= ACCESS_TRUNCATE;
+        }
+        access
+    }
+
+    fn build_ruleset(
+        config: &McpSandboxConfig,
+        program: &str,
+        scratch_dir: &Path,
+    ) -> Result<OwnedFd> {
+        let Some(abi) = landlock_abi() else {
+            return Err(anyhow!(
+                "filesystem sandboxing needs Landlock (Linux 5.13+); set `restrictFilesystem: false` to run without it"
+            ));
+        };
+        let access_all = handled_access(abi);
+
+        let attr = LandlockRulesetAttr {
+            handled_access_fs: access_all,
+        };
+        // SAFETY: attr is a valid ruleset description of the given size.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const LandlockRulesetAttr,
+                std::mem::size_of::<LandlockRulesetAttr>(),
+                0u32,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error()).context("creating Landlock ruleset");
+        }
+        // SAFETY: the kernel returned a fresh close-on-exec descriptor we own.
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        let program_root = resolve_program(program)
+            .as_deref()
+            .and_then(program_install_root);
+        for path in SYSTEM_READ_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .chain(program_root)
+        {
+            add_path_rule(&ruleset, &path, ACCESS_READ, false)?;
+        }
+        for path in SYSTEM_WRITE_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .chain([scratch_dir.to_path_buf()])
+        {
+            add_path_rule(&ruleset, &path, access_all, false)?;
+        }
+        for path in config.read_paths.iter().filter_map(|raw| expand_path(raw)) {
+            add_path_rule(&ruleset, &path, ACCESS_READ, true)?;
+        }
+        for path in config.write_paths.iter().filter_map(|raw| expand_path(raw)) {
+            add_path_rule(&ruleset, &path, access_all, true)?;
+        }
+        Ok(ruleset)
+    }
+
+    /// Grants `access` beneath `path`. Missing system paths are skipped.
+    fn add_path_rule(ruleset: &OwnedFd, path: &Path, access: u64, required: bool) -> Result<()> {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return if required {
+                Err(anyhow!("sandbox path {} does not exist", path.display()))
+            } else {
+                Ok(())
+            };
+        };
+        let allowed_access = if metadata.is_dir() {
+            access
+        } else {
+            access & ACCESS_FILE_ONLY
+        };
+
+        let c_path = CString::new(path.as_os_str().as_encoded_bytes())
+            .with_context(|| format!("invalid sandbox path {}", path.display()))?;
+        // SAFETY: c_path is NUL-terminated; O_PATH opens without reading.
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("opening sandbox path {}", path.display()));
+        }
+        // SAFETY: open returned a descriptor we now own.
+        let parent = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let rule = LandlockPathBeneathAttr {
+            allowed_access,
+            parent_fd: parent.as_raw_fd(),
+        };
+        // SAFETY: rule is a valid path-beneath attribute for this rule type.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                LANDLOCK_RULE_PATH_BENEATH,
+                &rule as *const LandlockPathBeneathAttr,
+                0u32,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("adding Landlock rule for {}", path.display()));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn build_seccomp_filter() -> Result<Vec<libc::sock_filter>> {
+        const ARCH_OFFSET: u32 = 4;
+        const NR_OFFSET: u32 = 0;
+        /// Low half of the first argument; both supported arches are
+        /// little-endian.
+        const ARG0_OFFSET: u32 = 16;
+
+        fn stmt(code: u32, k: u32) -> libc::sock_filter {
+            libc::sock_filter {
+                code: code as u16,
+                jt: 0,
+                jf: 0,
+                k,
+            }
+        }
+        fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+            libc::sock_filter {
+                code: code as u16,
+                jt,
+                jf,
+                k,
+            }
+        }
+
+        let mut filter = vec![
+            stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, ARCH_OFFSET),
+            jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                AUDIT_ARCH,
+                1,
+                0,
+            ),
+            stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, NR_OFFSET),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([
+            jump(
+                libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+                X32_SYSCALL_BIT,
+                0,
+                1,
+            ),
+            stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        ]);
+        for nr in NAMESPACE_SYSCALLS {
+            filter.extend([
+                jump(
+                    libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                    *nr as u32,
+                    0,
+                    4,
+                ),
+                stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, ARG0_OFFSET),
+                jump(
+                    libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K,
+                    CLONE_NAMESPACE_FLAGS,
+                    0,
+                    1,
+                ),
+                stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+                stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, NR_OFFSET),
+            ]);
+        }
+        // clone3 passes its flags behind a pointer the filter cannot read;
+        // ENOSYS makes libc fall back to clone, which is checked above.
+        filter.extend([
+            jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                libc::SYS_clone3 as u32,
+                0,
+                1,
+            ),
+            stmt(
+                libc::BPF_RET | libc::BPF_K,
+                libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32,
+            ),
+        ]);
+        for nr in DENIED_SYSCALLS {
+            filter.push(jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                *nr as u32,
+                0,
+                1,
+            ));
+            filter.push(stmt(
+                libc::BPF_RET | libc::BPF_K,
+                libc::SECCOMP_RET_KILL_PROCESS,
+            ));
+        }
+        filter.push(stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+        Ok(filter)
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn build_seccomp_filter() -> Result<Vec<libc::sock_filter>> {
+        Err(anyhow!(
+            "syscall filtering is not available on this architecture; set `filterSyscalls: false`"
+        ))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    fn sandbox() -> McpSandboxConfig {
+        McpSandboxConfig {
+            allow_network: true,
+            restrict_filesystem: false,
+            filter_syscalls: false,
+            ..McpSandboxConfig::default()
+        }
+    }
+
+    async fn run(config: &McpSandboxConfig, script: &str) -> ExitStatus {
+        let prepared = config.prepare("sandbox-test", "/bin/sh").expect("prepare");
+        let mut command = Command::new("/bin/sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        prepared.configure(&mut command);
+        command.status().await.expect("spawn sandboxed shell")
+    }
+
+    #[test]
+    fn manifest_block_defaults_to_locked_down() {
+        let config: McpSandboxConfig =
+            serde_json::from_value(serde_json::json!({ "maxCpuSeconds": 5 })).unwrap();
+        assert!(config.enabled);
+        assert!(!config.allow_network);
+        assert!(config.restrict_filesystem);
+        assert!(config.filter_syscalls);
+        assert_eq!(config.max_cpu_seconds, Some(5));
+    }
+
+    #[tokio::test]
+    async fn cpu_limit_is_enforced_and_classified() {
+        let config = McpSandboxConfig {
+            max_cpu_seconds: Some(1),
+            ..sandbox()
+        };
+        let status = run(&config, "while :; do :; done").await;
+        let (kind, _) = config.classify_exit(status, false).expect("cpu violation");
+        assert_eq!(kind, McpSandboxViolationKind::CpuLimit);
+    }
+
+    #[tokio::test]
+    async fn filesystem_rules_hide_unlisted_paths() {
+        if linux_landlock_unavailable() {
+            return;
+        }
+        let outside =
+            std::env::temp_dir().join(format!("mcp-sandbox-outside-{}", std::process::id()));
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+
+        let config = McpSandboxConfig {
+            restrict_filesystem: true,
+            ..sandbox()
+        };
+        let script = format!("cat {}", outside.join("secret.txt").display());
+        assert!(!run(&config, &script).await.success());
+
+        let allowed = McpSandboxConfig {
+            read_paths: vec![outside.display().to_string()],
+            ..config
+        };
+        assert!(run(&allowed, &script).await.success());
+        let _ = std::fs::remove_dir_all(outside);
+    }
+
+    #[tokio::test]
+    async fn readable_files_cannot_be_truncated() {
+        if linux::landlock_abi().is_none_or(|abi| abi < 3) {
+            return;
+        }
+        let outside =
+            std::env::temp_dir().join(format!("mcp-sandbox-truncate-{}", std::process::id()));
+        std::fs::create_dir_all(&outside).unwrap();
+        let file = outside.join("notes.txt");
+        std::fs::write(&file, "keep these notes").unwrap();
+
+        let config = McpSandboxConfig {
+            restrict_filesystem: true,
+            read_paths: vec![outside.display().to_string()],
+            ..sandbox()
+        };
+        let script = format!("perl -e 'truncate(\"{}\", 0) or exit 1'", file.display());
+        assert!(!run(&config, &script).await.success());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep these notes");
+
+        let writable = McpSandboxConfig {
+            write_paths: vec![outside.display().to_string()],
+            ..config
+        };
+        assert!(run(&writable, &script).await.success());
+        assert_eq!(std::fs::metadata(&file).unwrap().len(), 0);
+        let _ = std::fs::remove_dir_all(outside);
+    }
+
+    #[tokio::test]
+    async fn namespace_creation_is_a_blocked_syscall() {
+        if !Path::new("/usr/bin/unshare").exists() {
+            return;
+        }
+        let config = McpSandboxConfig {
+            filter_syscalls: true,
+            ..sandbox()
+        };
+        assert!(run(&config, "true").await.success());
+        let status = run(&config, "/usr/bin/unshare --user --map-root-user true").await;
+        let (kind, _) = config
+            .classify_exit(status, false)
+            .expect("unshare is killed");
+        assert_eq!(kind, McpSandboxViolationKind::BlockedSyscall);
+    }
+
+    fn linux_landlock_unavailable() -> bool {
+        McpSandboxConfig::default()
+            .prepare("landlock-probe", "/bin/sh")
+            .is_err()
+    }
+}
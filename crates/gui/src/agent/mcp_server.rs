@@ -0,0 +1,983 @@
+//! Exposes browser tools to external MCP hosts (IDEs, other agents).
+//!
+//! [`BrowserMcpServer`] implements the JSON-RPC surface (`initialize`,
+//! `tools/list`, `tools/call`) on top of the same [`CapabilityGate`] the agent
+//! runtime uses, so approvals and per-capability quotas apply identically to
+//! external callers. Two transports are provided: [`serve_stdio`] and
+//! [`serve_http`], which speaks streamable HTTP on `/mcp` and the legacy SSE
+//! transport on `/sse` + `/messages`.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use agent_core::{ApprovalHandler, CapabilityGate, CapabilityKind, CapabilityRegistry};
+use ai_agent::{McpTool, McpToolError};
+use anyhow::{anyhow, Context, Result};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch, Mutex as AsyncMutex};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+pub const DEFAULT_MCP_SERVER_PORT: u16 = 39217;
+const SERVER_NAME: &str = "advatar-browser";
+const MAX_HTTP_HEADER_BYTES: usize = 16 * 1024;
+const MAX_HTTP_BODY_BYTES: usize = 1024 * 1024;
+const SSE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const SESSION_HEADER: &str = "mcp-session-id";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+pub struct BrowserMcpServer {
+    tools: IndexMap<String, Arc<dyn McpTool>>,
+    gate: CapabilityGate,
+}
+
+impl BrowserMcpServer {
+    /// Every tool is wrapped so each call is approved and charged against
+    /// `capabilities` before it runs. Tools the agent runtime leaves ungated
+    /// (page reads, tab and wallet listings) are guarded as
+    /// [`CapabilityKind::BrowserRead`], since an external client has not been
+    /// vetted the way a user-started run has.
+    pub fn new(
+        tools: Vec<(Arc<dyn McpTool>, Option<CapabilityKind>)>,
+        capabilities: CapabilityRegistry,
+        approval: Option<Arc<dyn ApprovalHandler>>,
+    ) -> Self {
+        let gate = CapabilityGate::new(capabilities, approval);
+        let tools = tools
+            .into_iter()
+            .map(|(tool, capability)| {
+                let capability = capability.unwrap_or(CapabilityKind::BrowserRead);
+                let tool = gate.guard(tool, capability);
+                (tool.description().name.clone(), tool)
+            })
+            .collect();
+        Self { tools, gate }
+    }
+
+    pub fn tool_names(&self) -> Vec<String> {
+        self.tools.keys().cloned().collect()
+    }
+
+    pub async fn capability_snapshot(&self) -> HashMap<String, Option<u32>> {
+        let registry = self.gate.registry();
+        let guard = registry.lock().await;
+        guard
+            .snapshot()
+            .into_iter()
+            .map(|(kind, remaining)| (kind.as_str().to_string(), remaining))
+            .collect()
+    }
+
+    /// Handles one JSON-RPC message. Notifications yield `None`.
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(method) => method.to_string(),
+            None => {
+                // Responses from the client (e.g. to server pings) need no reply.
+                if message.get("result").is_some() || message.get("error").is_some() {
+                    return None;
+                }
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    RpcError::new(INVALID_REQUEST, "missing method"),
+                ));
+            }
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let Some(id) = id else {
+            // Notifications such as `notifications/initialized` carry no reply.
+            return None;
+        };
+
+        let response = match self.dispatch(&method, params).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => error_response(id, err),
+        };
+        Some(response)
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": MCP_PROTOCOL_VERSION,
+                "capabilities": {
+                    "tools": { "listChanged": false }
+                },
+                "serverInfo": {
+                    "name": SERVER_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({
+                "tools": self
+                    .tools
+                    .values()
+                    .map(|tool| {
+                        let description = tool.description();
+                        json!({
+                            "name": description.name,
+                            "description": description.description,
+                            "inputSchema": description.input_schema,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })),
+            "tools/call" => self.call_tool(params).await,
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method not found: {other}"),
+            )),
+        }
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, RpcError> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "tools/call requires a tool name"))?;
+        let tool = self
+            .tools
+            .get(name)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown tool: {name}")))?;
+        let arguments = match params.get("arguments") {
+            Some(Value::Null) | None => json!({}),
+            Some(arguments) => arguments.clone(),
+        };
+
+        // Per MCP, tool failures (including rejected approvals and exhausted
+        // quotas) are reported in-band so the calling model can react.
+        match tool.invoke(arguments).await {
+            Ok(result) => Ok(json!({
+                "content": [{ "type": "text", "text": result.content.to_string() }],
+                "structuredContent": result.content,
+                "isError": false,
+            })),
+            Err(err) => {
+                let message = match &err {
+                    McpToolError::Invocation(message) | McpToolError::InvalidInput(message) => {
+                        message.clone()
+                    }
+                };
+                Ok(json!({
+                    "content": [{ "type": "text", "text": message }],
+                    "isError": true,
+                }))
+            }
+        }
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StdioFraming {
+    NewlineDelimited,
+    ContentLength,
+}
+
+/// Serves MCP over a byte stream until EOF. Accepts both newline-delimited
+/// JSON (the MCP stdio convention) and `Content-Length` framed messages, and
+/// replies using the framing of each request.
+pub async fn serve_stdio<R, W>(
+    server: Arc<BrowserMcpServer>,
+    reader: R,
+    mut writer: W,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    loop {
+        let Some((payload, framing)) = read_stdio_frame(&mut reader).await? else {
+            return Ok(());
+        };
+        let response = match serde_json::from_slice::<Value>(&payload) {
+            Ok(message) => server.handle_message(message).await,
+            Err(err) => Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, format!("invalid JSON: {err}")),
+            )),
+        };
+        if let Some(response) = response {
+            let body = serde_json::to_vec(&response)?;
+            match framing {
+                StdioFraming::NewlineDelimited => {
+                    writer.write_all(&body).await?;
+                    writer.write_all(b"\n").await?;
+                }
+                StdioFraming::ContentLength => {
+                    writer
+                        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+                        .await?;
+                    writer.write_all(&body).await?;
+                }
+            }
+            writer.flush().await?;
+        }
+    }
+}
+
+async fn read_stdio_frame<R>(reader: &mut BufReader<R>) -> Result<Option<(Vec<u8>, StdioFraming)>>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let Some(length) = parse_content_length(trimmed) else {
+            return Ok(Some((
+                trimmed.as_bytes().to_vec(),
+                StdioFraming::NewlineDelimited,
+            )));
+        };
+        // Skip any remaining headers up to the blank separator line.
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await? == 0 {
+                return Err(anyhow!("unexpected EOF in MCP frame headers"));
+            }
+            if header.trim().is_empty() {
+                break;
+            }
+        }
+        if length > MAX_HTTP_BODY_BYTES {
+            return Err(anyhow!("MCP frame of {length} bytes exceeds limit"));
+        }
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).await?;
+        return Ok(Some((body, StdioFraming::ContentLength)));
+    }
+}
+
+fn parse_content_length(line: &str) -> Option<usize> {
+    let (name, value) = line.split_once(':')?;
+    if !name.trim().eq_ignore_ascii_case("content-length") {
+        return None;
+    }
+    value.trim().parse().ok()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpHttpServerInfo {
+    pub address: String,
+    pub endpoint: String,
+    pub sse_endpoint: String,
+}
+
+pub struct McpHttpServerHandle {
+    address: SocketAddr,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+    context: Arc<HttpContext>,
+}
+
+impl McpHttpServerHandle {
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn info(&self) -> McpHttpServerInfo {
+        McpHttpServerInfo {
+            address: self.address.to_string(),
+            endpoint: format!("http://{}/mcp", self.address),
+            sse_endpoint: format!("http://{}/sse", self.address),
+        }
+    }
+
+    pub async fn session_count(&self) -> usize {
+        self.context.sessions.lock().await.len()
+    }
+
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        self.context.sessions.lock().await.clear();
+        let _ = self.task.await;
+    }
+}
+
+struct HttpContext {
+    server: Arc<BrowserMcpServer>,
+    auth_token: String,
+    /// Active sessions; SSE sessions carry the channel their stream drains.
+    sessions: AsyncMutex<HashMap<String, Option<mpsc::UnboundedSender<Value>>>>,
+}
+
+/// Binds a local HTTP listener. Every request must present
+/// `Authorization: Bearer <auth_token>`, and browser-originated requests from
+/// non-loopback origins are refused to prevent DNS rebinding.
+pub async fn serve_http(
+    server: Arc<BrowserMcpServer>,
+    bind: SocketAddr,
+    auth_token: String,
+) -> Result<McpHttpServerHandle> {
+    if !bind.ip().is_loopback() {
+        return Err(anyhow!("MCP server mode only binds to loopback addresses"));
+    }
+    let listener = TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("failed to bind MCP server on {bind}"))?;
+    let address = listener.local_addr()?;
+    let (shutdown, mut shutdown_rx) = watch::channel(false);
+    let context = Arc::new(HttpContext {
+        server,
+        auth_token,
+        sessions: AsyncMutex::new(HashMap::new()),
+    });
+
+    let accept_context = context.clone();
+    let task = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                changed = shutdown_rx.changed() => {
+                    if changed.is_err() || *shutdown_rx.borrow() {
+                        break;
+                    }
+                }
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else {
+                        continue;
+                    };
+                    let context = accept_context.clone();
+                    let shutdown_rx = shutdown_rx.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = handle_connection(stream, context, shutdown_rx).await {
+                            tracing::debug!(error = %err, "MCP server connection closed with error");
+                        }
+                    });
+                }
+            }
+        }
+    });
+
+    Ok(McpHttpServerHandle {
+        address,
+        shutdown,
+        task,
+        context,
+    })
+}
+
+pub(crate) struct HttpRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: HashMap<String, String>,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+pub(crate) async fn read_http_request(
+    stream: &mut BufReader<TcpStream>,
+) -> Result<Option<HttpRequest>> {
+    let mut request_line = String::new();
+    if stream.read_line(&mut request_line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| anyhow!("malformed request line"))?
+        .to_ascii_uppercase();
+    let target = parts
+        .next()
+        .ok_or_else(|| anyhow!("malformed request line"))?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (target.to_string(), HashMap::new()),
+    };
+
+    let mut headers = HashMap::new();
+    let mut header_bytes = request_line.len();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("unexpected EOF in request headers"));
+        }
+        header_bytes += line.len();
+        if header_bytes > MAX_HTTP_HEADER_BYTES {
+            return Err(anyhow!("request headers too large"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = headers
+        .get("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if length > MAX_HTTP_BODY_BYTES {
+        return Err(anyhow!("request body too large"));
+    }
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body).await?;
+
+    Ok(Some(HttpRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = urlencoding::decode(key).ok()?.into_owned();
+            let value = urlencoding::decode(value).ok()?.into_owned();
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Whether `authorization` is `Bearer <token>`. The token bytes are compared
+/// in constant time so response timing does not reveal a matching prefix.
+fn bearer_matches(authorization: Option<&str>, token: &str) -> bool {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| bool::from(presented.as_bytes().ct_eq(token.as_bytes())))
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    context: Arc<HttpContext>,
+    shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let Some(request) = read_http_request(&mut stream).await? else {
+        return Ok(());
+    };
+    let mut stream = stream.into_inner();
+
+    if !origin_allowed(request.header("origin")) {
+        return write_http_response(&mut stream, 403, "Forbidden", &[], b"origin not allowed")
+            .await;
+    }
+    if !bearer_matches(request.header("authorization"), &context.auth_token) {
+        return write_http_response(
+            &mut stream,
+            401,
+            "Unauthorized",
+            &[],
+            b"missing or invalid token",
+        )
+        .await;
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/mcp") => handle_streamable_post(&mut stream, &context, &request).await,
+        ("DELETE", "/mcp") => {
+            let removed = match request.header(SESSION_HEADER) {
+                Some(session_id) => context.sessions.lock().await.remove(session_id).is_some(),
+                None => false,
+            };
+            let (status, reason) = if removed {
+                (200, "OK")
+            } else {
+                (404, "Not Found")
+            };
+            write_http_response(&mut stream, status, reason, &[], b"").await
+        }
+        ("GET", "/sse") => handle_sse_stream(stream, context, shutdown).await,
+        ("POST", "/messages") => handle_sse_post(&mut stream, &context, &request).await,
+        _ => write_http_response(&mut stream, 404, "Not Found", &[], b"").await,
+    }
+}
+
+async fn handle_streamable_post(
+    stream: &mut TcpStream,
+    context: &HttpContext,
+    request: &HttpRequest,
+) -> Result<()> {
+    let message: Value = match serde_json::from_slice(&request.body) {
+        Ok(message) => message,
+        Err(err) => {
+            let body = serde_json::to_vec(&error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, format!("invalid JSON: {err}")),
+            ))?;
+            return write_json_response(stream, 400, "Bad Request", &[], &body).await;
+        }
+    };
+
+    let is_initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+    let mut extra_headers = Vec::new();
+    if is_initialize {
+        let session_id = Uuid::new_v4().to_string();
+        context
+            .sessions
+            .lock()
+            .await
+            .insert(session_id.clone(), None);
+        extra_headers.push(("Mcp-Session-Id".to_string(), session_id));
+    } else {
+        let known = match request.header(SESSION_HEADER) {
+            Some(session_id) => context.sessions.lock().await.contains_key(session_id),
+            None => false,
+        };
+        if !known {
+            return write_http_response(stream, 404, "Not Found", &[], b"unknown MCP session")
+                .await;
+        }
+    }
+
+    match context.server.handle_message(message).await {
+        Some(response) => {
+            let body = serde_json::to_vec(&response)?;
+            write_json_response(stream, 200, "OK", &extra_headers, &body).await
+        }
+        None => write_http_response(stream, 202, "Accepted", &extra_headers, b"").await,
+    }
+}
+
+async fn handle_sse_stream(
+    mut stream: TcpStream,
+    context: Arc<HttpContext>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let session_id = Uuid::new_v4().to_string();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    context
+        .sessions
+        .lock()
+        .await
+        .insert(session_id.clone(), Some(sender));
+
+    let result = async {
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+            )
+            .await?;
+        stream
+            .write_all(format!("event: endpoint\ndata: /messages?sessionId={session_id}\n\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let mut keepalive = tokio::time::interval(SSE_KEEPALIVE_INTERVAL);
+        loop {
+            tokio::select! {
+                message = receiver.recv() => {
+                    let Some(message) = message else { break; };
+                    let payload = serde_json::to_string(&message)?;
+                    stream
+                        .write_all(format!("event: message\ndata: {payload}\n\n").as_bytes())
+                        .await?;
+                    stream.flush().await?;
+                }
+                _ = keepalive.tick() => {
+                    stream.write_all(b": keepalive\n\n").await?;
+                    stream.flush().await?;
+                }
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    }
+    .await;
+
+    context.sessions.lock().await.remove(&session_id);
+    result
+}
+
+async fn handle_sse_post(
+    stream: &mut TcpStream,
+    context: &HttpContext,
+    request: &HttpRequest,
+) -> Result<()> {
+    let sender = match request.query.get("sessionId") {
+        Some(session_id) => context
+            .sessions
+            .lock()
+            .await
+            .get(session_id)
+            .cloned()
+            .flatten(),
+        None => None,
+    };
+    let Some(sender) = sender else {
+        return write_http_response(stream, 404, "Not Found", &[], b"unknown MCP session").await;
+    };
+    let message: Value = match serde_json::from_slice(&request.body) {
+        Ok(message) => message,
+        Err(err) => {
+            return write_http_response(
+                stream,
+                400,
+                "Bad Request",
+                &[],
+                format!("invalid JSON: {err}").as_bytes(),
+            )
+            .await;
+        }
+    };
+
+    write_http_response(stream, 202, "Accepted", &[], b"").await?;
+    if let Some(response) = context.server.handle_message(message).await {
+        let _ = sender.send(response);
+    }
+    Ok(())
+}
+
+pub(crate) fn origin_allowed(origin: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    match url::Url::parse(origin) {
+        Ok(url) => matches!(
+            url.host_str(),
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]") | Some("tauri.localhost")
+        ),
+        Err(_) => false,
+    }
+}
+
+pub(crate) async fn write_json_response(
+    stream: &mut TcpStream,
+    status: u16,
+    reason: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<()> {
+    let mut all_headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+    all_headers.extend_from_slice(headers);
+    write_http_response(stream, status, reason, &all_headers, body).await
+}
+
+pub(crate) async fn write_http_response(
+    stream: &mut TcpStream,
+    status: u16,
+    reason: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_core::CapabilityLimit;
+    use ai_agent::{McpToolDescription, McpToolResult};
+    use async_trait::async_trait;
+
+    struct EchoTool {
+        description: McpToolDescription,
+    }
+
+    impl EchoTool {
+        fn new(name: &str) -> Arc<Self> {
+            Arc::new(Self {
+                description: McpToolDescription::new(
+                    name.to_string(),
+                    "Echo the provided arguments".to_string(),
+                    json!({ "type": "object" }),
+                ),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl McpTool for EchoTool {
+        fn description(&self) -> &McpToolDescription {
+            &self.description
+        }
+
+        async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
+            Ok(McpToolResult {
+                content: json!({ "echo": args }),
+                metadata: Default::default(),
+            })
+        }
+    }
+
+    fn test_server() -> Arc<BrowserMcpServer> {
+        let mut capabilities = CapabilityRegistry::new();
+        capabilities.grant(CapabilityKind::Navigate, CapabilityLimit::limited(1));
+        capabilities.grant(CapabilityKind::BrowserRead, CapabilityLimit::unlimited());
+        Arc::new(BrowserMcpServer::new(
+            vec![
+                (EchoTool::new("browser.tabs"), None),
+                (
+                    EchoTool::new("browser.navigate"),
+                    Some(CapabilityKind::Navigate),
+                ),
+            ],
+            capabilities,
+            None,
+        ))
+    }
+
+    #[tokio::test]
+    async fn lists_and_calls_tools_with_capability_limits() {
+        let server = test_server();
+        let listed = server
+            .handle_message(json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }))
+            .await
+            .unwrap();
+        assert_eq!(listed["result"]["tools"].as_array().unwrap().len(), 2);
+
+        let call = |id: u64| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "browser.navigate", "arguments": { "url": "https://example.com" } }
+            })
+        };
+        let first = server.handle_message(call(2)).await.unwrap();
+        assert_eq!(first["result"]["isError"], json!(false));
+        assert_eq!(
+            first["result"]["structuredContent"]["echo"]["url"],
+            json!("https://example.com")
+        );
+
+        let second = server.handle_message(call(3)).await.unwrap();
+        assert_eq!(second["result"]["isError"], json!(true));
+
+        let missing = server
+            .handle_message(json!({ "jsonrpc": "2.0", "id": 4, "method": "resources/list" }))
+            .await
+            .unwrap();
+        assert_eq!(missing["error"]["code"], json!(METHOD_NOT_FOUND));
+        assert!(server
+            .handle_message(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await
+            .is_none());
+    }
+
+    #[derive(Default)]
+    struct RecordingApproval {
+        requests: std::sync::Mutex<Vec<(CapabilityKind, Value)>>,
+    }
+
+    #[async_trait]
+    impl ApprovalHandler for RecordingApproval {
+        async fn request_approval(
+            &self,
+            capability: &CapabilityKind,
+            payload: &Value,
+        ) -> anyhow::Result<bool> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((capability.clone(), payload.clone()));
+            Ok(false)
+        }
+    }
+
+    #[tokio::test]
+    async fn ungated_read_tools_still_ask_for_approval() {
+        let approval = Arc::new(RecordingApproval::default());
+        let server = BrowserMcpServer::new(
+            vec![(EchoTool::new("browser.dom_query"), None)],
+            CapabilityRegistry::with_browser_defaults(),
+            Some(approval.clone()),
+        );
+
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": "browser.dom_query", "arguments": { "selector": "input" } }
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(response["result"]["isError"], json!(true));
+        assert!(response["result"]["structuredContent"].is_null());
+        let requests = approval.requests.lock().unwrap();
+        assert_eq!(
+            *requests,
+            vec![(CapabilityKind::BrowserRead, json!({ "selector": "input" }))]
+        );
+    }
+
+    #[tokio::test]
+    async fn stdio_transport_supports_both_framings() {
+        let server = test_server();
+        let (mut client, server_io) = tokio::io::duplex(64 * 1024);
+        let (server_read, server_write) = tokio::io::split(server_io);
+        let task = tokio::spawn(serve_stdio(server, server_read, server_write));
+
+        let line = json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }).to_string();
+        client
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .unwrap();
+        let framed = json!({ "jsonrpc": "2.0", "id": 2, "method": "initialize" }).to_string();
+        client
+            .write_all(format!("Content-Length: {}\r\n\r\n{framed}", framed.len()).as_bytes())
+            .await
+            .unwrap();
+
+        let mut reader = BufReader::new(&mut client);
+        let mut first = String::new();
+        reader.read_line(&mut first).await.unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&first).unwrap()["id"],
+            json!(1)
+        );
+        let (body, framing) = read_stdio_frame(&mut reader).await.unwrap().unwrap();
+        assert_eq!(framing, StdioFraming::ContentLength);
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            response["result"]["protocolVersion"],
+            json!(MCP_PROTOCOL_VERSION)
+        );
+
+        drop(reader);
+        drop(client);
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn http_transport_requires_token_and_session() {
+        let handle = serve_http(
+            test_server(),
+            "127.0.0.1:0".parse().unwrap(),
+            "secret".to_string(),
+        )
+        .await
+        .unwrap();
+        let endpoint = handle.info().endpoint;
+        let client = reqwest::Client::new();
+
+        let unauthorised = client
+            .post(&endpoint)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unauthorised.status().as_u16(), 401);
+        let wrong_token = client
+            .post(&endpoint)
+            .bearer_auth("secreT")
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong_token.status().as_u16(), 401);
+
+        let initialised = client
+            .post(&endpoint)
+            .bearer_auth("secret")
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(initialised.status().as_u16(), 200);
+        let session_id = initialised
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|value| value.to_str().ok())
+            .unwrap()
+            .to_string();
+
+        let without_session = client
+            .post(&endpoint)
+            .bearer_auth("secret")
+            .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(without_session.status().as_u16(), 404);
+
+        let listed: Value = client
+            .post(&endpoint)
+            .bearer_auth("secret")
+            .header("Mcp-Session-Id", &session_id)
+            .json(&json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/list" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(listed["result"]["tools"].as_array().unwrap().len(), 2);
+
+        let cross_origin = client
+            .post(&endpoint)
+            .bearer_auth("secret")
+            .header("Origin", "https://evil.example")
+            .json(&json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(cross_origin.status().as_u16(), 403);
+
+        handle.shutdown().await;
+    }
+}
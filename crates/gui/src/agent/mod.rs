@@ -1,18 +1,48 @@
 pub mod approvals;
+mod blockchain_access;
 pub mod credits;
 pub mod iproov;
+pub mod iproov_http;
 pub mod manager;
 mod mcp_client;
+mod mcp_health;
+mod mcp_oauth;
+mod mcp_sandbox;
+pub mod mcp_server;
+mod run_history;
+mod run_queue;
 pub mod skills;
+pub mod spent_mandates;
 pub mod tools;
 
 pub use approvals::{ApprovalBroker, GuiApprovalHandler};
-pub use credits::{CreditAccount, CreditSnapshot};
+pub use blockchain_access::BlockchainAccessPolicy;
+pub use credits::{
+    CreditAccount, CreditLedgerEntry, CreditLedgerKind, CreditSnapshot, CreditStatement,
+    CreditStatementFormat, CreditUsage,
+};
 pub use manager::{
     AgentManager, AgentRunRequest, AgentRunResponse, AgentRunStatus, AgentRunSummary,
-    AgentSkillSummary,
+    AgentRunSurface, AgentSkillStatus, AgentSkillSummary, McpServerModeStatus,
 };
 pub use mcp_client::{
-    McpConfigValue, McpResolvedServerConfig, McpRuntimeStatus, McpSecretValue, McpServerConfig,
-    McpServerRegistry, McpServerState, McpTransportKind,
+    McpConfigValue, McpPrompt, McpPromptArgument, McpPromptMessage, McpPromptRef, McpPromptResult,
+    McpResolvedServerConfig, McpResource, McpResourceContents, McpResourceRef,
+    McpResourceSubscription, McpRuntimeStatus, McpSecretValue, McpServerConfig, McpServerRegistry,
+    McpServerState, McpTransportKind,
+};
+pub use mcp_health::{
+    McpCircuitBreakerConfig, McpCircuitState, McpLatencyBucket, McpLatencySummary, McpToolHealth,
+};
+pub use mcp_oauth::{
+    McpAuthorizationRequired, McpOAuthAuthorization, McpOAuthConfig, McpOAuthPending,
+    McpOAuthStatus,
+};
+pub use mcp_sandbox::{McpSandboxConfig, McpSandboxViolation, McpSandboxViolationKind};
+pub use run_history::{
+    AgentRunExportFormat, AgentRunHistory, AgentRunOutputFormat, AgentRunQuery, AgentRunRetention,
+    AgentRunTranscript,
+};
+pub use run_queue::{
+    AgentQueuedRun, AgentRunPriority, AgentRunQueue, AgentRunQueueLimits, AgentRunQueueSnapshot,
 };
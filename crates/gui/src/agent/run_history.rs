@@ -0,0 +1,819 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use agent_core::LedgerEntry;
+use ai_agent::{AgentEvent, PlanStep};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::manager::{AgentRunEventPayload, AgentRunStatus, AgentRunSummary};
+
+const DEFAULT_HISTORY_DIR: &str = "configs/agent_runs";
+const INDEX_FILE: &str = "index.json";
+const RETENTION_FILE: &str = "retention.json";
+const TRANSCRIPTS_DIR: &str = "runs";
+const DEFAULT_MAX_RUNS: usize = 500;
+const DEFAULT_MAX_AGE_DAYS: u32 = 90;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 500;
+const DAY_MS: u64 = 86_400_000;
+
+/// How much run history is kept on disk. `max_age_days == 0` keeps runs
+/// regardless of age.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRunRetention {
+    #[serde(default = "default_max_runs")]
+    pub max_runs: usize,
+    #[serde(default = "default_max_age_days")]
+    pub max_age_days: u32,
+}
+
+impl Default for AgentRunRetention {
+    fn default() -> Self {
+        Self {
+            max_runs: DEFAULT_MAX_RUNS,
+            max_age_days: DEFAULT_MAX_AGE_DAYS,
+        }
+    }
+}
+
+fn default_max_runs() -> usize {
+    DEFAULT_MAX_RUNS
+}
+
+fn default_max_age_days() -> u32 {
+    DEFAULT_MAX_AGE_DAYS
+}
+
+/// Everything recorded about a finished run: the summary shown in lists plus
+/// the event stream, plan steps and ledger needed to replay what happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRunTranscript {
+    pub summary: AgentRunSummary,
+    #[serde(default)]
+    pub events: Vec<AgentRunEventPayload>,
+    #[serde(default)]
+    pub steps: Vec<PlanStep>,
+    #[serde(default)]
+    pub ledger_root: Option<String>,
+    #[serde(default)]
+    pub ledger_entries: Vec<LedgerEntry>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRunQuery {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub status: Option<AgentRunStatus>,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub schedule_id: Option<String>,
+    #[serde(default)]
+    pub skill_id: Option<String>,
+    #[serde(default)]
+    pub since_ms: Option<u64>,
+    #[serde(default)]
+    pub until_ms: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRunExportFormat {
+    Json,
+    Markdown,
+}
+
+/// Formats a run's structured output can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRunOutputFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RunIndex {
+    runs: Vec<AgentRunSummary>,
+}
+
+/// On-disk store of finished agent runs. Summaries live in a single index
+/// file kept in memory; transcripts are one JSON file per run and are only
+/// read when a run is opened or exported.
+pub struct AgentRunHistory {
+    dir: PathBuf,
+    index: RwLock<Vec<AgentRunSummary>>,
+    retention: RwLock<AgentRunRetention>,
+}
+
+impl AgentRunHistory {
+    pub fn load_default() -> Result<Self> {
+        Self::from_dir(DEFAULT_HISTORY_DIR)
+    }
+
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let index = read_json::<RunIndex>(&dir.join(INDEX_FILE))?.unwrap_or_default();
+        let retention =
+            read_json::<AgentRunRetention>(&dir.join(RETENTION_FILE))?.unwrap_or_default();
+        let history = Self {
+            dir,
+            index: RwLock::new(index.runs),
+            retention: RwLock::new(retention),
+        };
+        history.prune(now_ms())?;
+        Ok(history)
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            dir: PathBuf::from(DEFAULT_HISTORY_DIR),
+            index: RwLock::new(Vec::new()),
+            retention: RwLock::new(AgentRunRetention::default()),
+        }
+    }
+
+    /// Highest numeric suffix among stored `run-N` ids, so new runs started
+    /// after a restart do not overwrite earlier transcripts.
+    pub fn last_sequence(&self) -> u64 {
+        self.index
+            .read()
+            .expect("agent run history poisoned")
+            .iter()
+            .filter_map(|run| run.run_id.strip_prefix("run-")?.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn record(&self, transcript: &AgentRunTranscript) -> Result<()> {
+        let path = self.transcript_path(&transcript.summary.run_id)?;
+        write_json(&path, transcript)?;
+        {
+            let mut index = self.index.write().expect("agent run history poisoned");
+            index.retain(|run| run.run_id != transcript.summary.run_id);
+            index.push(transcript.summary.clone());
+            sort_newest_first(&mut index);
+        }
+        self.prune(now_ms())
+    }
+
+    pub fn search(&self, query: &AgentRunQuery) -> Vec<AgentRunSummary> {
+        let needle = query
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_lowercase);
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT);
+        let index = self.index.read().expect("agent run history poisoned");
+        index
+            .iter()
+            .filter(|run| query.status.is_none_or(|status| run.status == status))
+            .filter(|run| matches_filter(&query.app_id, &run.app_id))
+            .filter(|run| matches_filter(&query.schedule_id, &run.schedule_id))
+            .filter(|run| matches_filter(&query.skill_id, &run.skill_id))
+            .filter(|run| {
+                query
+                    .since_ms
+                    .is_none_or(|since| run.started_at_ms >= since)
+            })
+            .filter(|run| {
+                query
+                    .until_ms
+                    .is_none_or(|until| run.started_at_ms <= until)
+            })
+            .filter(|run| {
+                needle
+                    .as_deref()
+                    .is_none_or(|needle| summary_matches(run, needle))
+            })
+            .skip(query.offset.unwrap_or(0))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, run_id: &str) -> Result<Option<AgentRunTranscript>> {
+        let known = self
+            .index
+            .read()
+            .expect("agent run history poisoned")
+            .iter()
+            .any(|run| run.run_id == run_id);
+        if !known {
+            return Ok(None);
+        }
+        read_json(&self.transcript_path(run_id)?)
+    }
+
+    pub fn export(&self, run_id: &str, format: AgentRunExportFormat) -> Result<Option<String>> {
+        let Some(transcript) = self.get(run_id)? else {
+            return Ok(None);
+        };
+        let rendered = match format {
+            AgentRunExportFormat::Json => serde_json::to_string_pretty(&transcript)?,
+            AgentRunExportFormat::Markdown => render_markdown(&transcript),
+        };
+        Ok(Some(rendered))
+    }
+
+    /// Structured output of a stored run, if it has one.
+    pub fn output(&self, run_id: &str) -> Option<Value> {
+        self.index
+            .read()
+            .expect("agent run history poisoned")
+            .iter()
+            .find(|run| run.run_id == run_id)
+            .and_then(|run| run.structured_output.clone())
+    }
+
+    pub fn export_output(
+        &self,
+        run_id: &str,
+        format: AgentRunOutputFormat,
+    ) -> Result<Option<String>> {
+        let output = {
+            let index = self.index.read().expect("agent run history poisoned");
+            let Some(run) = index.iter().find(|run| run.run_id == run_id) else {
+                return Ok(None);
+            };
+            run.structured_output
+                .clone()
+                .ok_or_else(|| anyhow!("agent run `{run_id}` has no structured output"))?
+        };
+        let rendered = match format {
+            AgentRunOutputFormat::Json => serde_json::to_string_pretty(&output)?,
+            AgentRunOutputFormat::Csv => render_csv(&output),
+        };
+        Ok(Some(rendered))
+    }
+
+    pub fn delete(&self, run_id: &str) -> Result<bool> {
+        let path = self.transcript_path(run_id)?;
+        let removed = {
+            let mut index = self.index.write().expect("agent run history poisoned");
+            let before = index.len();
+            index.retain(|run| run.run_id != run_id);
+            index.len() != before
+        };
+        if removed {
+            remove_file_if_present(&path)?;
+            self.write_index()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn retention(&self) -> AgentRunRetention {
+        *self.retention.read().expect("agent run retention poisoned")
+    }
+
+    pub fn set_retention(&self, retention: AgentRunRetention) -> Result<AgentRunRetention> {
+        if retention.max_runs == 0 {
+            return Err(anyhow!("maxRuns must be at least 1"));
+        }
+        *self
+            .retention
+            .write()
+            .expect("agent run retention poisoned") = retention;
+        write_json(&self.dir.join(RETENTION_FILE), &retention)?;
+        self.prune(now_ms())?;
+        Ok(retention)
+    }
+
+    fn prune(&self, now_ms: u64) -> Result<()> {
+        let retention = self.retention();
+        let expired = {
+            let mut index = self.index.write().expect("agent run history poisoned");
+            let cutoff = (retention.max_age_days > 0)
+                .then(|| now_ms.saturating_sub(retention.max_age_days as u64 * DAY_MS));
+            let mut expired = Vec::new();
+            let mut kept = Vec::with_capacity(index.len());
+            for run in index.drain(..) {
+                let too_old = cutoff.is_some_and(|cutoff| run_timestamp(&run) < cutoff);
+                if too_old || kept.len() >= retention.max_runs {
+                    expired.push(run.run_id);
+                } else {
+                    kept.push(run);
+                }
+            }
+            *index = kept;
+            expired
+        };
+        for run_id in &expired {
+            if let Ok(path) = self.transcript_path(run_id) {
+                remove_file_if_present(&path)?;
+            }
+        }
+        self.write_index()
+    }
+
+    fn write_index(&self) -> Result<()> {
+        let runs = self
+            .index
+            .read()
+            .expect("agent run history poisoned")
+            .clone();
+        write_json(&self.dir.join(INDEX_FILE), &RunIndex { runs })
+    }
+
+    fn transcript_path(&self, run_id: &str) -> Result<PathBuf> {
+        let valid = !run_id.is_empty()
+            && run_id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+        if !valid {
+            return Err(anyhow!("invalid agent run id `{run_id}`"));
+        }
+        Ok(self
+            .dir
+            .join(TRANSCRIPTS_DIR)
+            .join(format!("{run_id}.json")))
+    }
+}
+
+fn matches_filter(filter: &Option<String>, value: &Option<String>) -> bool {
+    match filter
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(expected) => value.as_deref() == Some(expected),
+        None => true,
+    }
+}
+
+fn summary_matches(run: &AgentRunSummary, needle: &str) -> bool {
+    [
+        Some(run.run_id.as_str()),
+        Some(run.label.as_str()),
+        Some(run.task.as_str()),
+        run.final_answer.as_deref(),
+        run.error.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|field| field.to_lowercase().contains(needle))
+}
+
+fn run_timestamp(run: &AgentRunSummary) -> u64 {
+    run.finished_at_ms.unwrap_or(run.started_at_ms)
+}
+
+fn sort_newest_first(runs: &mut [AgentRunSummary]) {
+    runs.sort_by(|left, right| {
+        right
+            .started_at_ms
+            .cmp(&left.started_at_ms)
+            .then_with(|| right.run_id.cmp(&left.run_id))
+    });
+}
+
+fn render_markdown(transcript: &AgentRunTranscript) -> String {
+    let summary = &transcript.summary;
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", summary.label);
+    let _ = writeln!(out, "- **Run:** `{}`", summary.run_id);
+    let _ = writeln!(out, "- **Status:** {}", summary.status.as_str());
+    let _ = writeln!(out, "- **Started:** {} ms", summary.started_at_ms);
+    if let Some(finished) = summary.finished_at_ms {
+        let _ = writeln!(out, "- **Finished:** {finished} ms");
+    }
+    for (label, value) in [
+        ("Skill", &summary.skill_id),
+        ("App", &summary.app_id),
+        ("Schedule", &summary.schedule_id),
+    ] {
+        if let Some(value) = value {
+            let _ = writeln!(out, "- **{label}:** `{value}`");
+        }
+    }
+    let _ = writeln!(
+        out,
+        "- **Tokens:** {}{}",
+        summary.tokens_used,
+        if summary.tokens_estimated {
+            " (estimated)"
+        } else {
+            ""
+        }
+    );
+    let _ = writeln!(out, "- **Credits spent:** {}", summary.credits_spent);
+
+    let _ = writeln!(out, "\n## Task\n\n{}", summary.task.trim());
+
+    if !transcript.steps.is_empty() {
+        let _ = writeln!(out, "\n## Steps\n");
+        for (position, step) in transcript.steps.iter().enumerate() {
+            match step {
+                PlanStep::Tool { thought, call } => {
+                    let _ = writeln!(out, "{}. Tool `{}`", position + 1, call.name);
+                    if let Some(thought) = thought {
+                        let _ = writeln!(out, "   - Thought: {thought}");
+                    }
+                    let _ = writeln!(out, "   - Arguments: `{}`", call.arguments);
+                    if let Some(observation) = &call.observation {
+                        let _ = writeln!(out, "   - Observation: `{observation}`");
+                    }
+                }
+                PlanStep::Finish { summary, answer } => {
+                    let _ = writeln!(out, "{}. Finish: {answer}", position + 1);
+                    if let Some(summary) = summary {
+                        let _ = writeln!(out, "   - Summary: {summary}");
+                    }
+                }
+            }
+        }
+    }
+
+    let activity = transcript
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            AgentRunEventPayload::Activity { event } => Some(event),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !activity.is_empty() {
+        let _ = writeln!(out, "\n## Events\n");
+        for event in activity {
+            let line = match event {
+                AgentEvent::ModelResponse { raw } => format!("Model response: {}", raw.trim()),
+                AgentEvent::ToolCall { name, args } => format!("Tool call `{name}`: `{args}`"),
+                AgentEvent::ToolResult { name, result } => {
+                    format!("Tool result `{name}`: `{result}`")
+                }
+                AgentEvent::OutputRejected { errors } => {
+                    format!("Output rejected: {}", errors.join("; "))
+                }
+                AgentEvent::Cancelled { reason } => format!("Cancelled: {reason}"),
+            };
+            let _ = writeln!(out, "- {line}");
+        }
+    }
+
+    if !transcript.ledger_entries.is_empty() || transcript.ledger_root.is_some() {
+        let _ = writeln!(out, "\n## Ledger\n");
+        if let Some(root) = &transcript.ledger_root {
+            let _ = writeln!(out, "Root: `{root}`\n");
+        }
+        for entry in &transcript.ledger_entries {
+            let _ = writeln!(
+                out,
+                "- `{}` {} (`{}`)",
+                entry.capability.as_str(),
+                entry.message,
+                entry.hash
+            );
+        }
+    }
+
+    if let Some(error) = &summary.error {
+        let _ = writeln!(out, "\n## Error\n\n{error}");
+    }
+    if let Some(answer) = &summary.final_answer {
+        let _ = writeln!(out, "\n## Final answer\n\n{}", answer.trim());
+    }
+    out
+}
+
+/// One row per item when the output is an array, or an object holding
+/// exactly one array of objects; otherwise a single row. Columns are the
+/// object keys in order of first appearance, or `value` for scalar rows;
+/// nested values are written as JSON.
+fn render_csv(output: &Value) -> String {
+    let rows: Vec<&Value> = match output {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(map) => {
+            let mut tables = map.values().filter(|value| {
+                value
+                    .as_array()
+                    .is_some_and(|items| !items.is_empty() && items.iter().all(Value::is_object))
+            });
+            match (tables.next(), tables.next()) {
+                (Some(Value::Array(items)), None) => items.iter().collect(),
+                _ => vec![output],
+            }
+        }
+        _ => vec![output],
+    };
+
+    let mut columns: Vec<&str> = Vec::new();
+    for row in &rows {
+        let keys: Vec<&str> = match row {
+            Value::Object(map) => map.keys().map(String::as_str).collect(),
+            _ => vec!["value"],
+        };
+        for key in keys {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|column| csv_field(column)).collect();
+    let _ = writeln!(out, "{}", header.join(","));
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let cell = match row {
+                    Value::Object(map) => map.get(*column),
+                    other if *column == "value" => Some(other),
+                    _ => None,
+                };
+                match cell {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(text)) => csv_field(text),
+                    Some(other) => csv_field(&other.to_string()),
+                }
+            })
+            .collect();
+        let _ = writeln!(out, "{}", cells.join(","));
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("reading agent run history at {}", path.display()))?;
+    let value = serde_json::from_str(&raw)
+        .with_context(|| format!("parsing agent run history at {}", path.display()))?;
+    Ok(Some(value))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating agent run history dir {}", parent.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(value)?;
+    fs::write(path, raw).with_context(|| format!("writing agent run history at {}", path.display()))
+}
+
+fn remove_file_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => {
+            Err(err).with_context(|| format!("removing agent run transcript {}", path.display()))
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::manager::AgentRunSurface;
+    use crate::agent::run_queue::AgentRunPriority;
+    use ai_agent::ToolInvocation;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn temp_history_dir() -> PathBuf {
+        std::env::temp_dir()
+            .join("dbrowser-agent-run-history-tests")
+            .join(Uuid::new_v4().to_string())
+    }
+
+    fn transcript(run_id: &str, task: &str, started_at_ms: u64) -> AgentRunTranscript {
+        AgentRunTranscript {
+            summary: AgentRunSummary {
+                run_id: run_id.to_string(),
+                task: task.to_string(),
+                label: task.to_string(),
+                skill_id: None,
+                app_id: Some("research-brief".to_string()),
+                schedule_id: None,
+                status: AgentRunStatus::Completed,
+                priority: AgentRunPriority::Normal,
+                surface: AgentRunSurface::Background,
+                tab_id: None,
+                started_at_ms,
+                finished_at_ms: Some(started_at_ms + 500),
+                tokens_used: 42,
+                tokens_estimated: false,
+                credits_spent: 42,
+                cancelled: false,
+                final_answer: Some(format!("answer for {task}")),
+                structured_output: None,
+                error: None,
+            },
+            events: vec![AgentRunEventPayload::Activity {
+                event: AgentEvent::ToolCall {
+                    name: "navigate".to_string(),
+                    args: json!({ "url": "https://example.com" }),
+                },
+            }],
+            steps: vec![
+                PlanStep::Tool {
+                    thought: Some("open the page".to_string()),
+                    call: ToolInvocation {
+                        name: "navigate".to_string(),
+                        arguments: json!({ "url": "https://example.com" }),
+                        observation: Some(json!({ "ok": true })),
+                    },
+                },
+                PlanStep::Finish {
+                    summary: None,
+                    answer: format!("answer for {task}"),
+                },
+            ],
+            ledger_root: Some("abc123".to_string()),
+            ledger_entries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn recorded_runs_survive_reload() {
+        let dir = temp_history_dir();
+        let history = AgentRunHistory::from_dir(&dir).expect("history");
+        let now = now_ms();
+        history
+            .record(&transcript("run-7", "Summarise funding news", now))
+            .expect("record run");
+
+        let reloaded = AgentRunHistory::from_dir(&dir).expect("reloaded");
+        assert_eq!(reloaded.last_sequence(), 7);
+        let runs = reloaded.search(&AgentRunQuery::default());
+        assert_eq!(runs.len(), 1);
+        let opened = reloaded
+            .get("run-7")
+            .expect("read transcript")
+            .expect("transcript exists");
+        assert_eq!(opened.steps.len(), 2);
+        assert_eq!(opened.events.len(), 1);
+        assert_eq!(opened.ledger_root.as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn search_filters_by_text_and_status() {
+        let history = AgentRunHistory::from_dir(temp_history_dir()).expect("history");
+        let now = now_ms();
+        history
+            .record(&transcript("run-1", "Compare laptop prices", now))
+            .expect("record");
+        let mut failed = transcript("run-2", "Book a flight", now + 1);
+        failed.summary.status = AgentRunStatus::Failed;
+        failed.summary.error = Some("gateway timeout".to_string());
+        history.record(&failed).expect("record");
+
+        let by_text = history.search(&AgentRunQuery {
+            text: Some("LAPTOP".to_string()),
+            ..AgentRunQuery::default()
+        });
+        assert_eq!(by_text.len(), 1);
+        assert_eq!(by_text[0].run_id, "run-1");
+
+        let by_error = history.search(&AgentRunQuery {
+            text: Some("timeout".to_string()),
+            status: Some(AgentRunStatus::Failed),
+            ..AgentRunQuery::default()
+        });
+        assert_eq!(by_error.len(), 1);
+        assert_eq!(by_error[0].run_id, "run-2");
+
+        let newest_first = history.search(&AgentRunQuery::default());
+        assert_eq!(newest_first[0].run_id, "run-2");
+    }
+
+    #[test]
+    fn retention_prunes_oldest_and_expired_runs() {
+        let dir = temp_history_dir();
+        let history = AgentRunHistory::from_dir(&dir).expect("history");
+        let now = now_ms();
+        history
+            .record(&transcript("run-1", "ancient", now - 40 * DAY_MS))
+            .expect("record");
+        history
+            .record(&transcript("run-2", "older", now - 2))
+            .expect("record");
+        history
+            .record(&transcript("run-3", "newest", now - 1))
+            .expect("record");
+
+        history
+            .set_retention(AgentRunRetention {
+                max_runs: 1,
+                max_age_days: 30,
+            })
+            .expect("retention");
+
+        let runs = history.search(&AgentRunQuery::default());
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].run_id, "run-3");
+        assert!(!dir.join(TRANSCRIPTS_DIR).join("run-1.json").exists());
+        assert!(!dir.join(TRANSCRIPTS_DIR).join("run-2.json").exists());
+
+        let reloaded = AgentRunHistory::from_dir(&dir).expect("reloaded");
+        assert_eq!(reloaded.retention().max_runs, 1);
+    }
+
+    #[test]
+    fn export_and_delete_runs() {
+        let dir = temp_history_dir();
+        let history = AgentRunHistory::from_dir(&dir).expect("history");
+        history
+            .record(&transcript("run-4", "Check order status", now_ms()))
+            .expect("record");
+
+        let markdown = history
+            .export("run-4", AgentRunExportFormat::Markdown)
+            .expect("export")
+            .expect("run exists");
+        assert!(markdown.starts_with("# Check order status"));
+        assert!(markdown.contains("1. Tool `navigate`"));
+        assert!(markdown.contains("## Final answer"));
+
+        let exported = history
+            .export("run-4", AgentRunExportFormat::Json)
+            .expect("export")
+            .expect("run exists");
+        let parsed: AgentRunTranscript = serde_json::from_str(&exported).expect("json transcript");
+        assert_eq!(parsed.summary.run_id, "run-4");
+
+        assert!(history.delete("run-4").expect("delete"));
+        assert!(!history.delete("run-4").expect("second delete"));
+        assert!(history.get("run-4").expect("get").is_none());
+        assert!(!dir.join(TRANSCRIPTS_DIR).join("run-4.json").exists());
+        assert!(history.delete("../index").is_err());
+    }
+
+    #[test]
+    fn structured_output_exports_as_json_and_csv() {
+        let history = AgentRunHistory::from_dir(temp_history_dir()).expect("history");
+        let mut run = transcript("run-9", "Compare flights", now_ms());
+        run.summary.structured_output = Some(json!({
+            "currency": "EUR",
+            "flights": [
+                { "carrier": "TAP", "price": 412, "stops": ["OPO"] },
+                { "carrier": "Iberia, S.A.", "price": 398.5, "note": "say \"hi\"" }
+            ]
+        }));
+        history.record(&run).expect("record");
+        history
+            .record(&transcript("run-10", "No schema", now_ms()))
+            .expect("record");
+
+        let csv = history
+            .export_output("run-9", AgentRunOutputFormat::Csv)
+            .expect("export")
+            .expect("run exists");
+        assert_eq!(
+            csv,
+            "carrier,price,stops,note\n\
+             TAP,412,\"[\"\"OPO\"\"]\",\n\
+             \"Iberia, S.A.\",398.5,,\"say \"\"hi\"\"\"\n"
+        );
+        let json = history
+            .export_output("run-9", AgentRunOutputFormat::Json)
+            .expect("export")
+            .expect("run exists");
+        assert_eq!(
+            serde_json::from_str::<Value>(&json).unwrap()["currency"],
+            "EUR"
+        );
+        assert_eq!(history.output("run-9").unwrap()["flights"][0]["price"], 412);
+
+        assert!(history
+            .export_output("run-10", AgentRunOutputFormat::Csv)
+            .is_err());
+        assert!(history
+            .export_output("run-11", AgentRunOutputFormat::Csv)
+            .expect("unknown run")
+            .is_none());
+        assert_eq!(render_csv(&json!([1, "two"])), "value\n1\ntwo\n");
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+const DEFAULT_QUEUE_CONFIG_PATH: &str = "configs/agent_run_queue.json";
+const DEFAULT_MAX_CONCURRENT: usize = 2;
+const DEFAULT_PER_APP: usize = 1;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRunPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Admission limits for agent runs. `per_app` caps concurrent runs launched
+/// from the same agent app; `app_overrides` replaces that cap for specific
+/// app ids. Runs without an app only count against `max_concurrent`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRunQueueLimits {
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    #[serde(default = "default_per_app")]
+    pub per_app: usize,
+    #[serde(default)]
+    pub app_overrides: HashMap<String, usize>,
+}
+
+impl Default for AgentRunQueueLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            per_app: DEFAULT_PER_APP,
+            app_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl AgentRunQueueLimits {
+    fn app_limit(&self, app_id: &str) -> usize {
+        self.app_overrides
+            .get(app_id)
+            .copied()
+            .unwrap_or(self.per_app)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.max_concurrent == 0 {
+            return Err(anyhow!("maxConcurrent must be at least 1"));
+        }
+        if self.per_app == 0 {
+            return Err(anyhow!("perApp must be at least 1"));
+        }
+        if let Some((app_id, _)) = self.app_overrides.iter().find(|(_, limit)| **limit == 0) {
+            return Err(anyhow!(
+                "concurrency limit for app `{app_id}` must be at least 1"
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_max_concurrent() -> usize {
+    DEFAULT_MAX_CONCURRENT
+}
+
+fn default_per_app() -> usize {
+    DEFAULT_PER_APP
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentRunTicket {
+    pub run_id: String,
+    pub label: String,
+    pub app_id: Option<String>,
+    pub priority: AgentRunPriority,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentQueuedRun {
+    pub run_id: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    pub priority: AgentRunPriority,
+    pub enqueued_at_ms: u64,
+    /// 1-based position in admission order.
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRunQueueSnapshot {
+    pub limits: AgentRunQueueLimits,
+    pub running: Vec<String>,
+    pub queued: Vec<AgentQueuedRun>,
+}
+
+pub type AgentRunQueueListener = Arc<dyn Fn(&AgentRunQueueSnapshot) + Send + Sync>;
+
+struct WaitingRun {
+    ticket: AgentRunTicket,
+    seq: u64,
+    enqueued_at_ms: u64,
+    admit: oneshot::Sender<bool>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    limits: AgentRunQueueLimits,
+    running: HashMap<String, Option<String>>,
+    waiting: Vec<WaitingRun>,
+    next_seq: u64,
+}
+
+impl QueueState {
+    /// Waiting runs in admission order: highest priority first, then FIFO.
+    fn ordered_waiting(&self) -> Vec<&WaitingRun> {
+        let mut waiting = self.waiting.iter().collect::<Vec<_>>();
+        waiting.sort_by(|left, right| {
+            right
+                .ticket
+                .priority
+                .cmp(&left.ticket.priority)
+                .then_with(|| left.seq.cmp(&right.seq))
+        });
+        waiting
+    }
+
+    fn running_for_app(&self, app_id: &str) -> usize {
+        self.running
+            .values()
+            .filter(|running| running.as_deref() == Some(app_id))
+            .count()
+    }
+
+    fn admissible(&self, ticket: &AgentRunTicket) -> bool {
+        if self.running.len() >= self.limits.max_concurrent {
+            return false;
+        }
+        match ticket.app_id.as_deref() {
+            Some(app_id) => self.running_for_app(app_id) < self.limits.app_limit(app_id),
+            None => true,
+        }
+    }
+
+    /// Admits as many waiting runs as the limits allow. A run blocked by its
+    /// app limit does not hold back runs from other apps queued behind it.
+    fn dispatch(&mut self) {
+        loop {
+            let next = self
+                .ordered_waiting()
+                .into_iter()
+                .find(|waiting| self.admissible(&waiting.ticket))
+                .map(|waiting| waiting.seq);
+            let Some(seq) = next else {
+                return;
+            };
+            let index = self
+                .waiting
+                .iter()
+                .position(|waiting| waiting.seq == seq)
+                .expect("admitted run is waiting");
+            let waiting = self.waiting.remove(index);
+            let run_id = waiting.ticket.run_id.clone();
+            self.running
+                .insert(run_id.clone(), waiting.ticket.app_id.clone());
+            if waiting.admit.send(true).is_err() {
+                self.running.remove(&run_id);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> AgentRunQueueSnapshot {
+        let mut running = self.running.keys().cloned().collect::<Vec<_>>();
+        running.sort();
+        AgentRunQueueSnapshot {
+            limits: self.limits.clone(),
+            running,
+            queued: self
+                .ordered_waiting()
+                .into_iter()
+                .enumerate()
+                .map(|(index, waiting)| AgentQueuedRun {
+                    run_id: waiting.ticket.run_id.clone(),
+                    label: waiting.ticket.label.clone(),
+                    app_id: waiting.ticket.app_id.clone(),
+                    priority: waiting.ticket.priority,
+                    enqueued_at_ms: waiting.enqueued_at_ms,
+                    position: index + 1,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Admission control for agent runs: a priority queue gated by a global and
+/// a per-app concurrency limit. Every change is reported to the listener so
+/// the UI can show queue positions.
+pub struct AgentRunQueue {
+    state: Mutex<QueueState>,
+    listener: Option<AgentRunQueueListener>,
+    config_path: Option<PathBuf>,
+}
+
+impl AgentRunQueue {
+    pub fn new(limits: AgentRunQueueLimits, listener: Option<AgentRunQueueListener>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(QueueState {
+                limits,
+                ..QueueState::default()
+            }),
+            listener,
+            config_path: None,
+        })
+    }
+
+    pub fn load_default(listener: Option<AgentRunQueueListener>) -> Result<Arc<Self>> {
+        Self::from_path(DEFAULT_QUEUE_CONFIG_PATH, listener)
+    }
+
+    pub fn from_path(
+        path: impl AsRef<Path>,
+        listener: Option<AgentRunQueueListener>,
+    ) -> Result<Arc<Self>> {
+        let path = path.as_ref().to_path_buf();
+        let limits = if path.exists() {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("reading agent run queue limits at {}", path.display()))?;
+            let limits: AgentRunQueueLimits = serde_json::from_str(&raw)
+                .with_context(|| format!("parsing agent run queue limits at {}", path.display()))?;
+            limits
+                .validate()
+                .with_context(|| format!("invalid agent run queue limits at {}", path.display()))?;
+            limits
+        } else {
+            AgentRunQueueLimits::default()
+        };
+        Ok(Arc::new(Self {
+            state: Mutex::new(QueueState {
+                limits,
+                ..QueueState::default()
+            }),
+            listener,
+            config_path: Some(path),
+        }))
+    }
+
+    /// Waits until the run is admitted. The returned permit holds the run's
+    /// slot until dropped. Fails if the run is cancelled while queued.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        ticket: AgentRunTicket,
+        now_ms: u64,
+    ) -> Result<AgentRunPermit> {
+        let run_id = ticket.run_id.clone();
+        let (admit, admitted) = oneshot::channel();
+        // Created before waiting so that dropping this future mid-queue still
+        // removes the run from the queue.
+        let permit = AgentRunPermit {
+            queue: self.clone(),
+            run_id: run_id.clone(),
+        };
+        self.update(|state| {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiting.push(WaitingRun {
+                ticket,
+                seq,
+                enqueued_at_ms: now_ms,
+                admit,
+            });
+        });
+
+        match admitted.await {
+            Ok(true) => Ok(permit),
+            _ => Err(anyhow!("agent run `{run_id}` was cancelled while queued")),
+        }
+    }
+
+    /// Removes a queued run. Returns `false` when the run is not waiting
+    /// (already admitted or unknown).
+    pub fn cancel(&self, run_id: &str) -> bool {
+        let mut removed = false;
+        self.update(|state| {
+            if let Some(index) = state
+                .waiting
+                .iter()
+                .position(|waiting| waiting.ticket.run_id == run_id)
+            {
+                let waiting = state.waiting.remove(index);
+                let _ = waiting.admit.send(false);
+                removed = true;
+            }
+        });
+        removed
+    }
+
+    pub fn snapshot(&self) -> AgentRunQueueSnapshot {
+        self.state
+            .lock()
+            .expect("agent run queue poisoned")
+            .snapshot()
+    }
+
+    pub fn limits(&self) -> AgentRunQueueLimits {
+        self.state
+            .lock()
+            .expect("agent run queue poisoned")
+            .limits
+            .clone()
+    }
+
+    pub fn set_limits(&self, limits: AgentRunQueueLimits) -> Result<AgentRunQueueSnapshot> {
+        limits.validate()?;
+        if let Some(path) = &self.config_path {
+            write_limits(path, &limits)?;
+        }
+        self.update(|state| state.limits = limits);
+        Ok(self.snapshot())
+    }
+
+    fn release(&self, run_id: &str) {
+        self.update(|state| {
+            state
+                .waiting
+                .retain(|waiting| waiting.ticket.run_id != run_id);
+            state.running.remove(run_id);
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut QueueState)) {
+        let snapshot = {
+            let mut state = self.state.lock().expect("agent run queue poisoned");
+            change(&mut state);
+            state.dispatch();
+            state.snapshot()
+        };
+        if let Some(listener) = &self.listener {
+            listener(&snapshot);
+        }
+    }
+}
+
+/// Slot held by an admitted (or still queued) run; dropping it frees the slot
+/// and admits the next run.
+pub struct AgentRunPermit {
+    queue: Arc<AgentRunQueue>,
+    run_id: String,
+}
+
+impl Drop for AgentRunPermit {
+    fn drop(&mut self) {
+        self.queue.release(&self.run_id);
+    }
+}
+
+fn write_limits(path: &Path, limits: &AgentRunQueueLimits) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating agent run queue dir {}", parent.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(limits)?;
+    fs::write(path, raw)
+        .with_context(|| format!("writing agent run queue limits at {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    fn ticket(run_id: &str, app_id: Option<&str>, priority: AgentRunPriority) -> AgentRunTicket {
+        AgentRunTicket {
+            run_id: run_id.to_string(),
+            label: run_id.to_string(),
+            app_id: app_id.map(str::to_string),
+            priority,
+        }
+    }
+
+    fn limits(max_concurrent: usize, per_app: usize) -> AgentRunQueueLimits {
+        AgentRunQueueLimits {
+            max_concurrent,
+            per_app,
+            app_overrides: HashMap::new(),
+        }
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn global_limit_queues_and_admits_by_priority() {
+        let queue = AgentRunQueue::new(limits(1, 4), None);
+        let first = queue
+            .acquire(ticket("run-1", None, AgentRunPriority::Normal), 0)
+            .await
+            .expect("first run admitted");
+
+        let low = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                queue
+                    .acquire(ticket("run-2", None, AgentRunPriority::Low), 1)
+                    .await
+            }
+        });
+        settle().await;
+        let high = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                queue
+                    .acquire(ticket("run-3", None, AgentRunPriority::High), 2)
+                    .await
+            }
+        });
+        settle().await;
+
+        let snapshot = queue.snapshot();
+        assert_eq!(snapshot.running, vec!["run-1".to_string()]);
+        let order = snapshot
+            .queued
+            .iter()
+            .map(|run| (run.run_id.as_str(), run.position))
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![("run-3", 1), ("run-2", 2)]);
+
+        drop(first);
+        let high = timeout(Duration::from_secs(1), high)
+            .await
+            .expect("high priority admitted")
+            .unwrap()
+            .expect("permit");
+        assert_eq!(queue.snapshot().running, vec!["run-3".to_string()]);
+
+        drop(high);
+        let _low = timeout(Duration::from_secs(1), low)
+            .await
+            .expect("low priority admitted")
+            .unwrap()
+            .expect("permit");
+        assert!(queue.snapshot().queued.is_empty());
+    }
+
+    #[tokio::test]
+    async fn per_app_limit_does_not_block_other_apps() {
+        let queue = AgentRunQueue::new(limits(3, 1), None);
+        let _brief = queue
+            .acquire(ticket("run-1", Some("brief"), AgentRunPriority::Normal), 0)
+            .await
+            .expect("admitted");
+
+        let blocked = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                queue
+                    .acquire(ticket("run-2", Some("brief"), AgentRunPriority::High), 1)
+                    .await
+            }
+        });
+        settle().await;
+
+        let other = timeout(
+            Duration::from_secs(1),
+            queue.acquire(ticket("run-3", Some("shopper"), AgentRunPriority::Low), 2),
+        )
+        .await
+        .expect("other app is not blocked")
+        .expect("permit");
+
+        let snapshot = queue.snapshot();
+        assert_eq!(snapshot.queued.len(), 1);
+        assert_eq!(snapshot.queued[0].run_id, "run-2");
+        drop(other);
+        assert!(!blocked.is_finished());
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_queued_run_fails_its_acquire_and_reports_positions() {
+        let events = Arc::new(Mutex::new(Vec::<AgentRunQueueSnapshot>::new()));
+        let listener: AgentRunQueueListener = {
+            let events = events.clone();
+            Arc::new(move |snapshot| events.lock().unwrap().push(snapshot.clone()))
+        };
+        let queue = AgentRunQueue::new(limits(1, 1), Some(listener));
+        let _running = queue
+            .acquire(ticket("run-1", None, AgentRunPriority::Normal), 0)
+            .await
+            .expect("admitted");
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                queue
+                    .acquire(ticket("run-2", None, AgentRunPriority::Normal), 1)
+                    .await
+            }
+        });
+        settle().await;
+
+        assert!(queue.cancel("run-2"));
+        assert!(!queue.cancel("run-1"));
+        let result = waiting.await.unwrap();
+        assert!(result.is_err());
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|snapshot| snapshot
+            .queued
+            .iter()
+            .any(|run| run.run_id == "run-2" && run.position == 1)));
+        assert!(events.last().unwrap().queued.is_empty());
+    }
+
+    #[tokio::test]
+    async fn raising_limits_admits_waiting_runs() {
+        let path = std::env::temp_dir()
+            .join("dbrowser-agent-run-queue-tests")
+            .join(uuid::Uuid::new_v4().to_string())
+            .join("queue.json");
+        let queue = AgentRunQueue::from_path(&path, None).expect("queue");
+        queue.set_limits(limits(1, 1)).expect("limits accepted");
+        let _first = queue
+            .acquire(ticket("run-1", None, AgentRunPriority::Normal), 0)
+            .await
+            .expect("admitted");
+        let second = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                queue
+                    .acquire(ticket("run-2", None, AgentRunPriority::Normal), 1)
+                    .await
+            }
+        });
+        settle().await;
+        assert_eq!(queue.snapshot().queued.len(), 1);
+
+        queue.set_limits(limits(2, 1)).expect("limits accepted");
+        timeout(Duration::from_secs(1), second)
+            .await
+            .expect("second run admitted")
+            .unwrap()
+            .expect("permit");
+
+        assert!(queue.set_limits(limits(0, 1)).is_err());
+        let reloaded = AgentRunQueue::from_path(&path, None).expect("reloaded");
+        assert_eq!(reloaded.limits().max_concurrent, 2);
+    }
+
+    #[test]
+    fn zero_limits_on_disk_are_rejected() {
+        let path = std::env::temp_dir()
+            .join("dbrowser-agent-run-queue-tests")
+            .join(uuid::Uuid::new_v4().to_string())
+            .join("queue.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"maxConcurrent":0}"#).unwrap();
+        let err = AgentRunQueue::from_path(&path, None)
+            .err()
+            .expect("zero concurrency refused");
+        assert!(format!("{err:#}").contains("maxConcurrent must be at least 1"));
+    }
+}
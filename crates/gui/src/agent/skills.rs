@@ -1,12 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
 
 use agent_core::{CapabilityKind, CapabilityLimit, CapabilityRegistry};
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use anyhow::{anyhow, Context, Result};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// Manifest schema version understood by this build. Bump it together with
+/// `skills/skill-manifest.schema.json` whenever the skill format changes.
+pub const SKILL_SCHEMA_VERSION: u32 = 1;
+
+const BUNDLED_MANIFEST_PATH: &str = "skills/manifest.json";
+const USER_SKILL_DIRS_ENV: &str = "ADVATAR_SKILL_DIRS";
+const MAX_SKILL_STEPS: usize = 50;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillSource {
+    /// Compiled-in fallback used when no bundled manifest is present.
+    Builtin,
+    #[default]
+    Bundled,
+    User,
+}
 
 #[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SkillDefinition {
     pub id: String,
     pub name: String,
@@ -19,156 +42,852 @@ pub struct SkillDefinition {
     pub tags: Vec<String>,
     #[serde(default)]
     pub capabilities: HashMap<String, CapabilityLimitSpec>,
+    /// Parent skill id. Unset fields are inherited, `tools` are added to the
+    /// parent's, and capabilities may only narrow the parent's limits.
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Tool allowlist. `None` exposes every tool.
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    #[serde(skip)]
+    pub source: SkillSource,
+    #[serde(skip)]
+    pub origin: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)]
+impl SkillDefinition {
+    fn build_capabilities(&self) -> CapabilityRegistry {
+        let mut registry = CapabilityRegistry::with_browser_defaults();
+        for (capability, spec) in &self.capabilities {
+            if let Some(kind) = CapabilityKind::parse(capability) {
+                registry.grant(kind, spec.to_limit());
+            }
+        }
+        registry
+    }
+}
+
+/// Per-run call limit for a capability. Accepts `null` or `"unlimited"`,
+/// a non-negative integer, or `{ "max_calls_per_run": <integer|null> }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CapabilityLimitSpec {
-    Number(u32),
-    Map { max_calls_per_run: Option<u32> },
-    Text(String),
-    Null,
+    Unlimited,
+    Limited(u32),
 }
 
 impl CapabilityLimitSpec {
-    fn to_limit(&self) -> CapabilityLimit {
-        match self {
-            CapabilityLimitSpec::Number(value) => CapabilityLimit::limited(*value),
-            CapabilityLimitSpec::Map { max_calls_per_run } => match max_calls_per_run {
-                Some(value) => CapabilityLimit::limited(*value),
-                None => CapabilityLimit::unlimited(),
-            },
-            CapabilityLimitSpec::Text(text) => {
-                if text.eq_ignore_ascii_case("unlimited") {
-                    CapabilityLimit::unlimited()
-                } else {
-                    CapabilityLimit::unlimited()
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Null => Ok(Self::Unlimited),
+            Value::Number(number) => number
+                .as_u64()
+                .and_then(|value| u32::try_from(value).ok())
+                .map(Self::Limited)
+                .ok_or_else(|| {
+                    format!(
+                        "limit `{number}` must be a whole number between 0 and {}",
+                        u32::MAX
+                    )
+                }),
+            Value::String(text) if text.eq_ignore_ascii_case("unlimited") => Ok(Self::Unlimited),
+            Value::String(text) => Err(format!(
+                "limit `{text}` is not recognised; use a number, null or \"unlimited\""
+            )),
+            Value::Object(map) => {
+                if let Some(key) = map.keys().find(|key| key.as_str() != "max_calls_per_run") {
+                    return Err(format!(
+                        "unknown limit field `{key}`; only `max_calls_per_run` is supported"
+                    ));
+                }
+                match map.get("max_calls_per_run") {
+                    Some(inner @ (Value::Null | Value::Number(_))) => Self::from_value(inner),
+                    Some(other) => Err(format!(
+                        "`max_calls_per_run` must be a number or null, found {other}"
+                    )),
+                    None => Ok(Self::Unlimited),
                 }
             }
-            CapabilityLimitSpec::Null => CapabilityLimit::unlimited(),
+            other => Err(format!(
+                "limit must be a number, null, \"unlimited\" or an object, found {other}"
+            )),
+        }
+    }
+
+    fn to_limit(self) -> CapabilityLimit {
+        match self {
+            Self::Unlimited => CapabilityLimit::unlimited(),
+            Self::Limited(value) => CapabilityLimit::limited(value),
+        }
+    }
+
+    fn exceeds(self, other: Self) -> bool {
+        match (self, other) {
+            (Self::Unlimited, Self::Limited(_)) => true,
+            (Self::Limited(value), Self::Limited(limit)) => value > limit,
+            (_, Self::Unlimited) => false,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct SkillRegistry {
+impl fmt::Display for CapabilityLimitSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unlimited => write!(f, "unlimited"),
+            Self::Limited(value) => write!(f, "{value} calls per run"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CapabilityLimitSpec {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(&value).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkillManifest {
+    #[serde(rename = "$schema", default)]
+    _schema: Option<String>,
+    // Checked before deserializing so newer manifests fail with a version
+    // error rather than an unknown-field error.
+    #[serde(rename = "schema_version")]
+    _schema_version: u32,
+    skills: Vec<SkillDefinition>,
+}
+
+/// A problem found while loading one skill source. Problems in per-user
+/// files are reported here instead of failing the whole registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillLoadIssue {
+    pub origin: String,
+    pub message: String,
+}
+
+impl fmt::Display for SkillLoadIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.origin, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+struct SkillState {
+    /// Skills as declared, after user overrides.
+    definitions: Vec<SkillDefinition>,
+    /// Skills with their `extends` chain applied.
     skills: Vec<SkillDefinition>,
+    issues: Vec<SkillLoadIssue>,
+    fingerprint: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+/// Skills loaded from the bundled manifest plus any per-user skill
+/// directories. Reloads swap the whole set atomically; a bundled manifest
+/// that fails validation on reload keeps the previous skills in place.
+#[derive(Debug)]
+pub struct SkillRegistry {
+    manifest_path: PathBuf,
+    user_dirs: Vec<PathBuf>,
+    state: RwLock<SkillState>,
 }
 
 impl SkillRegistry {
     pub fn load() -> Result<Self> {
-        let manifest_path = PathBuf::from("skills/manifest.json");
-        let skills = if manifest_path.exists() {
-            let contents = fs::read_to_string(&manifest_path)
-                .with_context(|| format!("reading skill manifest at {:?}", manifest_path))?;
-            serde_json::from_str::<Vec<SkillDefinition>>(&contents)
-                .with_context(|| "parsing skills manifest")?
-        } else {
-            default_skills()
+        Self::from_sources(PathBuf::from(BUNDLED_MANIFEST_PATH), default_user_dirs())
+    }
+
+    pub fn from_sources(manifest_path: PathBuf, user_dirs: Vec<PathBuf>) -> Result<Self> {
+        let registry = Self {
+            manifest_path,
+            user_dirs,
+            state: RwLock::new(SkillState::default()),
         };
+        registry.reload()?;
+        Ok(registry)
+    }
 
-        Ok(Self { skills })
+    pub fn user_dirs(&self) -> &[PathBuf] {
+        &self.user_dirs
     }
 
-    pub fn list(&self) -> &[SkillDefinition] {
-        &self.skills
+    pub fn list(&self) -> Vec<SkillDefinition> {
+        self.read_state().skills.clone()
     }
 
-    pub fn find(&self, id: &str) -> Option<&SkillDefinition> {
-        self.skills.iter().find(|skill| skill.id == id)
+    pub fn issues(&self) -> Vec<SkillLoadIssue> {
+        self.read_state().issues.clone()
     }
 
-    pub fn build_capabilities(&self, skill_id: Option<&str>) -> CapabilityRegistry {
-        let mut registry = CapabilityRegistry::with_browser_defaults();
-        if let Some(skill_id) = skill_id {
-            if let Some(skill) = self.find(skill_id) {
-                for (capability, spec) in &skill.capabilities {
-                    if let Some(kind) = CapabilityKind::parse(capability) {
-                        registry.grant(kind, spec.to_limit());
+    pub fn find(&self, id: &str) -> Option<SkillDefinition> {
+        self.read_state()
+            .skills
+            .iter()
+            .find(|skill| skill.id == id)
+            .cloned()
+    }
+
+    /// Capabilities for a skill after walking its `extends` chain. Unknown
+    /// parents, cycles and parents granting less than the child are errors.
+    pub fn build_capabilities(&self, skill_id: Option<&str>) -> Result<CapabilityRegistry> {
+        let Some(skill_id) = skill_id else {
+            return Ok(CapabilityRegistry::with_browser_defaults());
+        };
+        let state = self.read_state();
+        let skill = resolve_skill(&state.definitions, skill_id).map_err(|err| anyhow!(err))?;
+        Ok(skill.build_capabilities())
+    }
+
+    /// Re-reads every source. On error the previous skills stay active and
+    /// the error is recorded as the only issue.
+    pub fn reload(&self) -> Result<()> {
+        let fingerprint = self.fingerprint();
+        let loaded = load_sources(&self.manifest_path, &self.user_dirs);
+        let mut state = self
+            .state
+            .write()
+            .map_err(|_| anyhow!("skill registry lock poisoned"))?;
+        state.fingerprint = fingerprint;
+        match loaded {
+            Ok((definitions, skills, issues)) => {
+                for issue in &issues {
+                    tracing::warn!(%issue, "skipping invalid skill source");
+                }
+                state.definitions = definitions;
+                state.skills = skills;
+                state.issues = issues;
+                Ok(())
+            }
+            Err(err) => {
+                state.issues = vec![SkillLoadIssue {
+                    origin: self.manifest_path.display().to_string(),
+                    message: format!("{err:#}"),
+                }];
+                Err(err)
+            }
+        }
+    }
+
+    /// Reloads when any source file was added, removed or modified since the
+    /// last load. Returns `None` when nothing changed.
+    pub fn reload_if_changed(&self) -> Option<Result<()>> {
+        let fingerprint = self.fingerprint();
+        if self.read_state().fingerprint == fingerprint {
+            return None;
+        }
+        Some(self.reload())
+    }
+
+    fn read_state(&self) -> std::sync::RwLockReadGuard<'_, SkillState> {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn fingerprint(&self) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+        std::iter::once(self.manifest_path.clone())
+            .chain(self.user_dirs.iter().flat_map(|dir| user_skill_files(dir)))
+            .map(|path| {
+                let metadata = fs::metadata(&path).ok();
+                let modified = metadata.as_ref().and_then(|meta| meta.modified().ok());
+                let len = metadata.map(|meta| meta.len()).unwrap_or_default();
+                (path, modified, len)
+            })
+            .collect()
+    }
+}
+
+fn default_user_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".advatar").join("skills"));
+    }
+    if let Some(extra) = std::env::var_os(USER_SKILL_DIRS_ENV) {
+        dirs.extend(std::env::split_paths(&extra).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    dirs
+}
+
+fn user_skill_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn load_sources(
+    manifest_path: &Path,
+    user_dirs: &[PathBuf],
+) -> Result<(
+    Vec<SkillDefinition>,
+    Vec<SkillDefinition>,
+    Vec<SkillLoadIssue>,
+)> {
+    let mut definitions = if manifest_path.exists() {
+        let contents = fs::read_to_string(manifest_path)
+            .with_context(|| format!("reading skill manifest at {:?}", manifest_path))?;
+        parse_manifest(&contents, manifest_path, SkillSource::Bundled)
+            .map_err(|err| anyhow!("invalid skill manifest {}: {err}", manifest_path.display()))?
+    } else {
+        default_skills()
+    };
+
+    let mut issues = Vec::new();
+    let mut user_ids: HashMap<String, PathBuf> = HashMap::new();
+    for path in user_dirs.iter().flat_map(|dir| user_skill_files(dir)) {
+        let origin = path.display().to_string();
+        let parsed = fs::read_to_string(&path)
+            .map_err(|err| format!("reading skill file: {err}"))
+            .and_then(|contents| parse_manifest(&contents, &path, SkillSource::User));
+        let user_skills = match parsed {
+            Ok(user_skills) => user_skills,
+            Err(message) => {
+                issues.push(SkillLoadIssue { origin, message });
+                continue;
+            }
+        };
+
+        for skill in user_skills {
+            if let Some(previous) = user_ids.get(&skill.id) {
+                issues.push(SkillLoadIssue {
+                    origin: origin.clone(),
+                    message: format!(
+                        "skill `{}` is already defined in {}; ignoring this copy",
+                        skill.id,
+                        previous.display()
+                    ),
+                });
+                continue;
+            }
+            user_ids.insert(skill.id.clone(), path.clone());
+            // User skills override bundled skills with the same id in place.
+            match definitions
+                .iter_mut()
+                .find(|existing| existing.id == skill.id)
+            {
+                Some(existing) => *existing = skill,
+                None => definitions.push(skill),
+            }
+        }
+    }
+
+    let mut skills = Vec::with_capacity(definitions.len());
+    for definition in &definitions {
+        match resolve_skill(&definitions, &definition.id) {
+            Ok(skill) => skills.push(skill),
+            Err(message) => issues.push(SkillLoadIssue {
+                origin: definition
+                    .origin
+                    .as_ref()
+                    .map(|origin| origin.display().to_string())
+                    .unwrap_or_default(),
+                message,
+            }),
+        }
+    }
+
+    Ok((definitions, skills, issues))
+}
+
+/// Flattens a skill's `extends` chain, root first. The result lists every
+/// capability explicitly so it no longer depends on its parents.
+fn resolve_skill(definitions: &[SkillDefinition], id: &str) -> Result<SkillDefinition, String> {
+    let mut chain: Vec<&SkillDefinition> = Vec::new();
+    let mut next = Some(id);
+    while let Some(current) = next {
+        if chain.iter().any(|skill| skill.id == current) {
+            let mut cycle: Vec<&str> = chain.iter().map(|skill| skill.id.as_str()).collect();
+            cycle.push(current);
+            return Err(format!("`extends` cycle: {}", cycle.join(" -> ")));
+        }
+        let skill = definitions
+            .iter()
+            .find(|skill| skill.id == current)
+            .ok_or_else(|| match chain.last() {
+                Some(child) => format!("skill `{}` extends unknown skill `{current}`", child.id),
+                None => format!("unknown skill `{current}`"),
+            })?;
+        chain.push(skill);
+        next = skill.extends.as_deref();
+    }
+
+    let mut chain = chain.into_iter().rev();
+    let root = chain.next().expect("chain contains the requested skill");
+    let mut resolved = root.clone();
+    resolved.capabilities = CapabilityRegistry::with_browser_defaults()
+        .snapshot()
+        .into_iter()
+        .map(|(kind, limit)| {
+            let spec = limit.map_or(CapabilityLimitSpec::Unlimited, CapabilityLimitSpec::Limited);
+            (kind.as_str().to_string(), spec)
+        })
+        .collect();
+    apply_capabilities(&mut resolved, root, None)?;
+
+    for child in chain {
+        let parent = resolved.id.clone();
+        apply_capabilities(&mut resolved, child, Some(&parent))?;
+        resolved.tools = match (resolved.tools.take(), &child.tools) {
+            (Some(mut tools), Some(added)) => {
+                for tool in added {
+                    if !tools.contains(tool) {
+                        tools.push(tool.clone());
                     }
                 }
+                Some(tools)
+            }
+            (tools, _) => tools,
+        };
+        for tag in &child.tags {
+            if !resolved.tags.contains(tag) {
+                resolved.tags.push(tag.clone());
             }
         }
-        registry
+        resolved.id = child.id.clone();
+        resolved.name = child.name.clone();
+        resolved.description = child.description.clone();
+        resolved.system_prompt = child.system_prompt.clone().or(resolved.system_prompt);
+        resolved.max_steps = child.max_steps.or(resolved.max_steps);
+        resolved.extends = child.extends.clone();
+        resolved.source = child.source;
+        resolved.origin = child.origin.clone();
     }
+    Ok(resolved)
 }
 
-fn default_skills() -> Vec<SkillDefinition> {
-    serde_json::from_value(serde_json::json!([
-        {
-            "id": "extractor-ella",
-            "name": "Extractor Ella",
-            "description": "Extracts structured data from listings and exports to local storage.",
-            "system_prompt": "You are Extractor Ella, focused on gathering structured leads into spreadsheets with minimal steps.",
-            "max_steps": 6,
-            "capabilities": {
-                "click": null,
-                "scroll": null,
-                "type": { "max_calls_per_run": 12 },
-                "navigate": { "max_calls_per_run": 6 },
-                "email:send": { "max_calls_per_run": 0 }
-            },
-            "tags": ["research", "data"]
-        },
+/// Applies `skill`'s capability limits on top of `resolved`. When `parent` is
+/// set the limits may only narrow what the parent already grants.
+fn apply_capabilities(
+    resolved: &mut SkillDefinition,
+    skill: &SkillDefinition,
+    parent: Option<&str>,
+) -> Result<(), String> {
+    let mut capabilities: Vec<(&String, &CapabilityLimitSpec)> =
+        skill.capabilities.iter().collect();
+    capabilities.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, spec) in capabilities {
+        let Some(kind) = CapabilityKind::parse(name) else {
+            continue;
+        };
+        let key = kind.as_str().to_string();
+        if let (Some(parent), Some(inherited)) = (parent, resolved.capabilities.get(&key)) {
+            if spec.exceeds(*inherited) {
+                return Err(format!(
+                    "skill `{}` widens `{key}` to {spec}, but `{parent}` only grants {inherited}",
+                    skill.id
+                ));
+            }
+        }
+        resolved.capabilities.insert(key, *spec);
+    }
+    Ok(())
+}
+
+/// Parses and validates a manifest document. A bare array of skills is still
+/// accepted as the pre-versioning format. Every problem found is reported in
+/// one message, one line per issue.
+fn parse_manifest(
+    contents: &str,
+    origin: &Path,
+    source: SkillSource,
+) -> Result<Vec<SkillDefinition>, String> {
+    let value: Value =
+        serde_json::from_str(contents).map_err(|err| format!("invalid JSON: {err}"))?;
+    let mut skills = match value {
+        Value::Array(_) => {
+            tracing::warn!(
+                origin = %origin.display(),
+                "skill manifest has no schema_version; treating it as version {SKILL_SCHEMA_VERSION}"
+            );
+            serde_json::from_value::<Vec<SkillDefinition>>(value)
+                .map_err(|err| format!("invalid skill definition: {err}"))?
+        }
+        Value::Object(ref map) => {
+            match map.get("schema_version").map(Value::as_u64) {
+                None => return Err("missing required field `schema_version`".to_string()),
+                Some(Some(version)) if version == u64::from(SKILL_SCHEMA_VERSION) => {}
+                Some(Some(version)) => {
+                    return Err(format!(
+                        "unsupported schema_version {version}; this build understands version {SKILL_SCHEMA_VERSION}"
+                    ))
+                }
+                Some(None) => return Err("`schema_version` must be an integer".to_string()),
+            }
+            serde_json::from_value::<SkillManifest>(value)
+                .map_err(|err| format!("invalid skill manifest: {err}"))?
+                .skills
+        }
+        _ => {
+            return Err(
+                "expected an object with `schema_version` and `skills`, or an array of skills"
+                    .to_string(),
+            )
+        }
+    };
+
+    let issues = validate_skills(&skills);
+    if !issues.is_empty() {
+        return Err(issues.join("\n"));
+    }
+    for skill in &mut skills {
+        skill.source = source;
+        skill.origin = Some(origin.to_path_buf());
+    }
+    Ok(skills)
+}
+
+fn validate_skills(skills: &[SkillDefinition]) -> Vec<String> {
+    let mut issues = Vec::new();
+    let mut seen = HashSet::new();
+    for (index, skill) in skills.iter().enumerate() {
+        let at = if skill.id.is_empty() {
+            format!("skills[{index}]")
+        } else {
+            format!("skills[{index}] (`{}`)", skill.id)
+        };
+        if skill.id.is_empty() {
+            issues.push(format!("{at}: `id` must not be empty"));
+        } else if !skill
+            .id
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-')
         {
-            "id": "sales-sally",
-            "name": "Sales Sally",
-            "description": "Finds contact emails, drafts outreach, and logs to CRM sheets.",
-            "system_prompt": "You are Sales Sally. Always confirm contact details and keep tone upbeat yet concise.",
-            "max_steps": 7,
-            "capabilities": {
-                "click": null,
-                "scroll": null,
-                "type": null,
-                "navigate": { "max_calls_per_run": 8 },
-                "email:send": { "max_calls_per_run": 2 }
+            issues.push(format!(
+                "{at}: `id` may only contain lowercase letters, digits and `-`"
+            ));
+        } else if !seen.insert(skill.id.as_str()) {
+            issues.push(format!("{at}: duplicate skill id"));
+        }
+        if skill.name.trim().is_empty() {
+            issues.push(format!("{at}: `name` must not be empty"));
+        }
+        if skill.description.trim().is_empty() {
+            issues.push(format!("{at}: `description` must not be empty"));
+        }
+        if let Some(max_steps) = skill.max_steps {
+            if !(1..=MAX_SKILL_STEPS).contains(&max_steps) {
+                issues.push(format!(
+                    "{at}: `max_steps` must be between 1 and {MAX_SKILL_STEPS}, found {max_steps}"
+                ));
+            }
+        }
+        if let Some(tools) = &skill.tools {
+            if tools.iter().any(|tool| tool.trim().is_empty()) {
+                issues.push(format!("{at}: `tools` entries must not be empty"));
+            }
+        }
+        let mut capabilities: Vec<&String> = skill.capabilities.keys().collect();
+        capabilities.sort();
+        for capability in capabilities {
+            if CapabilityKind::parse(capability).is_none() {
+                let known: Vec<&str> = CapabilityKind::ALL
+                    .iter()
+                    .map(|kind| kind.as_str())
+                    .collect();
+                issues.push(format!(
+                    "{at}: unknown capability `{capability}`; expected one of {}",
+                    known.join(", ")
+                ));
+            }
+        }
+    }
+    issues
+}
+
+fn default_skills() -> Vec<SkillDefinition> {
+    let manifest = serde_json::json!({
+        "schema_version": SKILL_SCHEMA_VERSION,
+        "skills": [
+            {
+                "id": "extractor-ella",
+                "name": "Extractor Ella",
+                "description": "Extracts structured data from listings and exports to local storage.",
+                "system_prompt": "You are Extractor Ella, focused on gathering structured leads into spreadsheets with minimal steps.",
+                "max_steps": 6,
+                "capabilities": {
+                    "click": null,
+                    "scroll": null,
+                    "type": { "max_calls_per_run": 12 },
+                    "navigate": { "max_calls_per_run": 6 },
+                    "email:send": { "max_calls_per_run": 0 }
+                },
+                "tags": ["research", "data"]
             },
-            "tags": ["sales", "outreach"]
-        },
-        {
-            "id": "recruiter-riley",
-            "name": "Recruiter Riley",
-            "description": "Searches professional networks and compiles candidate shortlists.",
-            "system_prompt": "You are Recruiter Riley. Focus on accuracy and keep notes brief.",
-            "max_steps": 6,
-            "capabilities": {
-                "click": null,
-                "scroll": null,
-                "type": { "max_calls_per_run": 10 },
-                "navigate": { "max_calls_per_run": 6 },
-                "email:send": { "max_calls_per_run": 1 }
+            {
+                "id": "sales-sally",
+                "name": "Sales Sally",
+                "description": "Finds contact emails, drafts outreach, and logs to CRM sheets.",
+                "system_prompt": "You are Sales Sally. Always confirm contact details and keep tone upbeat yet concise.",
+                "max_steps": 7,
+                "capabilities": {
+                    "click": null,
+                    "scroll": null,
+                    "type": null,
+                    "navigate": { "max_calls_per_run": 8 },
+                    "email:send": { "max_calls_per_run": 2 }
+                },
+                "tags": ["sales", "outreach"]
             },
-            "tags": ["recruiting"]
-        },
-        {
-            "id": "assistant-ari",
-            "name": "Assistant Ari",
-            "description": "General browser assistant for summarising pages and scheduling tasks.",
-            "system_prompt": "You are Assistant Ari, a safety-first general purpose browsing assistant.",
-            "max_steps": 5,
-            "capabilities": {
-                "click": null,
-                "scroll": null,
-                "type": { "max_calls_per_run": 6 },
-                "navigate": { "max_calls_per_run": 5 },
-                "email:send": { "max_calls_per_run": 0 }
+            {
+                "id": "recruiter-riley",
+                "name": "Recruiter Riley",
+                "description": "Searches professional networks and compiles candidate shortlists.",
+                "system_prompt": "You are Recruiter Riley. Focus on accuracy and keep notes brief.",
+                "max_steps": 6,
+                "capabilities": {
+                    "click": null,
+                    "scroll": null,
+                    "type": { "max_calls_per_run": 10 },
+                    "navigate": { "max_calls_per_run": 6 },
+                    "email:send": { "max_calls_per_run": 1 }
+                },
+                "tags": ["recruiting"]
             },
-            "tags": ["assistant"]
-        },
-        {
-            "id": "research-ravi",
-            "name": "Research Ravi",
-            "description": "Deep dives into competitive intelligence with rigorous note taking.",
-            "system_prompt": "You are Research Ravi. Surface key numbers, cite sources, and capture follow-ups.",
-            "max_steps": 8,
-            "capabilities": {
-                "click": null,
-                "scroll": null,
-                "type": { "max_calls_per_run": 12 },
-                "navigate": { "max_calls_per_run": 9 },
-                "email:send": { "max_calls_per_run": 0 }
+            {
+                "id": "assistant-ari",
+                "name": "Assistant Ari",
+                "description": "General browser assistant for summarising pages and scheduling tasks.",
+                "system_prompt": "You are Assistant Ari, a safety-first general purpose browsing assistant.",
+                "max_steps": 5,
+                "capabilities": {
+                    "click": null,
+                    "scroll": null,
+                    "type": { "max_calls_per_run": 6 },
+                    "navigate": { "max_calls_per_run": 5 },
+                    "email:send": { "max_calls_per_run": 0 }
+                },
+                "tags": ["assistant"]
             },
-            "tags": ["research", "analysis"]
-        }
-    ])).expect("default skills to be valid JSON")
+            {
+                "id": "research-ravi",
+                "name": "Research Ravi",
+                "description": "Deep dives into competitive intelligence with rigorous note taking.",
+                "system_prompt": "You are Research Ravi. Surface key numbers, cite sources, and capture follow-ups.",
+                "max_steps": 8,
+                "capabilities": {
+                    "click": null,
+                    "scroll": null,
+                    "type": { "max_calls_per_run": 12 },
+                    "navigate": { "max_calls_per_run": 9 },
+                    "email:send": { "max_calls_per_run": 0 }
+                },
+                "tags": ["research", "analysis"]
+            }
+        ]
+    });
+    parse_manifest(
+        &manifest.to_string(),
+        Path::new("<builtin>"),
+        SkillSource::Builtin,
+    )
+    .expect("default skills to be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("dbrowser-skill-tests")
+            .join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn manifest(skills: Value) -> String {
+        serde_json::json!({ "schema_version": SKILL_SCHEMA_VERSION, "skills": skills }).to_string()
+    }
+
+    fn skill(id: &str, capabilities: Value) -> Value {
+        serde_json::json!({
+            "id": id,
+            "name": "Test Skill",
+            "description": "Used in tests.",
+            "capabilities": capabilities
+        })
+    }
+
+    #[test]
+    fn builtin_skills_are_valid() {
+        let skills = default_skills();
+        assert_eq!(skills.len(), 5);
+        assert!(skills
+            .iter()
+            .all(|skill| skill.source == SkillSource::Builtin));
+    }
+
+    #[test]
+    fn validation_reports_every_issue() {
+        let contents = manifest(serde_json::json!([
+            skill("ok-skill", serde_json::json!({ "emial:send": 1, "type": "lots" })),
+            skill("ok-skill", serde_json::json!({})),
+            { "id": "Bad Id", "name": " ", "description": "x", "max_steps": 500 }
+        ]));
+        let err = parse_manifest(&contents, Path::new("m.json"), SkillSource::Bundled).unwrap_err();
+        assert!(err.contains("limit `lots` is not recognised"), "{err}");
+
+        let contents = manifest(serde_json::json!([
+            skill("ok-skill", serde_json::json!({ "emial:send": 1 })),
+            skill("ok-skill", serde_json::json!({})),
+            { "id": "Bad Id", "name": " ", "description": "x", "max_steps": 500 }
+        ]));
+        let err = parse_manifest(&contents, Path::new("m.json"), SkillSource::Bundled).unwrap_err();
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines.len(), 5, "{err}");
+        assert!(err.contains("skills[0] (`ok-skill`): unknown capability `emial:send`"));
+        assert!(err.contains("expected one of click, scroll, type"));
+        assert!(err.contains("skills[1] (`ok-skill`): duplicate skill id"));
+        assert!(err.contains("skills[2] (`Bad Id`): `id` may only contain"));
+        assert!(err.contains("`name` must not be empty"));
+        assert!(err.contains("`max_steps` must be between 1 and 50, found 500"));
+    }
+
+    #[test]
+    fn schema_version_and_unknown_fields_are_enforced() {
+        let path = Path::new("m.json");
+        let newer = serde_json::json!({ "schema_version": 2, "skills": [] }).to_string();
+        assert!(parse_manifest(&newer, path, SkillSource::Bundled)
+            .unwrap_err()
+            .contains("unsupported schema_version 2"));
+        let missing = serde_json::json!({ "skills": [] }).to_string();
+        assert!(parse_manifest(&missing, path, SkillSource::Bundled)
+            .unwrap_err()
+            .contains("schema_version"));
+        let mut extra = skill("typo", serde_json::json!({}));
+        extra["max_step"] = serde_json::json!(3);
+        let err = parse_manifest(
+            &manifest(serde_json::json!([extra])),
+            path,
+            SkillSource::Bundled,
+        )
+        .unwrap_err();
+        assert!(err.contains("unknown field `max_step`"), "{err}");
+
+        let legacy = serde_json::json!([skill("legacy", serde_json::json!({ "navigate": 2 }))]);
+        let skills = parse_manifest(&legacy.to_string(), path, SkillSource::Bundled).unwrap();
+        assert_eq!(
+            skills[0].capabilities["navigate"],
+            CapabilityLimitSpec::Limited(2)
+        );
+    }
+
+    #[test]
+    fn extends_inherits_adds_tools_and_only_narrows() {
+        let dir = temp_dir();
+        let manifest_path = dir.join("manifest.json");
+        let mut base = skill("base", serde_json::json!({ "navigate": 5, "type": null }));
+        base["system_prompt"] = serde_json::json!("base prompt");
+        base["max_steps"] = serde_json::json!(4);
+        base["tools"] = serde_json::json!(["navigate", "tabs"]);
+        let mut child = skill("child", serde_json::json!({ "navigate": 2 }));
+        child["extends"] = serde_json::json!("base");
+        child["system_prompt"] = serde_json::json!("child prompt");
+        child["tools"] = serde_json::json!(["recall", "tabs"]);
+        let mut greedy = skill("greedy", serde_json::json!({ "navigate": null }));
+        greedy["extends"] = serde_json::json!("child");
+        let mut loop_a = skill("loop-a", serde_json::json!({}));
+        loop_a["extends"] = serde_json::json!("loop-b");
+        let mut loop_b = skill("loop-b", serde_json::json!({}));
+        loop_b["extends"] = serde_json::json!("loop-a");
+        fs::write(
+            &manifest_path,
+            manifest(serde_json::json!([base, child, greedy, loop_a, loop_b])),
+        )
+        .unwrap();
+
+        let registry = SkillRegistry::from_sources(manifest_path, Vec::new()).unwrap();
+        let resolved = registry.find("child").unwrap();
+        assert_eq!(resolved.system_prompt.as_deref(), Some("child prompt"));
+        assert_eq!(resolved.max_steps, Some(4));
+        assert_eq!(
+            resolved.tools,
+            Some(vec![
+                "navigate".to_string(),
+                "tabs".to_string(),
+                "recall".to_string()
+            ])
+        );
+
+        let capabilities = registry.build_capabilities(Some("child")).unwrap();
+        assert_eq!(capabilities.remaining(CapabilityKind::Navigate), Some(2));
+        assert_eq!(capabilities.remaining(CapabilityKind::EmailSend), Some(3));
+
+        assert!(registry.find("greedy").is_none());
+        assert!(registry.find("loop-a").is_none());
+        let issues: Vec<String> = registry
+            .issues()
+            .into_iter()
+            .map(|issue| issue.message)
+            .collect();
+        assert!(issues.iter().any(|issue| issue
+            == "skill `greedy` widens `navigate` to unlimited, but `child` only grants 2 calls per run"));
+        assert!(issues
+            .iter()
+            .any(|issue| issue == "`extends` cycle: loop-a -> loop-b -> loop-a"));
+        let err = registry.build_capabilities(Some("loop-b")).unwrap_err();
+        assert!(err.to_string().contains("cycle"), "{err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn user_skills_override_bundled_and_hot_reload() {
+        let dir = temp_dir();
+        let manifest_path = dir.join("manifest.json");
+        let user_dir = dir.join("user");
+        fs::create_dir_all(&user_dir).unwrap();
+        fs::write(
+            &manifest_path,
+            manifest(serde_json::json!([skill(
+                "shared",
+                serde_json::json!({ "navigate": 1 })
+            )])),
+        )
+        .unwrap();
+        fs::write(user_dir.join("broken.json"), "{ not json").unwrap();
+
+        let registry =
+            SkillRegistry::from_sources(manifest_path.clone(), vec![user_dir.clone()]).unwrap();
+        assert_eq!(registry.list().len(), 1);
+        assert_eq!(registry.issues().len(), 1);
+        assert!(registry.reload_if_changed().is_none());
+
+        fs::write(
+            user_dir.join("mine.json"),
+            manifest(serde_json::json!([
+                skill(
+                    "shared",
+                    serde_json::json!({ "navigate": { "max_calls_per_run": 4 } })
+                ),
+                skill("extra", serde_json::json!({}))
+            ])),
+        )
+        .unwrap();
+        assert!(registry.reload_if_changed().unwrap().is_ok());
+        let shared = registry.find("shared").unwrap();
+        assert_eq!(shared.source, SkillSource::User);
+        assert_eq!(
+            shared.capabilities["navigate"],
+            CapabilityLimitSpec::Limited(4)
+        );
+        assert!(registry.find("extra").is_some());
+
+        // A broken bundled manifest keeps the last good skill set.
+        fs::write(&manifest_path, "[]]").unwrap();
+        assert!(registry.reload_if_changed().unwrap().is_err());
+        assert!(registry.find("extra").is_some());
+        assert_eq!(registry.issues().len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::iproov::CartMandate;
+
+const DEFAULT_SPENT_MANDATES_PATH: &str = "configs/agent_spent_mandates.json";
+
+/// A mandate nonce that has already been used to place an order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpentMandate {
+    pub nonce: String,
+    pub cart_id: String,
+    pub spent_at: u64,
+    /// Once this passes the mandate is rejected as expired anyway, so the
+    /// entry can be dropped.
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SpentMandateManifest {
+    #[serde(default)]
+    spent: Vec<SpentMandate>,
+}
+
+/// Nonces of mandates that have been spent, persisted so a mandate cannot be
+/// replayed after a restart.
+pub struct SpentMandateStore {
+    path: Option<PathBuf>,
+    spent: Mutex<Vec<SpentMandate>>,
+}
+
+impl SpentMandateStore {
+    pub fn load_default() -> Result<Self> {
+        Self::from_path(DEFAULT_SPENT_MANDATES_PATH)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let spent = if path.exists() {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("reading spent mandates at {}", path.display()))?;
+            serde_json::from_str::<SpentMandateManifest>(&raw)
+                .with_context(|| format!("parsing spent mandates at {}", path.display()))?
+                .spent
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            spent: Mutex::new(spent),
+        })
+    }
+
+    /// A store that is not persisted.
+    pub fn empty() -> Self {
+        Self {
+            path: None,
+            spent: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_spent(&self, nonce: &str) -> bool {
+        self.spent
+            .lock()
+            .expect("spent mandates poisoned")
+            .iter()
+            .any(|entry| entry.nonce == nonce)
+    }
+
+    /// Marks the mandate's nonce as spent, failing when it already was. The
+    /// claim is written out before returning, so it holds even if placing
+    /// the order then fails.
+    pub fn claim(&self, mandate: &CartMandate) -> Result<()> {
+        let mut spent = self.spent.lock().expect("spent mandates poisoned");
+        let now = now_ts();
+        spent.retain(|entry| entry.expires_at > now);
+        if spent.iter().any(|entry| entry.nonce == mandate.nonce) {
+            return Err(anyhow!("mandate {} was already used", mandate.nonce));
+        }
+        spent.push(SpentMandate {
+            nonce: mandate.nonce.clone(),
+            cart_id: mandate.cart_id.clone(),
+            spent_at: now,
+            expires_at: mandate.approval_expires_at,
+        });
+        if let Some(path) = &self.path {
+            if let Err(err) = Self::write_manifest(path, &spent) {
+                spent.pop();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_manifest(path: &Path, spent: &[SpentMandate]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating spent mandates dir {}", parent.display()))?;
+        }
+        let raw = serde_json::to_string_pretty(&json!({ "spent": spent }))?;
+        // Written beside the manifest and renamed over it, so a crash mid-write
+        // cannot leave a truncated file that fails to load.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, raw)
+            .with_context(|| format!("writing spent mandates at {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("replacing spent mandates at {}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn now_ts() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0))
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn mandate(nonce: &str, expires_in: u64) -> CartMandate {
+        let now = now_ts();
+        CartMandate {
+            cart_id: "cart_1".into(),
+            cart_hash: "hash".into(),
+            merchant_id: "merchant.example".into(),
+            total_cents: 1_000,
+            currency: "USD".into(),
+            nonce: nonce.into(),
+            approved_at: now,
+            approval_expires_at: now + expires_in,
+            kid: None,
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn spent_nonces_survive_a_reload() -> Result<()> {
+        let path = std::env::temp_dir()
+            .join("dbrowser-spent-mandates-tests")
+            .join(Uuid::new_v4().to_string())
+            .join("spent.json");
+        let store = SpentMandateStore::from_path(&path)?;
+        store.claim(&mandate("nonce-1", 300))?;
+        assert!(store.claim(&mandate("nonce-1", 300)).is_err());
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let reloaded = SpentMandateStore::from_path(&path)?;
+        assert!(reloaded.is_spent("nonce-1"));
+        let err = reloaded
+            .claim(&mandate("nonce-1", 300))
+            .expect_err("replayed nonce is refused");
+        assert!(err.to_string().contains("already used"), "{err}");
+        reloaded.claim(&mandate("nonce-2", 300))?;
+        Ok(())
+    }
+
+    #[test]
+    fn expired_entries_are_pruned() -> Result<()> {
+        let store = SpentMandateStore::empty();
+        store.claim(&mandate("stale", 0))?;
+        store.claim(&mandate("fresh", 300))?;
+        assert!(!store.is_spent("stale"));
+        assert!(store.is_spent("fresh"));
+        Ok(())
+    }
+}
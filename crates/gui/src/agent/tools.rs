@@ -12,11 +12,14 @@ use std::io::Write;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager, Wry};
 
-use crate::agent::iproov::{CartMandate, IproovServices};
+use crate::agent::blockchain_access::evm_chain_id;
+use crate::agent::iproov::{CartMandate, GatewayJwks, IdentityGateway, MerchantCartService};
+use crate::agent::spent_mandates::SpentMandateStore;
 use crate::app_state::AppState;
 use crate::browser_engine::BrowserEngine;
+use crate::semantic_index::{SemanticIndex, SemanticSourceKind};
 use crate::wallet_store::{WalletOwner, WalletStore};
-use crate::webview_automation::{active_tab_webview, query_active_dom, snapshot_active_page};
+use crate::webview_automation::{query_dom, snapshot_page, tab_webview, TabTarget};
 
 fn build_description(name: &str, description: &str, schema: Value) -> McpToolDescription {
     McpToolDescription::new(name.to_string(), description.to_string(), schema)
@@ -54,6 +57,23 @@ pub fn build_tabs_schema() -> Value {
     })
 }
 
+pub fn build_recall_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string", "description": "Natural-language description of what to recall" },
+            "limit": { "type": "integer", "minimum": 1, "maximum": 50, "description": "Maximum number of results" },
+            "kinds": {
+                "type": "array",
+                "items": { "type": "string", "enum": ["history", "bookmark", "snapshot"] },
+                "description": "Restrict results to these sources"
+            }
+        },
+        "required": ["query"],
+        "additionalProperties": false
+    })
+}
+
 pub fn build_page_snapshot_schema() -> Value {
     json!({
         "type": "object",
@@ -81,7 +101,8 @@ pub fn build_wallet_spend_schema() -> Value {
             "memo": { "type": "string", "description": "Optional memo for the approval prompt" },
             "gas_price": { "type": "number", "minimum": 0, "description": "Override gas price (wei)" },
             "gas_limit": { "type": "integer", "minimum": 21000, "description": "Override gas limit" },
-            "nonce": { "type": "integer", "minimum": 0, "description": "Override transaction nonce" }
+            "nonce": { "type": "integer", "minimum": 0, "description": "Override transaction nonce" },
+            "broadcast": { "type": "boolean", "description": "Broadcast the signed transaction (default true); false only signs it" }
         },
         "additionalProperties": false
     })
@@ -90,13 +111,15 @@ pub fn build_wallet_spend_schema() -> Value {
 #[derive(Clone)]
 pub struct NavigateTool {
     app_handle: AppHandle<Wry>,
+    target: TabTarget,
     description: McpToolDescription,
 }
 
 impl NavigateTool {
-    pub fn new(app_handle: AppHandle<Wry>) -> Self {
+    pub fn new(app_handle: AppHandle<Wry>, target: TabTarget) -> Self {
         Self {
             app_handle,
+            target,
             description: build_description(
                 "browser.navigate",
                 "Navigate the content webview to the provided URL",
@@ -106,7 +129,7 @@ impl NavigateTool {
     }
 
     fn resolve_webview(&self) -> Result<tauri::webview::Webview<Wry>, McpToolError> {
-        active_tab_webview(&self.app_handle).map_err(|err| McpToolError::Invocation(err.into()))
+        tab_webview(&self.app_handle, &self.target).map_err(McpToolError::Invocation)
     }
 }
 
@@ -133,10 +156,13 @@ impl McpTool for NavigateTool {
 
         let webview = self.resolve_webview()?;
 
-        // Update the shared state for current URL
-        if let Some(state) = self.app_handle.try_state::<AppState>() {
-            if let Ok(mut current_url) = state.current_url.lock() {
-                *current_url = params.url.clone();
+        // Update the shared state for current URL; run-owned tabs leave the
+        // address bar alone.
+        if self.target == TabTarget::Active {
+            if let Some(state) = self.app_handle.try_state::<AppState>() {
+                if let Ok(mut current_url) = state.current_url.lock() {
+                    *current_url = params.url.clone();
+                }
             }
         }
 
@@ -178,13 +204,15 @@ impl McpTool for NavigateTool {
 #[derive(Clone)]
 pub struct DomQueryTool {
     app_handle: AppHandle<Wry>,
+    target: TabTarget,
     description: McpToolDescription,
 }
 
 impl DomQueryTool {
-    pub fn new(app_handle: AppHandle<Wry>) -> Self {
+    pub fn new(app_handle: AppHandle<Wry>, target: TabTarget) -> Self {
         Self {
             app_handle,
+            target,
             description: build_description(
                 "browser.dom_query",
                 "Query the active document in the real browser tab using a CSS selector",
@@ -216,9 +244,14 @@ impl McpTool for DomQueryTool {
             ));
         }
 
-        let content = query_active_dom(&self.app_handle, params.selector.trim(), params.limit)
-            .await
-            .map_err(McpToolError::Invocation)?;
+        let content = query_dom(
+            &self.app_handle,
+            &self.target,
+            params.selector.trim(),
+            params.limit,
+        )
+        .await
+        .map_err(McpToolError::Invocation)?;
 
         Ok(McpToolResult {
             content,
@@ -230,13 +263,15 @@ impl McpTool for DomQueryTool {
 #[derive(Clone)]
 pub struct PageSnapshotTool {
     app_handle: AppHandle<Wry>,
+    target: TabTarget,
     description: McpToolDescription,
 }
 
 impl PageSnapshotTool {
-    pub fn new(app_handle: AppHandle<Wry>) -> Self {
+    pub fn new(app_handle: AppHandle<Wry>, target: TabTarget) -> Self {
         Self {
             app_handle,
+            target,
             description: build_description(
                 "browser.page_snapshot",
                 "Capture a structured snapshot of the active browser tab including title, main text, key links, buttons, and forms",
@@ -259,7 +294,7 @@ impl McpTool for PageSnapshotTool {
             ));
         }
 
-        let content = snapshot_active_page(&self.app_handle)
+        let content = snapshot_page(&self.app_handle, &self.target)
             .await
             .map_err(McpToolError::Invocation)?;
 
@@ -309,6 +344,63 @@ impl McpTool for TabsTool {
     }
 }
 
+#[derive(Clone)]
+pub struct RecallTool {
+    browser_engine: Arc<BrowserEngine>,
+    index: Arc<SemanticIndex>,
+    description: McpToolDescription,
+}
+
+impl RecallTool {
+    pub fn new(browser_engine: Arc<BrowserEngine>, index: Arc<SemanticIndex>) -> Self {
+        Self {
+            browser_engine,
+            index,
+            description: build_description(
+                "browser.recall",
+                "Semantically search browsing history, bookmarks and captured page snapshots",
+                build_recall_schema(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecallArgs {
+    query: String,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    kinds: Vec<SemanticSourceKind>,
+}
+
+#[async_trait]
+impl McpTool for RecallTool {
+    fn description(&self) -> &McpToolDescription {
+        &self.description
+    }
+
+    async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
+        let params: RecallArgs = serde_json::from_value(args)
+            .map_err(|e| McpToolError::InvalidInput(format!("invalid arguments: {e}")))?;
+        self.index
+            .sync_browser_data(&self.browser_engine)
+            .await
+            .map_err(|err| McpToolError::Invocation(format!("failed to refresh index: {err}")))?;
+        let hits = self
+            .index
+            .search(&params.query, params.limit.unwrap_or(8), &params.kinds)
+            .await
+            .map_err(|err| McpToolError::Invocation(format!("recall failed: {err}")))?;
+        Ok(McpToolResult {
+            content: json!({
+                "results": hits,
+            }),
+            metadata: Default::default(),
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct WalletInfoTool {
     store: Arc<StdMutex<WalletStore>>,
@@ -414,6 +506,12 @@ struct WalletSpendArgs {
     gas_limit: Option<u64>,
     #[serde(default)]
     nonce: Option<u64>,
+    #[serde(default = "default_broadcast")]
+    broadcast: bool,
+}
+
+fn default_broadcast() -> bool {
+    true
 }
 
 #[async_trait]
@@ -484,8 +582,9 @@ impl McpTool for WalletSpendTool {
             nonce: params.nonce,
         };
 
-        let broadcasted = record_local_broadcast(&intent)
-            || broadcast_signed_intent(&intent, &seed, &params.chain).await;
+        let broadcasted = params.broadcast
+            && (record_local_broadcast(&intent)
+                || broadcast_signed_intent(&intent, &seed, &params.chain).await);
         let network_hash = intent.tx_hash.clone();
 
         Ok(McpToolResult {
@@ -554,7 +653,10 @@ fn broadcast_endpoint() -> Option<String> {
 
 async fn broadcast_signed_intent(intent: &BroadcastIntent, seed: &[u8; 32], chain: &str) -> bool {
     if chain.starts_with("eth") {
-        return broadcast_eth(intent, seed).await.unwrap_or(false);
+        return broadcast_eth(intent, seed).await.unwrap_or_else(|err| {
+            tracing::warn!("not broadcasting {chain} spend: {err:#}");
+            false
+        });
     }
 
     if let Some(url) = broadcast_endpoint() {
@@ -580,6 +682,20 @@ async fn broadcast_eth(intent: &BroadcastIntent, seed: &[u8; 32]) -> anyhow::Res
     let provider = Provider::<Http>::try_from(rpc)?;
 
     let chain_id = provider.get_chainid().await?.as_u64();
+    // A network-qualified chain ref must be the network the RPC serves; the
+    // bare `eth` alias means whichever network is configured.
+    let chain = intent.chain.trim().to_ascii_lowercase();
+    match evm_chain_id(&chain) {
+        Some(expected) if expected != chain_id => {
+            return Err(anyhow::anyhow!(
+                "the configured RPC serves chain id {chain_id}, not {chain} ({expected})"
+            ));
+        }
+        None if chain != "eth" && chain != "ethereum" => {
+            return Err(anyhow::anyhow!("unknown EVM chain `{chain}`"));
+        }
+        _ => {}
+    }
     let to = Address::from_str(&intent.to)?;
     let from = Address::from_str(&intent.from)?;
     let value = U256::from(intent.amount as u128);
@@ -693,12 +809,12 @@ fn default_policy() -> String {
 
 #[derive(Clone)]
 pub struct GatewayCreatePresentationTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn IdentityGateway>,
     description: McpToolDescription,
 }
 
 impl GatewayCreatePresentationTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn IdentityGateway>) -> Self {
         Self {
             service,
             description: build_description(
@@ -756,12 +872,12 @@ impl McpTool for GatewayCreatePresentationTool {
 
 #[derive(Clone)]
 pub struct GatewayApprovePresentationTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn IdentityGateway>,
     description: McpToolDescription,
 }
 
 impl GatewayApprovePresentationTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn IdentityGateway>) -> Self {
         Self {
             service,
             description: build_description(
@@ -808,12 +924,12 @@ impl McpTool for GatewayApprovePresentationTool {
 
 #[derive(Clone)]
 pub struct GatewayAwaitDecisionTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn IdentityGateway>,
     description: McpToolDescription,
 }
 
 impl GatewayAwaitDecisionTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn IdentityGateway>) -> Self {
         Self {
             service,
             description: build_description(
@@ -858,12 +974,12 @@ impl McpTool for GatewayAwaitDecisionTool {
 
 #[derive(Clone)]
 pub struct GatewayIntrospectTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn IdentityGateway>,
     description: McpToolDescription,
 }
 
 impl GatewayIntrospectTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn IdentityGateway>) -> Self {
         Self {
             service,
             description: build_description(
@@ -903,12 +1019,12 @@ impl McpTool for GatewayIntrospectTool {
 
 #[derive(Clone)]
 pub struct MerchantQuoteCartTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn MerchantCartService>,
     description: McpToolDescription,
 }
 
 impl MerchantQuoteCartTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn MerchantCartService>) -> Self {
         Self {
             service,
             description: build_description(
@@ -958,14 +1074,16 @@ impl McpTool for MerchantQuoteCartTool {
 
 #[derive(Clone)]
 pub struct GatewayApproveCartTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn IdentityGateway>,
+    merchant: Arc<dyn MerchantCartService>,
     description: McpToolDescription,
 }
 
 impl GatewayApproveCartTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn IdentityGateway>, merchant: Arc<dyn MerchantCartService>) -> Self {
         Self {
             service,
+            merchant,
             description: build_description(
                 "gateway.approve_cart",
                 "Approve an AP2 cart and return the cart mandate",
@@ -984,9 +1102,14 @@ impl McpTool for GatewayApproveCartTool {
     async fn invoke(&self, args: Value) -> Result<McpToolResult, McpToolError> {
         let params: CartArgs = serde_json::from_value(args)
             .map_err(|e| McpToolError::InvalidInput(format!("invalid arguments: {e}")))?;
+        let quote = self
+            .merchant
+            .start_cart(&params.cart_id)
+            .await
+            .map_err(|e| McpToolError::Invocation(e.to_string()))?;
         let mandate = self
             .service
-            .approve_cart(&params.cart_id)
+            .approve_cart(&quote)
             .await
             .map_err(|e| McpToolError::Invocation(e.to_string()))?;
         Ok(McpToolResult {
@@ -998,12 +1121,12 @@ impl McpTool for GatewayApproveCartTool {
 
 #[derive(Clone)]
 pub struct GatewayFetchMandateTool {
-    service: Arc<IproovServices>,
+    service: Arc<dyn IdentityGateway>,
     description: McpToolDescription,
 }
 
 impl GatewayFetchMandateTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(service: Arc<dyn IdentityGateway>) -> Self {
         Self {
             service,
             description: build_description(
@@ -1036,16 +1159,26 @@ impl McpTool for GatewayFetchMandateTool {
     }
 }
 
+/// Places orders only with mandates that verify against the gateway JWKS,
+/// approve exactly the current quote and have not been used before.
 #[derive(Clone)]
 pub struct MerchantPlaceOrderTool {
-    service: Arc<IproovServices>,
+    gateway: Arc<dyn IdentityGateway>,
+    service: Arc<dyn MerchantCartService>,
+    spent_mandates: Arc<SpentMandateStore>,
     description: McpToolDescription,
 }
 
 impl MerchantPlaceOrderTool {
-    pub fn new(service: Arc<IproovServices>) -> Self {
+    pub fn new(
+        gateway: Arc<dyn IdentityGateway>,
+        service: Arc<dyn MerchantCartService>,
+        spent_mandates: Arc<SpentMandateStore>,
+    ) -> Self {
         Self {
+            gateway,
             service,
+            spent_mandates,
             description: build_description(
                 "merchant.place_order",
                 "Place an order with the merchant, verifying mandates and decisions",
@@ -1077,6 +1210,33 @@ impl McpTool for MerchantPlaceOrderTool {
             })?),
             None => None,
         };
+        let quote = self
+            .service
+            .start_cart(&params.cart_id)
+            .await
+            .map_err(|e| McpToolError::Invocation(e.to_string()))?;
+        match &mandate {
+            Some(mandate) => {
+                let jwks = self
+                    .gateway
+                    .jwks()
+                    .await
+                    .and_then(|jwks| GatewayJwks::from_value(&jwks))
+                    .map_err(|e| McpToolError::Invocation(e.to_string()))?;
+                jwks.verify_mandate(mandate)
+                    .and_then(|()| mandate.check_quote(&quote))
+                    .map_err(|e| McpToolError::Invocation(e.to_string()))?;
+                // Spent before the order is placed, so a mandate is never
+                // honoured twice even if the merchant call fails midway.
+                self.spent_mandates
+                    .claim(mandate)
+                    .map_err(|e| McpToolError::Invocation(e.to_string()))?;
+            }
+            None if quote.user_signature_required => {
+                return Err(McpToolError::Invocation("mandate required".into()));
+            }
+            None => {}
+        }
         let confirmation = self
             .service
             .place_order(&params.cart_id, mandate, params.decision_jwt)
@@ -1088,3 +1248,296 @@ impl McpTool for MerchantPlaceOrderTool {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::iproov::{
+        CartItem, CartQuote, DecisionToken, OrderConfirmation, PresentationInfo,
+    };
+    use anyhow::{anyhow, Result};
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine as _;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::{Signature, SigningKey};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn now_ts() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// A gateway that only publishes the JWKS of its own P-256 key and signs
+    /// whatever mandates a test asks for.
+    struct StubGateway {
+        kid: String,
+        signing: SigningKey,
+    }
+
+    impl StubGateway {
+        fn new(kid: &str) -> Self {
+            Self {
+                kid: kid.into(),
+                signing: SigningKey::random(&mut rand::thread_rng()),
+            }
+        }
+
+        fn mandate(&self, quote: &CartQuote, nonce: &str, expires_at: u64) -> CartMandate {
+            let mut mandate = CartMandate {
+                cart_id: quote.cart_id.clone(),
+                cart_hash: quote.cart_hash.clone(),
+                merchant_id: quote.merchant_id.clone(),
+                total_cents: quote.total_cents,
+                currency: quote.currency.clone(),
+                nonce: nonce.into(),
+                approved_at: now_ts(),
+                approval_expires_at: expires_at,
+                kid: Some(self.kid.clone()),
+                signature: String::new(),
+            };
+            self.sign(&mut mandate);
+            mandate
+        }
+
+        fn sign(&self, mandate: &mut CartMandate) {
+            let signature: Signature = self.signing.sign(mandate.signing_payload().as_bytes());
+            mandate.signature = URL_SAFE_NO_PAD.encode(signature.to_der());
+        }
+    }
+
+    #[async_trait]
+    impl IdentityGateway for StubGateway {
+        async fn jwks(&self) -> Result<Value> {
+            let point = self.signing.verifying_key().to_encoded_point(false);
+            Ok(json!({
+                "keys": [{
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "kid": self.kid,
+                    "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+                    "y": URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+                }]
+            }))
+        }
+
+        async fn create_presentation(
+            &self,
+            _policy: String,
+            _amount_cents: u64,
+            _sku: Option<String>,
+            _metadata: Value,
+        ) -> Result<PresentationInfo> {
+            Err(anyhow!("not used by merchant.place_order"))
+        }
+
+        async fn approve_presentation(
+            &self,
+            _request_id: &str,
+            _agent_id: &str,
+            _audience: &str,
+        ) -> Result<DecisionToken> {
+            Err(anyhow!("not used by merchant.place_order"))
+        }
+
+        async fn await_decision(&self, _request_id: &str) -> Result<Option<DecisionToken>> {
+            Err(anyhow!("not used by merchant.place_order"))
+        }
+
+        async fn introspect_decision(&self, _token: &str) -> Result<Value> {
+            Err(anyhow!("not used by merchant.place_order"))
+        }
+
+        async fn approve_cart(&self, _quote: &CartQuote) -> Result<CartMandate> {
+            Err(anyhow!("not used by merchant.place_order"))
+        }
+
+        async fn fetch_mandate(&self, _cart_id: &str) -> Result<Option<CartMandate>> {
+            Err(anyhow!("not used by merchant.place_order"))
+        }
+    }
+
+    /// A merchant serving one fixed quote and recording the nonces of the
+    /// orders placed with it.
+    struct StubMerchant {
+        quote: CartQuote,
+        orders: StdMutex<Vec<Option<String>>>,
+    }
+
+    impl StubMerchant {
+        fn new() -> Self {
+            Self {
+                quote: CartQuote {
+                    cart_id: "cart_1".into(),
+                    merchant_id: "merchant.example".into(),
+                    merchant_name: "Example Merchant".into(),
+                    items: vec![CartItem {
+                        sku: "MBA13-2025".into(),
+                        label: "Laptop".into(),
+                        quantity: 1,
+                        unit_price_cents: 129_900,
+                    }],
+                    total_cents: 129_900,
+                    currency: "USD".into(),
+                    cart_hash: "cart-hash".into(),
+                    merchant_signature: String::new(),
+                    user_signature_required: true,
+                    expires_at: now_ts() + 600,
+                },
+                orders: StdMutex::new(Vec::new()),
+            }
+        }
+
+        fn orders(&self) -> Vec<Option<String>> {
+            self.orders.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl MerchantCartService for StubMerchant {
+        async fn quote_cart(
+            &self,
+            _term: Option<String>,
+            _sku: Option<String>,
+            _quantity: Option<u32>,
+        ) -> Result<CartQuote> {
+            Ok(self.quote.clone())
+        }
+
+        async fn start_cart(&self, cart_id: &str) -> Result<CartQuote> {
+            if cart_id != self.quote.cart_id {
+                return Err(anyhow!("cart not found"));
+            }
+            Ok(self.quote.clone())
+        }
+
+        async fn place_order(
+            &self,
+            _cart_id: &str,
+            mandate: Option<CartMandate>,
+            _decision: Option<String>,
+        ) -> Result<OrderConfirmation> {
+            self.orders
+                .lock()
+                .unwrap()
+                .push(mandate.map(|mandate| mandate.nonce));
+            Ok(OrderConfirmation {
+                order_id: "order-1".into(),
+                pickup_code: "1234".into(),
+            })
+        }
+    }
+
+    fn place_order_tool(
+        gateway: &Arc<StubGateway>,
+        merchant: &Arc<StubMerchant>,
+    ) -> (MerchantPlaceOrderTool, Arc<SpentMandateStore>) {
+        let spent = Arc::new(SpentMandateStore::empty());
+        let tool = MerchantPlaceOrderTool::new(gateway.clone(), merchant.clone(), spent.clone());
+        (tool, spent)
+    }
+
+    async fn place(tool: &MerchantPlaceOrderTool, mandate: &CartMandate) -> Result<McpToolResult> {
+        tool.invoke(json!({ "cart_id": mandate.cart_id, "mandate": mandate }))
+            .await
+            .map_err(|err| anyhow!(err))
+    }
+
+    #[tokio::test]
+    async fn replayed_mandates_are_refused() -> Result<()> {
+        let gateway = Arc::new(StubGateway::new("gw-1"));
+        let merchant = Arc::new(StubMerchant::new());
+        let (tool, spent) = place_order_tool(&gateway, &merchant);
+        let mandate = gateway.mandate(&merchant.quote, "nonce-1", now_ts() + 300);
+
+        let placed = place(&tool, &mandate).await?;
+        assert_eq!(placed.content["confirmation"]["order_id"], "order-1");
+        assert!(spent.is_spent("nonce-1"));
+
+        let err = place(&tool, &mandate)
+            .await
+            .expect_err("a spent mandate is refused");
+        assert!(err.to_string().contains("already used"), "{err}");
+        assert_eq!(merchant.orders(), vec![Some("nonce-1".to_string())]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_or_missing_mandates_are_refused() -> Result<()> {
+        let gateway = Arc::new(StubGateway::new("gw-1"));
+        let merchant = Arc::new(StubMerchant::new());
+        let (tool, spent) = place_order_tool(&gateway, &merchant);
+
+        let expired = gateway.mandate(&merchant.quote, "nonce-1", now_ts() - 1);
+        let err = place(&tool, &expired)
+            .await
+            .expect_err("an expired mandate is refused");
+        assert!(err.to_string().contains("expired"), "{err}");
+        assert!(!spent.is_spent("nonce-1"));
+
+        let err = tool
+            .invoke(json!({ "cart_id": merchant.quote.cart_id }))
+            .await
+            .expect_err("the quote requires a mandate");
+        assert!(err.to_string().contains("mandate required"), "{err}");
+        assert!(merchant.orders().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mandates_must_be_signed_by_the_named_gateway_key() -> Result<()> {
+        let gateway = Arc::new(StubGateway::new("gw-1"));
+        let merchant = Arc::new(StubMerchant::new());
+        let (tool, spent) = place_order_tool(&gateway, &merchant);
+        let expires_at = now_ts() + 300;
+
+        // Signed by a different key that claims the gateway's `kid`.
+        let forged = StubGateway::new("gw-1").mandate(&merchant.quote, "nonce-1", expires_at);
+        let err = place(&tool, &forged)
+            .await
+            .expect_err("a foreign signature is refused");
+        assert!(err.to_string().contains("mandate signature"), "{err}");
+
+        // Signed by the gateway's key but naming one the JWKS lacks.
+        let mut renamed = gateway.mandate(&merchant.quote, "nonce-2", expires_at);
+        renamed.kid = Some("gw-2".into());
+        gateway.sign(&mut renamed);
+        let err = place(&tool, &renamed)
+            .await
+            .expect_err("an unknown kid is refused");
+        assert!(err.to_string().contains("mandate signature"), "{err}");
+
+        assert!(!spent.is_spent("nonce-1") && !spent.is_spent("nonce-2"));
+        assert!(merchant.orders().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mandates_must_match_the_quoted_total_and_merchant() -> Result<()> {
+        let gateway = Arc::new(StubGateway::new("gw-1"));
+        let merchant = Arc::new(StubMerchant::new());
+        let (tool, spent) = place_order_tool(&gateway, &merchant);
+        let expires_at = now_ts() + 300;
+
+        let mut cheaper = merchant.quote.clone();
+        cheaper.total_cents -= 100;
+        let mandate = gateway.mandate(&cheaper, "nonce-1", expires_at);
+        let err = place(&tool, &mandate)
+            .await
+            .expect_err("a mandate for another total is refused");
+        assert!(err.to_string().contains("approves"), "{err}");
+
+        let mut elsewhere = merchant.quote.clone();
+        elsewhere.merchant_id = "merchant.example:elsewhere".into();
+        let mandate = gateway.mandate(&elsewhere, "nonce-2", expires_at);
+        let err = place(&tool, &mandate)
+            .await
+            .expect_err("a mandate for another merchant is refused");
+        assert!(err.to_string().contains("mandate is for merchant"), "{err}");
+
+        assert!(!spent.is_spent("nonce-1") && !spent.is_spent("nonce-2"));
+        assert!(merchant.orders().is_empty());
+        Ok(())
+    }
+}
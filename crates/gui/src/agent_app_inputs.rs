@@ -0,0 +1,314 @@
+//! Typed input parameters declared by agent apps.
+//!
+//! The launcher renders a form from an app's `inputs`; the submitted values
+//! are checked and normalised here before they become template variables of
+//! the app's prompt.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Template variables the runtime sets itself, unavailable as input names.
+const RESERVED_NAMES: &[&str] = &["input", "event", "this"];
+
+const URL_SCHEMES: &[&str] = &["http", "https", "ipfs", "ipns"];
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentAppInputKind {
+    String,
+    Number,
+    /// A calendar date, `YYYY-MM-DD`.
+    Date,
+    /// An `http(s)://`, `ipfs://` or `ipns://` URL.
+    Url,
+    /// One of `options`.
+    Enum,
+    /// A `0x` EVM address, or a base58 or bech32 address for Solana and
+    /// Bitcoin.
+    WalletAddress,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppInput {
+    /// Template variable the value is bound to.
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: AgentAppInputKind,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<Value>,
+    /// Allowed values of an `enum` input.
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Bounds of a `number` input.
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+impl AgentAppInput {
+    /// Problems with the declaration itself, as opposed to a submitted value.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !is_valid_input_name(&self.name) {
+            problems.push(format!(
+                "input name `{}` must be letters, digits and underscores, starting with a letter",
+                self.name
+            ));
+        } else if RESERVED_NAMES.contains(&self.name.as_str()) {
+            problems.push(format!("input name `{}` is reserved", self.name));
+        }
+        if self.kind == AgentAppInputKind::Enum && self.options.is_empty() {
+            problems.push(format!("enum input `{}` needs options", self.name));
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                problems.push(format!("input `{}` has min above max", self.name));
+            }
+        }
+        if let Some(default) = &self.default {
+            if let Err(err) = self.coerce(default) {
+                problems.push(format!("default of input `{}`: {err}", self.name));
+            }
+        }
+        problems
+    }
+
+    /// Checks `value` against the declared type and returns it normalised:
+    /// trimmed strings, numbers parsed from text, lowercase EVM addresses.
+    pub fn coerce(&self, value: &Value) -> Result<Value> {
+        let text = match value {
+            Value::String(text) => Some(text.trim()),
+            _ => None,
+        };
+        match self.kind {
+            AgentAppInputKind::String => match value {
+                Value::String(_) | Value::Number(_) | Value::Bool(_) => Ok(Value::String(
+                    text.map(str::to_string)
+                        .unwrap_or_else(|| value.to_string()),
+                )),
+                _ => Err(anyhow!("expected text")),
+            },
+            AgentAppInputKind::Number => {
+                let number = match value {
+                    Value::Number(number) => number.as_f64(),
+                    _ => text.and_then(|text| text.parse::<f64>().ok()),
+                }
+                .filter(|number| number.is_finite())
+                .ok_or_else(|| anyhow!("expected a number"))?;
+                if self.min.is_some_and(|min| number < min) {
+                    return Err(anyhow!("must be at least {}", self.min.unwrap_or_default()));
+                }
+                if self.max.is_some_and(|max| number > max) {
+                    return Err(anyhow!("must be at most {}", self.max.unwrap_or_default()));
+                }
+                if number.fract() == 0.0 && number.abs() < 9_007_199_254_740_992.0 {
+                    Ok(Value::from(number as i64))
+                } else {
+                    Ok(Value::from(number))
+                }
+            }
+            AgentAppInputKind::Date => {
+                let text = text.ok_or_else(|| anyhow!("expected a YYYY-MM-DD date"))?;
+                NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("`{text}` is not a YYYY-MM-DD date"))?;
+                Ok(Value::String(text.to_string()))
+            }
+            AgentAppInputKind::Url => {
+                let text = text.ok_or_else(|| anyhow!("expected a URL"))?;
+                let url =
+                    url::Url::parse(text).map_err(|_| anyhow!("`{text}` is not a valid URL"))?;
+                if !URL_SCHEMES.contains(&url.scheme()) {
+                    return Err(anyhow!("`{text}` must be an http, https, ipfs or ipns URL"));
+                }
+                Ok(Value::String(url.to_string()))
+            }
+            AgentAppInputKind::Enum => {
+                let text = text.ok_or_else(|| anyhow!("expected one of the options"))?;
+                if !self.options.iter().any(|option| option == text) {
+                    return Err(anyhow!(
+                        "`{text}` is not one of {}",
+                        self.options.join(", ")
+                    ));
+                }
+                Ok(Value::String(text.to_string()))
+            }
+            AgentAppInputKind::WalletAddress => {
+                let text = text.ok_or_else(|| anyhow!("expected a wallet address"))?;
+                normalize_wallet_address(text)
+                    .map(Value::String)
+                    .ok_or_else(|| anyhow!("`{text}` is not a wallet address"))
+            }
+        }
+    }
+}
+
+/// Validates `values` submitted for `inputs` and fills in defaults. Empty
+/// strings and `null` count as not given; optional inputs without a default
+/// are left out so `{{#if name}}` sees them as unset.
+pub fn resolve_inputs(
+    inputs: &[AgentAppInput],
+    values: &Map<String, Value>,
+) -> Result<Map<String, Value>> {
+    let mut problems = Vec::new();
+    for name in values.keys() {
+        if !inputs.iter().any(|input| &input.name == name) {
+            problems.push(format!("unknown input `{name}`"));
+        }
+    }
+
+    let mut resolved = Map::new();
+    for input in inputs {
+        let given = values.get(&input.name).filter(|value| match value {
+            Value::Null => false,
+            Value::String(text) => !text.trim().is_empty(),
+            _ => true,
+        });
+        let Some(value) = given.or(input.default.as_ref()) else {
+            if input.required {
+                problems.push(format!("`{}` is required", display_name(input)));
+            }
+            continue;
+        };
+        match input.coerce(value) {
+            Ok(value) => {
+                resolved.insert(input.name.clone(), value);
+            }
+            Err(err) => problems.push(format!("`{}`: {err}", display_name(input))),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(resolved)
+    } else {
+        Err(anyhow!("invalid inputs: {}", problems.join("; ")))
+    }
+}
+
+fn display_name(input: &AgentAppInput) -> &str {
+    input.label.as_deref().unwrap_or(&input.name)
+}
+
+fn is_valid_input_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|ch| ch.is_ascii_alphabetic())
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+fn normalize_wallet_address(address: &str) -> Option<String> {
+    if let Some(hex) = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+    {
+        return (hex.len() == 40 && hex.chars().all(|ch| ch.is_ascii_hexdigit()))
+            .then(|| format!("0x{}", hex.to_ascii_lowercase()));
+    }
+    let lower = address.to_ascii_lowercase();
+    if lower.starts_with("bc1") || lower.starts_with("tb1") {
+        let mixed_case = address != lower && address != address.to_ascii_uppercase();
+        return (!mixed_case
+            && (14..=74).contains(&address.len())
+            && lower[3..].chars().all(|ch| ch.is_ascii_alphanumeric()))
+        .then_some(lower);
+    }
+    ((26..=44).contains(&address.len()) && address.chars().all(|ch| BASE58_ALPHABET.contains(ch)))
+        .then(|| address.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn inputs() -> Vec<AgentAppInput> {
+        serde_json::from_value(json!([
+            { "name": "destination", "type": "string", "required": true },
+            { "name": "nights", "type": "number", "min": 1, "max": 30, "default": 2 },
+            { "name": "departure", "type": "date" },
+            { "name": "cabin", "type": "enum", "options": ["economy", "business"], "default": "economy" },
+            { "name": "payout", "label": "Payout address", "type": "wallet_address" },
+            { "name": "itinerary", "type": "url" }
+        ]))
+        .expect("inputs")
+    }
+
+    #[test]
+    fn resolves_values_and_defaults() {
+        let values = json!({
+            "destination": " Lisbon ",
+            "nights": "4",
+            "departure": "2026-11-02",
+            "payout": "0xAbCdEf0123456789aBcDeF0123456789AbCdEf01",
+            "itinerary": ""
+        });
+        let resolved = resolve_inputs(&inputs(), values.as_object().unwrap()).unwrap();
+        assert_eq!(
+            Value::Object(resolved),
+            json!({
+                "destination": "Lisbon",
+                "nights": 4,
+                "departure": "2026-11-02",
+                "cabin": "economy",
+                "payout": "0xabcdef0123456789abcdef0123456789abcdef01"
+            })
+        );
+    }
+
+    #[test]
+    fn reports_every_invalid_value() {
+        let values = json!({
+            "nights": 45,
+            "departure": "2026-02-30",
+            "cabin": "first",
+            "payout": "0x1234",
+            "itinerary": "ftp://example.com/plan",
+            "pets": 2
+        });
+        let err = resolve_inputs(&inputs(), values.as_object().unwrap())
+            .unwrap_err()
+            .to_string();
+        for expected in [
+            "unknown input `pets`",
+            "`destination` is required",
+            "`nights`: must be at most 30",
+            "`departure`: `2026-02-30` is not a YYYY-MM-DD date",
+            "`cabin`: `first` is not one of economy, business",
+            "`Payout address`: `0x1234` is not a wallet address",
+            "`itinerary`: `ftp://example.com/plan` must be an http",
+        ] {
+            assert!(err.contains(expected), "{err} should mention {expected}");
+        }
+    }
+
+    #[test]
+    fn declarations_are_checked() {
+        let input: AgentAppInput = serde_json::from_value(json!({
+            "name": "event",
+            "type": "enum",
+            "default": "later"
+        }))
+        .unwrap();
+        let problems = input.problems().join("; ");
+        assert!(problems.contains("`event` is reserved"));
+        assert!(problems.contains("needs options"));
+        assert!(problems.contains("default of input `event`"));
+
+        for address in [
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+            "So11111111111111111111111111111111111111112",
+        ] {
+            assert!(normalize_wallet_address(address).is_some(), "{address}");
+        }
+    }
+}
@@ -0,0 +1,818 @@
+//! Signed, distributable agent-app packages.
+//!
+//! A package bundles an app manifest with its prompt templates, an optional
+//! skill and an icon, and is signed by its publisher's ed25519 key. Packages
+//! are fetched from a local file or from IPFS (the same gateway setup used
+//! for browser updates), verified against the trusted publisher keys and
+//! installed under `~/.advatar/apps/<app-id>/`. Updates must come from the
+//! publisher that signed the installed version and carry a newer version.
+//!
+//! The signature covers the package's `payload`, which is kept verbatim so
+//! that later changes to the app definition shape cannot break packages
+//! signed before them.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use cid::Cid;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use updater::{IpfsFetcher, IpfsGatewayClient};
+use url::Url;
+
+use crate::agent_apps::AgentAppDefinition;
+
+pub const AGENT_APP_PACKAGE_FORMAT: &str = "advatar-agent-app";
+const AGENT_APP_PACKAGE_VERSION: u8 = 1;
+const SIGNATURE_ALGORITHM: &str = "ed25519";
+/// Comma-separated hex ed25519 keys whose packages may be installed. The
+/// browser update key is trusted as well, for first-party apps.
+const PUBLISHER_KEYS_ENV: &str = "ADVATAR_AGENT_APP_PUBLISHER_KEYS";
+const UPDATE_PUBLIC_KEY_ENV: &str = "BROWSER_UPDATE_PUBLIC_KEY_HEX";
+const APP_GATEWAY_ENV: &str = "ADVATAR_AGENT_APP_GATEWAY";
+const UPDATE_GATEWAY_ENV: &str = "BROWSER_UPDATE_GATEWAY";
+const PACKAGE_FILE: &str = "package.json";
+const INSTALL_FILE: &str = "install.json";
+const MAX_PACKAGE_BYTES: usize = 4 * 1024 * 1024;
+const MAX_ICON_BYTES: usize = 512 * 1024;
+const ICON_MEDIA_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp", "image/svg+xml"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPublisher {
+    pub algorithm: String,
+    /// Hex-encoded ed25519 verifying key.
+    pub public_key: String,
+}
+
+impl AgentAppPublisher {
+    pub fn from_key(key: &VerifyingKey) -> Self {
+        Self {
+            algorithm: SIGNATURE_ALGORITHM.to_string(),
+            public_key: hex::encode(key.to_bytes()),
+        }
+    }
+
+    fn verifying_key(&self) -> Result<VerifyingKey> {
+        if self.algorithm != SIGNATURE_ALGORITHM {
+            bail!("unsupported package signature `{}`", self.algorithm);
+        }
+        parse_public_key(&self.public_key)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPackageIcon {
+    pub media_type: String,
+    /// Base64-encoded image bytes.
+    pub data: String,
+}
+
+/// Unsigned package contents, as assembled by a publisher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPackageContents {
+    /// Semantic version of the app, compared on update.
+    pub version: String,
+    pub app: AgentAppDefinition,
+    /// Named prompt templates, pulled into the app's `promptTemplate` and
+    /// `instructions` with `{{> name}}`.
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+    /// One skill in the per-user skill manifest format, installed alongside
+    /// the app and used as its skill.
+    #[serde(default)]
+    pub skill: Option<Value>,
+    #[serde(default)]
+    pub icon: Option<AgentAppPackageIcon>,
+}
+
+/// A package with its contents parsed from the signed payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "PackageDocument", into = "PackageDocument")]
+pub struct AgentAppPackage {
+    pub format: String,
+    pub format_version: u8,
+    pub publisher: AgentAppPublisher,
+    pub contents: AgentAppPackageContents,
+    /// Base64 ed25519 signature over `payload`.
+    pub signature: String,
+    /// The exact text that was signed.
+    payload: String,
+}
+
+/// On-disk and on-IPFS form of a package.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageDocument {
+    format: String,
+    format_version: u8,
+    publisher: AgentAppPublisher,
+    /// JSON text of the [`SignedFields`].
+    payload: String,
+    signature: String,
+}
+
+/// Everything the publisher signs.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedFields {
+    format: String,
+    format_version: u8,
+    publisher: AgentAppPublisher,
+    #[serde(flatten)]
+    contents: AgentAppPackageContents,
+}
+
+impl TryFrom<PackageDocument> for AgentAppPackage {
+    type Error = anyhow::Error;
+
+    fn try_from(document: PackageDocument) -> Result<Self> {
+        let package = Self::from_payload(document.payload, document.signature)?;
+        if package.publisher != document.publisher {
+            bail!("agent app package publisher does not match its signed payload");
+        }
+        Ok(package)
+    }
+}
+
+impl From<AgentAppPackage> for PackageDocument {
+    fn from(package: AgentAppPackage) -> Self {
+        Self {
+            format: package.format,
+            format_version: package.format_version,
+            publisher: package.publisher,
+            payload: package.payload,
+            signature: package.signature,
+        }
+    }
+}
+
+/// Where an installed package came from and who signed it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledAgentAppPackage {
+    pub version: String,
+    pub publisher_key: String,
+    pub source: String,
+    pub installed_at_ms: u64,
+}
+
+impl AgentAppPackage {
+    pub fn sign(contents: AgentAppPackageContents, signing_key: &SigningKey) -> Result<Self> {
+        let payload = serde_json::to_string(&SignedFields {
+            format: AGENT_APP_PACKAGE_FORMAT.to_string(),
+            format_version: AGENT_APP_PACKAGE_VERSION,
+            publisher: AgentAppPublisher::from_key(&signing_key.verifying_key()),
+            contents,
+        })
+        .context("serialising agent app package for signing")?;
+        let signature = signing_key.sign(payload.as_bytes());
+        Self::from_payload(payload, BASE64.encode(signature.to_bytes()))
+    }
+
+    pub fn from_slice(raw: &[u8]) -> Result<Self> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Header {
+            format: String,
+            format_version: u8,
+        }
+
+        if raw.len() > MAX_PACKAGE_BYTES {
+            bail!("agent app package exceeds {MAX_PACKAGE_BYTES} bytes");
+        }
+        let header: Header = serde_json::from_slice(raw).context("parsing agent app package")?;
+        if header.format != AGENT_APP_PACKAGE_FORMAT {
+            bail!("not an agent app package");
+        }
+        if header.format_version != AGENT_APP_PACKAGE_VERSION {
+            bail!(
+                "unsupported agent app package version {}",
+                header.format_version
+            );
+        }
+        serde_json::from_slice(raw).context("parsing agent app package")
+    }
+
+    /// A package whose fields all come from `payload`. The signature is
+    /// checked later by [`Self::verify`].
+    fn from_payload(payload: String, signature: String) -> Result<Self> {
+        let signed: SignedFields =
+            serde_json::from_str(&payload).context("parsing signed package payload")?;
+        if signed.format != AGENT_APP_PACKAGE_FORMAT {
+            bail!("not an agent app package");
+        }
+        if signed.format_version != AGENT_APP_PACKAGE_VERSION {
+            bail!(
+                "unsupported agent app package version {}",
+                signed.format_version
+            );
+        }
+        Ok(Self {
+            format: signed.format,
+            format_version: signed.format_version,
+            publisher: signed.publisher,
+            contents: signed.contents,
+            signature,
+            payload,
+        })
+    }
+
+    pub fn app_id(&self) -> &str {
+        &self.contents.app.id
+    }
+
+    pub fn version(&self) -> Result<semver::Version> {
+        semver::Version::parse(self.contents.version.trim()).with_context(|| {
+            format!(
+                "agent app package version `{}` is not a semantic version",
+                self.contents.version
+            )
+        })
+    }
+
+    /// Checks the signature over the payload, that the publisher is one of
+    /// `trusted_keys` and that the contents are still the signed ones.
+    pub fn verify(&self, trusted_keys: &[VerifyingKey]) -> Result<()> {
+        let key = self.publisher.verifying_key()?;
+        if !trusted_keys.contains(&key) {
+            bail!(
+                "agent app package publisher {} is not trusted; add it to {PUBLISHER_KEYS_ENV}",
+                self.publisher.public_key
+            );
+        }
+        let signature_bytes = BASE64
+            .decode(&self.signature)
+            .context("decoding agent app package signature")?;
+        let signature = Signature::from_slice(&signature_bytes)
+            .map_err(|err| anyhow!("malformed agent app package signature: {err}"))?;
+        key.verify(self.payload.as_bytes(), &signature)
+            .map_err(|_| anyhow!("agent app package signature verification failed"))?;
+        let signed: SignedFields = serde_json::from_str(&self.payload)?;
+        if signed.publisher != self.publisher
+            || serde_json::to_value(&signed.contents)? != serde_json::to_value(&self.contents)?
+        {
+            bail!("agent app package signature verification failed: contents were modified");
+        }
+        Ok(())
+    }
+
+    /// The app as it will run: templates expanded, the packaged skill and
+    /// icon wired in, and the result validated.
+    pub fn to_definition(&self) -> Result<AgentAppDefinition> {
+        self.version()?;
+        let mut app = self.contents.app.clone();
+        app.prompt_template = expand_templates(&app.prompt_template, &self.contents.templates)?;
+        app.instructions = app
+            .instructions
+            .as_deref()
+            .map(|text| expand_templates(text, &self.contents.templates))
+            .transpose()?;
+
+        if let Some(skill_id) = self.skill_id()? {
+            if app.skill_id.as_deref().is_some_and(|id| id != skill_id) {
+                bail!(
+                    "app `{}` names skill `{}` but packages skill `{skill_id}`",
+                    app.id,
+                    app.skill_id.as_deref().unwrap_or_default()
+                );
+            }
+            app.skill_id = Some(packaged_skill_id(&app.id));
+        }
+
+        if let Some(icon) = &self.contents.icon {
+            app.icon = Some(icon_data_url(icon)?);
+        }
+
+        app.validate()?;
+        Ok(app)
+    }
+
+    /// The id the packaged skill declares for itself.
+    fn skill_id(&self) -> Result<Option<String>> {
+        let Some(skill) = &self.contents.skill else {
+            return Ok(None);
+        };
+        let skill: crate::agent::skills::SkillDefinition =
+            serde_json::from_value(skill.clone()).context("parsing packaged skill")?;
+        Ok(Some(skill.id))
+    }
+
+    /// The packaged skill as installed, renamed into the app's namespace so
+    /// it cannot replace a bundled or user skill.
+    fn scoped_skill(&self) -> Option<Value> {
+        let mut skill = self.contents.skill.clone()?;
+        if let Some(fields) = skill.as_object_mut() {
+            fields.insert(
+                "id".to_string(),
+                Value::from(packaged_skill_id(self.app_id())),
+            );
+        }
+        Some(skill)
+    }
+}
+
+/// Skill id an app's packaged skill is installed under. Bundled skills do
+/// not use the `app-` prefix, and app ids are unique.
+fn packaged_skill_id(app_id: &str) -> String {
+    format!("app-{app_id}")
+}
+
+fn expand_templates(text: &str, templates: &BTreeMap<String, String>) -> Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{>") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 3..start + end].trim();
+        let template = templates
+            .get(name)
+            .ok_or_else(|| anyhow!("prompt template `{name}` is not in the package"))?;
+        output.push_str(&rest[..start]);
+        output.push_str(template);
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn icon_data_url(icon: &AgentAppPackageIcon) -> Result<String> {
+    if !ICON_MEDIA_TYPES.contains(&icon.media_type.as_str()) {
+        bail!("unsupported icon type `{}`", icon.media_type);
+    }
+    let bytes = BASE64
+        .decode(&icon.data)
+        .context("decoding packaged icon")?;
+    if bytes.len() > MAX_ICON_BYTES {
+        bail!("packaged icon exceeds {MAX_ICON_BYTES} bytes");
+    }
+    Ok(format!("data:{};base64,{}", icon.media_type, icon.data))
+}
+
+fn parse_public_key(hex_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())
+        .context("decoding publisher key")?
+        .try_into()
+        .map_err(|_| anyhow!("publisher key must be 32 bytes"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|err| anyhow!("invalid publisher key: {err}"))
+}
+
+/// Publisher keys from `ADVATAR_AGENT_APP_PUBLISHER_KEYS` plus the browser
+/// update key. Malformed entries are skipped.
+pub fn trusted_publisher_keys() -> Vec<VerifyingKey> {
+    let mut keys = Vec::new();
+    for var in [PUBLISHER_KEYS_ENV, UPDATE_PUBLIC_KEY_ENV] {
+        let Ok(value) = env::var(var) else {
+            continue;
+        };
+        for entry in value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            match parse_public_key(entry) {
+                Ok(key) if !keys.contains(&key) => keys.push(key),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("ignoring {var} entry: {err}"),
+            }
+        }
+    }
+    keys
+}
+
+/// Reads a package from `ipfs://<cid>[/path]`, a bare CID, or a local file.
+/// Returns the package and the normalised source to record for updates.
+pub async fn fetch_agent_app_package(source: &str) -> Result<(AgentAppPackage, String)> {
+    let source = source.trim();
+    if source.is_empty() {
+        bail!("package source must not be empty");
+    }
+    let ipfs_ref = source.strip_prefix("ipfs://").or_else(|| {
+        Cid::try_from(source.split('/').next().unwrap_or_default())
+            .ok()
+            .map(|_| source)
+    });
+    if let Some(reference) = ipfs_ref {
+        let (cid, path) = match reference.split_once('/') {
+            Some((cid, path)) => (cid, Some(path).filter(|path| !path.is_empty())),
+            None => (reference, None),
+        };
+        let cid = Cid::try_from(cid).map_err(|err| anyhow!("invalid package CID: {err}"))?;
+        let bytes = gateway_client()?.fetch_bytes(&cid, path).await?;
+        let normalised = match path {
+            Some(path) => format!("ipfs://{cid}/{path}"),
+            None => format!("ipfs://{cid}"),
+        };
+        return Ok((AgentAppPackage::from_slice(&bytes)?, normalised));
+    }
+
+    let path = PathBuf::from(source);
+    let bytes = tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("reading agent app package at {}", path.display()))?;
+    Ok((
+        AgentAppPackage::from_slice(&bytes)?,
+        path.display().to_string(),
+    ))
+}
+
+fn gateway_client() -> Result<IpfsGatewayClient> {
+    let mut builder = IpfsGatewayClient::builder();
+    let gateway = [APP_GATEWAY_ENV, UPDATE_GATEWAY_ENV]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.trim().is_empty());
+    if let Some(gateway) = gateway {
+        let url = Url::parse(gateway.trim()).context("parsing IPFS gateway URL")?;
+        builder = builder.base_url(url);
+    }
+    Ok(builder.build()?)
+}
+
+/// Installed packages, one directory per app, plus the skill files they
+/// contribute to the per-user skill directory.
+#[derive(Debug, Clone)]
+pub struct AgentAppPackageStore {
+    apps_dir: PathBuf,
+    skills_dir: PathBuf,
+}
+
+impl AgentAppPackageStore {
+    pub fn new(apps_dir: impl Into<PathBuf>, skills_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            apps_dir: apps_dir.into(),
+            skills_dir: skills_dir.into(),
+        }
+    }
+
+    /// `~/.advatar/apps` and `~/.advatar/skills`, when `HOME` is set.
+    pub fn default_location() -> Option<Self> {
+        let root = PathBuf::from(env::var_os("HOME")?).join(".advatar");
+        Some(Self::new(root.join("apps"), root.join("skills")))
+    }
+
+    /// Installed apps that still verify against the key they were installed
+    /// with and pass validation. Broken installs are skipped with a warning.
+    pub fn load(&self) -> Vec<AgentAppDefinition> {
+        let Ok(entries) = fs::read_dir(&self.apps_dir) else {
+            return Vec::new();
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        dirs.into_iter()
+            .filter_map(|dir| match self.load_dir(&dir) {
+                Ok(app) => Some(app),
+                Err(err) => {
+                    tracing::warn!("skipping installed agent app at {}: {err:#}", dir.display());
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn load_dir(&self, dir: &Path) -> Result<AgentAppDefinition> {
+        let raw = fs::read(dir.join(PACKAGE_FILE)).context("reading package")?;
+        let package = AgentAppPackage::from_slice(&raw)?;
+        let install = Self::read_install(dir)?;
+        package.verify(&[parse_public_key(&install.publisher_key)?])?;
+        let mut app = package.to_definition()?;
+        if dir.file_name().and_then(|name| name.to_str()) != Some(app.id.as_str()) {
+            bail!("package is for app `{}`", app.id);
+        }
+        // Installs from before skills were namespaced kept the declared id.
+        self.write_skill(&package)?;
+        app.package = Some(install);
+        Ok(app)
+    }
+
+    fn read_install(dir: &Path) -> Result<InstalledAgentAppPackage> {
+        let raw = fs::read_to_string(dir.join(INSTALL_FILE)).context("reading install record")?;
+        serde_json::from_str(&raw).context("parsing install record")
+    }
+
+    pub fn installed(&self, app_id: &str) -> Option<InstalledAgentAppPackage> {
+        Self::read_install(&self.app_dir(app_id).ok()?).ok()
+    }
+
+    /// Writes an already verified package, replacing any previous version.
+    pub fn write(
+        &self,
+        package: &AgentAppPackage,
+        install: &InstalledAgentAppPackage,
+    ) -> Result<()> {
+        let dir = self.app_dir(package.app_id())?;
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating agent app dir {}", dir.display()))?;
+        fs::write(dir.join(PACKAGE_FILE), serde_json::to_vec_pretty(package)?)
+            .with_context(|| format!("writing agent app package in {}", dir.display()))?;
+        fs::write(dir.join(INSTALL_FILE), serde_json::to_vec_pretty(install)?)
+            .with_context(|| format!("writing agent app install record in {}", dir.display()))?;
+
+        self.write_skill(package)
+    }
+
+    /// Writes the package's namespaced skill, or removes a stale one. Leaves
+    /// an up-to-date file untouched so skill hot-reload is not triggered.
+    fn write_skill(&self, package: &AgentAppPackage) -> Result<()> {
+        let skill_path = self.skill_path(package.app_id());
+        let Some(skill) = package.scoped_skill() else {
+            return remove_if_present(&skill_path);
+        };
+        let manifest = serde_json::json!({ "schema_version": 1, "skills": [skill] });
+        let current = fs::read(&skill_path)
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Value>(&raw).ok());
+        if current.as_ref() == Some(&manifest) {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.skills_dir)
+            .with_context(|| format!("creating skills dir {}", self.skills_dir.display()))?;
+        fs::write(&skill_path, serde_json::to_vec_pretty(&manifest)?)
+            .with_context(|| format!("writing app skill at {}", skill_path.display()))?;
+        Ok(())
+    }
+
+    /// Removes an installed app and its skill. Returns `false` when the app
+    /// was not installed.
+    pub fn remove(&self, app_id: &str) -> Result<bool> {
+        let dir = self.app_dir(app_id)?;
+        if !dir.exists() {
+            return Ok(false);
+        }
+        fs::remove_dir_all(&dir)
+            .with_context(|| format!("removing agent app dir {}", dir.display()))?;
+        remove_if_present(&self.skill_path(app_id))?;
+        Ok(true)
+    }
+
+    fn app_dir(&self, app_id: &str) -> Result<PathBuf> {
+        if !crate::agent_apps::is_valid_app_id(app_id) {
+            bail!("invalid agent app id `{app_id}`");
+        }
+        Ok(self.apps_dir.join(app_id))
+    }
+
+    fn skill_path(&self, app_id: &str) -> PathBuf {
+        self.skills_dir.join(format!("app-{app_id}.json"))
+    }
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("removing {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_apps::AgentAppRegistry;
+    use uuid::Uuid;
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir()
+            .join("dbrowser-agent-app-package-tests")
+            .join(Uuid::new_v4().to_string())
+    }
+
+    fn registry(root: &Path) -> AgentAppRegistry {
+        AgentAppRegistry::with_package_store(
+            root.join("agent_apps.json"),
+            Some(AgentAppPackageStore::new(
+                root.join("apps"),
+                root.join("skills"),
+            )),
+        )
+        .expect("registry")
+    }
+
+    fn contents(version: &str) -> AgentAppPackageContents {
+        serde_json::from_value(serde_json::json!({
+            "version": version,
+            "app": {
+                "id": "price-watch",
+                "name": "Price Watch",
+                "tagline": "Track prices",
+                "description": "Checks a product page for price drops.",
+                "requiredTools": ["browser.page_snapshot", "browser.navigate"],
+                "promptTemplate": "{{> task}}\n\nProduct: {{input}}",
+                "instructions": "{{> rules}}"
+            },
+            "templates": {
+                "task": "Open the product page and report the current price.",
+                "rules": "Never buy anything."
+            },
+            "skill": {
+                "id": "price-watch-skill",
+                "name": "Price watch",
+                "description": "Read-only shopping research.",
+                "tools": ["browser.page_snapshot", "browser.navigate"]
+            },
+            "icon": { "mediaType": "image/png", "data": BASE64.encode([0x89, b'P', b'N', b'G']) }
+        }))
+        .expect("contents")
+    }
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn signed_package_installs_with_templates_skill_and_icon() {
+        let root = temp_root();
+        let registry = registry(&root);
+        let key = signing_key(7);
+        let package = AgentAppPackage::sign(contents("1.0.0"), &key).expect("sign");
+        let raw = serde_json::to_vec(&package).expect("serialise");
+        let package = AgentAppPackage::from_slice(&raw).expect("parse");
+
+        let summary = registry
+            .install_package(&package, &[key.verifying_key()], "ipfs://example", 10)
+            .expect("install");
+        assert_eq!(summary.id, "price-watch");
+        assert!(summary
+            .icon
+            .as_deref()
+            .unwrap()
+            .starts_with("data:image/png;base64,"));
+        let info = summary.package.expect("package info");
+        assert_eq!(info.version, "1.0.0");
+        assert_eq!(info.source, "ipfs://example");
+
+        let app = registry.find("price-watch").expect("installed app");
+        assert_eq!(app.skill_id.as_deref(), Some("app-price-watch"));
+        let task = app
+            .render_task(Some("a kettle"), &serde_json::Map::new())
+            .expect("task");
+        assert!(task.starts_with("Never buy anything."));
+        assert!(task.contains("report the current price"));
+        assert!(task.contains("Product: a kettle"));
+
+        let skill: Value = serde_json::from_slice(
+            &fs::read(root.join("skills").join("app-price-watch.json")).expect("skill file"),
+        )
+        .expect("skill json");
+        assert_eq!(skill["skills"][0]["id"], "app-price-watch");
+
+        // A fresh registry picks the install up from disk.
+        assert!(self::registry(&root).find("price-watch").is_some());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn untrusted_or_tampered_packages_are_rejected() {
+        let root = temp_root();
+        let registry = registry(&root);
+        let key = signing_key(7);
+        let package = AgentAppPackage::sign(contents("1.0.0"), &key).expect("sign");
+
+        let err = registry
+            .install_package(&package, &[signing_key(8).verifying_key()], "pkg.json", 10)
+            .unwrap_err();
+        assert!(err.to_string().contains("not trusted"));
+
+        let mut tampered = package.clone();
+        tampered
+            .contents
+            .app
+            .required_tools
+            .push("wallet.spend".to_string());
+        let err = registry
+            .install_package(&tampered, &[key.verifying_key()], "pkg.json", 10)
+            .unwrap_err();
+        assert!(err.to_string().contains("signature verification failed"));
+        assert!(registry.list().is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn packaged_skills_cannot_replace_bundled_skills() {
+        let root = temp_root();
+        let registry = registry(&root);
+        let key = signing_key(7);
+        let mut contents = contents("1.0.0");
+        contents.skill.as_mut().expect("skill")["id"] = Value::from("research-ravi");
+        let package = AgentAppPackage::sign(contents, &key).expect("sign");
+        registry
+            .install_package(&package, &[key.verifying_key()], "pkg.json", 10)
+            .expect("install");
+
+        let skill_path = root.join("skills").join("app-price-watch.json");
+        let skill_id = |path: &Path| {
+            let skill: Value =
+                serde_json::from_slice(&fs::read(path).expect("skill file")).expect("skill json");
+            skill["skills"][0]["id"].clone()
+        };
+        assert_eq!(skill_id(&skill_path), "app-price-watch");
+        let app = registry.find("price-watch").expect("installed app");
+        assert_eq!(app.skill_id.as_deref(), Some("app-price-watch"));
+
+        // An install written before skills were namespaced is fixed on load.
+        let stale = serde_json::json!({
+            "schema_version": 1,
+            "skills": [package.contents.skill.clone().expect("skill")],
+        });
+        fs::write(&skill_path, serde_json::to_vec(&stale).expect("stale")).expect("write");
+        assert!(self::registry(&root).find("price-watch").is_some());
+        assert_eq!(skill_id(&skill_path), "app-price-watch");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn packages_with_unknown_tools_fail_validation() {
+        let root = temp_root();
+        let registry = registry(&root);
+        let key = signing_key(7);
+        let mut contents = contents("1.0.0");
+        contents
+            .app
+            .required_tools
+            .push("browser.teleport".to_string());
+        contents.app.blockchain_access = Some(
+            serde_json::from_value(serde_json::json!({
+                "readChainData": true,
+                "hostTools": ["dbrowser.tx.request_signature"]
+            }))
+            .expect("contract"),
+        );
+        let package = AgentAppPackage::sign(contents, &key).expect("sign");
+
+        let err = registry
+            .install_package(&package, &[key.verifying_key()], "pkg.json", 10)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("required tool `browser.teleport` does not exist"));
+        assert!(err.contains("`dbrowser.tx.request_signature` is not permitted"));
+        assert!(!root.join("apps").join("price-watch").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn updates_need_a_newer_version_from_the_same_publisher() {
+        let root = temp_root();
+        let registry = registry(&root);
+        let key = signing_key(7);
+        let trusted = [key.verifying_key(), signing_key(8).verifying_key()];
+        let v1 = AgentAppPackage::sign(contents("1.0.0"), &key).expect("sign");
+        registry
+            .install_package(&v1, &trusted, "pkg.json", 10)
+            .expect("install");
+        assert!(registry
+            .install_package(&v1, &trusted, "pkg.json", 10)
+            .is_err());
+
+        let err = registry
+            .update_package("price-watch", &v1, &trusted, "pkg.json", 20)
+            .unwrap_err();
+        assert!(err.to_string().contains("not newer"));
+
+        let other = AgentAppPackage::sign(contents("2.0.0"), &signing_key(8)).expect("sign");
+        let err = registry
+            .update_package("price-watch", &other, &trusted, "pkg.json", 20)
+            .unwrap_err();
+        assert!(err.to_string().contains("rather than its publisher"));
+
+        let mut next = contents("1.1.0");
+        next.skill = None;
+        let v2 = AgentAppPackage::sign(next, &key).expect("sign");
+        let summary = registry
+            .update_package("price-watch", &v2, &trusted, "ipfs://next", 20)
+            .expect("update");
+        assert_eq!(summary.package.unwrap().version, "1.1.0");
+        assert!(!root.join("skills").join("app-price-watch.json").exists());
+
+        assert!(registry
+            .uninstall_package("price-watch")
+            .expect("uninstall"));
+        assert!(registry.find("price-watch").is_none());
+        assert!(!root.join("apps").join("price-watch").exists());
+        assert!(!registry
+            .uninstall_package("price-watch")
+            .expect("second uninstall"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn template_includes_must_exist() {
+        let templates = BTreeMap::from([("a".to_string(), "alpha".to_string())]);
+        assert_eq!(
+            expand_templates("{{> a}} and {{input}}", &templates).unwrap(),
+            "alpha and {{input}}"
+        );
+        assert!(expand_templates("{{> missing}}", &templates).is_err());
+    }
+}
@@ -0,0 +1,667 @@
+//! Pipelines that chain several agent apps into one run.
+//!
+//! A pipeline is a list of stages run in order. The steps of a stage run
+//! concurrently and the next stage sees all of their outputs. Step inputs
+//! are templates (see [`crate::agent_app_templates`]) rendered against:
+//!
+//! - `input`: the free-text input the pipeline was started with,
+//! - `previous`: the previous stage's output; the step's output when the
+//!   stage had a single step, otherwise an object keyed by step id,
+//! - `steps`: the output of every finished step, keyed by step id.
+//!
+//! A step's output is its run's structured output, or its final answer as
+//! text when the app declares no output schema. A step without explicit
+//! `inputs` takes the fields of `previous` matching its app's declared
+//! inputs, or the whole of `previous` as its free-text input.
+//!
+//! Pipeline ids share the namespace of app ids so a schedule can name
+//! either.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use agent_core::{AgentLedger, LedgerEntry};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::agent::{AgentRunPriority, AgentRunRequest};
+use crate::agent_app_templates::{render_template, render_value, PromptTemplate};
+use crate::agent_apps::{is_valid_app_id, AgentAppDefinition, AgentAppRegistry};
+
+const DEFAULT_PIPELINES_PATH: &str = "configs/agent_app_pipelines.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPipeline {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub stages: Vec<AgentAppPipelineStage>,
+    /// Actions every step must stop and ask the user about, on top of the
+    /// gates of its own app.
+    #[serde(default)]
+    pub approval_gates: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPipelineStage {
+    pub id: String,
+    /// Steps run concurrently; more than one fans out.
+    pub steps: Vec<AgentAppPipelineStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPipelineStep {
+    /// Key of the step's output under `steps`; defaults to the app id.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub app_id: String,
+    /// Template for the app's free-text input.
+    #[serde(default)]
+    pub input: Option<String>,
+    /// Values of the app's declared inputs. Strings are templates; one that
+    /// is a single `{{path}}` tag passes the value through with its type.
+    #[serde(default)]
+    pub inputs: Map<String, Value>,
+}
+
+impl AgentAppPipelineStep {
+    pub fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.app_id)
+    }
+}
+
+/// How a step's run ended, as reported by a [`PipelineStepRunner`].
+#[derive(Debug, Clone)]
+pub struct PipelineStepOutcome {
+    pub run_id: String,
+    pub output: Value,
+    pub cancelled: bool,
+    pub ledger_entries: Vec<LedgerEntry>,
+}
+
+/// Starts the run of one pipeline step; implemented by the agent manager.
+#[async_trait]
+pub trait PipelineStepRunner: Send + Sync {
+    async fn run_step(&self, request: AgentRunRequest) -> Result<PipelineStepOutcome>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPipelineStepRun {
+    pub stage_id: String,
+    pub step_id: String,
+    pub app_id: String,
+    pub run_id: String,
+    pub output: Value,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppPipelineRun {
+    pub pipeline_id: String,
+    pub steps: Vec<AgentAppPipelineStepRun>,
+    /// Output of the last stage that ran.
+    pub output: Value,
+    /// Set when a step was cancelled; later stages did not run.
+    pub cancelled: bool,
+    /// Ledger entries of every step, in stage order, under one root.
+    pub ledger_root: Option<String>,
+    pub ledger_entries: Vec<LedgerEntry>,
+}
+
+impl AgentAppPipelineRun {
+    /// Run id of the last step that ran.
+    pub fn last_run_id(&self) -> Option<&str> {
+        self.steps.last().map(|step| step.run_id.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AgentAppPipelineManifest {
+    pipelines: Vec<AgentAppPipeline>,
+}
+
+impl AgentAppPipeline {
+    pub fn validate(&self, apps: &AgentAppRegistry) -> Result<()> {
+        let mut problems = Vec::new();
+        if !is_valid_app_id(&self.id) {
+            problems.push("id must be lowercase letters, digits and dashes".to_string());
+        } else if apps.find(&self.id).is_some() {
+            problems.push(format!("id `{}` is already used by an agent app", self.id));
+        }
+        if self.name.trim().is_empty() {
+            problems.push("name must not be empty".to_string());
+        }
+        if self.stages.is_empty() {
+            problems.push("needs at least one stage".to_string());
+        }
+        if self
+            .approval_gates
+            .iter()
+            .any(|gate| gate.trim().is_empty())
+        {
+            problems.push("approval gates must not be empty".to_string());
+        }
+
+        let mut stage_ids = HashSet::new();
+        let mut step_keys = HashSet::new();
+        for stage in &self.stages {
+            if stage.id.trim().is_empty() {
+                problems.push("stage ids must not be empty".to_string());
+            } else if !stage_ids.insert(stage.id.as_str()) {
+                problems.push(format!("duplicate stage `{}`", stage.id));
+            }
+            if stage.steps.is_empty() {
+                problems.push(format!("stage `{}` has no steps", stage.id));
+            }
+            for step in &stage.steps {
+                let key = step.key();
+                if !is_valid_app_id(key) {
+                    problems.push(format!(
+                        "step id `{key}` must be lowercase letters, digits and dashes"
+                    ));
+                } else if !step_keys.insert(key) {
+                    problems.push(format!("duplicate step `{key}`"));
+                }
+                problems.extend(
+                    step_problems(step, apps)
+                        .into_iter()
+                        .map(|problem| format!("step `{key}`: {problem}")),
+                );
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "agent app pipeline `{}` is invalid: {}",
+                self.id,
+                problems.join("; ")
+            ))
+        }
+    }
+
+    /// Runs the stages in order through `runner`. A failed step fails the
+    /// pipeline once the rest of its stage has finished; a cancelled step
+    /// stops it after its stage.
+    pub async fn run(
+        &self,
+        apps: &AgentAppRegistry,
+        runner: &dyn PipelineStepRunner,
+        input: Option<&str>,
+        schedule_id: Option<&str>,
+    ) -> Result<AgentAppPipelineRun> {
+        let mut context = json!({
+            "input": input.unwrap_or_default(),
+            "previous": null,
+            "steps": {},
+        });
+        let mut run = AgentAppPipelineRun {
+            pipeline_id: self.id.clone(),
+            steps: Vec::new(),
+            output: Value::Null,
+            cancelled: false,
+            ledger_root: None,
+            ledger_entries: Vec::new(),
+        };
+
+        for (index, stage) in self.stages.iter().enumerate() {
+            let mut requests = Vec::with_capacity(stage.steps.len());
+            for step in &stage.steps {
+                let app = apps
+                    .find(&step.app_id)
+                    .ok_or_else(|| anyhow!("agent app `{}` not found", step.app_id))?;
+                let request = self
+                    .step_request(&app, step, &context, index == 0, schedule_id)
+                    .with_context(|| format!("preparing step `{}`", step.key()))?;
+                requests.push(request);
+            }
+
+            let outcomes =
+                join_all(requests.into_iter().map(|request| runner.run_step(request))).await;
+
+            let mut outputs = Map::new();
+            let mut failures = Vec::new();
+            for (step, outcome) in stage.steps.iter().zip(outcomes) {
+                match outcome {
+                    Ok(outcome) => {
+                        run.cancelled |= outcome.cancelled;
+                        run.ledger_entries.extend(outcome.ledger_entries);
+                        outputs.insert(step.key().to_string(), outcome.output.clone());
+                        run.steps.push(AgentAppPipelineStepRun {
+                            stage_id: stage.id.clone(),
+                            step_id: step.key().to_string(),
+                            app_id: step.app_id.clone(),
+                            run_id: outcome.run_id,
+                            output: outcome.output,
+                            cancelled: outcome.cancelled,
+                        });
+                    }
+                    Err(err) => failures.push(format!("step `{}`: {err}", step.key())),
+                }
+            }
+            if !failures.is_empty() {
+                bail!(
+                    "pipeline `{}` failed in stage `{}`: {}",
+                    self.id,
+                    stage.id,
+                    failures.join("; ")
+                );
+            }
+
+            let previous = if outputs.len() == 1 {
+                outputs.values().next().cloned().unwrap_or_default()
+            } else {
+                Value::Object(outputs.clone())
+            };
+            if let Some(steps) = context["steps"].as_object_mut() {
+                steps.extend(outputs);
+            }
+            context["previous"] = previous.clone();
+            run.output = previous;
+            if run.cancelled {
+                break;
+            }
+        }
+
+        run.ledger_root = AgentLedger::compute_root_snapshot(&run.ledger_entries);
+        Ok(run)
+    }
+
+    fn step_request(
+        &self,
+        app: &AgentAppDefinition,
+        step: &AgentAppPipelineStep,
+        context: &Value,
+        first_stage: bool,
+        schedule_id: Option<&str>,
+    ) -> Result<AgentRunRequest> {
+        let previous = &context["previous"];
+        let mut inputs = if first_stage {
+            Map::new()
+        } else {
+            app.inputs_from_output(previous)
+        };
+        for (name, value) in &step.inputs {
+            inputs.insert(name.clone(), render_value(value, context)?);
+        }
+
+        let step_input = match &step.input {
+            Some(template) => Some(render_template(template, context)?),
+            None if first_stage => context["input"].as_str().map(str::to_string),
+            None if inputs.is_empty() => Some(match previous {
+                Value::String(text) => text.clone(),
+                other => serde_json::to_string_pretty(other).unwrap_or_default(),
+            }),
+            None => None,
+        };
+
+        let mut task = app.render_task(step_input.as_deref(), &inputs)?;
+        if !self.approval_gates.is_empty() {
+            task.push_str(&format!(
+                "\n\nThis run is a step of the `{}` pipeline. Stop and ask the user for approval before any of these:\n",
+                self.name
+            ));
+            for gate in &self.approval_gates {
+                task.push_str(&format!("- {}\n", gate.trim()));
+            }
+        }
+
+        Ok(AgentRunRequest {
+            task,
+            skill_id: app.skill_id.clone(),
+            no_egress: app.no_egress,
+            label: Some(format!("{} · {}", self.name, app.name)),
+            app_id: Some(app.id.clone()),
+            schedule_id: schedule_id.map(str::to_string),
+            mcp_resources: app.mcp_resources.clone(),
+            mcp_prompts: app.render_mcp_prompts(step_input.as_deref()),
+            priority: schedule_id.map(|_| AgentRunPriority::Low),
+            surface: None,
+            hand_over: false,
+        })
+    }
+}
+
+fn step_problems(step: &AgentAppPipelineStep, apps: &AgentAppRegistry) -> Vec<String> {
+    let mut problems = Vec::new();
+    match apps.find(&step.app_id) {
+        Some(app) => {
+            for name in step.inputs.keys() {
+                if !app.inputs.iter().any(|input| &input.name == name) {
+                    problems.push(format!("app `{}` has no input `{name}`", app.id));
+                }
+            }
+        }
+        None => problems.push(format!("agent app `{}` not found", step.app_id)),
+    }
+    if let Some(template) = &step.input {
+        if let Err(err) = PromptTemplate::parse(template) {
+            problems.push(format!("input: {err}"));
+        }
+    }
+    for (name, value) in &step.inputs {
+        if let Err(err) = render_value(value, &Value::Null) {
+            problems.push(format!("input `{name}`: {err}"));
+        }
+    }
+    problems
+}
+
+pub struct AgentAppPipelineRegistry {
+    pipelines: Arc<RwLock<Vec<AgentAppPipeline>>>,
+    manifest_path: PathBuf,
+}
+
+impl AgentAppPipelineRegistry {
+    pub fn load_default() -> Result<Self> {
+        Self::from_path(DEFAULT_PIPELINES_PATH)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let manifest_path = path.as_ref().to_path_buf();
+        let pipelines = Self::read_manifest(&manifest_path)?;
+        Ok(Self {
+            pipelines: Arc::new(RwLock::new(pipelines)),
+            manifest_path,
+        })
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            pipelines: Arc::new(RwLock::new(Vec::new())),
+            manifest_path: PathBuf::from(DEFAULT_PIPELINES_PATH),
+        }
+    }
+
+    pub fn list(&self) -> Vec<AgentAppPipeline> {
+        self.pipelines
+            .read()
+            .expect("agent app pipelines poisoned")
+            .clone()
+    }
+
+    pub fn find(&self, id: &str) -> Option<AgentAppPipeline> {
+        self.pipelines
+            .read()
+            .expect("agent app pipelines poisoned")
+            .iter()
+            .find(|pipeline| pipeline.id == id)
+            .cloned()
+    }
+
+    /// Validates `pipeline` against the installed apps and stores it,
+    /// replacing the pipeline with the same id.
+    pub fn upsert(
+        &self,
+        pipeline: AgentAppPipeline,
+        apps: &AgentAppRegistry,
+    ) -> Result<AgentAppPipeline> {
+        pipeline.validate(apps)?;
+        let mut pipelines = self
+            .pipelines
+            .write()
+            .expect("agent app pipelines poisoned");
+        match pipelines
+            .iter_mut()
+            .find(|existing| existing.id == pipeline.id)
+        {
+            Some(existing) => *existing = pipeline.clone(),
+            None => pipelines.push(pipeline.clone()),
+        }
+        Self::write_manifest(&self.manifest_path, &pipelines)?;
+        Ok(pipeline)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        let mut pipelines = self
+            .pipelines
+            .write()
+            .expect("agent app pipelines poisoned");
+        let before = pipelines.len();
+        pipelines.retain(|pipeline| pipeline.id != id);
+        let removed = pipelines.len() != before;
+        if removed {
+            Self::write_manifest(&self.manifest_path, &pipelines)?;
+        }
+        Ok(removed)
+    }
+
+    fn read_manifest(path: &Path) -> Result<Vec<AgentAppPipeline>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("reading agent app pipelines at {}", path.display()))?;
+        let manifest: AgentAppPipelineManifest = serde_json::from_str(&raw)
+            .with_context(|| format!("parsing agent app pipelines at {}", path.display()))?;
+        Ok(manifest.pipelines)
+    }
+
+    fn write_manifest(path: &Path, pipelines: &[AgentAppPipeline]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("creating agent app pipelines dir {}", parent.display())
+            })?;
+        }
+        let manifest = json!({ "pipelines": pipelines });
+        let raw = serde_json::to_string_pretty(&manifest)?;
+        fs::write(path, raw)
+            .with_context(|| format!("writing agent app pipelines at {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("dbrowser-agent-app-pipelines-tests")
+            .join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).expect("temp dir");
+        dir
+    }
+
+    fn apps() -> AgentAppRegistry {
+        let path = temp_dir().join("agent_apps.json");
+        let manifest = json!({
+            "apps": [
+                {
+                    "id": "flight-search",
+                    "name": "Flight Search",
+                    "tagline": "Find flights",
+                    "description": "Finds flights.",
+                    "inputs": [{ "name": "city", "type": "string", "required": true }],
+                    "promptTemplate": "Find flights to {{city}}.",
+                    "outputSchema": { "type": "object", "required": ["carrier", "price"] }
+                },
+                {
+                    "id": "hotel-search",
+                    "name": "Hotel Search",
+                    "tagline": "Find hotels",
+                    "description": "Finds hotels.",
+                    "promptTemplate": "Find hotels: {{input}}"
+                },
+                {
+                    "id": "trip-summary",
+                    "name": "Trip Summary",
+                    "tagline": "Summarise a trip",
+                    "description": "Summarises a trip.",
+                    "inputs": [
+                        { "name": "carrier", "type": "string" },
+                        { "name": "budget", "type": "number" }
+                    ],
+                    "promptTemplate": "Summarise {{carrier}} within {{budget}}: {{input}}"
+                }
+            ]
+        });
+        fs::write(&path, manifest.to_string()).expect("apps manifest");
+        AgentAppRegistry::from_path(path).expect("apps")
+    }
+
+    fn pipeline() -> AgentAppPipeline {
+        serde_json::from_value(json!({
+            "id": "trip-planner",
+            "name": "Trip Planner",
+            "approvalGates": ["paying for anything"],
+            "stages": [
+                {
+                    "id": "search",
+                    "steps": [
+                        { "appId": "flight-search", "inputs": { "city": "{{input}}" } },
+                        { "id": "hotels", "appId": "hotel-search" }
+                    ]
+                },
+                {
+                    "id": "summary",
+                    "steps": [{
+                        "appId": "trip-summary",
+                        "input": "Hotels: {{previous.hotels}}",
+                        "inputs": {
+                            "carrier": "{{steps.flight-search.carrier}}",
+                            "budget": "{{steps.flight-search.price}}"
+                        }
+                    }]
+                }
+            ]
+        }))
+        .expect("pipeline")
+    }
+
+    #[derive(Default)]
+    struct ScriptedRunner {
+        requests: Mutex<Vec<AgentRunRequest>>,
+        fail_app: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl PipelineStepRunner for ScriptedRunner {
+        async fn run_step(&self, request: AgentRunRequest) -> Result<PipelineStepOutcome> {
+            let app_id = request.app_id.clone().unwrap_or_default();
+            let run_id = format!("run-{}", self.requests.lock().unwrap().len());
+            self.requests.lock().unwrap().push(request);
+            if self.fail_app == Some(app_id.as_str()) {
+                bail!("model unavailable");
+            }
+            let output = match app_id.as_str() {
+                "flight-search" => json!({ "carrier": "TAP", "price": 412 }),
+                "hotel-search" => json!("Casa do Rio, 3 nights"),
+                _ => json!("Done."),
+            };
+            Ok(PipelineStepOutcome {
+                run_id,
+                output,
+                cancelled: false,
+                ledger_entries: Vec::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn stages_fan_out_and_map_outputs_into_the_next_app() {
+        let apps = apps();
+        let pipeline = pipeline();
+        pipeline.validate(&apps).expect("valid pipeline");
+
+        let runner = ScriptedRunner::default();
+        let run = pipeline
+            .run(&apps, &runner, Some("Lisbon"), Some("nightly"))
+            .await
+            .expect("pipeline run");
+
+        let requests = runner.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].task.starts_with("Find flights to Lisbon."));
+        assert!(requests[1].task.starts_with("Find hotels: Lisbon"));
+        assert!(requests[2]
+            .task
+            .starts_with("Summarise TAP within 412: Hotels: Casa do Rio, 3 nights"));
+        assert!(requests.iter().all(|request| {
+            request.task.ends_with("- paying for anything\n")
+                && request.schedule_id.as_deref() == Some("nightly")
+        }));
+
+        let step_ids: Vec<_> = run.steps.iter().map(|step| step.step_id.as_str()).collect();
+        assert_eq!(step_ids, ["flight-search", "hotels", "trip-summary"]);
+        assert_eq!(run.output, json!("Done."));
+        assert_eq!(run.last_run_id(), Some("run-2"));
+        assert!(!run.cancelled);
+    }
+
+    #[tokio::test]
+    async fn a_failed_step_fails_the_pipeline_after_its_stage() {
+        let apps = apps();
+        let runner = ScriptedRunner {
+            fail_app: Some("hotel-search"),
+            ..ScriptedRunner::default()
+        };
+        let err = pipeline()
+            .run(&apps, &runner, Some("Lisbon"), None)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("stage `search`"));
+        assert!(err.contains("step `hotels`: model unavailable"));
+        assert_eq!(runner.requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn registry_validates_and_persists_pipelines() {
+        let apps = apps();
+        let path = temp_dir().join("agent_app_pipelines.json");
+        let registry = AgentAppPipelineRegistry::from_path(&path).expect("registry");
+
+        let mut invalid = pipeline();
+        invalid.id = "hotel-search".to_string();
+        invalid.stages[1].steps[0]
+            .inputs
+            .insert("nights".to_string(), json!("{{#if x}}"));
+        invalid.stages[0].steps.push(AgentAppPipelineStep {
+            id: None,
+            app_id: "car-rental".to_string(),
+            input: None,
+            inputs: Map::new(),
+        });
+        let err = registry.upsert(invalid, &apps).unwrap_err().to_string();
+        for expected in [
+            "already used by an agent app",
+            "app `trip-summary` has no input `nights`",
+            "input `nights`: `{{#if x}}` is never closed",
+            "agent app `car-rental` not found",
+        ] {
+            assert!(err.contains(expected), "{err} should mention {expected}");
+        }
+
+        registry.upsert(pipeline(), &apps).expect("upsert");
+        let reloaded = AgentAppPipelineRegistry::from_path(&path).expect("reload");
+        assert_eq!(reloaded.list().len(), 1);
+        assert_eq!(
+            reloaded
+                .find("trip-planner")
+                .expect("pipeline")
+                .stages
+                .len(),
+            2
+        );
+        assert!(reloaded.delete("trip-planner").expect("delete"));
+        assert!(AgentAppPipelineRegistry::from_path(&path)
+            .expect("reload")
+            .list()
+            .is_empty());
+    }
+}
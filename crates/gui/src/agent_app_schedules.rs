@@ -2,11 +2,58 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike, Utc,
+};
+use chrono_tz::Tz;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 const DEFAULT_SCHEDULES_PATH: &str = "configs/agent_app_schedules.json";
+/// A missed occurrence this recent still counts as on time for the `skip`
+/// catch-up policy. Comfortably larger than the scheduler's poll interval.
+const MISSED_RUN_GRACE_MS: u64 = 2 * 60_000;
+/// Upper bound on runs queued by the `run_all` catch-up policy in one claim.
+const MAX_CATCH_UP_RUNS: usize = 24;
+/// Upper bound on missed occurrences scanned in one claim.
+const MAX_CATCH_UP_SCAN: usize = 10_000;
+/// Run records kept per schedule, oldest dropped first.
+const MAX_RUN_HISTORY: usize = 25;
+/// Ceiling for the doubling retry delay.
+const MAX_RETRY_BACKOFF_MS: u64 = 60 * 60_000;
+
+/// Emitted to the UI when a scheduled run fails or a schedule is disabled
+/// after repeated failures.
+pub const AGENT_APP_SCHEDULE_ALERT_EVENT: &str = "agent://schedule-alert";
+
+/// What to do with occurrences that passed while the browser was closed or
+/// asleep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleCatchUp {
+    /// Drop missed occurrences; only run when the latest one is on time.
+    Skip,
+    /// Run once for any number of missed occurrences.
+    #[default]
+    RunOnce,
+    /// Run once per missed occurrence, up to `MAX_CATCH_UP_RUNS`.
+    RunAll,
+}
+
+/// Daily window, in the schedule's time zone, during which runs are deferred
+/// to the end of the window. `start` may be later than `end` to span
+/// midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    /// `HH:MM`, inclusive.
+    pub start: String,
+    /// `HH:MM`, exclusive.
+    pub end: String,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,7 +63,37 @@ pub struct AgentAppScheduleDefinition {
     pub label: String,
     #[serde(default)]
     pub input: Option<String>,
+    /// Used when neither `cron` nor `run_at_ms` is set.
+    #[serde(default)]
     pub interval_minutes: u64,
+    /// Five-field cron expression (`minute hour day-of-month month
+    /// day-of-week`) or one of `@hourly`, `@daily`, `@weekly`, `@monthly`,
+    /// `@yearly`, evaluated in `timezone`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    /// Run exactly once at this time, then disable the schedule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_at_ms: Option<u64>,
+    /// IANA time zone name; defaults to UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+    /// Random delay of up to this many seconds added to each run.
+    #[serde(default)]
+    pub jitter_seconds: u64,
+    #[serde(default)]
+    pub catch_up: ScheduleCatchUp,
+    /// Retries after a failed run before waiting for the next occurrence.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles for each further retry.
+    #[serde(default = "default_retry_backoff_seconds")]
+    pub retry_backoff_seconds: u64,
+    /// Disable the schedule after this many failed runs in a row; 0 never
+    /// disables it.
+    #[serde(default = "default_disable_after_failures")]
+    pub disable_after_failures: u32,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
@@ -25,6 +102,70 @@ pub struct AgentAppScheduleDefinition {
     pub next_run_at_ms: Option<u64>,
     #[serde(default)]
     pub last_run_status: Option<String>,
+    /// Set while a claimed run is in flight; further occurrences queue up in
+    /// `pending_runs` instead of overlapping it.
+    #[serde(default)]
+    pub running_since_ms: Option<u64>,
+    /// Occurrences waiting for the in-flight run to finish.
+    #[serde(default)]
+    pub pending_runs: u32,
+    #[serde(default)]
+    pub retry_at_ms: Option<u64>,
+    /// Retry number of the in-flight or next retried run; 0 for a regular run.
+    #[serde(default)]
+    pub retry_attempt: u32,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Most recent runs, oldest first.
+    #[serde(default)]
+    pub history: Vec<ScheduleRunRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRunRecord {
+    pub started_at_ms: u64,
+    #[serde(default)]
+    pub finished_at_ms: Option<u64>,
+    /// `running`, `completed`, `failed`, `cancelled`, `missed` or
+    /// `interrupted`.
+    pub status: String,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub attempt: u32,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// How a claimed run ended, reported back through
+/// [`AgentAppScheduleRegistry::record_run_result`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleRunOutcome {
+    Completed {
+        run_id: String,
+    },
+    Cancelled {
+        run_id: String,
+    },
+    Failed {
+        run_id: Option<String>,
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppScheduleAlert {
+    pub schedule_id: String,
+    pub app_id: String,
+    pub label: String,
+    pub run_id: Option<String>,
+    pub error: String,
+    pub consecutive_failures: u32,
+    pub retry_at_ms: Option<u64>,
+    /// The schedule was disabled because of this failure.
+    pub disabled: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,7 +178,26 @@ pub struct AgentAppScheduleDraft {
     pub label: Option<String>,
     #[serde(default)]
     pub input: Option<String>,
+    #[serde(default)]
     pub interval_minutes: u64,
+    #[serde(default)]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub run_at_ms: Option<u64>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub jitter_seconds: u64,
+    #[serde(default)]
+    pub catch_up: ScheduleCatchUp,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_retry_backoff_seconds")]
+    pub retry_backoff_seconds: u64,
+    #[serde(default = "default_disable_after_failures")]
+    pub disable_after_failures: u32,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
 }
@@ -50,10 +210,24 @@ pub struct AgentAppScheduleSummary {
     pub label: String,
     pub input: Option<String>,
     pub interval_minutes: u64,
+    pub cron: Option<String>,
+    pub run_at_ms: Option<u64>,
+    pub timezone: Option<String>,
+    pub quiet_hours: Option<QuietHours>,
+    pub jitter_seconds: u64,
+    pub catch_up: ScheduleCatchUp,
+    pub max_retries: u32,
+    pub retry_backoff_seconds: u64,
+    pub disable_after_failures: u32,
     pub enabled: bool,
     pub last_run_at_ms: Option<u64>,
     pub next_run_at_ms: Option<u64>,
     pub last_run_status: Option<String>,
+    pub running: bool,
+    pub pending_runs: u32,
+    pub retry_at_ms: Option<u64>,
+    pub consecutive_failures: u32,
+    pub history: Vec<ScheduleRunRecord>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -65,12 +239,20 @@ fn default_enabled() -> bool {
     true
 }
 
-fn interval_millis(interval_minutes: u64) -> u64 {
-    interval_minutes.max(1).saturating_mul(60_000)
+fn default_max_retries() -> u32 {
+    2
 }
 
-fn compute_next_run_at_ms(interval_minutes: u64, from_ms: u64) -> u64 {
-    from_ms.saturating_add(interval_millis(interval_minutes))
+fn default_retry_backoff_seconds() -> u64 {
+    60
+}
+
+fn default_disable_after_failures() -> u32 {
+    5
+}
+
+fn interval_millis(interval_minutes: u64) -> u64 {
+    interval_minutes.max(1).saturating_mul(60_000)
 }
 
 impl AgentAppScheduleDefinition {
@@ -81,40 +263,427 @@ impl AgentAppScheduleDefinition {
             label: self.label.clone(),
             input: self.input.clone(),
             interval_minutes: self.interval_minutes,
+            cron: self.cron.clone(),
+            run_at_ms: self.run_at_ms,
+            timezone: self.timezone.clone(),
+            quiet_hours: self.quiet_hours.clone(),
+            jitter_seconds: self.jitter_seconds,
+            catch_up: self.catch_up,
+            max_retries: self.max_retries,
+            retry_backoff_seconds: self.retry_backoff_seconds,
+            disable_after_failures: self.disable_after_failures,
             enabled: self.enabled,
             last_run_at_ms: self.last_run_at_ms,
             next_run_at_ms: self.next_run_at_ms,
             last_run_status: self.last_run_status.clone(),
+            running: self.running_since_ms.is_some(),
+            pending_runs: self.pending_runs,
+            retry_at_ms: self.retry_at_ms,
+            consecutive_failures: self.consecutive_failures,
+            history: self.history.clone(),
         }
     }
 
-    fn from_draft(draft: AgentAppScheduleDraft, now_ms: u64) -> Self {
+    fn from_draft(draft: AgentAppScheduleDraft, now_ms: u64) -> Result<Self> {
         let id = draft.id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        let app_id = draft.app_id.trim().to_string();
         let label = draft
             .label
             .map(|value| value.trim().to_string())
             .filter(|value| !value.is_empty())
-            .unwrap_or_else(|| draft.app_id.clone());
-        let next_run_at_ms = if draft.enabled {
-            Some(compute_next_run_at_ms(draft.interval_minutes, now_ms))
-        } else {
-            None
-        };
+            .unwrap_or_else(|| app_id.clone());
+        let cron = draft.cron.and_then(trimmed_or_none);
+        if cron.is_some() && draft.run_at_ms.is_some() {
+            return Err(anyhow!("set either cron or runAtMs, not both"));
+        }
+        if cron.is_none() && draft.run_at_ms.is_none() && draft.interval_minutes == 0 {
+            return Err(anyhow!("interval_minutes must be at least 1"));
+        }
+        if let Some(run_at_ms) = draft.run_at_ms {
+            if draft.enabled && run_at_ms <= now_ms {
+                return Err(anyhow!("runAtMs must be in the future"));
+            }
+        }
 
-        Self {
+        let mut schedule = Self {
             id,
-            app_id: draft.app_id.trim().to_string(),
+            app_id,
             label,
             input: draft.input.and_then(trimmed_or_none),
-            interval_minutes: draft.interval_minutes.max(1),
+            interval_minutes: draft.interval_minutes,
+            cron,
+            run_at_ms: draft.run_at_ms,
+            timezone: draft.timezone.and_then(trimmed_or_none),
+            quiet_hours: draft.quiet_hours,
+            jitter_seconds: draft.jitter_seconds,
+            catch_up: draft.catch_up,
+            max_retries: draft.max_retries,
+            retry_backoff_seconds: draft.retry_backoff_seconds,
+            disable_after_failures: draft.disable_after_failures,
             enabled: draft.enabled,
             last_run_at_ms: None,
-            next_run_at_ms,
+            next_run_at_ms: None,
             last_run_status: Some("scheduled".to_string()),
+            running_since_ms: None,
+            pending_runs: 0,
+            retry_at_ms: None,
+            retry_attempt: 0,
+            consecutive_failures: 0,
+            history: Vec::new(),
+        };
+        if schedule.cron.is_none() && schedule.run_at_ms.is_none() {
+            schedule.interval_minutes = schedule.interval_minutes.max(1);
+        }
+        let timing = schedule.timing()?;
+        if schedule.enabled {
+            schedule.next_run_at_ms = timing.next_run_after(now_ms);
+        }
+        Ok(schedule)
+    }
+
+    fn timing(&self) -> Result<ScheduleTiming> {
+        let rule = if let Some(run_at_ms) = self.run_at_ms {
+            ScheduleRule::Once(run_at_ms)
+        } else if let Some(expression) = &self.cron {
+            ScheduleRule::Cron(CronExpression::parse(expression)?)
+        } else {
+            ScheduleRule::Interval(interval_millis(self.interval_minutes))
+        };
+        let timezone = match self.timezone.as_deref() {
+            Some(name) => name
+                .parse::<Tz>()
+                .map_err(|_| anyhow!("unknown time zone `{name}`"))?,
+            None => Tz::UTC,
+        };
+        let quiet_hours = match &self.quiet_hours {
+            Some(window) => {
+                let start = parse_clock_time(&window.start)?;
+                let end = parse_clock_time(&window.end)?;
+                if start == end {
+                    return Err(anyhow!("quiet hours start and end must differ"));
+                }
+                Some((start, end))
+            }
+            None => None,
+        };
+        Ok(ScheduleTiming {
+            rule,
+            timezone,
+            quiet_hours,
+            jitter_ms: self.jitter_seconds.saturating_mul(1_000),
+        })
+    }
+
+    /// A run is in flight, queued or waiting to be retried.
+    fn has_outstanding_runs(&self) -> bool {
+        self.running_since_ms.is_some() || self.pending_runs > 0 || self.retry_at_ms.is_some()
+    }
+
+    fn retry_backoff_ms(&self, attempt: u32) -> u64 {
+        let base = self.retry_backoff_seconds.max(1).saturating_mul(1_000);
+        let factor = 1u64 << attempt.saturating_sub(1).min(20);
+        base.saturating_mul(factor).min(MAX_RETRY_BACKOFF_MS)
+    }
+
+    fn push_history(&mut self, record: ScheduleRunRecord) {
+        self.history.push(record);
+        if self.history.len() > MAX_RUN_HISTORY {
+            let excess = self.history.len() - MAX_RUN_HISTORY;
+            self.history.drain(..excess);
+        }
+    }
+
+    fn start_run(&mut self, now_ms: u64) {
+        self.running_since_ms = Some(now_ms);
+        self.last_run_at_ms = Some(now_ms);
+        self.last_run_status = Some("running".to_string());
+        let attempt = self.retry_attempt;
+        self.push_history(ScheduleRunRecord {
+            started_at_ms: now_ms,
+            finished_at_ms: None,
+            status: "running".to_string(),
+            run_id: None,
+            attempt,
+            error: None,
+        });
+    }
+
+    /// Closes the in-flight run record, if any.
+    fn finish_run(
+        &mut self,
+        now_ms: u64,
+        status: &str,
+        run_id: Option<String>,
+        error: Option<String>,
+    ) {
+        self.running_since_ms = None;
+        self.last_run_status = Some(status.to_string());
+        if let Some(record) = self
+            .history
+            .iter_mut()
+            .rev()
+            .find(|record| record.finished_at_ms.is_none())
+        {
+            record.finished_at_ms = Some(now_ms);
+            record.status = status.to_string();
+            record.run_id = run_id;
+            record.error = error;
+        }
+    }
+
+    /// One-off schedules switch off once nothing is left to run.
+    fn disable_if_exhausted(&mut self) {
+        if self.next_run_at_ms.is_none() && !self.has_outstanding_runs() {
+            self.enabled = false;
         }
     }
 }
 
+enum ScheduleRule {
+    Interval(u64),
+    Cron(CronExpression),
+    Once(u64),
+}
+
+/// Parsed timing fields of a schedule. All times are Unix milliseconds.
+struct ScheduleTiming {
+    rule: ScheduleRule,
+    timezone: Tz,
+    quiet_hours: Option<(NaiveTime, NaiveTime)>,
+    jitter_ms: u64,
+}
+
+impl ScheduleTiming {
+    /// First occurrence strictly after `after_ms`, moved out of quiet hours.
+    fn next_occurrence(&self, after_ms: u64) -> Option<u64> {
+        let next = match &self.rule {
+            ScheduleRule::Interval(interval_ms) => Some(after_ms.saturating_add(*interval_ms)),
+            ScheduleRule::Cron(expression) => expression.next_after(self.timezone, after_ms),
+            ScheduleRule::Once(run_at_ms) => (*run_at_ms > after_ms).then_some(*run_at_ms),
+        }?;
+        Some(self.defer_past_quiet_hours(next))
+    }
+
+    /// Next time to store in `next_run_at_ms`, with jitter applied.
+    fn next_run_after(&self, after_ms: u64) -> Option<u64> {
+        let next = self.next_occurrence(after_ms)?;
+        if self.jitter_ms == 0 {
+            return Some(next);
+        }
+        let jitter = rand::thread_rng().gen_range(0..=self.jitter_ms);
+        Some(next.saturating_add(jitter))
+    }
+
+    fn defer_past_quiet_hours(&self, at_ms: u64) -> u64 {
+        let Some((start, end)) = self.quiet_hours else {
+            return at_ms;
+        };
+        let Some(local) = to_local(self.timezone, at_ms) else {
+            return at_ms;
+        };
+        let time = local.time();
+        let date = local.date_naive();
+        let end_date = if start < end {
+            if time < start || time >= end {
+                return at_ms;
+            }
+            date
+        } else if time >= start {
+            match date.succ_opt() {
+                Some(next) => next,
+                None => return at_ms,
+            }
+        } else if time < end {
+            date
+        } else {
+            return at_ms;
+        };
+        resolve_local(self.timezone, end_date.and_time(end))
+            .map(|end| end.max(at_ms))
+            .unwrap_or(at_ms)
+    }
+}
+
+fn parse_clock_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| anyhow!("invalid quiet hours time `{value}`; expected HH:MM"))
+}
+
+fn to_local(timezone: Tz, at_ms: u64) -> Option<DateTime<Tz>> {
+    let at_ms = i64::try_from(at_ms).ok()?;
+    Utc.timestamp_millis_opt(at_ms)
+        .single()
+        .map(|at| at.with_timezone(&timezone))
+}
+
+/// Converts a wall-clock time to Unix milliseconds. Ambiguous times (clocks
+/// going back) resolve to the first occurrence; times skipped by clocks going
+/// forward resolve to the first valid minute after the gap.
+fn resolve_local(timezone: Tz, local: NaiveDateTime) -> Option<u64> {
+    let resolved = match timezone.from_local_datetime(&local) {
+        LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at,
+        LocalResult::None => (1..=24 * 60).find_map(|minutes| {
+            timezone
+                .from_local_datetime(&(local + Duration::minutes(minutes)))
+                .earliest()
+        })?,
+    };
+    u64::try_from(resolved.timestamp_millis()).ok()
+}
+
+/// Standard five-field cron expression. Fields accept `*`, numbers, ranges
+/// (`1-5`), steps (`*/15`, `0-30/10`) and lists; months and weekdays also
+/// accept three-letter names. As in Vixie cron, when neither day-of-month nor
+/// day-of-week starts with `*` a day matching either runs; otherwise a day
+/// must match both (so `*/2` in one day field still restricts the other).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpression {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+/// How far ahead to search; covers `29 2 *` style expressions across leap
+/// years.
+const CRON_SEARCH_DAYS: usize = 366 * 8;
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim().to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            other => other.to_string(),
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow!(
+                "cron expression `{expression}` must have 5 fields (minute hour day-of-month month day-of-week), found {}",
+                fields.len()
+            ));
+        }
+        let field = |index: usize, name: &str, min: u32, max: u32, names: &[&str]| {
+            parse_cron_field(fields[index], min, max, names)
+                .with_context(|| format!("invalid {name} field in cron expression `{expression}`"))
+        };
+        let minutes = field(0, "minute", 0, 59, &[])?;
+        let hours = field(1, "hour", 0, 23, &[])?;
+        let days_of_month = field(2, "day-of-month", 1, 31, &[])?;
+        let months = field(3, "month", 1, 12, &MONTH_NAMES)?;
+        let mut days_of_week = field(4, "day-of-week", 0, 7, &WEEKDAY_NAMES)?;
+        // Both 0 and 7 mean Sunday.
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            day_of_month_restricted: !fields[2].starts_with('*'),
+            day_of_week_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day_of_month = self.days_of_month & (1 << date.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.day_of_month_restricted && self.day_of_week_restricted {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+
+    /// First matching instant strictly after `after_ms`, evaluated on the
+    /// wall clock of `timezone`.
+    pub fn next_after(&self, timezone: Tz, after_ms: u64) -> Option<u64> {
+        let after = to_local(timezone, after_ms)?.naive_local();
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..CRON_SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in (0..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                    for minute in (0..60).filter(|minute| self.minutes & (1 << minute) != 0) {
+                        let local = date.and_hms_opt(hour, minute, 0)?;
+                        if local < start {
+                            continue;
+                        }
+                        match resolve_local(timezone, local) {
+                            Some(at_ms) if at_ms > after_ms => return Some(at_ms),
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let value = |token: &str| -> Result<u32> {
+        let parsed = match names.iter().position(|name| *name == token) {
+            // Names count from the field minimum: `jan` is 1, `sun` is 0.
+            Some(index) => index as u32 + min,
+            None => token
+                .parse::<u32>()
+                .map_err(|_| anyhow!("`{token}` is not a number"))?,
+        };
+        if parsed < min || parsed > max {
+            return Err(anyhow!("`{token}` is outside {min}-{max}"));
+        }
+        Ok(parsed)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| anyhow!("step `{step}` must be a positive number"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let (start, end) = (value(start)?, value(end)?);
+            if start > end {
+                return Err(anyhow!("range `{range}` is reversed"));
+            }
+            (start, end)
+        } else {
+            let start = value(range)?;
+            // `5/15` means every 15 starting at 5.
+            (start, if part.contains('/') { max } else { start })
+        };
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
 pub struct AgentAppScheduleRegistry {
     schedules: Arc<RwLock<Vec<AgentAppScheduleDefinition>>>,
     manifest_path: PathBuf,
@@ -127,7 +696,21 @@ impl AgentAppScheduleRegistry {
 
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
         let manifest_path = path.as_ref().to_path_buf();
-        let schedules = Self::read_manifest(&manifest_path)?;
+        let mut schedules = Self::read_manifest(&manifest_path)?;
+        // Runs in flight when the browser last exited will never report
+        // back; release them so the schedules can run again.
+        let now_ms = Utc::now().timestamp_millis().max(0) as u64;
+        for schedule in schedules.iter_mut() {
+            if schedule.running_since_ms.is_some() {
+                schedule.finish_run(
+                    now_ms,
+                    "interrupted",
+                    None,
+                    Some("browser exited during the run".to_string()),
+                );
+                schedule.disable_if_exhausted();
+            }
+        }
         Ok(Self {
             schedules: Arc::new(RwLock::new(schedules)),
             manifest_path,
@@ -160,10 +743,7 @@ impl AgentAppScheduleRegistry {
 
     pub fn upsert(&self, draft: AgentAppScheduleDraft, now_ms: u64) -> Result<AgentAppScheduleSummary> {
         if draft.app_id.trim().is_empty() {
-            return Err(anyhow::anyhow!("app_id must not be empty"));
-        }
-        if draft.interval_minutes == 0 {
-            return Err(anyhow::anyhow!("interval_minutes must be at least 1"));
+            return Err(anyhow!("app_id must not be empty"));
         }
 
         let mut schedules = self
@@ -171,35 +751,24 @@ impl AgentAppScheduleRegistry {
             .write()
             .expect("agent app schedules poisoned");
 
-        let summary = if let Some(id) = draft.id.as_deref().filter(|value| !value.trim().is_empty()) {
+        let summary = if let Some(id) = draft.id.clone().filter(|value| !value.trim().is_empty()) {
             let schedule = schedules
                 .iter_mut()
                 .find(|schedule| schedule.id == id)
-                .ok_or_else(|| anyhow::anyhow!("agent app schedule `{id}` not found"))?;
-            schedule.app_id = draft.app_id.trim().to_string();
-            schedule.label = draft
-                .label
-                .as_deref()
-                .map(str::trim)
-                .filter(|value| !value.is_empty())
-                .unwrap_or(schedule.app_id.as_str())
-                .to_string();
-            schedule.input = draft.input.and_then(trimmed_or_none);
-            schedule.interval_minutes = draft.interval_minutes.max(1);
-            schedule.enabled = draft.enabled;
-            schedule.next_run_at_ms = if schedule.enabled {
-                Some(compute_next_run_at_ms(schedule.interval_minutes, now_ms))
-            } else {
-                None
-            };
-            schedule.last_run_status = Some(if schedule.enabled {
+                .ok_or_else(|| anyhow!("agent app schedule `{id}` not found"))?;
+            let mut updated = AgentAppScheduleDefinition::from_draft(draft, now_ms)?;
+            updated.last_run_at_ms = schedule.last_run_at_ms;
+            updated.running_since_ms = schedule.running_since_ms;
+            updated.history = std::mem::take(&mut schedule.history);
+            updated.last_run_status = Some(if updated.enabled {
                 "scheduled".to_string()
             } else {
                 "disabled".to_string()
             });
+            *schedule = updated;
             schedule.summary()
         } else {
-            let schedule = AgentAppScheduleDefinition::from_draft(draft, now_ms);
+            let schedule = AgentAppScheduleDefinition::from_draft(draft, now_ms)?;
             let summary = schedule.summary();
             schedules.push(schedule);
             summary
@@ -223,46 +792,179 @@ impl AgentAppScheduleRegistry {
         Ok(removed)
     }
 
+    /// Returns the schedules to run now, advancing each one's next run.
+    /// At most one run per schedule is in flight: occurrences that come due
+    /// meanwhile, including those replayed by the `run_all` catch-up policy,
+    /// wait in `pending_runs`, and failed runs are retried with backoff.
     pub fn claim_due(&self, now_ms: u64) -> Result<Vec<AgentAppScheduleDefinition>> {
         let mut schedules = self
             .schedules
             .write()
             .expect("agent app schedules poisoned");
         let mut due = Vec::new();
+        let mut changed = false;
 
         for schedule in schedules.iter_mut() {
             if !schedule.enabled {
                 continue;
             }
-            let next_run_at_ms = schedule
-                .next_run_at_ms
-                .unwrap_or_else(|| compute_next_run_at_ms(schedule.interval_minutes, now_ms));
-            if next_run_at_ms > now_ms {
-                continue;
+            let timing = match schedule.timing() {
+                Ok(timing) => timing,
+                Err(err) => {
+                    tracing::warn!(
+                        schedule = %schedule.id,
+                        error = %err,
+                        "disabling invalid agent app schedule"
+                    );
+                    schedule.enabled = false;
+                    schedule.next_run_at_ms = None;
+                    schedule.last_run_status = Some("invalid".to_string());
+                    changed = true;
+                    continue;
+                }
+            };
+            match schedule.next_run_at_ms {
+                None if !schedule.has_outstanding_runs() => {
+                    schedule.next_run_at_ms = timing.next_run_after(now_ms);
+                    changed = true;
+                    continue;
+                }
+                Some(next_run_at_ms) if next_run_at_ms <= now_ms => {
+                    let mut missed = 1;
+                    let mut latest = next_run_at_ms;
+                    while missed < MAX_CATCH_UP_SCAN {
+                        match timing.next_occurrence(latest) {
+                            Some(next) if next <= now_ms => {
+                                missed += 1;
+                                latest = next;
+                            }
+                            _ => break,
+                        }
+                    }
+                    let runs = match schedule.catch_up {
+                        ScheduleCatchUp::RunAll => missed.min(MAX_CATCH_UP_RUNS),
+                        ScheduleCatchUp::RunOnce => 1,
+                        ScheduleCatchUp::Skip if now_ms - latest <= MISSED_RUN_GRACE_MS => 1,
+                        ScheduleCatchUp::Skip => 0,
+                    } as u32;
+
+                    schedule.next_run_at_ms = timing.next_run_after(now_ms);
+                    if runs == 0 {
+                        schedule.last_run_status = Some("missed".to_string());
+                        schedule.push_history(ScheduleRunRecord {
+                            started_at_ms: now_ms,
+                            finished_at_ms: Some(now_ms),
+                            status: "missed".to_string(),
+                            run_id: None,
+                            attempt: 0,
+                            error: None,
+                        });
+                    } else if schedule.catch_up == ScheduleCatchUp::RunAll {
+                        schedule.pending_runs =
+                            (schedule.pending_runs + runs).min(MAX_CATCH_UP_RUNS as u32);
+                    } else {
+                        schedule.pending_runs = schedule.pending_runs.max(1);
+                    }
+                    changed = true;
+                }
+                _ => {}
             }
-            schedule.last_run_at_ms = Some(now_ms);
-            schedule.next_run_at_ms = Some(compute_next_run_at_ms(schedule.interval_minutes, now_ms));
-            schedule.last_run_status = Some("running".to_string());
-            due.push(schedule.clone());
+
+            if schedule.running_since_ms.is_none() {
+                if let Some(retry_at_ms) = schedule.retry_at_ms {
+                    if retry_at_ms <= now_ms {
+                        schedule.retry_at_ms = None;
+                        schedule.start_run(now_ms);
+                        due.push(schedule.clone());
+                        changed = true;
+                    }
+                } else if schedule.pending_runs > 0 {
+                    schedule.pending_runs -= 1;
+                    schedule.retry_attempt = 0;
+                    schedule.start_run(now_ms);
+                    due.push(schedule.clone());
+                    changed = true;
+                }
+            }
+            schedule.disable_if_exhausted();
         }
 
-        if !due.is_empty() {
+        if changed {
             Self::write_manifest(&self.manifest_path, &schedules)?;
         }
 
         Ok(due)
     }
 
-    pub fn record_run_result(&self, id: &str, status: &str) -> Result<()> {
+    /// Records how a claimed run ended. Failures schedule a retry or, after
+    /// `disable_after_failures` in a row, disable the schedule; the returned
+    /// alert should be shown to the user.
+    pub fn record_run_result(
+        &self,
+        id: &str,
+        outcome: ScheduleRunOutcome,
+        now_ms: u64,
+    ) -> Result<Option<AgentAppScheduleAlert>> {
         let mut schedules = self
             .schedules
             .write()
             .expect("agent app schedules poisoned");
-        if let Some(schedule) = schedules.iter_mut().find(|schedule| schedule.id == id) {
-            schedule.last_run_status = Some(status.to_string());
-            Self::write_manifest(&self.manifest_path, &schedules)?;
-        }
-        Ok(())
+        let Some(schedule) = schedules.iter_mut().find(|schedule| schedule.id == id) else {
+            return Ok(None);
+        };
+
+        let alert = match outcome {
+            ScheduleRunOutcome::Completed { run_id } => {
+                schedule.finish_run(now_ms, "completed", Some(run_id), None);
+                schedule.consecutive_failures = 0;
+                schedule.retry_attempt = 0;
+                None
+            }
+            ScheduleRunOutcome::Cancelled { run_id } => {
+                schedule.finish_run(now_ms, "cancelled", Some(run_id), None);
+                schedule.retry_attempt = 0;
+                None
+            }
+            ScheduleRunOutcome::Failed { run_id, error } => {
+                schedule.finish_run(now_ms, "failed", run_id.clone(), Some(error.clone()));
+                schedule.consecutive_failures += 1;
+                let disabled = schedule.disable_after_failures > 0
+                    && schedule.consecutive_failures >= schedule.disable_after_failures;
+                if disabled {
+                    schedule.enabled = false;
+                    schedule.next_run_at_ms = None;
+                    schedule.pending_runs = 0;
+                    schedule.retry_at_ms = None;
+                    schedule.retry_attempt = 0;
+                    schedule.last_run_status = Some("disabled_after_failures".to_string());
+                } else if schedule.retry_attempt < schedule.max_retries {
+                    schedule.retry_attempt += 1;
+                    let retry_at_ms =
+                        now_ms.saturating_add(schedule.retry_backoff_ms(schedule.retry_attempt));
+                    schedule.retry_at_ms = Some(match schedule.timing() {
+                        Ok(timing) => timing.defer_past_quiet_hours(retry_at_ms),
+                        Err(_) => retry_at_ms,
+                    });
+                    schedule.last_run_status = Some("retry_scheduled".to_string());
+                } else {
+                    schedule.retry_attempt = 0;
+                }
+                Some(AgentAppScheduleAlert {
+                    schedule_id: schedule.id.clone(),
+                    app_id: schedule.app_id.clone(),
+                    label: schedule.label.clone(),
+                    run_id,
+                    error,
+                    consecutive_failures: schedule.consecutive_failures,
+                    retry_at_ms: schedule.retry_at_ms,
+                    disabled,
+                })
+            }
+        };
+        schedule.disable_if_exhausted();
+
+        Self::write_manifest(&self.manifest_path, &schedules)?;
+        Ok(alert)
     }
 
     fn read_manifest(path: &Path) -> Result<Vec<AgentAppScheduleDefinition>> {
@@ -310,6 +1012,39 @@ mod tests {
         dir.join(name)
     }
 
+    fn draft(app_id: &str) -> AgentAppScheduleDraft {
+        AgentAppScheduleDraft {
+            id: None,
+            app_id: app_id.to_string(),
+            label: None,
+            input: None,
+            interval_minutes: 0,
+            cron: None,
+            run_at_ms: None,
+            timezone: None,
+            quiet_hours: None,
+            jitter_seconds: 0,
+            catch_up: ScheduleCatchUp::default(),
+            max_retries: 2,
+            retry_backoff_seconds: 60,
+            disable_after_failures: 3,
+            enabled: true,
+        }
+    }
+
+    fn utc_ms(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+            .timestamp_millis() as u64
+    }
+
+    fn next_cron(expression: &str, timezone: Tz, after_ms: u64) -> u64 {
+        CronExpression::parse(expression)
+            .unwrap()
+            .next_after(timezone, after_ms)
+            .unwrap()
+    }
+
     #[test]
     fn upsert_persists_schedule_manifest() {
         let path = temp_manifest_path("schedules.json");
@@ -318,12 +1053,10 @@ mod tests {
         let summary = registry
             .upsert(
                 AgentAppScheduleDraft {
-                    id: None,
-                    app_id: "research-brief".to_string(),
                     label: Some("Morning brief".to_string()),
                     input: Some("Summarise today's web3 funding".to_string()),
                     interval_minutes: 60,
-                    enabled: true,
+                    ..draft("research-brief")
                 },
                 1_000,
             )
@@ -344,12 +1077,9 @@ mod tests {
         let summary = registry
             .upsert(
                 AgentAppScheduleDraft {
-                    id: None,
-                    app_id: "workflow-designer".to_string(),
                     label: Some("Ops cadence".to_string()),
-                    input: None,
                     interval_minutes: 15,
-                    enabled: true,
+                    ..draft("workflow-designer")
                 },
                 0,
             )
@@ -371,12 +1101,8 @@ mod tests {
         let summary = registry
             .upsert(
                 AgentAppScheduleDraft {
-                    id: None,
-                    app_id: "research-brief".to_string(),
-                    label: None,
-                    input: None,
                     interval_minutes: 30,
-                    enabled: true,
+                    ..draft("research-brief")
                 },
                 100,
             )
@@ -385,4 +1111,464 @@ mod tests {
         assert!(registry.delete(&summary.id).expect("delete schedule"));
         assert!(registry.list().is_empty());
     }
+
+    #[test]
+    fn cron_fields_support_lists_ranges_steps_and_names() {
+        let expression = CronExpression::parse("*/20 9-17/4 * jan,jul MON-fri").unwrap();
+        assert_eq!(expression.minutes, (1 << 0) | (1 << 20) | (1 << 40));
+        assert_eq!(expression.hours, (1 << 9) | (1 << 13) | (1 << 17));
+        assert_eq!(expression.months, (1 << 1) | (1 << 7));
+        assert_eq!(expression.days_of_week, 0b0111110);
+        assert_eq!(
+            CronExpression::parse("0 0 * * 7").unwrap().days_of_week,
+            CronExpression::parse("@weekly").unwrap().days_of_week
+        );
+
+        assert!(CronExpression::parse("0 0 * *").is_err());
+        assert!(CronExpression::parse("60 * * * *").is_err());
+        assert!(CronExpression::parse("0 5-1 * * *").is_err());
+        assert!(CronExpression::parse("*/0 * * * *").is_err());
+
+        // Day-of-month and day-of-week both restricted: either matches.
+        let after = utc_ms(2025, 6, 1, 12, 0); // a Sunday
+        assert_eq!(
+            next_cron("0 8 15 * mon", Tz::UTC, after),
+            utc_ms(2025, 6, 2, 8, 0)
+        );
+    }
+
+    #[test]
+    fn starred_day_fields_are_anded_like_vixie_cron() {
+        // 2025-06-01 is a Sunday.
+        let after = utc_ms(2025, 6, 1, 12, 0);
+        // Odd days of the month that are also Sun/Tue/Thu/Sat: June 2nd (an
+        // even Monday) must not run just because both fields start with `*`.
+        assert_eq!(
+            next_cron("0 8 */2 * */2", Tz::UTC, after),
+            utc_ms(2025, 6, 3, 8, 0)
+        );
+        // A starred day-of-week still filters a restricted day-of-month.
+        assert_eq!(
+            next_cron("0 8 1-10 * */2", Tz::UTC, after),
+            utc_ms(2025, 6, 3, 8, 0)
+        );
+        // Two restricted fields keep OR semantics.
+        assert_eq!(
+            next_cron("0 8 1-10/2 * mon", Tz::UTC, after),
+            utc_ms(2025, 6, 2, 8, 0)
+        );
+    }
+
+    #[test]
+    fn cron_follows_wall_clock_across_dst_transitions() {
+        let new_york = Tz::America__New_York;
+
+        // Clocks jump from 02:00 to 03:00 on 2025-03-09; 02:30 runs at 03:00.
+        let skipped = next_cron("30 2 * * *", new_york, utc_ms(2025, 3, 8, 12, 0));
+        assert_eq!(skipped, utc_ms(2025, 3, 9, 7, 0));
+        let following = next_cron("30 2 * * *", new_york, skipped);
+        assert_eq!(following, utc_ms(2025, 3, 10, 6, 30));
+
+        // Clocks fall back from 02:00 to 01:00 on 2025-11-02; 01:30 runs once.
+        let first = next_cron("30 1 * * *", new_york, utc_ms(2025, 11, 1, 12, 0));
+        assert_eq!(first, utc_ms(2025, 11, 2, 5, 30));
+        let second = next_cron("30 1 * * *", new_york, first);
+        assert_eq!(second, utc_ms(2025, 11, 3, 6, 30));
+
+        // Hourly runs skip the repeated hour instead of running twice.
+        let mut at = utc_ms(2025, 11, 2, 4, 30);
+        let mut runs = Vec::new();
+        for _ in 0..3 {
+            at = next_cron("0 * * * *", new_york, at);
+            runs.push(at);
+        }
+        assert_eq!(
+            runs,
+            vec![
+                utc_ms(2025, 11, 2, 5, 0),
+                utc_ms(2025, 11, 2, 7, 0),
+                utc_ms(2025, 11, 2, 8, 0)
+            ]
+        );
+
+        // 09:00 in Berlin stays at 09:00 local on both sides of the change.
+        let berlin = Tz::Europe__Berlin;
+        let before = next_cron("0 9 * * *", berlin, utc_ms(2025, 3, 29, 0, 0));
+        assert_eq!(before, utc_ms(2025, 3, 29, 8, 0));
+        let after = next_cron("0 9 * * *", berlin, utc_ms(2025, 3, 30, 12, 0));
+        assert_eq!(after, utc_ms(2025, 3, 31, 7, 0));
+    }
+
+    #[test]
+    fn quiet_hours_defer_runs_in_schedule_time_zone() {
+        let path = temp_manifest_path("quiet.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        let quiet_hours = Some(QuietHours {
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+        });
+
+        // 21:30 local in Tokyo (UTC+9) plus an hour lands in quiet hours.
+        let summary = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    interval_minutes: 60,
+                    timezone: Some("Asia/Tokyo".to_string()),
+                    quiet_hours: quiet_hours.clone(),
+                    ..draft("digest")
+                },
+                utc_ms(2025, 5, 1, 12, 30),
+            )
+            .unwrap();
+        assert_eq!(summary.next_run_at_ms, Some(utc_ms(2025, 5, 1, 22, 0)));
+
+        let err = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    interval_minutes: 60,
+                    timezone: Some("Mars/Olympus".to_string()),
+                    ..draft("digest")
+                },
+                0,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown time zone"));
+        assert!(registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    cron: Some("0 9 * * *".to_string()),
+                    quiet_hours: Some(QuietHours {
+                        start: "9pm".to_string(),
+                        end: "07:00".to_string(),
+                    }),
+                    ..draft("digest")
+                },
+                0,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn catch_up_policies_control_missed_runs() {
+        let start = utc_ms(2025, 1, 1, 0, 30);
+        // Three hourly occurrences were missed; the latest was 10 minutes ago.
+        let now = utc_ms(2025, 1, 1, 3, 10);
+        let mut counts = Vec::new();
+        for catch_up in [
+            ScheduleCatchUp::Skip,
+            ScheduleCatchUp::RunOnce,
+            ScheduleCatchUp::RunAll,
+        ] {
+            let path = temp_manifest_path("catch-up.json");
+            let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+            registry
+                .upsert(
+                    AgentAppScheduleDraft {
+                        cron: Some("@hourly".to_string()),
+                        catch_up,
+                        ..draft("digest")
+                    },
+                    start,
+                )
+                .unwrap();
+            let mut claimed = registry.claim_due(now).unwrap().len();
+            let summary = &registry.list()[0];
+            assert_eq!(summary.next_run_at_ms, Some(utc_ms(2025, 1, 1, 4, 0)));
+            if catch_up == ScheduleCatchUp::Skip {
+                assert_eq!(summary.last_run_status.as_deref(), Some("missed"));
+            }
+            // Replayed occurrences run one after another, never overlapping.
+            while registry.list()[0].running {
+                assert!(registry.claim_due(now).unwrap().is_empty());
+                let id = registry.list()[0].id.clone();
+                registry
+                    .record_run_result(
+                        &id,
+                        ScheduleRunOutcome::Completed {
+                            run_id: format!("run-{claimed}"),
+                        },
+                        now,
+                    )
+                    .unwrap();
+                claimed += registry.claim_due(now).unwrap().len();
+            }
+            counts.push(claimed);
+        }
+        assert_eq!(counts, vec![0, 1, 3]);
+
+        // An on-time occurrence still runs under `skip`.
+        let path = temp_manifest_path("on-time.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    cron: Some("@hourly".to_string()),
+                    catch_up: ScheduleCatchUp::Skip,
+                    ..draft("digest")
+                },
+                start,
+            )
+            .unwrap();
+        assert_eq!(
+            registry.claim_due(utc_ms(2025, 1, 1, 1, 0)).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn run_once_schedule_fires_then_disables() {
+        let path = temp_manifest_path("once.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        assert!(registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    run_at_ms: Some(500),
+                    ..draft("digest")
+                },
+                1_000,
+            )
+            .is_err());
+        assert!(registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    run_at_ms: Some(5_000),
+                    cron: Some("@daily".to_string()),
+                    ..draft("digest")
+                },
+                1_000,
+            )
+            .is_err());
+
+        let summary = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    run_at_ms: Some(5_000),
+                    ..draft("digest")
+                },
+                1_000,
+            )
+            .unwrap();
+        assert_eq!(summary.next_run_at_ms, Some(5_000));
+        assert!(registry.claim_due(4_999).unwrap().is_empty());
+        assert_eq!(registry.claim_due(5_000).unwrap().len(), 1);
+        registry
+            .record_run_result(
+                &summary.id,
+                ScheduleRunOutcome::Completed {
+                    run_id: "run-1".to_string(),
+                },
+                6_000,
+            )
+            .unwrap();
+
+        let summary = &registry.list()[0];
+        assert!(!summary.enabled);
+        assert_eq!(summary.next_run_at_ms, None);
+        assert!(registry.claim_due(10_000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn jitter_delays_runs_within_bound() {
+        let path = temp_manifest_path("jitter.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        for _ in 0..20 {
+            let summary = registry
+                .upsert(
+                    AgentAppScheduleDraft {
+                        interval_minutes: 10,
+                        jitter_seconds: 30,
+                        ..draft("digest")
+                    },
+                    0,
+                )
+                .unwrap();
+            let next = summary.next_run_at_ms.unwrap();
+            assert!((600_000..=630_000).contains(&next), "{next}");
+        }
+    }
+
+    fn failed(error: &str) -> ScheduleRunOutcome {
+        ScheduleRunOutcome::Failed {
+            run_id: None,
+            error: error.to_string(),
+        }
+    }
+
+    #[test]
+    fn occurrences_wait_for_the_running_run() {
+        let path = temp_manifest_path("overlap.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        let summary = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    interval_minutes: 1,
+                    ..draft("digest")
+                },
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(registry.claim_due(60_000).unwrap().len(), 1);
+        // The next two occurrences come due while the first run is going.
+        assert!(registry.claim_due(120_000).unwrap().is_empty());
+        assert!(registry.claim_due(180_000).unwrap().is_empty());
+        let listed = &registry.list()[0];
+        assert!(listed.running);
+        assert_eq!(listed.pending_runs, 1);
+
+        registry
+            .record_run_result(
+                &summary.id,
+                ScheduleRunOutcome::Completed {
+                    run_id: "run-7".to_string(),
+                },
+                190_000,
+            )
+            .unwrap();
+        assert_eq!(registry.claim_due(200_000).unwrap().len(), 1);
+
+        let history = &registry.list()[0].history;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].status, "completed");
+        assert_eq!(history[0].run_id.as_deref(), Some("run-7"));
+        assert_eq!(history[0].finished_at_ms, Some(190_000));
+        assert_eq!(history[1].status, "running");
+    }
+
+    #[test]
+    fn failures_retry_with_backoff_then_disable() {
+        let path = temp_manifest_path("retry.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        let summary = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    interval_minutes: 24 * 60,
+                    ..draft("digest")
+                },
+                0,
+            )
+            .unwrap();
+        let first = summary.next_run_at_ms.unwrap();
+        assert_eq!(registry.claim_due(first).unwrap().len(), 1);
+
+        let alert = registry
+            .record_run_result(&summary.id, failed("timeout"), first)
+            .unwrap()
+            .expect("alert");
+        assert_eq!(alert.consecutive_failures, 1);
+        assert_eq!(alert.retry_at_ms, Some(first + 60_000));
+        assert!(!alert.disabled);
+
+        assert!(registry.claim_due(first + 59_999).unwrap().is_empty());
+        let retried = registry.claim_due(first + 60_000).unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].retry_attempt, 1);
+
+        // The second retry waits twice as long.
+        let alert = registry
+            .record_run_result(&summary.id, failed("timeout"), first + 60_000)
+            .unwrap()
+            .expect("alert");
+        assert_eq!(alert.retry_at_ms, Some(first + 180_000));
+        assert_eq!(registry.claim_due(first + 180_000).unwrap().len(), 1);
+
+        let alert = registry
+            .record_run_result(&summary.id, failed("still down"), first + 180_000)
+            .unwrap()
+            .expect("alert");
+        assert_eq!(alert.consecutive_failures, 3);
+        assert!(alert.disabled);
+        assert_eq!(alert.error, "still down");
+
+        let listed = &registry.list()[0];
+        assert!(!listed.enabled);
+        assert_eq!(
+            listed.last_run_status.as_deref(),
+            Some("disabled_after_failures")
+        );
+        let attempts: Vec<u32> = listed.history.iter().map(|run| run.attempt).collect();
+        assert_eq!(attempts, vec![0, 1, 2]);
+        assert!(registry
+            .claim_due(first + 10 * 86_400_000)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn success_resets_failure_count() {
+        let path = temp_manifest_path("reset.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        let summary = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    interval_minutes: 60,
+                    max_retries: 0,
+                    ..draft("digest")
+                },
+                0,
+            )
+            .unwrap();
+
+        let mut now = 0;
+        for round in 0..4 {
+            now += 3_600_000;
+            assert_eq!(registry.claim_due(now).unwrap().len(), 1);
+            let outcome = if round == 1 {
+                ScheduleRunOutcome::Completed {
+                    run_id: "run-ok".to_string(),
+                }
+            } else {
+                failed("boom")
+            };
+            let alert = registry
+                .record_run_result(&summary.id, outcome, now)
+                .unwrap();
+            if let Some(alert) = alert {
+                assert_eq!(alert.retry_at_ms, None);
+            }
+        }
+        let listed = &registry.list()[0];
+        assert!(listed.enabled);
+        assert_eq!(listed.consecutive_failures, 2);
+    }
+
+    #[test]
+    fn history_is_bounded_and_interrupted_runs_are_released_on_load() {
+        let path = temp_manifest_path("history.json");
+        let registry = AgentAppScheduleRegistry::from_path(&path).expect("registry");
+        let summary = registry
+            .upsert(
+                AgentAppScheduleDraft {
+                    interval_minutes: 1,
+                    ..draft("digest")
+                },
+                0,
+            )
+            .unwrap();
+        for minute in 1..=(MAX_RUN_HISTORY as u64 + 5) {
+            let now = minute * 60_000;
+            assert_eq!(registry.claim_due(now).unwrap().len(), 1);
+            registry
+                .record_run_result(
+                    &summary.id,
+                    ScheduleRunOutcome::Completed {
+                        run_id: format!("run-{minute}"),
+                    },
+                    now,
+                )
+                .unwrap();
+        }
+        let history = registry.list()[0].history.clone();
+        assert_eq!(history.len(), MAX_RUN_HISTORY);
+        assert_eq!(history[0].run_id.as_deref(), Some("run-6"));
+
+        let now = (MAX_RUN_HISTORY as u64 + 6) * 60_000;
+        assert_eq!(registry.claim_due(now).unwrap().len(), 1);
+        let reloaded = AgentAppScheduleRegistry::from_path(&path).expect("reloaded");
+        let listed = &reloaded.list()[0];
+        assert!(!listed.running);
+        assert_eq!(listed.last_run_status.as_deref(), Some("interrupted"));
+        assert_eq!(listed.history.last().unwrap().status, "interrupted");
+        assert_eq!(reloaded.claim_due(now + 60_000).unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,433 @@
+//! The template language of agent app prompts.
+//!
+//! A small Handlebars-like subset rendered against a JSON context:
+//!
+//! - `{{path}}` inserts a value; `path` is dot-separated object keys and
+//!   array indices. Missing values render as nothing, objects and arrays as
+//!   pretty-printed JSON.
+//! - `{{#if path}}…{{else}}…{{/if}}` and `{{#unless path}}…{{/unless}}`
+//!   branch on truthiness: `null`, `false`, `0`, `""`, `[]` and `{}` are false.
+//! - `{{#each path}}…{{else}}…{{/each}}` repeats its body for every array item
+//!   or object value, with `{{this}}`, `{{@index}}` and `{{@key}}` in scope.
+//!   Inside the body, names resolve against the item first, then outwards.
+//!
+//! Templates are scanned once, so inserted text is never expanded again.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value(String),
+    If {
+        path: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        path: String,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// A block being parsed: its opening tag and the nodes collected so far.
+struct OpenBlock {
+    tag: BlockTag,
+    path: String,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockTag {
+    If,
+    Unless,
+    Each,
+}
+
+impl BlockTag {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "if" => Some(Self::If),
+            "unless" => Some(Self::Unless),
+            "each" => Some(Self::Each),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::If => "if",
+            Self::Unless => "unless",
+            Self::Each => "each",
+        }
+    }
+}
+
+impl PromptTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut stack: Vec<OpenBlock> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut rest = template;
+
+        fn current<'a>(stack: &'a mut [OpenBlock], nodes: &'a mut Vec<Node>) -> &'a mut Vec<Node> {
+            match stack.last_mut() {
+                Some(block) => block.otherwise.as_mut().unwrap_or(&mut block.then),
+                None => nodes,
+            }
+        }
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                current(&mut stack, &mut nodes).push(Node::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| anyhow!("unclosed `{{{{` in template"))?;
+            let tag = after[..end].trim();
+            rest = &after[end + 2..];
+
+            if let Some(open) = tag.strip_prefix('#') {
+                let (name, path) = open.split_once(char::is_whitespace).unwrap_or((open, ""));
+                let block = BlockTag::parse(name)
+                    .ok_or_else(|| anyhow!("unknown block `{{{{#{name}}}}}` in template"))?;
+                let path = path.trim();
+                if path.is_empty() {
+                    bail!("`{{{{#{name}}}}}` needs a value to test");
+                }
+                stack.push(OpenBlock {
+                    tag: block,
+                    path: path.to_string(),
+                    then: Vec::new(),
+                    otherwise: None,
+                });
+            } else if let Some(close) = tag.strip_prefix('/') {
+                let close = close.trim();
+                let block = stack
+                    .pop()
+                    .ok_or_else(|| anyhow!("`{{{{/{close}}}}}` has no matching block"))?;
+                if block.tag.name() != close {
+                    bail!("`{{{{/{close}}}}}` closes `{{{{#{}}}}}`", block.tag.name());
+                }
+                let otherwise = block.otherwise.unwrap_or_default();
+                let node = match block.tag {
+                    BlockTag::Each => Node::Each {
+                        path: block.path,
+                        body: block.then,
+                        otherwise,
+                    },
+                    tag => Node::If {
+                        path: block.path,
+                        negate: tag == BlockTag::Unless,
+                        then: block.then,
+                        otherwise,
+                    },
+                };
+                current(&mut stack, &mut nodes).push(node);
+            } else if tag == "else" {
+                let block = stack
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("`{{{{else}}}}` outside a block"))?;
+                if block.otherwise.is_some() {
+                    bail!(
+                        "`{{{{#{}}}}}` has more than one `{{{{else}}}}`",
+                        block.tag.name()
+                    );
+                }
+                block.otherwise = Some(Vec::new());
+            } else if tag.is_empty() {
+                bail!("empty `{{{{}}}}` in template");
+            } else {
+                current(&mut stack, &mut nodes).push(Node::Value(tag.to_string()));
+            }
+        }
+        if !rest.is_empty() {
+            current(&mut stack, &mut nodes).push(Node::Text(rest.to_string()));
+        }
+        if let Some(block) = stack.last() {
+            bail!(
+                "`{{{{#{} {}}}}}` is never closed",
+                block.tag.name(),
+                block.path
+            );
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn render(&self, context: &Value) -> String {
+        let mut rendered = String::new();
+        render_nodes(&self.nodes, &[Scope::root(context)], &mut rendered);
+        rendered
+    }
+}
+
+/// Parses and renders `template` in one step.
+pub fn render_template(template: &str, context: &Value) -> Result<String> {
+    Ok(PromptTemplate::parse(template)?.render(context))
+}
+
+/// Renders every string inside `value` as a template. A string that is a
+/// single `{{path}}` tag is replaced by the value it names, keeping its JSON
+/// type, or `null` when it is missing.
+pub fn render_value(value: &Value, context: &Value) -> Result<Value> {
+    match value {
+        Value::String(template) => {
+            let single_tag = template
+                .trim()
+                .strip_prefix("{{")
+                .and_then(|rest| rest.strip_suffix("}}"))
+                .map(str::trim)
+                .filter(|path| {
+                    !path.is_empty()
+                        && *path != "else"
+                        && !path.contains(['{', '}'])
+                        && !path.starts_with(['#', '/'])
+                });
+            match single_tag {
+                Some(path) => Ok(lookup(&[Scope::root(context)], path).unwrap_or(Value::Null)),
+                None => render_template(template, context).map(Value::String),
+            }
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render_value(item, context))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, item)| Ok((key.clone(), render_value(item, context)?)))
+            .collect::<Result<serde_json::Map<_, _>>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+/// One level of `{{#each}}` nesting.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    this: &'a Value,
+    index: Option<usize>,
+    key: Option<&'a str>,
+}
+
+impl<'a> Scope<'a> {
+    fn root(context: &'a Value) -> Self {
+        Self {
+            this: context,
+            index: None,
+            key: None,
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], scopes: &[Scope<'_>], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(path) => {
+                if let Some(value) = lookup(scopes, path) {
+                    out.push_str(&display(&value));
+                }
+            }
+            Node::If {
+                path,
+                negate,
+                then,
+                otherwise,
+            } => {
+                let truthy = lookup(scopes, path).is_some_and(|value| is_truthy(&value));
+                let branch = if truthy != *negate { then } else { otherwise };
+                render_nodes(branch, scopes, out);
+            }
+            Node::Each {
+                path,
+                body,
+                otherwise,
+            } => {
+                let items: Vec<(usize, Option<&str>, &Value)> = match lookup_ref(scopes, path) {
+                    Some(Value::Array(items)) => items
+                        .iter()
+                        .enumerate()
+                        .map(|(index, item)| (index, None, item))
+                        .collect(),
+                    Some(Value::Object(map)) => map
+                        .iter()
+                        .enumerate()
+                        .map(|(index, (key, item))| (index, Some(key.as_str()), item))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                if items.is_empty() {
+                    render_nodes(otherwise, scopes, out);
+                    continue;
+                }
+                for (index, key, item) in items {
+                    let mut nested = scopes.to_vec();
+                    nested.push(Scope {
+                        this: item,
+                        index: Some(index),
+                        key,
+                    });
+                    render_nodes(body, &nested, out);
+                }
+            }
+        }
+    }
+}
+
+/// Resolves `path` in the innermost scope that has its first segment;
+/// `@index` and `@key` come from the innermost `{{#each}}`.
+fn lookup(scopes: &[Scope<'_>], path: &str) -> Option<Value> {
+    let innermost = scopes.last()?;
+    match path {
+        "@index" => innermost.index.map(Value::from),
+        "@key" => innermost.key.map(Value::from),
+        _ => lookup_ref(scopes, path).cloned(),
+    }
+}
+
+fn lookup_ref<'a>(scopes: &[Scope<'a>], path: &str) -> Option<&'a Value> {
+    let innermost = scopes.last()?;
+    if path == "this" {
+        return Some(innermost.this);
+    }
+    if let Some(rest) = path.strip_prefix("this.") {
+        return descend(innermost.this, rest);
+    }
+    let first = path.split('.').next().unwrap_or(path);
+    scopes
+        .iter()
+        .rev()
+        .find(|scope| scope.this.get(first).is_some())
+        .and_then(|scope| descend(scope.this, path))
+}
+
+fn descend<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index)),
+            _ => None,
+        })
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(flag) => *flag,
+        Value::Number(number) => number.as_f64().is_some_and(|number| number != 0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(_) | Value::Object(_) => {
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn substitutes_nested_values_once() {
+        let context = json!({
+            "input": "{{budget}}",
+            "trip": { "city": "Lisbon", "nights": 3 },
+            "budget": 900
+        });
+        let rendered = render_template(
+            "Plan {{trip.nights}} nights in {{trip.city}} for {{input}}{{missing}}.",
+            &context,
+        )
+        .unwrap();
+        assert_eq!(rendered, "Plan 3 nights in Lisbon for {{budget}}.");
+    }
+
+    #[test]
+    fn renders_conditionals_and_loops() {
+        let template = "{{#if budget}}Budget: {{budget}}.{{else}}No budget.{{/if}}\
+            {{#unless refundable}} Non-refundable is fine.{{/unless}}\
+            {{#each stops}} {{@index}}:{{this.city}}/{{currency}}{{else}} Direct.{{/each}}";
+        let rendered = render_template(
+            template,
+            &json!({
+                "budget": 0,
+                "currency": "EUR",
+                "stops": [{ "city": "Porto" }, { "city": "Faro", "currency": "GBP" }]
+            }),
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "No budget. Non-refundable is fine. 0:Porto/EUR 1:Faro/GBP"
+        );
+
+        let rendered =
+            render_template(template, &json!({ "budget": 250, "refundable": true })).unwrap();
+        assert_eq!(rendered, "Budget: 250. Direct.");
+    }
+
+    #[test]
+    fn single_tags_keep_their_json_type() {
+        let context = json!({ "offer": { "price": 412, "stops": ["OPO"] } });
+        let rendered = render_value(
+            &json!({
+                "price": "{{ offer.price }}",
+                "stops": "{{offer.stops}}",
+                "summary": ["{{offer.price}} EUR", true],
+                "missing": "{{offer.refund}}"
+            }),
+            &context,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            json!({
+                "price": 412,
+                "stops": ["OPO"],
+                "summary": ["412 EUR", true],
+                "missing": null
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        for template in [
+            "{{#if a}}open",
+            "{{/if}}",
+            "{{#if a}}x{{/each}}",
+            "{{#with a}}x{{/with}}",
+            "{{#if}}x{{/if}}",
+            "{{#if a}}x{{else}}y{{else}}z{{/if}}",
+            "{{input",
+        ] {
+            assert!(
+                PromptTemplate::parse(template).is_err(),
+                "{template} should not parse"
+            );
+        }
+    }
+}
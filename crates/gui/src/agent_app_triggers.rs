@@ -0,0 +1,942 @@
+//! Event triggers that start agent app runs.
+//!
+//! Apps declare [`AgentAppTrigger`]s in `configs/agent_apps.json`. Browser
+//! subsystems report what happened as [`AgentAppTriggerEvent`]s through the
+//! [`AgentAppTriggerHub`]; the dispatcher in `main.rs` claims the matching
+//! triggers and starts one run per match, with the event payload available
+//! to the app's templates as `{{event}}` and `{{event.<field>}}`. An app's
+//! triggers stay inert until the user enables them in
+//! [`AgentAppTriggerSettings`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
+
+use crate::agent::mcp_server::{
+    origin_allowed, read_http_request, write_http_response, write_json_response,
+};
+use crate::agent_apps::{AgentAppDefinition, AgentAppRegistry};
+
+pub const DEFAULT_TRIGGER_WEBHOOK_PORT: u16 = 39218;
+const DEFAULT_TRIGGER_COOLDOWN_SECONDS: u64 = 60;
+const WEBHOOK_PATH_PREFIX: &str = "/hooks/";
+pub const MIN_WEBHOOK_SECRET_CHARS: usize = 16;
+
+/// What an agent app reacts to. Patterns use `*` to match any run of
+/// characters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AgentAppTriggerKind {
+    /// A page the user opened finished loading.
+    #[serde(rename_all = "camelCase")]
+    PageVisit { url_pattern: String },
+    #[serde(rename_all = "camelCase")]
+    DownloadCompleted {
+        #[serde(default)]
+        filename_pattern: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    BookmarkAdded {
+        #[serde(default)]
+        url_pattern: Option<String>,
+    },
+    /// The native balance of an EVM address changed on the configured RPC.
+    WalletBalanceChanged { address: String },
+    IpfsPinned {
+        #[serde(default)]
+        cid: Option<String>,
+    },
+    /// `POST /hooks/<path>` on the loopback webhook listener. The caller
+    /// must send `Authorization: Bearer <secret>`.
+    Webhook { path: String, secret: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentAppTrigger {
+    #[serde(flatten)]
+    pub kind: AgentAppTriggerKind,
+    /// Template for the run's `{{input}}`; may reference `{{event.*}}`.
+    /// Falls back to the app's `defaultInput`.
+    #[serde(default)]
+    pub input: Option<String>,
+    /// Minimum time between two runs started by this trigger.
+    #[serde(default = "default_cooldown_seconds")]
+    pub cooldown_seconds: u64,
+}
+
+fn default_cooldown_seconds() -> u64 {
+    DEFAULT_TRIGGER_COOLDOWN_SECONDS
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AgentAppTriggerEvent {
+    #[serde(rename_all = "camelCase")]
+    PageVisit {
+        tab_id: String,
+        url: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DownloadCompleted {
+        download_id: String,
+        url: String,
+        filename: String,
+        path: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    BookmarkAdded {
+        bookmark_id: String,
+        url: String,
+        title: String,
+        folder: Option<String>,
+        tags: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    WalletBalanceChanged {
+        address: String,
+        chain_id: u64,
+        previous_wei: String,
+        balance_wei: String,
+    },
+    IpfsPinned {
+        cid: String,
+        url: String,
+    },
+    Webhook {
+        path: String,
+        query: HashMap<String, String>,
+        body: Value,
+        /// Bearer token the caller presented; never exposed to templates.
+        #[serde(skip)]
+        token: Option<String>,
+    },
+}
+
+impl AgentAppTriggerEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::PageVisit { .. } => "page_visit",
+            Self::DownloadCompleted { .. } => "download_completed",
+            Self::BookmarkAdded { .. } => "bookmark_added",
+            Self::WalletBalanceChanged { .. } => "wallet_balance_changed",
+            Self::IpfsPinned { .. } => "ipfs_pinned",
+            Self::Webhook { .. } => "webhook",
+        }
+    }
+
+    /// The event as seen by templates.
+    pub fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+impl AgentAppTrigger {
+    /// Problems with this trigger's declaration, for app validation.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let AgentAppTriggerKind::Webhook { path, secret } = &self.kind {
+            if normalize_webhook_path(path).is_empty() {
+                problems.push("webhook trigger needs a path".to_string());
+            }
+            if secret.trim().chars().count() < MIN_WEBHOOK_SECRET_CHARS {
+                problems.push(format!(
+                    "webhook trigger `{}` needs a secret of at least {MIN_WEBHOOK_SECRET_CHARS} characters",
+                    normalize_webhook_path(path)
+                ));
+            }
+        }
+        problems
+    }
+
+    pub fn matches(&self, event: &AgentAppTriggerEvent) -> bool {
+        match (&self.kind, event) {
+            (
+                AgentAppTriggerKind::PageVisit { url_pattern },
+                AgentAppTriggerEvent::PageVisit { url, .. },
+            ) => glob_match(url_pattern, url),
+            (
+                AgentAppTriggerKind::DownloadCompleted { filename_pattern },
+                AgentAppTriggerEvent::DownloadCompleted { filename, .. },
+            ) => filename_pattern
+                .as_deref()
+                .is_none_or(|pattern| glob_match(pattern, filename)),
+            (
+                AgentAppTriggerKind::BookmarkAdded { url_pattern },
+                AgentAppTriggerEvent::BookmarkAdded { url, .. },
+            ) => url_pattern
+                .as_deref()
+                .is_none_or(|pattern| glob_match(pattern, url)),
+            (
+                AgentAppTriggerKind::WalletBalanceChanged { address },
+                AgentAppTriggerEvent::WalletBalanceChanged {
+                    address: changed, ..
+                },
+            ) => address.trim().eq_ignore_ascii_case(changed),
+            (
+                AgentAppTriggerKind::IpfsPinned { cid },
+                AgentAppTriggerEvent::IpfsPinned { cid: pinned, .. },
+            ) => cid.as_deref().is_none_or(|cid| cid.trim() == pinned),
+            (
+                AgentAppTriggerKind::Webhook { path, secret },
+                AgentAppTriggerEvent::Webhook {
+                    path: called,
+                    token,
+                    ..
+                },
+            ) => {
+                normalize_webhook_path(path) == normalize_webhook_path(called)
+                    && !secret.is_empty()
+                    && token
+                        .as_deref()
+                        .is_some_and(|token| bool::from(token.as_bytes().ct_eq(secret.as_bytes())))
+            }
+            _ => false,
+        }
+    }
+
+    /// The run input for `event`, or `None` to use the app's default.
+    pub fn render_input(&self, event: &AgentAppTriggerEvent) -> Option<String> {
+        self.input
+            .as_deref()
+            .map(|template| render_event_template(template, None, &event.payload()))
+    }
+}
+
+/// A trigger that fired for an event and is past its cooldown.
+#[derive(Debug, Clone)]
+pub struct AgentAppTriggerMatch {
+    pub app: AgentAppDefinition,
+    pub trigger_index: usize,
+    pub input: Option<String>,
+}
+
+impl AgentAppTriggerMatch {
+    pub fn render_task(&self, event: &AgentAppTriggerEvent) -> Result<String> {
+        self.app.render_event_task(self.input.as_deref(), event)
+    }
+
+    pub fn label(&self, event: &AgentAppTriggerEvent) -> String {
+        format!("{} · {}", self.app.name, event.kind().replace('_', " "))
+    }
+}
+
+/// Apps whose triggers the user switched on, persisted next to the apps
+/// manifest. Triggers an app declares do nothing until it is listed here.
+pub struct AgentAppTriggerSettings {
+    path: Option<PathBuf>,
+    enabled: RwLock<BTreeSet<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AgentAppTriggerSettingsFile {
+    #[serde(default)]
+    enabled: BTreeSet<String>,
+}
+
+impl AgentAppTriggerSettings {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let enabled = if path.exists() {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("reading agent app triggers at {}", path.display()))?;
+            serde_json::from_str::<AgentAppTriggerSettingsFile>(&raw)
+                .with_context(|| format!("parsing agent app triggers at {}", path.display()))?
+                .enabled
+        } else {
+            BTreeSet::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            enabled: RwLock::new(enabled),
+        })
+    }
+
+    /// Settings that are not persisted.
+    pub fn empty() -> Self {
+        Self {
+            path: None,
+            enabled: RwLock::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn is_enabled(&self, app_id: &str) -> bool {
+        self.enabled
+            .read()
+            .expect("agent app trigger settings poisoned")
+            .contains(app_id)
+    }
+
+    /// Switches an app's triggers on or off. Returns whether anything
+    /// changed.
+    pub fn set_enabled(&self, app_id: &str, enabled: bool) -> Result<bool> {
+        let mut apps = self
+            .enabled
+            .write()
+            .expect("agent app trigger settings poisoned");
+        let mut next = apps.clone();
+        let changed = if enabled {
+            next.insert(app_id.to_string())
+        } else {
+            next.remove(app_id)
+        };
+        if changed {
+            if let Some(path) = &self.path {
+                write_settings(path, &next)?;
+            }
+            *apps = next;
+        }
+        Ok(changed)
+    }
+}
+
+fn write_settings(path: &Path, enabled: &BTreeSet<String>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating agent app triggers dir {}", parent.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(&json!({ "enabled": enabled }))?;
+    let staging = path.with_extension("json.tmp");
+    fs::write(&staging, raw)
+        .with_context(|| format!("writing agent app triggers at {}", staging.display()))?;
+    fs::rename(&staging, path)
+        .with_context(|| format!("replacing agent app triggers at {}", path.display()))
+}
+
+/// Collects trigger events from browser subsystems and tracks per-trigger
+/// cooldowns.
+pub struct AgentAppTriggerHub {
+    sender: mpsc::UnboundedSender<AgentAppTriggerEvent>,
+    receiver: Mutex<Option<mpsc::UnboundedReceiver<AgentAppTriggerEvent>>>,
+    last_fired_ms: Mutex<HashMap<(String, usize), u64>>,
+    webhook_address: AsyncMutex<Option<SocketAddr>>,
+}
+
+impl Default for AgentAppTriggerHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AgentAppTriggerHub {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            last_fired_ms: Mutex::new(HashMap::new()),
+            webhook_address: AsyncMutex::new(None),
+        }
+    }
+
+    /// Starts the webhook listener on `bind` once an enabled app declares a
+    /// webhook trigger. Call it whenever apps are installed, updated or have
+    /// their triggers switched; the listener looks triggers up per request,
+    /// so once running it needs no further refresh. Returns the listening
+    /// address, if any.
+    pub async fn ensure_webhook_listener(
+        self: &Arc<Self>,
+        bind: SocketAddr,
+        apps: Arc<AgentAppRegistry>,
+    ) -> Result<Option<SocketAddr>> {
+        let mut address = self.webhook_address.lock().await;
+        if address.is_none() && has_webhook_triggers(&apps.triggered_apps()) {
+            *address = Some(serve_webhooks(bind, apps, self.clone()).await?);
+        }
+        Ok(*address)
+    }
+
+    /// Queues `event` for the dispatcher. Never blocks.
+    pub fn emit(&self, event: AgentAppTriggerEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Hands the event stream to the dispatcher; only the first call gets it.
+    pub fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<AgentAppTriggerEvent>> {
+        self.receiver
+            .lock()
+            .expect("agent app trigger receiver poisoned")
+            .take()
+    }
+
+    /// Triggers of `apps` that match `event`, marking each as fired at
+    /// `now_ms`. Triggers still cooling down are skipped.
+    pub fn claim(
+        &self,
+        apps: &[AgentAppDefinition],
+        event: &AgentAppTriggerEvent,
+        now_ms: u64,
+    ) -> Vec<AgentAppTriggerMatch> {
+        let mut last_fired = self
+            .last_fired_ms
+            .lock()
+            .expect("agent app trigger cooldowns poisoned");
+        let mut matches = Vec::new();
+        for app in apps {
+            for (index, trigger) in app.triggers.iter().enumerate() {
+                if !trigger.matches(event) {
+                    continue;
+                }
+                let key = (app.id.clone(), index);
+                let cooldown_ms = trigger.cooldown_seconds.saturating_mul(1000);
+                if last_fired
+                    .get(&key)
+                    .is_some_and(|fired| now_ms.saturating_sub(*fired) < cooldown_ms)
+                {
+                    continue;
+                }
+                last_fired.insert(key, now_ms);
+                matches.push(AgentAppTriggerMatch {
+                    app: app.clone(),
+                    trigger_index: index,
+                    input: trigger.render_input(event),
+                });
+            }
+        }
+        matches
+    }
+}
+
+/// Remembers the last balance seen per address so only changes become
+/// events. The first observation of an address only records it.
+#[derive(Debug, Default)]
+pub struct WalletBalanceTracker {
+    balances: HashMap<String, String>,
+}
+
+impl WalletBalanceTracker {
+    pub fn observe(
+        &mut self,
+        address: &str,
+        chain_id: u64,
+        balance_wei: String,
+    ) -> Option<AgentAppTriggerEvent> {
+        let key = address.trim().to_ascii_lowercase();
+        let previous = self.balances.insert(key, balance_wei.clone())?;
+        (previous != balance_wei).then(|| AgentAppTriggerEvent::WalletBalanceChanged {
+            address: address.trim().to_string(),
+            chain_id,
+            previous_wei: previous,
+            balance_wei,
+        })
+    }
+}
+
+/// Addresses watched by `WalletBalanceChanged` triggers, deduplicated.
+pub fn watched_wallet_addresses(apps: &[AgentAppDefinition]) -> Vec<String> {
+    let mut addresses: Vec<String> = Vec::new();
+    for trigger in apps.iter().flat_map(|app| &app.triggers) {
+        if let AgentAppTriggerKind::WalletBalanceChanged { address } = &trigger.kind {
+            let address = address.trim();
+            if !addresses
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(address))
+            {
+                addresses.push(address.to_string());
+            }
+        }
+    }
+    addresses
+}
+
+pub fn has_webhook_triggers(apps: &[AgentAppDefinition]) -> bool {
+    apps.iter()
+        .flat_map(|app| &app.triggers)
+        .any(|trigger| matches!(trigger.kind, AgentAppTriggerKind::Webhook { .. }))
+}
+
+/// Expands `{{event}}` (the payload as JSON), `{{event.<path>}}` (a field,
+/// with `.` separating object keys and array indices) and, when `input` is
+/// given, `{{input}}`. The template is scanned once, so text taken from the
+/// event is never expanded again. Other placeholders are left untouched.
+pub fn render_event_template(template: &str, input: Option<&str>, event: &Value) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let key = after[..end].trim();
+        let replacement = match key {
+            "input" => input.map(str::to_string),
+            "event" => Some(serde_json::to_string_pretty(event).unwrap_or_default()),
+            _ => key
+                .strip_prefix("event.")
+                .map(|path| event_field(event, path)),
+        };
+        match replacement {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn event_field(event: &Value, path: &str) -> String {
+    let mut current = event;
+    for segment in path.split('.') {
+        let next = match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index)),
+            _ => None,
+        };
+        match next {
+            Some(value) => current = value,
+            None => return String::new(),
+        }
+    }
+    match current {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `*` matches any run of characters, everything else matches literally.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.trim().chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn normalize_webhook_path(path: &str) -> &str {
+    path.trim().trim_matches('/')
+}
+
+/// Listens for `POST /hooks/<path>` on a loopback address and turns each
+/// request a webhook trigger accepts into an event. The JSON body (or the raw
+/// text when it is not JSON) becomes `{{event.body}}`.
+pub async fn serve_webhooks(
+    bind: SocketAddr,
+    apps: Arc<AgentAppRegistry>,
+    hub: Arc<AgentAppTriggerHub>,
+) -> Result<SocketAddr> {
+    if !bind.ip().is_loopback() {
+        return Err(anyhow!(
+            "agent app webhooks only bind to loopback addresses"
+        ));
+    }
+    let listener = TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("failed to bind agent app webhooks on {bind}"))?;
+    let address = listener.local_addr()?;
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let apps = apps.clone();
+            let hub = hub.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle_webhook(stream, &apps, &hub).await {
+                    tracing::debug!(error = %err, "agent app webhook connection closed with error");
+                }
+            });
+        }
+    });
+    Ok(address)
+}
+
+async fn handle_webhook(
+    stream: TcpStream,
+    apps: &AgentAppRegistry,
+    hub: &AgentAppTriggerHub,
+) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let Some(request) = read_http_request(&mut stream).await? else {
+        return Ok(());
+    };
+    let mut stream = stream.into_inner();
+
+    if !origin_allowed(request.header("origin")) {
+        return write_http_response(&mut stream, 403, "Forbidden", &[], b"origin not allowed")
+            .await;
+    }
+    let Some(path) = request.path.strip_prefix(WEBHOOK_PATH_PREFIX) else {
+        return write_http_response(&mut stream, 404, "Not Found", &[], b"").await;
+    };
+    if request.method != "POST" {
+        return write_http_response(&mut stream, 405, "Method Not Allowed", &[], b"").await;
+    }
+
+    let body = serde_json::from_slice(&request.body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&request.body).into_owned()));
+    let event = AgentAppTriggerEvent::Webhook {
+        path: normalize_webhook_path(path).to_string(),
+        query: request.query.clone(),
+        body,
+        token: request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::to_string),
+    };
+
+    let apps = apps.triggered_apps();
+    let triggers: Vec<&AgentAppTrigger> = apps
+        .iter()
+        .flat_map(|app| &app.triggers)
+        .filter(|trigger| match &trigger.kind {
+            AgentAppTriggerKind::Webhook { path: hook, .. } => {
+                normalize_webhook_path(hook) == normalize_webhook_path(path)
+            }
+            _ => false,
+        })
+        .collect();
+    if triggers.is_empty() {
+        return write_http_response(&mut stream, 404, "Not Found", &[], b"unknown webhook").await;
+    }
+    if !triggers.iter().any(|trigger| trigger.matches(&event)) {
+        return write_http_response(
+            &mut stream,
+            401,
+            "Unauthorized",
+            &[],
+            b"missing or invalid secret",
+        )
+        .await;
+    }
+
+    hub.emit(event);
+    write_json_response(&mut stream, 202, "Accepted", &[], br#"{"accepted":true}"#).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn app(triggers: Value) -> AgentAppDefinition {
+        serde_json::from_value(json!({
+            "id": "download-sorter",
+            "name": "Download Sorter",
+            "tagline": "Files downloads",
+            "description": "Sorts finished downloads.",
+            "promptTemplate": "File {{event.filename}} from {{event.url}}. {{input}}",
+            "defaultInput": "Use the default folders.",
+            "triggers": triggers
+        }))
+        .expect("app definition")
+    }
+
+    fn download(filename: &str) -> AgentAppTriggerEvent {
+        AgentAppTriggerEvent::DownloadCompleted {
+            download_id: "d1".to_string(),
+            url: "https://example.com/files/report.pdf".to_string(),
+            filename: filename.to_string(),
+            path: format!("/tmp/{filename}"),
+        }
+    }
+
+    #[test]
+    fn triggers_parse_from_manifest_json() {
+        let app = app(json!([
+            { "kind": "page_visit", "urlPattern": "https://*.example.com/*" },
+            { "kind": "download_completed", "filenamePattern": "*.pdf", "cooldownSeconds": 0 },
+            { "kind": "bookmark_added" },
+            { "kind": "wallet_balance_changed", "address": "0xAbC" },
+            { "kind": "ipfs_pinned", "cid": "bafyexample" },
+            { "kind": "webhook", "path": "/ci/done", "secret": "s3cret", "input": "{{event.body.status}}" }
+        ]));
+
+        assert_eq!(app.triggers.len(), 6);
+        assert_eq!(
+            app.triggers[1].kind,
+            AgentAppTriggerKind::DownloadCompleted {
+                filename_pattern: Some("*.pdf".to_string())
+            }
+        );
+        assert_eq!(app.triggers[1].cooldown_seconds, 0);
+        assert_eq!(
+            app.triggers[0].cooldown_seconds,
+            DEFAULT_TRIGGER_COOLDOWN_SECONDS
+        );
+        assert_eq!(
+            app.triggers[5].input.as_deref(),
+            Some("{{event.body.status}}")
+        );
+    }
+
+    #[test]
+    fn webhook_triggers_need_a_strong_secret() {
+        let parsed = serde_json::from_value::<AgentAppTrigger>(json!({
+            "kind": "webhook",
+            "path": "ci"
+        }));
+        assert!(parsed.is_err(), "a webhook without a secret is refused");
+
+        let err = app(json!([{ "kind": "webhook", "path": "ci", "secret": "short" }]))
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("needs a secret of at least"), "{err}");
+        app(json!([{ "kind": "webhook", "path": "ci", "secret": "0123456789abcdef" }]))
+            .validate()
+            .expect("strong secret accepted");
+    }
+
+    #[test]
+    fn triggers_match_their_own_event_kind_and_filters() {
+        let app = app(json!([
+            { "kind": "page_visit", "urlPattern": "https://*.example.com/*" },
+            { "kind": "download_completed", "filenamePattern": "*.pdf" },
+            { "kind": "wallet_balance_changed", "address": "0xAbC" },
+            { "kind": "webhook", "path": "/ci/done", "secret": "s3cret" }
+        ]));
+        let visit = |url: &str| AgentAppTriggerEvent::PageVisit {
+            tab_id: "t1".to_string(),
+            url: url.to_string(),
+        };
+        let webhook = |token: Option<&str>| AgentAppTriggerEvent::Webhook {
+            path: "ci/done".to_string(),
+            query: HashMap::new(),
+            body: Value::Null,
+            token: token.map(str::to_string),
+        };
+
+        assert!(app.triggers[0].matches(&visit("https://shop.example.com/cart")));
+        assert!(!app.triggers[0].matches(&visit("https://example.org/")));
+        assert!(app.triggers[1].matches(&download("report.pdf")));
+        assert!(!app.triggers[1].matches(&download("photo.png")));
+        assert!(!app.triggers[0].matches(&download("report.pdf")));
+        assert!(
+            app.triggers[2].matches(&AgentAppTriggerEvent::WalletBalanceChanged {
+                address: "0xabc".to_string(),
+                chain_id: 1,
+                previous_wei: "1".to_string(),
+                balance_wei: "2".to_string(),
+            })
+        );
+        assert!(app.triggers[3].matches(&webhook(Some("s3cret"))));
+        assert!(!app.triggers[3].matches(&webhook(Some("wrong"))));
+        assert!(!app.triggers[3].matches(&webhook(None)));
+    }
+
+    #[test]
+    fn event_payload_is_interpolated_into_the_task() {
+        let app = app(json!([
+            { "kind": "download_completed", "input": "Put {{event.filename}} under Reports." }
+        ]));
+        let hub = AgentAppTriggerHub::new();
+        let event = download("report.pdf");
+
+        let matches = hub.claim(std::slice::from_ref(&app), &event, 1_000);
+        assert_eq!(matches.len(), 1);
+        let task = matches[0].render_task(&event).expect("task");
+        assert_eq!(
+            task,
+            "File report.pdf from https://example.com/files/report.pdf. Put report.pdf under Reports."
+        );
+        assert_eq!(
+            matches[0].label(&event),
+            "Download Sorter · download completed"
+        );
+
+        let without_input = app.render_event_task(None, &event).expect("task");
+        assert!(without_input.ends_with("Use the default folders."));
+    }
+
+    #[test]
+    fn event_text_is_not_expanded_twice() {
+        let payload = json!({
+            "kind": "webhook",
+            "body": { "note": "{{event.body.secret}} {{input}}", "secret": "hidden", "items": [1, {"id": "x"}] }
+        });
+
+        assert_eq!(
+            render_event_template("{{ event.body.note }}", Some("in"), &payload),
+            "{{event.body.secret}} {{input}}"
+        );
+        assert_eq!(
+            render_event_template("{{event.body.items.1.id}}", None, &payload),
+            "x"
+        );
+        assert_eq!(
+            render_event_template("{{event.body.items.0}}", None, &payload),
+            "1"
+        );
+        assert_eq!(
+            render_event_template("{{event.missing}}|{{other}}|{{", None, &payload),
+            "|{{other}}|{{"
+        );
+        assert_eq!(
+            render_event_template("{{input}}", None, &payload),
+            "{{input}}"
+        );
+    }
+
+    #[test]
+    fn cooldown_suppresses_repeat_runs() {
+        let app = app(json!([{ "kind": "download_completed", "cooldownSeconds": 60 }]));
+        let hub = AgentAppTriggerHub::new();
+        let apps = [app];
+        let event = download("a.pdf");
+
+        assert_eq!(hub.claim(&apps, &event, 0).len(), 1);
+        assert!(hub.claim(&apps, &event, 59_999).is_empty());
+        assert_eq!(hub.claim(&apps, &event, 60_000).len(), 1);
+    }
+
+    #[test]
+    fn wallet_tracker_reports_only_changes() {
+        let mut tracker = WalletBalanceTracker::default();
+
+        assert!(tracker.observe("0xAbC", 1, "100".to_string()).is_none());
+        assert!(tracker.observe("0xabc", 1, "100".to_string()).is_none());
+        let event = tracker
+            .observe("0xAbC", 1, "250".to_string())
+            .expect("change");
+        assert_eq!(
+            event.payload(),
+            json!({
+                "kind": "wallet_balance_changed",
+                "address": "0xAbC",
+                "chainId": 1,
+                "previousWei": "100",
+                "balanceWei": "250"
+            })
+        );
+    }
+
+    #[test]
+    fn glob_patterns_match_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match(
+            "https://*.example.com/*",
+            "https://a.b.example.com/x?y=1"
+        ));
+        assert!(glob_match("*.tar.gz", "release.tar.gz"));
+        assert!(!glob_match("*.tar.gz", "release.tar.gz.sig"));
+        assert!(!glob_match("https://example.com/", "https://example.com/a"));
+    }
+
+    async fn post(address: SocketAddr, path: &str, token: Option<&str>, body: &str) -> String {
+        let mut stream = TcpStream::connect(address).await.expect("connect");
+        let auth = token
+            .map(|token| format!("Authorization: Bearer {token}\r\n"))
+            .unwrap_or_default();
+        let request = format!(
+            "POST {path} HTTP/1.1\r\nHost: localhost\r\n{auth}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.expect("write");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.expect("read");
+        response
+    }
+
+    #[tokio::test]
+    async fn webhook_listener_emits_authorized_events() {
+        let dir = std::env::temp_dir()
+            .join("dbrowser-agent-app-trigger-tests")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let manifest = dir.join("agent_apps.json");
+        std::fs::write(
+            &manifest,
+            json!({
+                "apps": [{
+                    "id": "ci-triage",
+                    "name": "CI Triage",
+                    "tagline": "Triage CI",
+                    "description": "Looks at failed builds.",
+                    "promptTemplate": "Build {{event.body.status}}",
+                    "triggers": [{ "kind": "webhook", "path": "ci", "secret": "s3cret" }]
+                }]
+            })
+            .to_string(),
+        )
+        .expect("manifest");
+        let apps = Arc::new(AgentAppRegistry::from_path(&manifest).expect("registry"));
+        let hub = Arc::new(AgentAppTriggerHub::new());
+        let mut events = hub.take_receiver().expect("receiver");
+        let bind: SocketAddr = "127.0.0.1:0".parse().unwrap();
+
+        // Declared triggers stay off until the user enables the app.
+        assert!(apps.triggered_apps().is_empty());
+        assert_eq!(
+            hub.ensure_webhook_listener(bind, apps.clone())
+                .await
+                .expect("nothing to listen for"),
+            None
+        );
+        assert!(
+            apps.set_triggers_enabled("ci-triage", true)
+                .unwrap()
+                .triggers_enabled
+        );
+        let address = hub
+            .ensure_webhook_listener(bind, apps.clone())
+            .await
+            .expect("listener")
+            .expect("listener started once enabled");
+        assert_eq!(
+            hub.ensure_webhook_listener(bind, apps.clone())
+                .await
+                .unwrap(),
+            Some(address)
+        );
+        let reloaded = AgentAppRegistry::from_path(&manifest).expect("registry");
+        assert_eq!(reloaded.triggered_apps().len(), 1);
+
+        assert!(post(address, "/hooks/unknown", None, "{}")
+            .await
+            .starts_with("HTTP/1.1 404"));
+        assert!(post(address, "/hooks/ci", Some("nope"), "{}")
+            .await
+            .starts_with("HTTP/1.1 401"));
+        let accepted = post(
+            address,
+            "/hooks/ci",
+            Some("s3cret"),
+            r#"{"status":"failed"}"#,
+        )
+        .await;
+        assert!(accepted.starts_with("HTTP/1.1 202"));
+
+        let event = events.recv().await.expect("event");
+        assert_eq!(event.kind(), "webhook");
+        assert_eq!(event.payload()["body"]["status"], "failed");
+        assert!(event.payload().get("token").is_none());
+
+        apps.set_triggers_enabled("ci-triage", false).unwrap();
+        assert!(post(address, "/hooks/ci", Some("s3cret"), "{}")
+            .await
+            .starts_with("HTTP/1.1 404"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,11 +1,47 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
-use anyhow::{Context, Result};
+use ai_agent::OutputContract;
+use anyhow::{anyhow, bail, Context, Result};
+use ed25519_dalek::VerifyingKey;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::agent::{McpPromptRef, McpResourceRef};
+use crate::agent_app_inputs::{resolve_inputs, AgentAppInput};
+use crate::agent_app_packages::{AgentAppPackage, AgentAppPackageStore, InstalledAgentAppPackage};
+use crate::agent_app_templates::{render_template, PromptTemplate};
+use crate::agent_app_triggers::{AgentAppTrigger, AgentAppTriggerEvent, AgentAppTriggerSettings};
 
 const DEFAULT_APPS_PATH: &str = "configs/agent_apps.json";
+/// Lives next to the apps manifest.
+const TRIGGER_SETTINGS_FILE: &str = "agent_app_triggers.json";
+
+/// Tools registered by the agent runtime (`agent::tools`, plus the DOM
+/// action tool from `agent-core`) that apps may list in `requiredTools`.
+const AGENT_TOOLS: &[&str] = &[
+    "browser.navigate",
+    "browser.dom_query",
+    "browser.page_snapshot",
+    "browser.tabs",
+    "browser.recall",
+    "wallet.info",
+    "wallet.spend",
+    "gateway.create_presentation",
+    "gateway.approve_presentation",
+    "gateway.await_decision",
+    "gateway.introspect_decision",
+    "merchant.quote_cart",
+    "gateway.approve_cart",
+    "gateway.fetch_mandate",
+    "merchant.place_order",
+    "dom_action",
+];
+
+/// Tools provided by an A2UI surface rather than the agent runtime.
+const SURFACE_TOOL_PREFIX: &str = "a2ui.";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,8 +62,10 @@ pub struct BlockchainAccessContract {
     pub account_scope: Option<String>,
     #[serde(default)]
     pub allowed_chain_refs: Vec<String>,
+    /// Per-chain caps on what one run may spend, each an amount with its
+    /// unit, e.g. `{ "ethereum-mainnet": "0.01 ETH", "bitcoin-mainnet": "25000 sat" }`.
     #[serde(default)]
-    pub spend_limit: Option<String>,
+    pub spend_limit: Option<BTreeMap<String, String>>,
     #[serde(default)]
     pub approval_gates: Vec<String>,
     #[serde(default)]
@@ -51,6 +89,10 @@ pub struct AgentAppDefinition {
     pub input_hint: Option<String>,
     #[serde(default)]
     pub default_input: Option<String>,
+    /// Typed parameters the launcher asks for; their values are variables of
+    /// `prompt_template` alongside `input`.
+    #[serde(default)]
+    pub inputs: Vec<AgentAppInput>,
     #[serde(default)]
     pub communication_surface: Option<String>,
     #[serde(default)]
@@ -66,6 +108,23 @@ pub struct AgentAppDefinition {
     pub prompt_template: String,
     #[serde(default)]
     pub instructions: Option<String>,
+    /// JSON Schema the run's final answer must match; such runs keep their
+    /// answer as structured output.
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+    #[serde(default)]
+    pub mcp_resources: Vec<McpResourceRef>,
+    #[serde(default)]
+    pub mcp_prompts: Vec<McpPromptRef>,
+    /// Browser events that start a run of this app on their own.
+    #[serde(default)]
+    pub triggers: Vec<AgentAppTrigger>,
+    /// Icon URL; packaged apps get a `data:` URL of their bundled icon.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Set for apps installed from a package rather than bundled.
+    #[serde(skip)]
+    pub package: Option<InstalledAgentAppPackage>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -80,10 +139,17 @@ pub struct AgentAppSummary {
     pub quick_prompts: Vec<String>,
     pub input_hint: Option<String>,
     pub default_input: Option<String>,
+    pub inputs: Vec<AgentAppInput>,
+    pub output_schema: Option<Value>,
     pub communication_surface: Option<String>,
     pub required_tools: Vec<String>,
     pub approval_gates: Vec<String>,
     pub blockchain_access: Option<BlockchainAccessContract>,
+    pub icon: Option<String>,
+    pub package: Option<InstalledAgentAppPackage>,
+    pub trigger_count: usize,
+    /// Whether the user switched this app's triggers on.
+    pub triggers_enabled: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -103,32 +169,321 @@ impl AgentAppDefinition {
             quick_prompts: self.quick_prompts.clone(),
             input_hint: self.input_hint.clone(),
             default_input: self.default_input.clone(),
+            inputs: self.inputs.clone(),
+            output_schema: self.output_schema.clone(),
             communication_surface: self.communication_surface.clone(),
             required_tools: self.required_tools.clone(),
             approval_gates: self.approval_gates.clone(),
             blockchain_access: self.blockchain_access.clone(),
+            icon: self.icon.clone(),
+            package: self.package.clone(),
+            trigger_count: self.triggers.len(),
+            triggers_enabled: false,
+        }
+    }
+
+    /// Checks the fields the runtime relies on: a usable id, non-empty copy,
+    /// well-formed templates, input declarations and output schema, required
+    /// tools that exist, and a blockchain contract whose host tools are
+    /// covered by its flags and whose signing steps are approval-gated.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if !is_valid_app_id(&self.id) {
+            problems.push(format!(
+                "id `{}` must use lowercase letters, digits and dashes",
+                self.id
+            ));
+        }
+        for (field, value) in [
+            ("name", &self.name),
+            ("tagline", &self.tagline),
+            ("description", &self.description),
+            ("promptTemplate", &self.prompt_template),
+        ] {
+            if value.trim().is_empty() {
+                problems.push(format!("{field} must not be empty"));
+            }
+        }
+        for (field, template) in [
+            ("promptTemplate", Some(&self.prompt_template)),
+            ("instructions", self.instructions.as_ref()),
+        ] {
+            if let Some(Err(err)) = template.map(|template| PromptTemplate::parse(template)) {
+                problems.push(format!("{field}: {err}"));
+            }
+        }
+        for (index, input) in self.inputs.iter().enumerate() {
+            problems.extend(input.problems());
+            if self.inputs[..index]
+                .iter()
+                .any(|other| other.name == input.name)
+            {
+                problems.push(format!("input `{}` is declared twice", input.name));
+            }
+        }
+        if let Some(schema) = &self.output_schema {
+            if let Err(err) = OutputContract::compile(schema.clone()) {
+                problems.push(format!("outputSchema: {err}"));
+            }
+        }
+        for trigger in &self.triggers {
+            problems.extend(trigger.problems());
+        }
+
+        let a2ui_surface = self
+            .communication_surface
+            .as_deref()
+            .is_some_and(|surface| surface.starts_with("a2ui"));
+        for tool in &self.required_tools {
+            let known = AGENT_TOOLS.contains(&tool.as_str())
+                || (a2ui_surface && tool.starts_with(SURFACE_TOOL_PREFIX))
+                || self
+                    .blockchain_access
+                    .as_ref()
+                    .is_some_and(|contract| contract.host_tools.contains(tool));
+            if !known {
+                problems.push(format!("required tool `{tool}` does not exist"));
+            }
+        }
+
+        if let Some(contract) = &self.blockchain_access {
+            for tool in &contract.host_tools {
+                match host_tool_permitted(tool, contract) {
+                    None => problems.push(format!("host tool `{tool}` does not exist")),
+                    Some(false) => problems.push(format!(
+                        "host tool `{tool}` is not permitted by the blockchain access flags"
+                    )),
+                    Some(true) => {}
+                }
+            }
+            if contract.request_broadcast && !contract.request_signing {
+                problems.push("requestBroadcast requires requestSigning".to_string());
+            }
+            if (contract.request_signing || contract.request_broadcast)
+                && contract.approval_gates.is_empty()
+                && self.approval_gates.is_empty()
+            {
+                problems.push(
+                    "signing or broadcasting requires at least one approval gate".to_string(),
+                );
+            }
+            if let Err(err) = contract.spend_limits() {
+                problems.push(format!("spendLimit: {err}"));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "agent app `{}` is invalid: {}",
+                self.id,
+                problems.join("; ")
+            ))
+        }
+    }
+
+    /// Renders the task for a run from the free-text input and the values of
+    /// the declared [`inputs`](Self::inputs), which are validated first.
+    pub fn render_task(
+        &self,
+        user_input: Option<&str>,
+        values: &Map<String, Value>,
+    ) -> Result<String> {
+        let context = self.template_context(user_input, values)?;
+        self.render_with(&Value::Object(context))
+    }
+
+    /// Like [`Self::render_task`] with input defaults, additionally exposing
+    /// the payload of the event that triggered the run as `{{event}}`.
+    pub fn render_event_task(
+        &self,
+        user_input: Option<&str>,
+        event: &AgentAppTriggerEvent,
+    ) -> Result<String> {
+        let mut context = self.template_context(user_input, &Map::new())?;
+        context.insert("event".to_string(), event.payload());
+        self.render_with(&Value::Object(context))
+    }
+
+    /// Inputs for this app taken from another run's structured output: the
+    /// output's fields that match declared input names.
+    pub fn inputs_from_output(&self, output: &Value) -> Map<String, Value> {
+        let Value::Object(fields) = output else {
+            return Map::new();
+        };
+        self.inputs
+            .iter()
+            .filter_map(|input| {
+                let value = fields.get(&input.name)?;
+                Some((input.name.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    fn template_context(
+        &self,
+        user_input: Option<&str>,
+        values: &Map<String, Value>,
+    ) -> Result<Map<String, Value>> {
+        let mut context = resolve_inputs(&self.inputs, values)?;
+        context.insert(
+            "input".to_string(),
+            Value::String(self.resolve_input(user_input)),
+        );
+        Ok(context)
+    }
+
+    fn render_with(&self, context: &Value) -> Result<String> {
+        let prompt = render_template(&self.prompt_template, context)
+            .with_context(|| format!("agent app `{}` has an invalid prompt template", self.id))?;
+        match &self.instructions {
+            Some(instructions) => {
+                let instructions = render_template(instructions, context)
+                    .with_context(|| format!("agent app `{}` has invalid instructions", self.id))?;
+                Ok(format!("{}\n\n{}", instructions.trim(), prompt.trim()))
+            }
+            None => Ok(prompt),
         }
     }
 
-    pub fn render_task(&self, user_input: Option<&str>) -> String {
-        let trimmed_input = user_input
+    /// MCP prompt references with `{{input}}` substituted in argument values.
+    pub fn render_mcp_prompts(&self, user_input: Option<&str>) -> Vec<McpPromptRef> {
+        let input = self.resolve_input(user_input);
+        self.mcp_prompts
+            .iter()
+            .map(|reference| McpPromptRef {
+                server_id: reference.server_id.clone(),
+                name: reference.name.clone(),
+                arguments: reference
+                    .arguments
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.replace("{{input}}", &input)))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn resolve_input(&self, user_input: Option<&str>) -> String {
+        user_input
             .map(|value| value.trim())
             .filter(|value| !value.is_empty())
             .map(|value| value.to_string())
             .or_else(|| self.default_input.clone())
-            .unwrap_or_else(|| "".to_string());
+            .unwrap_or_else(|| "".to_string())
+    }
+}
 
-        let mut prompt = self.prompt_template.clone();
-        prompt = prompt.replace("{{input}}", &trimmed_input);
+impl BlockchainAccessContract {
+    /// The spend limits keyed by lowercase chain ref, in each chain's
+    /// smallest unit.
+    pub fn spend_limits(&self) -> Result<BTreeMap<String, u128>> {
+        let mut limits = BTreeMap::new();
+        for (chain, limit) in self.spend_limit.iter().flatten() {
+            let chain = chain.trim().to_ascii_lowercase();
+            if !self.allowed_chain_refs.is_empty()
+                && !self
+                    .allowed_chain_refs
+                    .iter()
+                    .any(|allowed| allowed.trim().eq_ignore_ascii_case(&chain))
+            {
+                bail!("chain `{chain}` is not in allowedChainRefs");
+            }
+            let amount = parse_chain_amount(&chain, limit)?;
+            limits.insert(chain, amount);
+        }
+        Ok(limits)
+    }
+}
+
+/// Units of each chain family's native asset and their decimals relative to
+/// the smallest unit `wallet.spend` amounts are given in.
+const CHAIN_UNITS: &[(&str, &[(&str, u32)])] = &[
+    ("ethereum", &[("eth", 18), ("gwei", 9), ("wei", 0)]),
+    ("base", &[("eth", 18), ("gwei", 9), ("wei", 0)]),
+    (
+        "polygon",
+        &[("pol", 18), ("matic", 18), ("gwei", 9), ("wei", 0)],
+    ),
+    ("bitcoin", &[("btc", 8), ("sat", 0), ("sats", 0)]),
+    ("solana", &[("sol", 9), ("lamport", 0), ("lamports", 0)]),
+];
 
-        if let Some(instructions) = &self.instructions {
-            format!("{}\n\n{}", instructions.trim(), prompt.trim())
+/// Parses an amount with its unit, such as `0.01 ETH` or `25000 sat`, into
+/// the smallest unit of `chain`.
+pub fn parse_chain_amount(chain: &str, amount: &str) -> Result<u128> {
+    let family = chain.split('-').next().unwrap_or_default();
+    let units = CHAIN_UNITS
+        .iter()
+        .find(|(name, _)| *name == family)
+        .map(|(_, units)| *units)
+        .ok_or_else(|| anyhow!("no known units for chain `{chain}`"))?;
+    let unit_names = || {
+        units
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let (value, unit) = amount
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("`{amount}` for `{chain}` needs a unit ({})", unit_names()))?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let decimals = units
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, decimals)| *decimals)
+        .ok_or_else(|| {
+            anyhow!(
+                "unknown unit `{unit}` for `{chain}`; expected one of {}",
+                unit_names()
+            )
+        })?;
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let digits = |text: &str| text.chars().all(|ch| ch.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+        bail!("`{amount}` for `{chain}` is not a non-negative decimal amount");
+    }
+    if fraction.len() > decimals as usize {
+        bail!("`{amount}` for `{chain}` is finer than the chain's smallest unit");
+    }
+    let parse = |text: &str| {
+        if text.is_empty() {
+            Some(0)
         } else {
-            prompt
+            text.parse::<u128>().ok()
         }
+    };
+    let fraction_scale = 10u128.pow(decimals - fraction.len() as u32);
+    parse(whole)
+        .and_then(|whole| whole.checked_mul(10u128.pow(decimals)))
+        .zip(parse(fraction))
+        .and_then(|(whole, fraction)| whole.checked_add(fraction * fraction_scale))
+        .ok_or_else(|| anyhow!("`{amount}` for `{chain}` is too large"))
+}
+
+/// Whether `contract` permits a host tool the browser exposes to apps with
+/// blockchain access, or `None` when no such host tool exists.
+fn host_tool_permitted(tool: &str, contract: &BlockchainAccessContract) -> Option<bool> {
+    match tool {
+        "dbrowser.chain.get_status" => Some(contract.read_chain_data),
+        "dbrowser.wallet.get_portfolio" => Some(contract.read_wallet_state),
+        "dbrowser.tx.prepare" => Some(contract.prepare_transactions),
+        "dbrowser.tx.simulate" => Some(contract.simulate_transactions),
+        "dbrowser.tx.request_signature" => Some(contract.request_signing),
+        "dbrowser.tx.request_broadcast" => Some(contract.request_broadcast),
+        _ => None,
     }
 }
 
+pub fn is_valid_app_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,6 +522,15 @@ mod tests {
             .any(|tool| tool == "dbrowser.tx.request_signature"));
     }
 
+    #[test]
+    fn default_manifest_apps_pass_validation() {
+        let registry = AgentAppRegistry::from_path(default_manifest_path()).expect("registry");
+        for summary in registry.list() {
+            let app = registry.find(&summary.id).expect("listed app");
+            app.validate().unwrap_or_else(|err| panic!("{err}"));
+        }
+    }
+
     #[test]
     fn default_manifest_includes_remaining_a2ui_demo_apps() {
         let registry = AgentAppRegistry::from_path(default_manifest_path()).expect("registry");
@@ -202,7 +566,9 @@ mod tests {
                 .iter()
                 .any(|gate| gate.contains("broadcast")));
 
-            let rendered = app.render_task(Some("Demo request with a current page."));
+            let rendered = app
+                .render_task(Some("Demo request with a current page."), &Map::new())
+                .expect("task");
             assert!(rendered.contains("Demo request"));
             assert!(rendered.contains("A2UI v0.9"));
             assert!(rendered.contains("browser.page_snapshot"));
@@ -239,7 +605,12 @@ mod tests {
         assert!(blockchain_access.request_signing);
         assert!(blockchain_access.request_broadcast);
 
-        let task = app.render_task(Some("Draft a thread for sunset photos with one image slot."));
+        let task = app
+            .render_task(
+                Some("Draft a thread for sunset photos with one image slot."),
+                &Map::new(),
+            )
+            .expect("task");
         assert!(task.contains("sunset photos"));
         assert!(task.contains("A2UI v0.9"));
         assert!(task.contains("boards"));
@@ -256,9 +627,12 @@ mod tests {
         let registry = AgentAppRegistry::from_path(default_manifest_path()).expect("registry");
         let app = registry.find("travel-booker").expect("travel-booker app");
 
-        let task = app.render_task(Some(
-            "Find flights from SFO to Tokyo for May 20-27 under $1500 with one checked bag.",
-        ));
+        let task = app
+            .render_task(
+                Some("Find flights from SFO to Tokyo for May 20-27 under $1500 with one checked bag."),
+                &Map::new(),
+            )
+            .expect("task");
 
         assert!(task.contains("SFO to Tokyo"));
         assert!(task.contains("A2UI v0.9"));
@@ -267,24 +641,173 @@ mod tests {
         assert!(task.contains("Do not book"));
         assert!(task.contains("explicit user approval"));
     }
+
+    #[test]
+    fn mcp_prompt_arguments_substitute_user_input() {
+        let app: AgentAppDefinition = serde_json::from_value(serde_json::json!({
+            "id": "policy-review",
+            "name": "Policy Review",
+            "tagline": "Review policies",
+            "description": "Checks a policy against server-provided guidance.",
+            "promptTemplate": "Review {{input}}",
+            "defaultInput": "the refund policy",
+            "mcpResources": [{ "serverId": "docs", "uri": "file:///policy.md" }],
+            "mcpPrompts": [{
+                "serverId": "docs",
+                "name": "review",
+                "arguments": { "topic": "{{input}}", "tone": "formal" }
+            }]
+        }))
+        .expect("app definition");
+
+        let prompts = app.render_mcp_prompts(Some("shipping terms"));
+        assert_eq!(prompts[0].arguments["topic"], "shipping terms");
+        assert_eq!(prompts[0].arguments["tone"], "formal");
+        assert_eq!(
+            app.render_mcp_prompts(None)[0].arguments["topic"],
+            "the refund policy"
+        );
+        assert_eq!(app.mcp_resources[0].uri, "file:///policy.md");
+    }
+
+    #[test]
+    fn typed_inputs_fill_the_prompt_template() {
+        let app: AgentAppDefinition = serde_json::from_value(serde_json::json!({
+            "id": "trip-planner",
+            "name": "Trip Planner",
+            "tagline": "Plan trips",
+            "description": "Plans a trip from typed parameters.",
+            "inputs": [
+                { "name": "city", "type": "string", "required": true },
+                { "name": "nights", "type": "number", "min": 1, "default": 2 },
+                { "name": "stops", "type": "string" }
+            ],
+            "promptTemplate": "Plan {{nights}} nights in {{city}}.\
+                {{#if stops}} Stop in {{stops}}.{{/if}}\
+                {{#if input}} Note: {{input}}{{/if}}"
+        }))
+        .expect("app definition");
+        app.validate().expect("valid app");
+
+        let values = serde_json::json!({ "city": "Lisbon", "stops": "Porto" });
+        let task = app
+            .render_task(Some("window seat"), values.as_object().unwrap())
+            .expect("task");
+        assert_eq!(
+            task,
+            "Plan 2 nights in Lisbon. Stop in Porto. Note: window seat"
+        );
+
+        let err = app.render_task(None, &Map::new()).unwrap_err();
+        assert!(err.to_string().contains("`city` is required"));
+
+        let mut broken = app.clone();
+        broken.prompt_template = "{{#each stops}}{{this}}".to_string();
+        broken.inputs[1].default = Some(Value::from(0));
+        let err = broken.validate().unwrap_err().to_string();
+        assert!(err.contains("promptTemplate: `{{#each stops}}` is never closed"));
+        assert!(err.contains("default of input `nights`: must be at least 1"));
+    }
+
+    #[test]
+    fn output_schemas_are_checked_and_outputs_feed_declared_inputs() {
+        let mut app: AgentAppDefinition = serde_json::from_value(serde_json::json!({
+            "id": "price-check",
+            "name": "Price Check",
+            "tagline": "Check prices",
+            "description": "Checks the price of a product.",
+            "inputs": [
+                { "name": "product", "type": "string" },
+                { "name": "budget", "type": "number" }
+            ],
+            "promptTemplate": "Check {{product}} under {{budget}}.",
+            "outputSchema": { "type": "object", "required": ["price"] }
+        }))
+        .expect("app definition");
+        app.validate().expect("valid app");
+
+        let inputs = app.inputs_from_output(&serde_json::json!({
+            "product": "kettle",
+            "budget": 40,
+            "currency": "EUR"
+        }));
+        assert_eq!(
+            Value::Object(inputs),
+            serde_json::json!({ "product": "kettle", "budget": 40 })
+        );
+        assert!(app.inputs_from_output(&serde_json::json!([1])).is_empty());
+
+        app.output_schema = Some(serde_json::json!({ "type": 7 }));
+        let err = app.validate().unwrap_err().to_string();
+        assert!(err.contains("outputSchema: invalid output schema"));
+    }
+
+    #[test]
+    fn spend_limits_need_a_unit_the_chain_understands() {
+        assert_eq!(
+            parse_chain_amount("ethereum-mainnet", "0.01 ETH").unwrap(),
+            10_000_000_000_000_000
+        );
+        assert_eq!(
+            parse_chain_amount("bitcoin-mainnet", "25000 sat").unwrap(),
+            25_000
+        );
+        assert_eq!(
+            parse_chain_amount("solana-mainnet", "1.5 SOL").unwrap(),
+            1_500_000_000
+        );
+        assert!(parse_chain_amount("ethereum-mainnet", "25")
+            .unwrap_err()
+            .to_string()
+            .contains("needs a unit"));
+        assert!(parse_chain_amount("bitcoin-mainnet", "1 ETH").is_err());
+        assert!(parse_chain_amount("bitcoin-mainnet", "0.000000001 BTC").is_err());
+        assert!(parse_chain_amount("ethereum-mainnet", "-1 wei").is_err());
+
+        let contract: BlockchainAccessContract = serde_json::from_value(serde_json::json!({
+            "allowedChainRefs": ["ethereum-sepolia"],
+            "spendLimit": { "ethereum-mainnet": "1 ETH" }
+        }))
+        .expect("contract");
+        assert!(contract
+            .spend_limits()
+            .unwrap_err()
+            .to_string()
+            .contains("not in allowedChainRefs"));
+    }
 }
 
 pub struct AgentAppRegistry {
     apps: Arc<RwLock<Vec<AgentAppDefinition>>>,
     manifest_path: PathBuf,
+    packages: Option<AgentAppPackageStore>,
+    trigger_settings: AgentAppTriggerSettings,
 }
 
 impl AgentAppRegistry {
     pub fn load_default() -> Result<Self> {
-        Self::from_path(DEFAULT_APPS_PATH)
+        Self::with_package_store(DEFAULT_APPS_PATH, AgentAppPackageStore::default_location())
     }
 
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_package_store(path, None)
+    }
+
+    /// Bundled apps from `path` plus the packages installed in `packages`.
+    pub fn with_package_store(
+        path: impl AsRef<Path>,
+        packages: Option<AgentAppPackageStore>,
+    ) -> Result<Self> {
         let manifest_path = path.as_ref().to_path_buf();
-        let apps = Self::read_manifest(&manifest_path)?;
+        let apps = Self::read_apps(&manifest_path, packages.as_ref())?;
+        let trigger_settings = AgentAppTriggerSettings::from_path(
+            manifest_path.with_file_name(TRIGGER_SETTINGS_FILE),
+        )?;
         Ok(Self {
             apps: Arc::new(RwLock::new(apps)),
             manifest_path,
+            packages,
+            trigger_settings,
         })
     }
 
@@ -292,6 +815,8 @@ impl AgentAppRegistry {
         Self {
             apps: Arc::new(RwLock::new(Vec::new())),
             manifest_path: PathBuf::from(DEFAULT_APPS_PATH),
+            packages: None,
+            trigger_settings: AgentAppTriggerSettings::empty(),
         }
     }
 
@@ -300,10 +825,17 @@ impl AgentAppRegistry {
             .read()
             .expect("agent apps poisoned")
             .iter()
-            .map(|app| app.summary())
+            .map(|app| self.summary_of(app))
             .collect()
     }
 
+    fn summary_of(&self, app: &AgentAppDefinition) -> AgentAppSummary {
+        AgentAppSummary {
+            triggers_enabled: self.trigger_settings.is_enabled(&app.id),
+            ..app.summary()
+        }
+    }
+
     pub fn find(&self, id: &str) -> Option<AgentAppDefinition> {
         self.apps
             .read()
@@ -313,13 +845,160 @@ impl AgentAppRegistry {
             .cloned()
     }
 
+    /// Apps that declare at least one trigger and whose triggers the user
+    /// enabled.
+    pub fn triggered_apps(&self) -> Vec<AgentAppDefinition> {
+        self.apps
+            .read()
+            .expect("agent apps poisoned")
+            .iter()
+            .filter(|app| !app.triggers.is_empty() && self.trigger_settings.is_enabled(&app.id))
+            .cloned()
+            .collect()
+    }
+
+    /// Switches the triggers of an installed app on or off.
+    pub fn set_triggers_enabled(&self, app_id: &str, enabled: bool) -> Result<AgentAppSummary> {
+        let app = self
+            .find(app_id)
+            .ok_or_else(|| anyhow!("agent app `{app_id}` is not installed"))?;
+        if enabled && app.triggers.is_empty() {
+            bail!("agent app `{app_id}` declares no triggers");
+        }
+        self.trigger_settings.set_enabled(app_id, enabled)?;
+        Ok(self.summary_of(&app))
+    }
+
     pub fn reload(&self) -> Result<()> {
-        let apps = Self::read_manifest(&self.manifest_path)?;
+        let apps = Self::read_apps(&self.manifest_path, self.packages.as_ref())?;
         let mut guard = self.apps.write().expect("agent apps poisoned");
         *guard = apps;
         Ok(())
     }
 
+    /// Verifies and installs a package for an app that is not installed yet.
+    pub fn install_package(
+        &self,
+        package: &AgentAppPackage,
+        trusted_keys: &[VerifyingKey],
+        source: &str,
+        now_ms: u64,
+    ) -> Result<AgentAppSummary> {
+        let store = self.package_store()?;
+        let app_id = package.app_id();
+        if store.installed(app_id).is_some() {
+            bail!("agent app `{app_id}` is already installed; update it instead");
+        }
+        if self.find(app_id).is_some() {
+            bail!("agent app `{app_id}` is bundled with the browser");
+        }
+        self.write_package(store, package, trusted_keys, source, now_ms)
+    }
+
+    /// Replaces an installed app with a newer version signed by the same
+    /// publisher.
+    pub fn update_package(
+        &self,
+        app_id: &str,
+        package: &AgentAppPackage,
+        trusted_keys: &[VerifyingKey],
+        source: &str,
+        now_ms: u64,
+    ) -> Result<AgentAppSummary> {
+        let store = self.package_store()?;
+        let installed = store
+            .installed(app_id)
+            .ok_or_else(|| anyhow!("agent app `{app_id}` is not installed from a package"))?;
+        if package.app_id() != app_id {
+            bail!("package is for app `{}`, not `{app_id}`", package.app_id());
+        }
+        if !package
+            .publisher
+            .public_key
+            .eq_ignore_ascii_case(&installed.publisher_key)
+        {
+            bail!(
+                "package for `{app_id}` is signed by {} rather than its publisher {}",
+                package.publisher.public_key,
+                installed.publisher_key
+            );
+        }
+        let current = semver::Version::parse(&installed.version)
+            .with_context(|| format!("parsing installed version of `{app_id}`"))?;
+        let next = package.version()?;
+        if next <= current {
+            bail!("package version {next} of `{app_id}` is not newer than {current}");
+        }
+        // Triggers the user has not reviewed must not start firing on their
+        // own, so changed triggers have to be enabled again.
+        let previous_triggers = self.find(app_id).map(|app| app.triggers);
+        if previous_triggers.as_ref() != Some(&package.to_definition()?.triggers) {
+            self.trigger_settings.set_enabled(app_id, false)?;
+        }
+        self.write_package(store, package, trusted_keys, source, now_ms)
+    }
+
+    /// Removes an installed app. Returns `false` when it was not installed.
+    pub fn uninstall_package(&self, app_id: &str) -> Result<bool> {
+        let store = self.package_store()?;
+        if store.installed(app_id).is_none() {
+            if self.find(app_id).is_some() {
+                bail!("agent app `{app_id}` is bundled with the browser");
+            }
+            return Ok(false);
+        }
+        let removed = store.remove(app_id)?;
+        self.trigger_settings.set_enabled(app_id, false)?;
+        self.reload()?;
+        Ok(removed)
+    }
+
+    fn package_store(&self) -> Result<&AgentAppPackageStore> {
+        self.packages
+            .as_ref()
+            .ok_or_else(|| anyhow!("agent app packages are unavailable without a home directory"))
+    }
+
+    fn write_package(
+        &self,
+        store: &AgentAppPackageStore,
+        package: &AgentAppPackage,
+        trusted_keys: &[VerifyingKey],
+        source: &str,
+        now_ms: u64,
+    ) -> Result<AgentAppSummary> {
+        package.verify(trusted_keys)?;
+        let mut app = package.to_definition()?;
+        let install = InstalledAgentAppPackage {
+            version: package.version()?.to_string(),
+            publisher_key: package.publisher.public_key.to_ascii_lowercase(),
+            source: source.to_string(),
+            installed_at_ms: now_ms,
+        };
+        store.write(package, &install)?;
+        self.reload()?;
+        app.package = Some(install);
+        Ok(self.summary_of(&app))
+    }
+
+    fn read_apps(
+        manifest_path: &Path,
+        packages: Option<&AgentAppPackageStore>,
+    ) -> Result<Vec<AgentAppDefinition>> {
+        let mut apps = Self::read_manifest(manifest_path)?;
+        for app in packages.map(AgentAppPackageStore::load).unwrap_or_default() {
+            if apps.iter().any(|existing| existing.id == app.id) {
+                tracing::warn!(
+                    "installed agent app `{}` shadows a bundled app; skipping",
+                    app.id
+                );
+                continue;
+            }
+            apps.push(app);
+        }
+        Ok(apps)
+    }
+
     fn read_manifest(path: &Path) -> Result<Vec<AgentAppDefinition>> {
         if !path.exists() {
             return Ok(Vec::new());
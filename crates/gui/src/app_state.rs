@@ -1,17 +1,21 @@
 use crate::wallet_store::WalletStore;
 use afm_node::{AfmNodeConfig, AfmNodeController, AfmNodeHandle};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tokio::sync::{watch, Mutex as AsyncMutex};
 
 use crate::agent::{AgentManager, ApprovalBroker, McpServerRegistry};
+use crate::agent_app_pipelines::AgentAppPipelineRegistry;
 use crate::agent_app_schedules::AgentAppScheduleRegistry;
+use crate::agent_app_triggers::AgentAppTriggerHub;
 use crate::agent_apps::AgentAppRegistry;
 use crate::browser_engine::BrowserEngine;
 use crate::mcp_profiles::McpConfigService;
 use crate::protocol_handlers::ProtocolHandler;
 use crate::security::SecurityManager;
+use crate::semantic_index::SemanticIndex;
 use crate::telemetry::TelemetryManager;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -28,6 +32,17 @@ impl ContentWebviewBounds {
     }
 }
 
+/// Where a tab owned by an agent run is shown while the run drives it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTabSurface {
+    /// Never shown; the run works off-screen.
+    #[default]
+    Hidden,
+    /// Shown read-only in the agent side panel next to the user's page.
+    SidePanel,
+}
+
 #[derive(Clone)]
 pub struct DownloadControl {
     pub url: String,
@@ -43,6 +58,10 @@ pub struct AppState {
     pub content_bounds: Mutex<Option<ContentWebviewBounds>>,
     pub last_content_bounds: Mutex<Option<ContentWebviewBounds>>,
     pub content_webview_visible: Mutex<bool>,
+    /// Tabs currently owned by agent runs. They stay out of the tab strip,
+    /// history and address bar until the run hands them over.
+    pub agent_run_tabs: Mutex<HashMap<String, RunTabSurface>>,
+    pub agent_panel_bounds: Mutex<Option<ContentWebviewBounds>>,
     pub browser_engine: Arc<BrowserEngine>,
     pub protocol_handler: Arc<Mutex<ProtocolHandler>>,
     pub security_manager: Arc<Mutex<SecurityManager>>,
@@ -56,6 +75,9 @@ pub struct AppState {
     pub mcp_registry: Arc<McpServerRegistry>,
     pub mcp_config: Arc<McpConfigService>,
     pub agent_apps: Arc<AgentAppRegistry>,
+    pub agent_app_pipelines: Arc<AgentAppPipelineRegistry>,
     pub agent_app_schedules: Arc<AgentAppScheduleRegistry>,
+    pub agent_app_triggers: Arc<AgentAppTriggerHub>,
     pub download_controls: Arc<AsyncMutex<HashMap<String, DownloadControl>>>,
+    pub semantic_index: Arc<SemanticIndex>,
 }
@@ -2,6 +2,7 @@ use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::sync::{Arc, Mutex};
 use url::Url;
 
@@ -75,6 +76,8 @@ pub struct BrowserEngine {
     pub history: Arc<Mutex<Vec<HistoryEntry>>>,
     pub bookmarks: Arc<Mutex<Vec<Bookmark>>>,
     pub downloads: Arc<Mutex<Vec<DownloadItem>>>,
+    /// Bumped whenever history or bookmarks change.
+    browsing_data_revision: Arc<AtomicU64>,
 }
 
 /// Download item
@@ -107,9 +110,21 @@ impl BrowserEngine {
             history: Arc::new(Mutex::new(Vec::new())),
             bookmarks: Arc::new(Mutex::new(Vec::new())),
             downloads: Arc::new(Mutex::new(Vec::new())),
+            browsing_data_revision: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Changes whenever history or bookmarks do, so derived data such as the
+    /// semantic index can tell whether it is stale.
+    pub fn browsing_data_revision(&self) -> u64 {
+        self.browsing_data_revision.load(AtomicOrdering::SeqCst)
+    }
+
+    fn touch_browsing_data(&self) {
+        self.browsing_data_revision
+            .fetch_add(1, AtomicOrdering::SeqCst);
+    }
+
     /// Create a new tab
     pub fn create_tab(&self, url: String) -> Result<String> {
         let tab_id = uuid::Uuid::new_v4().to_string();
@@ -297,7 +312,11 @@ impl BrowserEngine {
             .map_err(|_| anyhow!("Failed to lock history"))?;
         let before = history.len();
         history.retain(|entry| entry.id != entry_id);
-        Ok(history.len() != before)
+        let removed = history.len() != before;
+        if removed {
+            self.touch_browsing_data();
+        }
+        Ok(removed)
     }
 
     pub fn clear_history(&self) -> Result<()> {
@@ -306,6 +325,7 @@ impl BrowserEngine {
             .lock()
             .map_err(|_| anyhow!("Failed to lock history"))?;
         history.clear();
+        self.touch_browsing_data();
         Ok(())
     }
 
@@ -333,6 +353,7 @@ impl BrowserEngine {
 
         if let Ok(mut bookmarks) = self.bookmarks.lock() {
             bookmarks.push(bookmark);
+            self.touch_browsing_data();
         }
 
         Ok(bookmark_id)
@@ -342,6 +363,7 @@ impl BrowserEngine {
     pub fn remove_bookmark(&self, bookmark_id: &str) -> Result<()> {
         if let Ok(mut bookmarks) = self.bookmarks.lock() {
             bookmarks.retain(|b| b.id != bookmark_id);
+            self.touch_browsing_data();
         }
         Ok(())
     }
@@ -496,6 +518,7 @@ impl BrowserEngine {
         if history_len > 1000 {
             history.drain(0..history_len - 1000);
         }
+        self.touch_browsing_data();
 
         Ok(())
     }
@@ -1,5 +1,11 @@
-use crate::agent::{McpRuntimeStatus, McpServerConfig, McpServerRegistry, McpServerState};
-use crate::mcp_profiles::McpProfileState;
+use crate::agent::{
+    McpOAuthAuthorization, McpPrompt, McpPromptResult, McpResource, McpResourceContents,
+    McpRuntimeStatus, McpServerConfig, McpServerRegistry, McpServerState,
+};
+use crate::agent_app_triggers::AgentAppTriggerEvent;
+use crate::mcp_bundle::McpBundleSigner;
+use crate::mcp_profiles::{McpBundleInspection, McpProfileState};
+use crate::semantic_index::{SemanticSearchHit, SemanticSourceKind};
 use crate::wallet_store::{SpendDecision, WalletOwner, WalletPolicy, WalletSnapshot};
 use crate::{afm, browser_engine::*, security::*, AppState};
 use afm_node::{AfmNodeController, AfmTaskDescriptor, GossipFrame, NodeStatus};
@@ -7,6 +13,7 @@ use anyhow::Result;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
 use futures_util::StreamExt;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -23,6 +30,7 @@ pub const HISTORY_UPDATED_EVENT: &str = "browser://history-updated";
 pub const TAB_STATE_UPDATED_EVENT: &str = "browser://tab-state-updated";
 pub const DOWNLOADS_UPDATED_EVENT: &str = "browser://downloads-updated";
 pub const NAVIGATION_BLOCKED_EVENT: &str = "browser://navigation-blocked";
+pub const MCP_OAUTH_EVENT: &str = "mcp://oauth-updated";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -175,7 +183,16 @@ pub async fn get_tabs<R: Runtime>(
     state: State<'_, AppState>,
     _app_handle: AppHandle<R>,
 ) -> Result<Vec<Tab>, String> {
-    state.browser_engine.get_tabs().map_err(|e| e.to_string())
+    let tabs = state.browser_engine.get_tabs().map_err(|e| e.to_string())?;
+    // Tabs owned by agent runs are not the user's until handed over.
+    let agent_tabs = state
+        .agent_run_tabs
+        .lock()
+        .map_err(|_| "agent run tab mutex poisoned".to_string())?;
+    Ok(tabs
+        .into_iter()
+        .filter(|tab| !agent_tabs.contains_key(&tab.id))
+        .collect())
 }
 
 // Bookmark management commands
@@ -189,10 +206,20 @@ pub async fn add_bookmark<R: Runtime>(
     state: State<'_, AppState>,
     _app_handle: AppHandle<R>,
 ) -> Result<String, String> {
-    state
+    let bookmark_id = state
         .browser_engine
-        .add_bookmark(title, url, folder, tags)
-        .map_err(|e| e.to_string())
+        .add_bookmark(title.clone(), url.clone(), folder.clone(), tags.clone())
+        .map_err(|e| e.to_string())?;
+    state
+        .agent_app_triggers
+        .emit(AgentAppTriggerEvent::BookmarkAdded {
+            bookmark_id: bookmark_id.clone(),
+            url,
+            title,
+            folder,
+            tags,
+        });
+    Ok(bookmark_id)
 }
 
 #[tauri::command]
@@ -264,6 +291,34 @@ pub async fn search_history<R: Runtime>(
         .map_err(|e| e.to_string())
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticSearchRequest {
+    pub query: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub kinds: Vec<SemanticSourceKind>,
+}
+
+#[tauri::command]
+pub async fn semantic_search<R: Runtime>(
+    request: SemanticSearchRequest,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<Vec<SemanticSearchHit>, String> {
+    state
+        .semantic_index
+        .sync_browser_data(&state.browser_engine)
+        .await
+        .map_err(|e| e.to_string())?;
+    state
+        .semantic_index
+        .search(&request.query, request.limit.unwrap_or(12), &request.kinds)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn remove_history_entry<R: Runtime>(
     entry_id: String,
@@ -335,6 +390,30 @@ pub async fn probe_runtime_url<R: Runtime>(
     Ok(available)
 }
 
+#[tauri::command]
+pub async fn pin_ipfs_content<R: Runtime>(
+    url: String,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<String, String> {
+    let handler = state
+        .protocol_handler
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone();
+    let cid = handler
+        .pin_ipfs_content(&url)
+        .await
+        .map_err(|e| e.to_string())?;
+    state
+        .agent_app_triggers
+        .emit(AgentAppTriggerEvent::IpfsPinned {
+            cid: cid.clone(),
+            url: url.trim().to_string(),
+        });
+    Ok(cid)
+}
+
 async fn probe_decentralized_url(
     state: &State<'_, AppState>,
     url: &str,
@@ -421,7 +500,7 @@ pub async fn get_security_status<R: Runtime>(
 
 // Download management commands
 
-fn preferred_data_root() -> Result<PathBuf, String> {
+pub(crate) fn preferred_data_root() -> Result<PathBuf, String> {
     if cfg!(target_os = "macos") {
         if let Some(home) = std::env::var_os("HOME") {
             return Ok(PathBuf::from(home)
@@ -656,9 +735,24 @@ async fn run_download_task<R: Runtime>(
         return;
     }
 
-    let _ = state
+    let path = destination.to_string_lossy().into_owned();
+    if state
         .browser_engine
-        .complete_download(&download_id, destination.to_string_lossy().into_owned());
+        .complete_download(&download_id, path.clone())
+        .is_ok()
+    {
+        state
+            .agent_app_triggers
+            .emit(AgentAppTriggerEvent::DownloadCompleted {
+                download_id: download_id.clone(),
+                url,
+                filename: destination
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path,
+            });
+    }
     let _ = emit_downloads_updated(&app_handle, &state);
     cleanup_download_control(&state, &download_id).await;
 }
@@ -1120,6 +1214,15 @@ pub struct McpProfileUpdatePayload {
     pub servers: Vec<ManagedMcpServer>,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpEncryptedImportPayload {
+    #[serde(flatten)]
+    pub update: McpProfileUpdatePayload,
+    pub signer: McpBundleSigner,
+    pub secrets_restored: usize,
+}
+
 #[tauri::command]
 pub async fn list_mcp_servers<R: Runtime>(
     state: State<'_, AppState>,
@@ -1262,6 +1365,103 @@ pub async fn export_mcp_profile<R: Runtime>(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn export_encrypted_mcp_profile<R: Runtime>(
+    profile_id: Option<String>,
+    target_path: String,
+    passphrase: String,
+    include_secrets: Option<bool>,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<McpBundleSigner, String> {
+    let profile = match profile_id {
+        Some(id) => id,
+        None => state
+            .mcp_config
+            .active_profile_id()
+            .map_err(|e| e.to_string())?,
+    };
+    let path = PathBuf::from(&target_path);
+    let config = state.mcp_config.clone();
+    // Key derivation is deliberately slow; keep it off the async runtime.
+    tokio::task::spawn_blocking(move || {
+        config.export_encrypted_profile(
+            &profile,
+            &path,
+            &passphrase,
+            include_secrets.unwrap_or(true),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn inspect_encrypted_mcp_bundle<R: Runtime>(
+    bundle_path: String,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<McpBundleInspection, String> {
+    state
+        .mcp_config
+        .inspect_encrypted_bundle(&PathBuf::from(&bundle_path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn import_encrypted_mcp_profile<R: Runtime>(
+    bundle_path: String,
+    passphrase: String,
+    expected_signer: Option<String>,
+    confirmed_fingerprint: Option<String>,
+    label: Option<String>,
+    make_active: Option<bool>,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<McpEncryptedImportPayload, String> {
+    let activate = make_active.unwrap_or(true);
+    let path = PathBuf::from(&bundle_path);
+    let config = state.mcp_config.clone();
+    let imported = tokio::task::spawn_blocking(move || {
+        config.import_encrypted_profile_from_path(
+            &path,
+            &passphrase,
+            expected_signer
+                .as_deref()
+                .filter(|signer| !signer.trim().is_empty()),
+            confirmed_fingerprint
+                .as_deref()
+                .filter(|fingerprint| !fingerprint.trim().is_empty()),
+            label.as_deref(),
+            activate,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    if activate {
+        state
+            .mcp_registry
+            .reload_active()
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(McpEncryptedImportPayload {
+        update: build_profile_payload(&state).await?,
+        signer: imported.signer,
+        secrets_restored: imported.secrets_restored,
+    })
+}
+
+#[tauri::command]
+pub async fn mcp_bundle_signer<R: Runtime>(
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<McpBundleSigner, String> {
+    state.mcp_config.bundle_signer().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn read_mcp_secret<R: Runtime>(
     secret_id: String,
@@ -1272,6 +1472,128 @@ pub async fn read_mcp_secret<R: Runtime>(
     vault.read(&secret_id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn list_mcp_resources<R: Runtime>(
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<Vec<McpResource>, String> {
+    Ok(state.mcp_registry.list_resources().await)
+}
+
+#[tauri::command]
+pub async fn read_mcp_resource<R: Runtime>(
+    server_id: String,
+    uri: String,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<Vec<McpResourceContents>, String> {
+    state
+        .mcp_registry
+        .read_resource(&server_id, &uri)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn subscribe_mcp_resource<R: Runtime>(
+    server_id: String,
+    uri: String,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<(), String> {
+    state
+        .mcp_registry
+        .subscribe_resource(&server_id, &uri)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unsubscribe_mcp_resource<R: Runtime>(
+    server_id: String,
+    uri: String,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<(), String> {
+    state
+        .mcp_registry
+        .unsubscribe_resource(&server_id, &uri)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_mcp_prompts<R: Runtime>(
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<Vec<McpPrompt>, String> {
+    Ok(state.mcp_registry.list_prompts().await)
+}
+
+#[tauri::command]
+pub async fn get_mcp_prompt<R: Runtime>(
+    server_id: String,
+    name: String,
+    arguments: Option<IndexMap<String, String>>,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<McpPromptResult, String> {
+    state
+        .mcp_registry
+        .get_prompt(&server_id, &name, &arguments.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpOAuthEvent {
+    pub server_id: String,
+    pub authorized: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Starts the OAuth flow for a server and returns the URL the UI should open
+/// in a tab. Completion is reported through [`MCP_OAUTH_EVENT`].
+#[tauri::command]
+pub async fn begin_mcp_oauth<R: Runtime>(
+    server_id: String,
+    state: State<'_, AppState>,
+    app_handle: AppHandle<R>,
+) -> Result<McpOAuthAuthorization, String> {
+    let registry = state.mcp_registry.clone();
+    let pending = registry
+        .begin_oauth(&server_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let authorization = pending.authorization().clone();
+    tauri::async_runtime::spawn(async move {
+        let outcome = registry.complete_oauth(&server_id, pending).await;
+        let event = McpOAuthEvent {
+            server_id,
+            authorized: outcome.is_ok(),
+            error: outcome.err().map(|err| format!("{err:#}")),
+        };
+        let _ = app_handle.emit(MCP_OAUTH_EVENT, &event);
+    });
+    Ok(authorization)
+}
+
+#[tauri::command]
+pub async fn sign_out_mcp_oauth<R: Runtime>(
+    server_id: String,
+    state: State<'_, AppState>,
+    _app_handle: AppHandle<R>,
+) -> Result<McpProfileUpdatePayload, String> {
+    state
+        .mcp_registry
+        .sign_out_oauth(&server_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    build_profile_payload(&state).await
+}
+
 async fn compose_mcp_view(
     configs: Vec<McpServerConfig>,
     registry: Arc<McpServerRegistry>,
@@ -1,14 +1,21 @@
 pub mod afm;
 pub mod agent;
 pub mod agent_apps;
+pub mod agent_app_inputs;
+pub mod agent_app_packages;
+pub mod agent_app_pipelines;
 pub mod agent_app_schedules;
+pub mod agent_app_templates;
+pub mod agent_app_triggers;
 pub mod app_state;
 pub mod browser_engine;
 pub mod commands;
+pub mod mcp_bundle;
 pub mod mcp_profiles;
 pub mod performance;
 pub mod protocol_handlers;
 pub mod security;
+pub mod semantic_index;
 pub mod telemetry;
 pub mod telemetry_commands;
 pub mod wallet_store;
@@ -4,7 +4,7 @@
 )]
 
 use afm_node::AfmNodeConfig;
-use ai_agent::McpToolDescription;
+use ai_agent::{HashingEmbeddingClient, McpToolDescription};
 use serde::Deserialize;
 use std::fs::{create_dir_all, OpenOptions};
 use std::io::Write;
@@ -22,22 +22,34 @@ use tokio::sync::Mutex as AsyncMutex;
 
 // Use the library crate modules
 use gui::agent::{
-    AgentManager, AgentRunRequest, AgentRunResponse, AgentRunSummary, AgentSkillSummary,
-    ApprovalBroker, CreditSnapshot, McpServerRegistry,
+    AgentManager, AgentRunExportFormat, AgentRunOutputFormat, AgentRunPriority, AgentRunQuery,
+    AgentRunQueueLimits, AgentRunQueueSnapshot, AgentRunRequest, AgentRunResponse,
+    AgentRunRetention, AgentRunSummary, AgentRunTranscript, AgentSkillStatus, AgentSkillSummary,
+    ApprovalBroker, CreditLedgerEntry, CreditSnapshot, CreditStatement, CreditStatementFormat,
+    McpServerModeStatus, McpServerRegistry,
 };
+use gui::agent_app_packages::{fetch_agent_app_package, trusted_publisher_keys};
+use gui::agent_app_pipelines::{AgentAppPipeline, AgentAppPipelineRegistry, AgentAppPipelineRun};
 use gui::agent_app_schedules::{
-    AgentAppScheduleDraft, AgentAppScheduleRegistry, AgentAppScheduleSummary,
+    AgentAppScheduleDefinition, AgentAppScheduleDraft, AgentAppScheduleRegistry,
+    AgentAppScheduleSummary, ScheduleRunOutcome, AGENT_APP_SCHEDULE_ALERT_EVENT,
+};
+use gui::agent_app_triggers::{
+    watched_wallet_addresses, AgentAppTriggerEvent, AgentAppTriggerHub, WalletBalanceTracker,
+    DEFAULT_TRIGGER_WEBHOOK_PORT,
 };
 use gui::agent_apps::{AgentAppRegistry, AgentAppSummary};
-use gui::app_state::{AppState, ContentWebviewBounds};
+use gui::app_state::{AppState, ContentWebviewBounds, RunTabSurface};
 use gui::browser_engine::BrowserEngine;
 use gui::commands::*;
 use gui::mcp_profiles::McpConfigService;
 use gui::protocol_handlers::ProtocolHandler;
 use gui::security::SecurityManager;
+use gui::semantic_index::SemanticIndex;
 use gui::telemetry::TelemetryManager;
 use gui::telemetry_commands::*;
 use gui::wallet_store::WalletStore;
+use gui::webview_automation::TabWebviewHost;
 
 const MAIN_WEBVIEW_LABEL: &str = "main";
 const CONTENT_WEBVIEW_PREFIX: &str = "content-tab-";
@@ -375,12 +387,19 @@ fn sync_runtime_tab_event<R: Runtime>(
         .try_state::<AppState>()
         .ok_or_else(|| "application state unavailable".to_string())?;
 
-    ensure_browser_engine_tab(&state, tab_id, url, true)?;
+    let agent_owned = agent_run_tab_surface(&state, tab_id)?.is_some();
+    ensure_browser_engine_tab(&state, tab_id, url, !agent_owned)?;
     state
         .browser_engine
         .update_tab(tab_id, None, Some(url.to_string()), Some(loading))
         .map_err(|err| err.to_string())?;
 
+    // Pages loaded by an agent run stay out of the address bar, history and
+    // tab strip until the run hands the tab over.
+    if agent_owned {
+        return Ok(());
+    }
+
     if let Ok(mut current_url) = state.current_url.lock() {
         *current_url = url.to_string();
     }
@@ -391,20 +410,44 @@ fn sync_runtime_tab_event<R: Runtime>(
             .add_to_history(url.to_string(), url.to_string())
             .map_err(|err| err.to_string())?;
         emit_history_updated(app_handle, &state)?;
+        state
+            .agent_app_triggers
+            .emit(AgentAppTriggerEvent::PageVisit {
+                tab_id: tab_id.to_string(),
+                url: url.to_string(),
+            });
     }
 
     emit_runtime_tab_state(app_handle, tab_id, Some(url), None, Some(loading));
     Ok(())
 }
 
+/// Polls the bundled skill manifest and user skill directories so edits
+/// take effect without restarting the browser.
+fn spawn_skill_watcher<R: Runtime>(app_handle: tauri::AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(2));
+        loop {
+            interval.tick().await;
+            if let Ok(manager) = get_agent_manager(&app_handle).await {
+                manager.reload_skills_if_changed();
+            }
+        }
+    });
+}
+
 fn spawn_agent_app_scheduler<R: Runtime>(app_handle: tauri::AppHandle<R>) {
     tauri::async_runtime::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(30));
         loop {
             interval.tick().await;
 
-            let (apps, schedules) = match app_handle.try_state::<AppState>() {
-                Some(state) => (state.agent_apps.clone(), state.agent_app_schedules.clone()),
+            let (apps, pipelines, schedules) = match app_handle.try_state::<AppState>() {
+                Some(state) => (
+                    state.agent_apps.clone(),
+                    state.agent_app_pipelines.clone(),
+                    state.agent_app_schedules.clone(),
+                ),
                 None => continue,
             };
 
@@ -424,11 +467,49 @@ fn spawn_agent_app_scheduler<R: Runtime>(app_handle: tauri::AppHandle<R>) {
                 let app = match apps.find(&schedule.app_id) {
                     Some(app) => app,
                     None => {
-                        let _ = schedules.record_run_result(&schedule.id, "missing_app");
+                        if let Some(pipeline) = pipelines.find(&schedule.app_id) {
+                            run_scheduled_pipeline(
+                                &app_handle,
+                                &apps,
+                                &schedules,
+                                pipeline,
+                                schedule,
+                            )
+                            .await;
+                            continue;
+                        }
                         log_startup(&format!(
                             "agent app scheduler missing app id={} schedule={}",
                             schedule.app_id, schedule.id
                         ));
+                        record_schedule_outcome(
+                            &app_handle,
+                            &schedules,
+                            &schedule.id,
+                            ScheduleRunOutcome::Failed {
+                                run_id: None,
+                                error: format!("agent app `{}` not found", schedule.app_id),
+                            },
+                        );
+                        continue;
+                    }
+                };
+                let task = match app.render_task(schedule.input.as_deref(), &Default::default()) {
+                    Ok(task) => task,
+                    Err(err) => {
+                        log_startup(&format!(
+                            "agent app scheduler could not render task schedule={} error={err}",
+                            schedule.id
+                        ));
+                        record_schedule_outcome(
+                            &app_handle,
+                            &schedules,
+                            &schedule.id,
+                            ScheduleRunOutcome::Failed {
+                                run_id: None,
+                                error: err.to_string(),
+                            },
+                        );
                         continue;
                     }
                 };
@@ -436,40 +517,292 @@ fn spawn_agent_app_scheduler<R: Runtime>(app_handle: tauri::AppHandle<R>) {
                 let manager = match get_agent_manager(&app_handle).await {
                     Ok(manager) => manager,
                     Err(err) => {
-                        let _ = schedules.record_run_result(&schedule.id, "manager_unavailable");
                         log_startup(&format!(
                             "agent app scheduler unavailable schedule={} error={err}",
                             schedule.id
                         ));
+                        record_schedule_outcome(
+                            &app_handle,
+                            &schedules,
+                            &schedule.id,
+                            ScheduleRunOutcome::Failed {
+                                run_id: None,
+                                error: err,
+                            },
+                        );
                         continue;
                     }
                 };
 
                 let schedules = schedules.clone();
+                let app_handle = app_handle.clone();
                 tauri::async_runtime::spawn(async move {
                     let outcome = manager
                         .run_task(AgentRunRequest {
-                            task: app.render_task(schedule.input.as_deref()),
+                            task,
                             skill_id: app.skill_id.clone(),
                             no_egress: app.no_egress,
                             label: Some(schedule.label.clone()),
                             app_id: Some(app.id.clone()),
                             schedule_id: Some(schedule.id.clone()),
+                            mcp_resources: app.mcp_resources.clone(),
+                            mcp_prompts: app.render_mcp_prompts(schedule.input.as_deref()),
+                            priority: Some(AgentRunPriority::Low),
+                            surface: None,
+                            hand_over: false,
                         })
                         .await;
 
-                    let status = match outcome {
-                        Ok(response) if response.cancelled => "cancelled",
-                        Ok(_) => "completed",
-                        Err(_) => "failed",
+                    let outcome = match outcome {
+                        Ok(response) if response.cancelled => ScheduleRunOutcome::Cancelled {
+                            run_id: response.run_id,
+                        },
+                        Ok(response) => ScheduleRunOutcome::Completed {
+                            run_id: response.run_id,
+                        },
+                        Err(err) => ScheduleRunOutcome::Failed {
+                            run_id: None,
+                            error: err.to_string(),
+                        },
                     };
-                    let _ = schedules.record_run_result(&schedule.id, status);
+                    record_schedule_outcome(&app_handle, &schedules, &schedule.id, outcome);
                 });
             }
         }
     });
 }
 
+/// Runs a pipeline named by a schedule in place of an app.
+async fn run_scheduled_pipeline<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    apps: &Arc<AgentAppRegistry>,
+    schedules: &Arc<AgentAppScheduleRegistry>,
+    pipeline: AgentAppPipeline,
+    schedule: AgentAppScheduleDefinition,
+) {
+    let manager = match get_agent_manager(app_handle).await {
+        Ok(manager) => manager,
+        Err(err) => {
+            log_startup(&format!(
+                "agent app scheduler unavailable schedule={} error={err}",
+                schedule.id
+            ));
+            record_schedule_outcome(
+                app_handle,
+                schedules,
+                &schedule.id,
+                ScheduleRunOutcome::Failed {
+                    run_id: None,
+                    error: err,
+                },
+            );
+            return;
+        }
+    };
+
+    let apps = apps.clone();
+    let schedules = schedules.clone();
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let outcome = pipeline
+            .run(
+                &apps,
+                &*manager,
+                schedule.input.as_deref(),
+                Some(&schedule.id),
+            )
+            .await;
+        let outcome = match outcome {
+            Ok(run) => {
+                let run_id = run.last_run_id().unwrap_or_default().to_string();
+                if run.cancelled {
+                    ScheduleRunOutcome::Cancelled { run_id }
+                } else {
+                    ScheduleRunOutcome::Completed { run_id }
+                }
+            }
+            Err(err) => ScheduleRunOutcome::Failed {
+                run_id: None,
+                error: format!("{err:#}"),
+            },
+        };
+        record_schedule_outcome(&app_handle, &schedules, &schedule.id, outcome);
+    });
+}
+
+/// Stores how a scheduled run ended and tells the UI about failures.
+fn record_schedule_outcome<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    schedules: &AgentAppScheduleRegistry,
+    schedule_id: &str,
+    outcome: ScheduleRunOutcome,
+) {
+    match schedules.record_run_result(schedule_id, outcome, unix_time_ms()) {
+        Ok(Some(alert)) => {
+            let _ = app_handle.emit(AGENT_APP_SCHEDULE_ALERT_EVENT, alert);
+        }
+        Ok(None) => {}
+        Err(err) => log_startup(&format!(
+            "agent app scheduler failed to record result schedule={schedule_id} error={err}"
+        )),
+    }
+}
+
+/// Starts a run for every app trigger matching an event reported through
+/// the trigger hub.
+fn spawn_agent_app_trigger_dispatcher<R: Runtime>(app_handle: tauri::AppHandle<R>) {
+    let Some(mut events) = app_handle
+        .try_state::<AppState>()
+        .and_then(|state| state.agent_app_triggers.take_receiver())
+    else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events.recv().await {
+            let (apps, hub) = match app_handle.try_state::<AppState>() {
+                Some(state) => (state.agent_apps.clone(), state.agent_app_triggers.clone()),
+                None => continue,
+            };
+            let matches = hub.claim(&apps.triggered_apps(), &event, unix_time_ms());
+            if matches.is_empty() {
+                continue;
+            }
+
+            let manager = match get_agent_manager(&app_handle).await {
+                Ok(manager) => manager,
+                Err(err) => {
+                    log_startup(&format!(
+                        "agent app trigger unavailable event={} error={err}",
+                        event.kind()
+                    ));
+                    continue;
+                }
+            };
+
+            for matched in matches {
+                let task = match matched.render_task(&event) {
+                    Ok(task) => task,
+                    Err(err) => {
+                        log_startup(&format!(
+                            "agent app trigger could not render task app={} error={err}",
+                            matched.app.id
+                        ));
+                        continue;
+                    }
+                };
+                let manager = manager.clone();
+                let request = AgentRunRequest {
+                    task,
+                    skill_id: matched.app.skill_id.clone(),
+                    no_egress: matched.app.no_egress,
+                    label: Some(matched.label(&event)),
+                    app_id: Some(matched.app.id.clone()),
+                    schedule_id: None,
+                    mcp_resources: matched.app.mcp_resources.clone(),
+                    mcp_prompts: matched.app.render_mcp_prompts(matched.input.as_deref()),
+                    priority: Some(AgentRunPriority::Low),
+                    surface: None,
+                    hand_over: false,
+                };
+                let app_id = matched.app.id.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(err) = manager.run_task(request).await {
+                        log_startup(&format!(
+                            "agent app trigger run failed app={app_id} error={err}"
+                        ));
+                    }
+                });
+            }
+        }
+    });
+}
+
+/// Polls the native balance of every address watched by a
+/// `wallet_balance_changed` trigger through `ADVATAR_ETH_RPC`/`ETH_RPC_URL`.
+fn spawn_wallet_balance_watcher<R: Runtime>(app_handle: tauri::AppHandle<R>) {
+    use ethers_core::types::Address;
+    use ethers_providers::{Http, Middleware, Provider};
+    use std::str::FromStr;
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        let mut tracker = WalletBalanceTracker::default();
+        loop {
+            interval.tick().await;
+            let Some(state) = app_handle.try_state::<AppState>() else {
+                continue;
+            };
+            let addresses = watched_wallet_addresses(&state.agent_apps.triggered_apps());
+            let hub = state.agent_app_triggers.clone();
+            drop(state);
+            if addresses.is_empty() {
+                continue;
+            }
+            let Ok(rpc) =
+                std::env::var("ADVATAR_ETH_RPC").or_else(|_| std::env::var("ETH_RPC_URL"))
+            else {
+                continue;
+            };
+            let Ok(provider) = Provider::<Http>::try_from(rpc) else {
+                continue;
+            };
+            let chain_id = match provider.get_chainid().await {
+                Ok(chain_id) => chain_id.as_u64(),
+                Err(err) => {
+                    log_startup(&format!("wallet balance trigger RPC unavailable: {err}"));
+                    continue;
+                }
+            };
+            for address in addresses {
+                let Ok(parsed) = Address::from_str(&address) else {
+                    continue;
+                };
+                match provider.get_balance(parsed, None).await {
+                    Ok(balance) => {
+                        if let Some(event) =
+                            tracker.observe(&address, chain_id, balance.to_string())
+                        {
+                            hub.emit(event);
+                        }
+                    }
+                    Err(err) => {
+                        log_startup(&format!(
+                            "wallet balance lookup failed address={address} error={err}"
+                        ));
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Opens the loopback webhook listener when an app declares a webhook
+/// trigger. `ADVATAR_TRIGGER_WEBHOOK_PORT` overrides the default port.
+/// Starts the webhook listener once an enabled app has a webhook trigger.
+/// Called at startup and whenever apps or their trigger settings change.
+fn refresh_agent_app_webhooks<R: Runtime>(app_handle: &tauri::AppHandle<R>) {
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return;
+    };
+    let apps = state.agent_apps.clone();
+    let hub = state.agent_app_triggers.clone();
+    drop(state);
+    let port = std::env::var("ADVATAR_TRIGGER_WEBHOOK_PORT")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_TRIGGER_WEBHOOK_PORT);
+    tauri::async_runtime::spawn(async move {
+        let bind = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+        match hub.ensure_webhook_listener(bind, apps).await {
+            Ok(Some(address)) => {
+                tracing::debug!(%address, "agent app webhooks listening")
+            }
+            Ok(None) => {}
+            Err(err) => log_startup(&format!("agent app webhooks unavailable: {err}")),
+        }
+    });
+}
+
 fn tab_webview_label(tab_id: &str) -> String {
     let sanitized = tab_id
         .chars()
@@ -500,6 +833,14 @@ fn active_tab_id(state: &AppState) -> Result<Option<String>, String> {
         .map(|active| active.clone())
 }
 
+fn agent_run_tab_surface(state: &AppState, tab_id: &str) -> Result<Option<RunTabSurface>, String> {
+    state
+        .agent_run_tabs
+        .lock()
+        .map_err(|_| "agent run tab mutex poisoned".to_string())
+        .map(|tabs| tabs.get(tab_id).copied())
+}
+
 fn ensure_tab_webview<R: Runtime>(
     app_handle: &tauri::AppHandle<R>,
     tab_id: &str,
@@ -927,11 +1268,11 @@ fn create_browser_window<R: Runtime>(
         tauri::async_runtime::spawn(async move {
             let id = event.id().as_ref();
             if id == "new_tab" {
-                println!("New tab requested");
+                eprintln!("New tab requested");
             } else if id == "new_window" {
-                println!("New window requested");
+                eprintln!("New window requested");
             } else if id == "settings" {
-                println!("Settings requested");
+                eprintln!("Settings requested");
             } else if id == "zoomin" || id == "zoomout" || id == "resetzoom" {
                 let mut level = zoom_level.lock().unwrap_or_else(|e| e.into_inner());
                 match id {
@@ -1077,6 +1418,13 @@ fn main() {
             AgentAppRegistry::empty()
         }
     });
+    let agent_app_pipelines = Arc::new(match AgentAppPipelineRegistry::load_default() {
+        Ok(registry) => registry,
+        Err(err) => {
+            log_startup(&format!("failed to load agent app pipelines: {err}"));
+            AgentAppPipelineRegistry::empty()
+        }
+    });
     let agent_app_schedules = Arc::new(match AgentAppScheduleRegistry::load_default() {
         Ok(registry) => registry,
         Err(err) => {
@@ -1084,6 +1432,14 @@ fn main() {
             AgentAppScheduleRegistry::empty()
         }
     });
+    let agent_app_triggers = Arc::new(AgentAppTriggerHub::new());
+    let semantic_index = Arc::new(match SemanticIndex::load_default() {
+        Ok(index) => index,
+        Err(err) => {
+            log_startup(&format!("failed to load semantic index: {err}"));
+            SemanticIndex::in_memory(Arc::new(HashingEmbeddingClient::default()))
+        }
+    });
     register_decentralized_protocols(tauri::Builder::default())
         .plugin(tauri_plugin_dialog::init())
         .manage(AppState {
@@ -1093,6 +1449,8 @@ fn main() {
             content_bounds: Mutex::new(None),
             last_content_bounds: Mutex::new(None),
             content_webview_visible: Mutex::new(false),
+            agent_run_tabs: Mutex::new(std::collections::HashMap::new()),
+            agent_panel_bounds: Mutex::new(None),
             browser_engine: Arc::new(BrowserEngine::new()),
             protocol_handler: Arc::new(Mutex::new(ProtocolHandler::new())),
             security_manager: Arc::new(Mutex::new(SecurityManager::new())),
@@ -1108,8 +1466,11 @@ fn main() {
             mcp_registry: mcp_registry.clone(),
             mcp_config: mcp_config.clone(),
             agent_apps: agent_apps.clone(),
+            agent_app_pipelines: agent_app_pipelines.clone(),
             agent_app_schedules: agent_app_schedules.clone(),
+            agent_app_triggers: agent_app_triggers.clone(),
             download_controls: Arc::new(AsyncMutex::new(std::collections::HashMap::new())),
+            semantic_index,
         })
         .setup(|app| {
             log_startup("setup: entered");
@@ -1124,12 +1485,23 @@ fn main() {
             if let Some(state) = app.app_handle().try_state::<AppState>() {
                 let agent_mutex = state.agent_manager.clone();
                 let app_handle = app.app_handle().clone();
-                match AgentManager::new(app_handle, &*state, state.approval_broker.clone()) {
+                match AgentManager::new(app_handle.clone(), &*state, state.approval_broker.clone())
+                {
                     Ok(manager) => {
+                        manager.set_tab_host(Arc::new(GuiTabWebviewHost { app_handle }));
+                        let manager = Arc::new(manager);
+                        let stdio_manager = manager.clone();
                         tauri::async_runtime::block_on(async move {
                             let mut slot = agent_mutex.lock().await;
-                            *slot = Some(Arc::new(manager));
+                            *slot = Some(manager);
                         });
+                        if mcp_stdio_requested() {
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(err) = stdio_manager.serve_mcp_stdio().await {
+                                    log_startup(&format!("MCP stdio server stopped: {err}"));
+                                }
+                            });
+                        }
                     }
                     Err(err) => {
                         log_startup(&format!("agent initialisation failed: {err}"));
@@ -1138,6 +1510,10 @@ fn main() {
             }
 
             spawn_agent_app_scheduler(app.app_handle().clone());
+            spawn_agent_app_trigger_dispatcher(app.app_handle().clone());
+            refresh_agent_app_webhooks(app.app_handle());
+            spawn_wallet_balance_watcher(app.app_handle().clone());
+            spawn_skill_watcher(app.app_handle().clone());
 
             if let Some(state) = app.app_handle().try_state::<AppState>() {
                 let telemetry = state.telemetry_manager.clone();
@@ -1174,20 +1550,46 @@ fn main() {
             agent_run_task,
             agent_list_runs,
             agent_cancel_run,
+            agent_search_runs,
+            agent_open_run,
+            agent_export_run,
+            agent_export_run_output,
+            agent_delete_run,
+            agent_get_run_retention,
+            agent_set_run_retention,
+            agent_run_queue_status,
+            agent_set_run_queue_limits,
             list_agent_apps,
+            install_agent_app,
+            update_agent_app,
+            uninstall_agent_app,
+            set_agent_app_triggers_enabled,
             launch_agent_app,
             list_agent_app_schedules,
             save_agent_app_schedule,
             delete_agent_app_schedule,
+            list_agent_app_pipelines,
+            save_agent_app_pipeline,
+            delete_agent_app_pipeline,
+            run_agent_app_pipeline,
             agent_list_skills,
+            agent_skill_status,
+            agent_reload_skills,
             agent_get_credits,
             agent_top_up_credits,
+            agent_credit_ledger,
+            agent_credit_statement,
+            agent_export_credit_statement,
             agent_set_no_egress,
             agent_resolve_approval,
+            agent_start_mcp_server,
+            agent_stop_mcp_server,
+            agent_mcp_server_status,
             activate_tab_webview,
             close_tab_webview,
             navigate_to,
             set_content_bounds,
+            set_agent_panel_bounds,
             content_go_back,
             content_go_forward,
             content_reload,
@@ -1204,7 +1606,19 @@ fn main() {
             create_mcp_profile,
             import_mcp_profile,
             export_mcp_profile,
+            export_encrypted_mcp_profile,
+            inspect_encrypted_mcp_bundle,
+            import_encrypted_mcp_profile,
+            mcp_bundle_signer,
+            begin_mcp_oauth,
+            sign_out_mcp_oauth,
             read_mcp_secret,
+            list_mcp_resources,
+            read_mcp_resource,
+            subscribe_mcp_resource,
+            unsubscribe_mcp_resource,
+            list_mcp_prompts,
+            get_mcp_prompt,
             create_tab,
             close_tab,
             switch_tab,
@@ -1215,6 +1629,7 @@ fn main() {
             get_history,
             clear_history,
             search_history,
+            semantic_search,
             remove_history_entry,
             start_download,
             get_downloads,
@@ -1222,6 +1637,7 @@ fn main() {
             reveal_download,
             resolve_protocol_url,
             probe_runtime_url,
+            pin_ipfs_content,
             update_security_settings,
             get_security_status,
             report_error,
@@ -1326,12 +1742,125 @@ async fn agent_cancel_run<R: Runtime>(
     }
 }
 
+#[tauri::command]
+async fn agent_search_runs<R: Runtime>(
+    query: Option<AgentRunQuery>,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<Vec<AgentRunSummary>, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.search_runs(&query.unwrap_or_default()))
+}
+
+#[tauri::command]
+async fn agent_open_run<R: Runtime>(
+    run_id: String,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentRunTranscript, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .open_run(run_id.trim())
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("unknown agent run `{}`", run_id.trim()))
+}
+
+#[tauri::command]
+async fn agent_export_run<R: Runtime>(
+    run_id: String,
+    format: AgentRunExportFormat,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<String, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .export_run(run_id.trim(), format)
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("unknown agent run `{}`", run_id.trim()))
+}
+
+#[tauri::command]
+async fn agent_export_run_output<R: Runtime>(
+    run_id: String,
+    format: AgentRunOutputFormat,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<String, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .export_run_output(run_id.trim(), format)
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("unknown agent run `{}`", run_id.trim()))
+}
+
+#[tauri::command]
+async fn agent_delete_run<R: Runtime>(
+    run_id: String,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<bool, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .delete_run(run_id.trim())
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn agent_get_run_retention<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentRunRetention, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.run_retention())
+}
+
+#[tauri::command]
+async fn agent_set_run_retention<R: Runtime>(
+    retention: AgentRunRetention,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentRunRetention, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .set_run_retention(retention)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn agent_run_queue_status<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentRunQueueSnapshot, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.run_queue_snapshot())
+}
+
+#[tauri::command]
+async fn agent_set_run_queue_limits<R: Runtime>(
+    limits: AgentRunQueueLimits,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentRunQueueSnapshot, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .set_run_queue_limits(limits)
+        .map_err(|err| err.to_string())
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct LaunchAgentAppRequest {
     app_id: String,
     #[serde(default)]
     input: Option<String>,
+    /// Values of the app's declared inputs, keyed by input name.
+    #[serde(default)]
+    inputs: serde_json::Map<String, serde_json::Value>,
+    /// Finished run whose structured output fills the inputs not given
+    /// explicitly; with no matching inputs and no free text, the output
+    /// itself becomes the free-text input.
+    #[serde(default)]
+    from_run_id: Option<String>,
 }
 
 #[tauri::command]
@@ -1345,6 +1874,125 @@ async fn list_agent_apps<R: Runtime>(
     Ok(state.agent_apps.list())
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstallAgentAppRequest {
+    /// `ipfs://<cid>[/path]`, a bare CID, or a local package file.
+    source: String,
+}
+
+#[tauri::command]
+async fn install_agent_app<R: Runtime>(
+    request: InstallAgentAppRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentAppSummary, String> {
+    let (package, source) = fetch_agent_app_package(&request.source)
+        .await
+        .map_err(|err| format!("{err:#}"))?;
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+    let summary = state
+        .agent_apps
+        .install_package(&package, &trusted_publisher_keys(), &source, unix_time_ms())
+        .map_err(|err| err.to_string())?;
+    refresh_agent_app_webhooks(&app_handle);
+    Ok(summary)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateAgentAppRequest {
+    app_id: String,
+    /// Defaults to the source the installed version came from.
+    #[serde(default)]
+    source: Option<String>,
+}
+
+#[tauri::command]
+async fn update_agent_app<R: Runtime>(
+    request: UpdateAgentAppRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentAppSummary, String> {
+    let agent_apps = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?
+        .agent_apps
+        .clone();
+    let app_id = request.app_id.trim();
+    let source = match request.source.filter(|source| !source.trim().is_empty()) {
+        Some(source) => source,
+        None => agent_apps
+            .find(app_id)
+            .and_then(|app| app.package)
+            .map(|package| package.source)
+            .ok_or_else(|| format!("agent app `{app_id}` is not installed from a package"))?,
+    };
+    let (package, source) = fetch_agent_app_package(&source)
+        .await
+        .map_err(|err| format!("{err:#}"))?;
+    let summary = agent_apps
+        .update_package(
+            app_id,
+            &package,
+            &trusted_publisher_keys(),
+            &source,
+            unix_time_ms(),
+        )
+        .map_err(|err| err.to_string())?;
+    refresh_agent_app_webhooks(&app_handle);
+    Ok(summary)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UninstallAgentAppRequest {
+    app_id: String,
+}
+
+#[tauri::command]
+async fn uninstall_agent_app<R: Runtime>(
+    request: UninstallAgentAppRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<bool, String> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+    let removed = state
+        .agent_apps
+        .uninstall_package(request.app_id.trim())
+        .map_err(|err| err.to_string())?;
+    refresh_agent_app_webhooks(&app_handle);
+    Ok(removed)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetAgentAppTriggersRequest {
+    app_id: String,
+    enabled: bool,
+}
+
+#[tauri::command]
+async fn set_agent_app_triggers_enabled<R: Runtime>(
+    request: SetAgentAppTriggersRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentAppSummary, String> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+    let summary = state
+        .agent_apps
+        .set_triggers_enabled(request.app_id.trim(), request.enabled)
+        .map_err(|err| err.to_string())?;
+    refresh_agent_app_webhooks(&app_handle);
+    Ok(summary)
+}
+
 #[tauri::command]
 async fn list_agent_app_schedules<R: Runtime>(
     _window: tauri::Window<R>,
@@ -1371,8 +2019,16 @@ async fn save_agent_app_schedule<R: Runtime>(
     let state = app_handle
         .try_state::<AppState>()
         .ok_or_else(|| "application state unavailable".to_string())?;
-    if state.agent_apps.find(&request.draft.app_id).is_none() {
-        return Err(format!("agent app `{}` not found", request.draft.app_id));
+    if state.agent_apps.find(&request.draft.app_id).is_none()
+        && state
+            .agent_app_pipelines
+            .find(&request.draft.app_id)
+            .is_none()
+    {
+        return Err(format!(
+            "agent app or pipeline `{}` not found",
+            request.draft.app_id
+        ));
     }
     state
         .agent_app_schedules
@@ -1401,6 +2057,89 @@ async fn delete_agent_app_schedule<R: Runtime>(
         .map_err(|err| err.to_string())
 }
 
+#[tauri::command]
+async fn list_agent_app_pipelines<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<Vec<AgentAppPipeline>, String> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+    Ok(state.agent_app_pipelines.list())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveAgentAppPipelineRequest {
+    pipeline: AgentAppPipeline,
+}
+
+#[tauri::command]
+async fn save_agent_app_pipeline<R: Runtime>(
+    request: SaveAgentAppPipelineRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentAppPipeline, String> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+    state
+        .agent_app_pipelines
+        .upsert(request.pipeline, &state.agent_apps)
+        .map_err(|err| err.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteAgentAppPipelineRequest {
+    pipeline_id: String,
+}
+
+#[tauri::command]
+async fn delete_agent_app_pipeline<R: Runtime>(
+    request: DeleteAgentAppPipelineRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<bool, String> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+    state
+        .agent_app_pipelines
+        .delete(request.pipeline_id.trim())
+        .map_err(|err| err.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RunAgentAppPipelineRequest {
+    pipeline_id: String,
+    #[serde(default)]
+    input: Option<String>,
+}
+
+#[tauri::command]
+async fn run_agent_app_pipeline<R: Runtime>(
+    request: RunAgentAppPipelineRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentAppPipelineRun, String> {
+    let (apps, pipelines) = {
+        let state = app_handle
+            .try_state::<AppState>()
+            .ok_or_else(|| "application state unavailable".to_string())?;
+        (state.agent_apps.clone(), state.agent_app_pipelines.clone())
+    };
+    let pipeline = pipelines
+        .find(request.pipeline_id.trim())
+        .ok_or_else(|| format!("agent app pipeline `{}` not found", request.pipeline_id))?;
+    let manager = get_agent_manager(&app_handle).await?;
+    pipeline
+        .run(&apps, &*manager, request.input.as_deref(), None)
+        .await
+        .map_err(|err| format!("{err:#}"))
+}
+
 #[tauri::command]
 async fn launch_agent_app<R: Runtime>(
     request: LaunchAgentAppRequest,
@@ -1417,7 +2156,6 @@ async fn launch_agent_app<R: Runtime>(
     let app = agent_apps
         .find(&request.app_id)
         .ok_or_else(|| format!("agent app `{}` not found", request.app_id))?;
-    let task = app.render_task(request.input.as_deref());
 
     let manager = {
         let guard = agent_mutex.lock().await;
@@ -1427,6 +2165,24 @@ async fn launch_agent_app<R: Runtime>(
             .ok_or_else(|| "agent manager not initialised".to_string())?
     };
 
+    let mut input = request.input;
+    let mut inputs = request.inputs;
+    if let Some(run_id) = request.from_run_id.as_deref().map(str::trim) {
+        let output = manager
+            .run_output(run_id)
+            .ok_or_else(|| format!("agent run `{run_id}` has no structured output"))?;
+        let piped = app.inputs_from_output(&output);
+        if piped.is_empty() && input.as_deref().is_none_or(|text| text.trim().is_empty()) {
+            input = Some(serde_json::to_string_pretty(&output).unwrap_or_default());
+        }
+        for (name, value) in piped {
+            inputs.entry(name).or_insert(value);
+        }
+    }
+    let task = app
+        .render_task(input.as_deref(), &inputs)
+        .map_err(|err| err.to_string())?;
+
     manager
         .run_task(AgentRunRequest {
             task,
@@ -1435,11 +2191,61 @@ async fn launch_agent_app<R: Runtime>(
             label: Some(app.name.clone()),
             app_id: Some(app.id.clone()),
             schedule_id: None,
+            mcp_resources: app.mcp_resources.clone(),
+            mcp_prompts: app.render_mcp_prompts(input.as_deref()),
+            priority: None,
+            surface: None,
+            hand_over: false,
         })
         .await
         .map_err(|err| err.to_string())
 }
 
+/// `--mcp-stdio` exposes the browser tools to an MCP client attached to this
+/// process's stdin/stdout.
+fn mcp_stdio_requested() -> bool {
+    std::env::args().any(|arg| arg == "--mcp-stdio")
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentStartMcpServerRequest {
+    #[serde(default)]
+    port: Option<u16>,
+}
+
+#[tauri::command]
+async fn agent_start_mcp_server<R: Runtime>(
+    request: Option<AgentStartMcpServerRequest>,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<McpServerModeStatus, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    let request = request.unwrap_or_default();
+    manager
+        .start_mcp_server_mode(request.port)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn agent_stop_mcp_server<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<McpServerModeStatus, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.stop_mcp_server_mode().await)
+}
+
+#[tauri::command]
+async fn agent_mcp_server_status<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<McpServerModeStatus, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.mcp_server_mode_status().await)
+}
+
 #[tauri::command]
 async fn agent_list_skills<R: Runtime>(
     _window: tauri::Window<R>,
@@ -1449,6 +2255,24 @@ async fn agent_list_skills<R: Runtime>(
     Ok(manager.list_skills())
 }
 
+#[tauri::command]
+async fn agent_skill_status<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentSkillStatus, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.skill_status())
+}
+
+#[tauri::command]
+async fn agent_reload_skills<R: Runtime>(
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<AgentSkillStatus, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.reload_skills())
+}
+
 #[tauri::command]
 async fn agent_get_credits<R: Runtime>(
     _window: tauri::Window<R>,
@@ -1465,7 +2289,50 @@ async fn agent_top_up_credits<R: Runtime>(
     app_handle: tauri::AppHandle<R>,
 ) -> Result<CreditSnapshot, String> {
     let manager = get_agent_manager(&app_handle).await?;
-    Ok(manager.top_up_credits(tokens).await)
+    manager
+        .top_up_credits(tokens)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn agent_credit_ledger<R: Runtime>(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<Vec<CreditLedgerEntry>, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    Ok(manager.credit_ledger(limit, offset).await)
+}
+
+#[tauri::command]
+async fn agent_credit_statement<R: Runtime>(
+    year: i32,
+    month: u32,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<CreditStatement, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .credit_statement(year, month)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn agent_export_credit_statement<R: Runtime>(
+    year: i32,
+    month: u32,
+    format: CreditStatementFormat,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<String, String> {
+    let manager = get_agent_manager(&app_handle).await?;
+    manager
+        .export_credit_statement(year, month, format)
+        .await
+        .map_err(|err| err.to_string())
 }
 
 #[tauri::command]
@@ -1532,31 +2399,43 @@ async fn activate_tab_webview<R: Runtime>(
         return Err("tab_id must not be empty".to_string());
     }
 
-    ensure_tab_webview(&app_handle, tab_id, request.initial_url.as_deref())?;
+    show_tab_webview(&app_handle, tab_id, request.initial_url.as_deref())
+}
+
+/// Makes `tab_id` the active content tab and hides the other user tabs.
+/// Webviews owned by agent runs keep their own visibility.
+fn show_tab_webview<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    tab_id: &str,
+    initial_url: Option<&str>,
+) -> Result<(), String> {
+    ensure_tab_webview(app_handle, tab_id, initial_url)?;
 
     let state = app_handle
         .try_state::<AppState>()
         .ok_or_else(|| "application state unavailable".to_string())?;
 
-    ensure_browser_engine_tab(
-        &state,
-        tab_id,
-        request.initial_url.as_deref().unwrap_or("about:blank"),
-        true,
-    )?;
+    ensure_browser_engine_tab(&state, tab_id, initial_url.unwrap_or("about:blank"), true)?;
 
-    if let Some(url) = &request.initial_url {
+    if let Some(url) = initial_url {
         if let Ok(mut current_url) = state.current_url.lock() {
-            *current_url = url.clone();
+            *current_url = url.to_string();
         }
     }
 
+    let agent_tabs = state
+        .agent_run_tabs
+        .lock()
+        .map_err(|_| "agent run tab mutex poisoned".to_string())?
+        .keys()
+        .cloned()
+        .collect::<std::collections::HashSet<_>>();
     let labels_to_hide = state
         .content_tab_webviews
         .lock()
         .map_err(|_| "content webview map mutex poisoned".to_string())?
         .iter()
-        .filter(|(id, _)| id.as_str() != tab_id)
+        .filter(|(id, _)| id.as_str() != tab_id && !agent_tabs.contains(id.as_str()))
         .map(|(_, label)| label.clone())
         .collect::<Vec<_>>();
 
@@ -1576,7 +2455,7 @@ async fn activate_tab_webview<R: Runtime>(
         }
     }
 
-    match apply_bounds_to_active_webview(&app_handle) {
+    match apply_bounds_to_active_webview(app_handle) {
         Ok(()) => {
             log_command("activate_tab_webview", &format!("success tab_id={tab_id}"));
             Ok(())
@@ -1608,6 +2487,13 @@ async fn close_tab_webview<R: Runtime>(
         return Err("tab_id must not be empty".to_string());
     }
 
+    remove_tab_webview(&app_handle, tab_id)
+}
+
+fn remove_tab_webview<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    tab_id: &str,
+) -> Result<(), String> {
     let state = app_handle
         .try_state::<AppState>()
         .ok_or_else(|| "application state unavailable".to_string())?;
@@ -1646,6 +2532,158 @@ async fn close_tab_webview<R: Runtime>(
     Ok(())
 }
 
+/// Backs agent run tabs with their own content webviews so a run never
+/// drives the page the user is looking at.
+struct GuiTabWebviewHost {
+    app_handle: tauri::AppHandle,
+}
+
+impl GuiTabWebviewHost {
+    fn state(&self) -> Result<tauri::State<'_, AppState>, String> {
+        self.app_handle
+            .try_state::<AppState>()
+            .ok_or_else(|| "application state unavailable".to_string())
+    }
+
+    fn release(&self, tab_id: &str) -> Result<Option<RunTabSurface>, String> {
+        Ok(self
+            .state()?
+            .agent_run_tabs
+            .lock()
+            .map_err(|_| "agent run tab mutex poisoned".to_string())?
+            .remove(tab_id))
+    }
+}
+
+impl TabWebviewHost for GuiTabWebviewHost {
+    fn open_tab(&self, tab_id: &str, url: &str, surface: RunTabSurface) -> Result<(), String> {
+        {
+            let state = self.state()?;
+            let mut tabs = state
+                .agent_run_tabs
+                .lock()
+                .map_err(|_| "agent run tab mutex poisoned".to_string())?;
+            // The side panel shows one run at a time; the newest run takes it
+            // and earlier ones carry on hidden.
+            if surface == RunTabSurface::SidePanel {
+                for other in tabs.values_mut() {
+                    *other = RunTabSurface::Hidden;
+                }
+            }
+            // Registered before the webview exists so its first page load is
+            // already treated as the run's.
+            tabs.insert(tab_id.to_string(), surface);
+        }
+
+        if let Err(err) = ensure_tab_webview(&self.app_handle, tab_id, Some(url)) {
+            let _ = self.release(tab_id);
+            return Err(err);
+        }
+        apply_agent_panel_bounds(&self.app_handle)
+    }
+
+    fn close_tab(&self, tab_id: &str) -> Result<(), String> {
+        self.release(tab_id)?;
+        remove_tab_webview(&self.app_handle, tab_id)?;
+        self.state()?
+            .browser_engine
+            .close_tab(tab_id)
+            .map_err(|err| err.to_string())
+    }
+
+    fn hand_over_tab(&self, tab_id: &str) -> Result<(), String> {
+        if self.release(tab_id)?.is_none() {
+            return Err(format!("tab `{tab_id}` is not owned by an agent run"));
+        }
+
+        let state = self.state()?;
+        // The run's webview was never laid out as the content view, so make
+        // the next layout pass size and show it.
+        *state
+            .last_content_bounds
+            .lock()
+            .map_err(|_| "content bounds mutex poisoned".to_string())? = None;
+        *state
+            .content_webview_visible
+            .lock()
+            .map_err(|_| "content visibility mutex poisoned".to_string())? = false;
+        show_tab_webview(&self.app_handle, tab_id, None)?;
+
+        let url = state
+            .browser_engine
+            .get_active_tab()
+            .map_err(|err| err.to_string())?
+            .map(|tab| tab.url);
+        if let Some(url) = &url {
+            if let Ok(mut current_url) = state.current_url.lock() {
+                *current_url = url.clone();
+            }
+        }
+        emit_runtime_tab_state(&self.app_handle, tab_id, url.as_deref(), None, Some(false));
+        Ok(())
+    }
+}
+
+/// Places the run tab that owns the agent side panel at the bounds reported
+/// by the UI, or hides it while the panel is collapsed.
+fn apply_agent_panel_bounds<R: Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
+    use tauri::{LogicalPosition, LogicalSize, Position, Rect, Size};
+
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+
+    let panel_tabs = state
+        .agent_run_tabs
+        .lock()
+        .map_err(|_| "agent run tab mutex poisoned".to_string())?
+        .iter()
+        .filter(|(_, surface)| **surface == RunTabSurface::SidePanel)
+        .map(|(tab_id, _)| tab_id.clone())
+        .collect::<Vec<_>>();
+    let bounds = state
+        .agent_panel_bounds
+        .lock()
+        .map_err(|_| "agent panel bounds mutex poisoned".to_string())?
+        .map(normalize_content_bounds);
+
+    for tab_id in panel_tabs {
+        let Some(label) = read_tab_label(&state, &tab_id)? else {
+            continue;
+        };
+        let Some(webview) = app_handle.get_webview(&label) else {
+            continue;
+        };
+        match bounds {
+            Some(bounds) if bounds.is_visible() => {
+                webview
+                    .set_bounds(Rect {
+                        position: Position::Logical(LogicalPosition::new(bounds.x, bounds.y)),
+                        size: Size::Logical(LogicalSize::new(bounds.width, bounds.height)),
+                    })
+                    .map_err(|e| format!("Failed to set agent panel bounds: {e}"))?;
+                webview
+                    .show()
+                    .map_err(|e| format!("Failed to show agent panel webview: {e}"))?;
+            }
+            _ => {
+                webview
+                    .hide()
+                    .map_err(|e| format!("Failed to hide agent panel webview: {e}"))?;
+            }
+        }
+        log_command(
+            "apply_agent_panel_bounds",
+            &format!(
+                "tab_id={tab_id} label={label} visible={}",
+                bounds.is_some_and(|b| b.is_visible())
+            ),
+        );
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct NavigateToRequest {
@@ -1827,6 +2865,36 @@ async fn set_content_bounds<R: Runtime>(
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct SetAgentPanelBoundsRequest {
+    bounds: Option<ContentBounds>,
+}
+
+// Tauri command to position or collapse the agent side panel webview.
+#[tauri::command]
+async fn set_agent_panel_bounds<R: Runtime>(
+    request: SetAgentPanelBoundsRequest,
+    _window: tauri::Window<R>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<(), String> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or_else(|| "application state unavailable".to_string())?;
+
+    *state
+        .agent_panel_bounds
+        .lock()
+        .map_err(|_| "agent panel bounds mutex poisoned".to_string())? =
+        request.bounds.map(|bounds| ContentWebviewBounds {
+            x: bounds.x,
+            y: bounds.y,
+            width: bounds.width,
+            height: bounds.height,
+        });
+
+    apply_agent_panel_bounds(&app_handle)
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct TabScopedRequest {
@@ -1985,7 +3053,9 @@ mod tests {
     use super::*;
     use afm_node::AfmNodeConfig;
     use gui::agent::McpServerRegistry;
+    use gui::agent_app_pipelines::AgentAppPipelineRegistry;
     use gui::agent_app_schedules::AgentAppScheduleRegistry;
+    use gui::agent_app_triggers::AgentAppTriggerHub;
     use gui::agent_apps::AgentAppRegistry;
     use gui::mcp_profiles::McpConfigService;
     use gui::wallet_store::WalletStore;
@@ -2003,6 +3073,8 @@ mod tests {
             content_bounds: Mutex::new(None),
             last_content_bounds: Mutex::new(None),
             content_webview_visible: Mutex::new(false),
+            agent_run_tabs: Mutex::new(std::collections::HashMap::new()),
+            agent_panel_bounds: Mutex::new(None),
             browser_engine: Arc::new(BrowserEngine::new()),
             protocol_handler: Arc::new(Mutex::new(ProtocolHandler::new())),
             security_manager: Arc::new(Mutex::new(SecurityManager::new())),
@@ -2018,8 +3090,13 @@ mod tests {
             mcp_registry: Arc::new(McpServerRegistry::empty(mcp_config.clone())),
             mcp_config,
             agent_apps: Arc::new(AgentAppRegistry::empty()),
+            agent_app_pipelines: Arc::new(AgentAppPipelineRegistry::empty()),
             agent_app_schedules: Arc::new(AgentAppScheduleRegistry::empty()),
+            agent_app_triggers: Arc::new(AgentAppTriggerHub::new()),
             download_controls: Arc::new(AsyncMutex::new(std::collections::HashMap::new())),
+            semantic_index: Arc::new(SemanticIndex::in_memory(Arc::new(
+                HashingEmbeddingClient::default(),
+            ))),
         };
 
         let url = state.current_url.lock().unwrap();
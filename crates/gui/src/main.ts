@@ -115,7 +115,7 @@ interface AfmNodeSnapshot {
   status: NodeStatus;
 }
 
-type McpTransportKind = 'http' | 'websocket' | 'stdio';
+type McpTransportKind = 'http' | 'streamable-http' | 'websocket' | 'stdio';
 
 interface McpSecretValue {
   isSecret: boolean;
@@ -138,6 +138,88 @@ interface McpServerConfig {
   program?: string | null;
   args: string[];
   env: Record<string, McpConfigValue>;
+  sandbox?: McpSandboxConfig | null;
+  circuitBreaker?: McpCircuitBreakerConfig | null;
+  oauth?: McpOAuthConfig | null;
+}
+
+interface McpOAuthConfig {
+  clientId?: string | null;
+  scopes?: string[];
+  authorizationServer?: string | null;
+  redirectPort?: number | null;
+}
+
+interface McpOAuthStatus {
+  authorized: boolean;
+  expiresAtMs?: number | null;
+  scope?: string | null;
+}
+
+interface McpOAuthAuthorization {
+  serverId: string;
+  authorizationUrl: string;
+  redirectUri: string;
+}
+
+interface McpOAuthEvent {
+  serverId: string;
+  authorized: boolean;
+  error?: string | null;
+}
+
+interface McpCircuitBreakerConfig {
+  enabled?: boolean;
+  failureThreshold?: number;
+  errorRateThreshold?: number;
+  minCalls?: number;
+  windowSize?: number;
+  cooldownMs?: number;
+  maxCooldownMs?: number;
+}
+
+type McpCircuitState = 'closed' | 'halfOpen' | 'open';
+
+interface McpLatencySummary {
+  buckets: { leMs?: number | null; count: number }[];
+  meanMs?: number | null;
+  p50Ms?: number | null;
+  p95Ms?: number | null;
+  maxMs?: number | null;
+}
+
+interface McpToolHealth {
+  tool: string;
+  calls: number;
+  errors: number;
+  rejected: number;
+  errorRate: number;
+  consecutiveFailures: number;
+  circuit: McpCircuitState;
+  retryAtMs?: number | null;
+  lastError?: string | null;
+  latency: McpLatencySummary;
+}
+
+interface McpSandboxConfig {
+  enabled?: boolean;
+  allowNetwork?: boolean;
+  restrictFilesystem?: boolean;
+  readPaths?: string[];
+  writePaths?: string[];
+  filterSyscalls?: boolean;
+  maxMemoryMb?: number | null;
+  maxCpuSeconds?: number | null;
+  maxWallSeconds?: number | null;
+  maxOpenFiles?: number | null;
+}
+
+type McpSandboxViolationKind = 'cpuLimit' | 'memoryLimit' | 'wallClockLimit' | 'blockedSyscall';
+
+interface McpSandboxViolation {
+  timestampMs: number;
+  kind: McpSandboxViolationKind;
+  message: string;
 }
 
 interface McpRuntimeStatus {
@@ -148,6 +230,10 @@ interface McpRuntimeStatus {
   successCount?: number;
   errorCount?: number;
   recentLogs?: McpLogEntry[];
+  sandboxed?: boolean;
+  sandboxViolations?: McpSandboxViolation[];
+  toolHealth?: McpToolHealth[];
+  oauth?: McpOAuthStatus | null;
 }
 
 interface ManagedMcpServer {
@@ -174,12 +260,31 @@ interface McpProfileUpdatePayload {
   servers: ManagedMcpServer[];
 }
 
+interface McpBundleSigner {
+  algorithm: string;
+  publicKey: string;
+}
+
+interface McpBundleInspection {
+  label: string;
+  createdAt: number;
+  signer: McpBundleSigner;
+  fingerprint: string;
+  ownKey: boolean;
+}
+
+interface McpEncryptedImportPayload extends McpProfileUpdatePayload {
+  signer: McpBundleSigner;
+  secretsRestored: number;
+}
+
 type McpLogLevel = 'info' | 'warn' | 'error';
 
 interface McpLogEntry {
   timestampMs: number;
   level: McpLogLevel;
   message: string;
+  sandboxViolation?: McpSandboxViolationKind | null;
 }
 
 interface EditableKeyValueEntry {
@@ -208,10 +313,37 @@ interface AgentAppSummary {
   quickPrompts: string[];
   inputHint?: string | null;
   defaultInput?: string | null;
+  inputs?: AgentAppInput[];
+  outputSchema?: Record<string, unknown> | null;
   communicationSurface?: string | null;
   requiredTools?: string[];
   approvalGates?: string[];
   blockchainAccess?: BlockchainAccessContract | null;
+  icon?: string | null;
+  package?: InstalledAgentAppPackage | null;
+  triggerCount: number;
+  triggersEnabled: boolean;
+}
+
+type AgentAppInputKind = 'string' | 'number' | 'date' | 'url' | 'enum' | 'wallet_address';
+
+interface AgentAppInput {
+  name: string;
+  label?: string | null;
+  description?: string | null;
+  type: AgentAppInputKind;
+  required?: boolean;
+  default?: unknown;
+  options?: string[];
+  min?: number | null;
+  max?: number | null;
+}
+
+interface InstalledAgentAppPackage {
+  version: string;
+  publisherKey: string;
+  source: string;
+  installedAtMs: number;
 }
 
 interface BlockchainAccessContract {
@@ -223,7 +355,7 @@ interface BlockchainAccessContract {
   requestBroadcast?: boolean;
   accountScope?: string | null;
   allowedChainRefs?: string[];
-  spendLimit?: string | null;
+  spendLimit?: Record<string, string> | null;
   approvalGates?: string[];
   hostTools?: string[];
 }
@@ -232,11 +364,13 @@ type AgentPlanStep = Record<string, unknown>;
 
 interface AgentResultPayload {
   final_answer?: string | null;
+  structured_output?: unknown;
   steps: AgentPlanStep[];
   halted: boolean;
 }
 
 interface AgentRunResponsePayload {
+  run_id: string;
   agent: AgentResultPayload;
   tokens_used: number;
   skill_id?: string | null;
@@ -693,7 +827,7 @@ class SettingsManager {
           <label>
             Transport
             <select data-mcp-index="${index}" data-mcp-field="transport">
-              ${['http', 'websocket', 'stdio'].map(kind => `<option value="${kind}" ${transport === kind ? 'selected' : ''}>${kind.toUpperCase()}</option>`).join('')}
+              ${['http', 'streamable-http', 'websocket', 'stdio'].map(kind => `<option value="${kind}" ${transport === kind ? 'selected' : ''}>${kind.toUpperCase()}</option>`).join('')}
             </select>
           </label>
           <label>
@@ -736,6 +870,7 @@ class SettingsManager {
         <div class="mcp-card-footer">
           <div class="mcp-footer-actions">
             <button type="button" data-action="test-mcp" data-mcp-index="${index}">Test connection</button>
+            ${this.renderMcpOAuthAction(server, index)}
             <button type="button" data-action="remove-server" data-mcp-index="${index}">Delete Server</button>
           </div>
         </div>
@@ -743,6 +878,16 @@ class SettingsManager {
     `;
   }
 
+  private renderMcpOAuthAction(server: EditableMcpServer, index: number): string {
+    if (!server.config.oauth) return '';
+    const status = server.status.oauth;
+    if (status?.authorized) {
+      const expires = status.expiresAtMs ? ` (expires ${this.formatTimestamp(status.expiresAtMs)})` : '';
+      return `<button type="button" data-action="sign-out-mcp-oauth" data-mcp-index="${index}" title="Authorized${this.htmlEscape(expires)}">Sign out</button>`;
+    }
+    return `<button type="button" data-action="authorize-mcp" data-mcp-index="${index}">Authorize</button>`;
+  }
+
   private renderTransportFields(
     transport: McpTransportKind,
     index: number,
@@ -828,6 +973,7 @@ class SettingsManager {
     const latency = server.status.lastLatencyMs;
     const success = server.status.successCount ?? 0;
     const errors = server.status.errorCount ?? 0;
+    const violations = server.status.sandboxViolations?.length ?? 0;
     return `
       <div class="mcp-metrics">
         <div class="mcp-metric">
@@ -842,6 +988,43 @@ class SettingsManager {
           <span>Errors</span>
           <strong>${errors}</strong>
         </div>
+        ${server.status.sandboxed ? `
+        <div class="mcp-metric">
+          <span>Sandbox Violations</span>
+          <strong>${violations}</strong>
+        </div>` : ''}
+      </div>
+      ${this.renderMcpToolHealth(server)}
+    `;
+  }
+
+  private renderMcpToolHealth(server: EditableMcpServer): string {
+    const tools = server.status.toolHealth ?? [];
+    if (!tools.length) {
+      return '';
+    }
+    const circuitLabel: Record<McpCircuitState, string> = {
+      closed: 'Healthy',
+      halfOpen: 'Probing',
+      open: 'Tripped',
+    };
+    return `
+      <div class="mcp-tool-health">
+        ${tools.map((tool) => {
+          const p50 = tool.latency.p50Ms ?? null;
+          const p95 = tool.latency.p95Ms ?? null;
+          const retry = tool.circuit === 'open' && tool.retryAtMs
+            ? ` · retry ${this.formatLogTimestamp(tool.retryAtMs)}`
+            : '';
+          return `
+            <div class="mcp-tool-health-row ${tool.circuit}" title="${this.htmlEscape(tool.lastError ?? '')}">
+              <span class="mcp-tool-health-name">${this.htmlEscape(tool.tool)}</span>
+              <span class="mcp-tool-health-circuit">${circuitLabel[tool.circuit]}${retry}</span>
+              <span>${tool.calls} calls · ${(tool.errorRate * 100).toFixed(0)}% errors</span>
+              <span>p50 ${p50 !== null ? `${p50} ms` : '—'} · p95 ${p95 !== null ? `${p95} ms` : '—'}</span>
+            </div>
+          `;
+        }).join('')}
       </div>
     `;
   }
@@ -999,6 +1182,12 @@ class SettingsManager {
       case 'test-mcp':
         void this.testMcpServer(index);
         break;
+      case 'authorize-mcp':
+        void this.authorizeMcpServer(index);
+        break;
+      case 'sign-out-mcp-oauth':
+        void this.signOutMcpServer(index);
+        break;
       default:
         break;
     }
@@ -1074,6 +1263,41 @@ class SettingsManager {
       const filePath = this.normalizeDialogSelection(selection);
       if (!filePath) return;
       const label = window.prompt('Profile label (optional)', 'Imported Profile');
+      const passphrase = window.prompt('Bundle passphrase (leave empty for unencrypted exports)', '');
+      if (passphrase) {
+        const inspection = (await invoke('inspect_encrypted_mcp_bundle', {
+          bundlePath: filePath,
+        })) as McpBundleInspection;
+        let expectedSigner: string | null = null;
+        let confirmedFingerprint: string | null = null;
+        if (!inspection.ownKey) {
+          expectedSigner =
+            window.prompt('Expected signer key (leave empty to check the fingerprint instead)', '')?.trim() ||
+            null;
+          if (!expectedSigner) {
+            const trusted = window.confirm(
+              `"${inspection.label}" is signed by a key with fingerprint\n\n${inspection.fingerprint}\n\n` +
+                'Only continue if the sender confirmed this fingerprint with you.'
+            );
+            if (!trusted) return;
+            confirmedFingerprint = inspection.fingerprint;
+          }
+        }
+        const payload = (await invoke('import_encrypted_mcp_profile', {
+          bundlePath: filePath,
+          passphrase,
+          expectedSigner,
+          confirmedFingerprint,
+          label: label?.trim() || null,
+          makeActive: true,
+        })) as McpEncryptedImportPayload;
+        this.applyProfilePayload(payload);
+        this.showToast(
+          `Profile imported from ${payload.signer.publicKey.slice(0, 12)}… with ${payload.secretsRestored} secret(s)`,
+          'success'
+        );
+        return;
+      }
       const payload = (await invoke('import_mcp_profile', {
         bundlePath: filePath,
         label: label?.trim() || null,
@@ -1099,6 +1323,20 @@ class SettingsManager {
         filters: [{ name: 'MCP Profile', extensions: ['json'] }],
       });
       if (!targetPath) return;
+      const passphrase = window.prompt(
+        'Passphrase to encrypt the bundle with its secrets (leave empty for a plain export without secrets)',
+        ''
+      );
+      if (passphrase) {
+        const signer = (await invoke('export_encrypted_mcp_profile', {
+          profileId: this.activeProfileId,
+          targetPath,
+          passphrase,
+          includeSecrets: true,
+        })) as McpBundleSigner;
+        this.showToast(`Encrypted profile signed by ${signer.publicKey.slice(0, 12)}…`, 'success');
+        return;
+      }
       await invoke('export_mcp_profile', {
         profileId: this.activeProfileId,
         targetPath,
@@ -1457,7 +1695,7 @@ class SettingsManager {
     const endpoint = (server.config.endpoint || '').trim();
     const program = (server.config.program || '').trim();
     if (enabled) {
-      if ((transport === 'http' || transport === 'websocket') && !endpoint) {
+      if ((transport === 'http' || transport === 'streamable-http' || transport === 'websocket') && !endpoint) {
         errors.push(`${label}: Endpoint is required for ${transport.toUpperCase()} servers`);
       }
       if (transport === 'stdio' && !program && !endpoint) {
@@ -1592,6 +1830,45 @@ class SettingsManager {
     }
   }
 
+  private async authorizeMcpServer(index: number): Promise<void> {
+    const server = this.mcpServers[index];
+    if (!server) return;
+    try {
+      const authorization = (await invoke('begin_mcp_oauth', {
+        serverId: server.config.id,
+      })) as McpOAuthAuthorization;
+      await browserEngine.createTab(authorization.authorizationUrl);
+      this.showToast(`Complete sign-in for ${this.formatServerLabel(server, index)} in the new tab`, 'info');
+    } catch (error) {
+      console.error('Failed to start MCP authorization', error);
+      this.showToast(`Authorization failed: ${error instanceof Error ? error.message : String(error)}`, 'error');
+    }
+  }
+
+  private async signOutMcpServer(index: number): Promise<void> {
+    const server = this.mcpServers[index];
+    if (!server) return;
+    try {
+      const payload = (await invoke('sign_out_mcp_oauth', {
+        serverId: server.config.id,
+      })) as McpProfileUpdatePayload;
+      this.applyProfilePayload(payload);
+      this.showToast(`Signed out of ${this.formatServerLabel(server, index)}`, 'success');
+    } catch (error) {
+      console.error('Failed to sign out of MCP server', error);
+      this.showToast('Sign out failed', 'error');
+    }
+  }
+
+  async handleMcpOAuthEvent(event: McpOAuthEvent): Promise<void> {
+    if (event.authorized) {
+      this.showToast(`${event.serverId} authorized`, 'success');
+    } else {
+      this.showToast(`${event.serverId} authorization failed: ${event.error || 'unknown error'}`, 'error');
+    }
+    await this.refreshMcpData();
+  }
+
   async saveSettings(): Promise<void> {
     if (!this.settings) return;
 
@@ -1641,6 +1918,7 @@ class AppLauncher {
   private apps: AgentAppSummary[] = [];
   private installedAppIds = new Set<string>();
   private openAppId: string | null = null;
+  private lastStructuredRunId: string | null = null;
   private readonly installedAppsKey = 'dbrowser.installedAgentApps.v1';
 
   async initialize(): Promise<void> {
@@ -1662,6 +1940,7 @@ class AppLauncher {
     this.toggleButton?.addEventListener('click', () => this.toggle());
     this.closeButton?.addEventListener('click', () => this.toggle(false));
     this.panel?.addEventListener('click', (event) => this.handlePanelClick(event));
+    this.resultEl?.addEventListener('click', (event) => void this.handleResultClick(event));
   }
 
   private async loadApps(): Promise<void> {
@@ -1716,15 +1995,29 @@ class AppLauncher {
     } else if (action === 'close-app') {
       this.openAppId = null;
       this.renderList();
+    } else if (action === 'toggle-triggers') {
+      void this.setTriggersEnabled(app, !app.triggersEnabled);
     } else if (action === 'launch-app') {
       const prompt = this.getInputValue(appId);
-      void this.launchApp(app, prompt);
+      void this.launchApp(app, prompt, this.getParameterValues(appId));
     } else if (action === 'quick-prompt') {
       const prompt = target.getAttribute('data-prompt') || '';
       this.setInputValue(appId, prompt);
     }
   }
 
+  private async setTriggersEnabled(app: AgentAppSummary, enabled: boolean): Promise<void> {
+    try {
+      const summary = (await invoke('set_agent_app_triggers_enabled', {
+        request: { appId: app.id, enabled },
+      })) as AgentAppSummary;
+      this.apps = this.apps.map((item) => (item.id === summary.id ? summary : item));
+      this.renderList();
+    } catch (error) {
+      console.error('Failed to update agent app triggers', error);
+    }
+  }
+
   private renderList(): void {
     if (!this.listEl) return;
     if (!this.apps.length) {
@@ -1763,6 +2056,7 @@ class AppLauncher {
       .join('');
     const metadataChips = [
       app.communicationSurface ? this.formatSurfaceLabel(app.communicationSurface) : null,
+      app.package ? `Package v${app.package.version}` : null,
       ...(app.requiredTools || []).slice(0, 3),
       app.approvalGates?.length ? `${app.approvalGates.length} approval gates` : null,
     ]
@@ -1783,14 +2077,23 @@ class AppLauncher {
     const placeholder = app.inputHint || 'Describe what you need';
     const defaultValue = app.defaultInput || '';
     const installState = installed ? '<span class="mcp-status-pill">Installed</span>' : '';
+    const parameters = (app.inputs || []).map((input) => this.renderParameterField(app.id, input)).join('');
     const runner = isOpen
       ? `
+        ${parameters ? `<div class="app-input-form">${parameters}</div>` : ''}
         <textarea data-app-input="${appId}" placeholder="${this.htmlEscape(placeholder)}">${this.htmlEscape(defaultValue)}</textarea>
         <div class="app-quick-prompts">
           ${quickPrompts}
         </div>
         <footer>
           <button type="button" data-action="launch-app" data-app-id="${appId}">Run ${this.htmlEscape(app.name)}</button>
+          ${
+            app.triggerCount
+              ? `<button type="button" data-action="toggle-triggers" data-app-id="${appId}">
+                  ${app.triggersEnabled ? 'Disable' : 'Enable'} ${app.triggerCount} trigger${app.triggerCount === 1 ? '' : 's'}
+                </button>`
+              : ''
+          }
           <button type="button" data-action="close-app" data-app-id="${appId}">Close</button>
         </footer>
       `
@@ -1816,6 +2119,56 @@ class AppLauncher {
     `;
   }
 
+  private renderParameterField(appId: string, input: AgentAppInput): string {
+    const attrs = [
+      `data-app-param="${this.htmlEscape(input.name)}"`,
+      `data-app-id="${this.htmlEscape(appId)}"`,
+      input.required ? 'required' : '',
+    ];
+    const defaultValue = input.default === undefined || input.default === null ? '' : String(input.default);
+    let control: string;
+    if (input.type === 'enum') {
+      const options = (input.options || [])
+        .map(
+          (option) =>
+            `<option value="${this.htmlEscape(option)}"${option === defaultValue ? ' selected' : ''}>${this.htmlEscape(option)}</option>`,
+        )
+        .join('');
+      const blank = input.required && defaultValue ? '' : '<option value=""></option>';
+      control = `<select ${attrs.join(' ')}>${blank}${options}</select>`;
+    } else {
+      const type = { string: 'text', number: 'number', date: 'date', url: 'url', wallet_address: 'text' }[input.type] || 'text';
+      if (input.type === 'number') {
+        if (typeof input.min === 'number') attrs.push(`min="${input.min}"`);
+        if (typeof input.max === 'number') attrs.push(`max="${input.max}"`);
+        attrs.push('step="any"');
+      }
+      const placeholder = input.type === 'wallet_address' ? '0x…' : input.type === 'url' ? 'https://' : '';
+      control = `<input type="${type}" ${attrs.join(' ')} value="${this.htmlEscape(defaultValue)}" placeholder="${placeholder}" />`;
+    }
+    const label = input.label || input.name;
+    return `
+      <label class="app-input-field">
+        <span>${this.htmlEscape(label)}${input.required ? ' *' : ''}</span>
+        ${control}
+        ${input.description ? `<small>${this.htmlEscape(input.description)}</small>` : ''}
+      </label>
+    `;
+  }
+
+  private getParameterValues(appId: string): Record<string, string> {
+    const card = this.panel?.querySelector<HTMLElement>(`.app-card[data-app-id="${this.cssEscape(appId)}"]`);
+    const values: Record<string, string> = {};
+    card?.querySelectorAll<HTMLInputElement | HTMLSelectElement>('[data-app-param]').forEach((field) => {
+      const name = field.getAttribute('data-app-param');
+      const value = field.value.trim();
+      if (name && value) {
+        values[name] = value;
+      }
+    });
+    return values;
+  }
+
   private loadInstalledApps(): Set<string> {
     try {
       const raw = window.localStorage.getItem(this.installedAppsKey);
@@ -1851,7 +2204,12 @@ class AppLauncher {
     }
   }
 
-  private async launchApp(app: AgentAppSummary, input?: string): Promise<void> {
+  private async launchApp(
+    app: AgentAppSummary,
+    input?: string,
+    inputs: Record<string, string> = {},
+    fromRunId?: string,
+  ): Promise<void> {
     try {
       this.setCardBusy(app.id, true);
       const payload = (input || '').trim() || null;
@@ -1859,6 +2217,8 @@ class AppLauncher {
         request: {
           appId: app.id,
           input: payload,
+          inputs,
+          fromRunId: fromRunId || null,
         },
       })) as AgentRunResponsePayload;
       this.showResult(app, response);
@@ -1881,13 +2241,63 @@ class AppLauncher {
       return;
     }
     const answer = response.agent?.final_answer?.trim() || 'No final answer returned.';
+    const output = response.agent?.structured_output;
+    const structured = output !== undefined && output !== null;
+    this.lastStructuredRunId = structured ? response.run_id : null;
+    const targets = this.apps
+      .filter((candidate) => candidate.id !== app.id && this.installedAppIds.has(candidate.id))
+      .map((candidate) => `<option value="${this.htmlEscape(candidate.id)}">${this.htmlEscape(candidate.name)}</option>`)
+      .join('');
+    const body = structured
+      ? `
+        <pre>${this.htmlEscape(JSON.stringify(output, null, 2))}</pre>
+        <footer>
+          <button type="button" data-output-action="export-json">Export JSON</button>
+          <button type="button" data-output-action="export-csv">Export CSV</button>
+          ${
+            targets
+              ? `<select data-output-target>${targets}</select>
+                 <button type="button" data-output-action="pipe">Send to app</button>`
+              : ''
+          }
+        </footer>
+      `
+      : `<p>${this.htmlEscape(answer)}</p>`;
     renderTrustedHtml(this.resultEl, trustedTemplate(`
       <h5>${this.htmlEscape(app.name)}</h5>
-      <p>${this.htmlEscape(answer)}</p>
+      ${body}
       <small style="color:#475467;">${this.htmlEscape(String(response.tokens_used))} tokens • ${new Date().toLocaleTimeString()}</small>
     `));
   }
 
+  private async handleResultClick(event: MouseEvent): Promise<void> {
+    const target = (event.target as HTMLElement | null)?.closest('[data-output-action]') as HTMLElement | null;
+    const runId = this.lastStructuredRunId;
+    if (!target || !runId) return;
+    const action = target.getAttribute('data-output-action');
+    if (action === 'pipe') {
+      const select = this.resultEl?.querySelector<HTMLSelectElement>('select[data-output-target]');
+      const next = this.apps.find((app) => app.id === select?.value);
+      if (next) {
+        await this.launchApp(next, '', {}, runId);
+      }
+      return;
+    }
+    const format = action === 'export-csv' ? 'csv' : 'json';
+    try {
+      const content = (await invoke('agent_export_run_output', { runId, format })) as string;
+      const blob = new Blob([content], { type: format === 'csv' ? 'text/csv' : 'application/json' });
+      const url = URL.createObjectURL(blob);
+      const link = document.createElement('a');
+      link.href = url;
+      link.download = `${runId}-output.${format}`;
+      link.click();
+      URL.revokeObjectURL(url);
+    } catch (error) {
+      console.error('Failed to export agent run output', error);
+    }
+  }
+
   private htmlEscape(value: string): string {
     const div = document.createElement('div');
     div.textContent = value;
@@ -2601,6 +3011,9 @@ export {
   appLauncher,
   initializeApp
 };
+listen('mcp://oauth-updated', ({ payload }) => {
+  void settingsManager.handleMcpOAuthEvent(payload as McpOAuthEvent);
+});
 listen('agent://approval-request', async ({ payload }) => {
   try {
     const data = typeof payload === 'string' ? JSON.parse(payload) : payload;
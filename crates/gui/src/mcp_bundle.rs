@@ -0,0 +1,488 @@
+//! Encrypted, signed MCP profile bundles.
+//!
+//! A bundle carries a profile's server manifests together with the secret
+//! values referenced from the keyring. The payload is sealed with
+//! XChaCha20-Poly1305 under a key derived from a passphrase with Argon2id,
+//! and the resulting envelope is signed with the exporting user's ed25519
+//! key so recipients can tell who produced it before they decrypt anything.
+
+use crate::agent::McpServerConfig;
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const BUNDLE_FORMAT: &str = "advatar-mcp-bundle";
+const BUNDLE_VERSION: u8 = 1;
+const KDF_ALGORITHM: &str = "argon2id";
+const CIPHER_ALGORITHM: &str = "xchacha20poly1305";
+const SIGNATURE_ALGORITHM: &str = "ed25519";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+pub const MIN_PASSPHRASE_CHARS: usize = 8;
+/// Upper bounds accepted when importing, so a crafted bundle cannot make the
+/// importer burn unbounded memory or CPU before the passphrase is checked.
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 16;
+const MAX_KDF_PARALLELISM: u32 = 8;
+
+/// Argon2id cost parameters used when sealing a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McpBundleKdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for McpBundleKdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundleKdf {
+    pub algorithm: String,
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl McpBundleKdf {
+    fn derive_key(&self, passphrase: &str) -> Result<[u8; 32]> {
+        if self.algorithm != KDF_ALGORITHM {
+            return Err(anyhow!("unsupported bundle KDF `{}`", self.algorithm));
+        }
+        if self.memory_kib > MAX_KDF_MEMORY_KIB
+            || self.iterations > MAX_KDF_ITERATIONS
+            || self.parallelism > MAX_KDF_PARALLELISM
+        {
+            return Err(anyhow!("bundle KDF parameters exceed the supported limits"));
+        }
+        let salt = BASE64
+            .decode(&self.salt)
+            .context("decoding bundle KDF salt")?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|err| anyhow!("invalid bundle KDF parameters: {err}"))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|err| anyhow!("deriving bundle key: {err}"))?;
+        Ok(key)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundleCipher {
+    pub algorithm: String,
+    pub nonce: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundleSigner {
+    pub algorithm: String,
+    /// Hex-encoded ed25519 verifying key.
+    pub public_key: String,
+}
+
+impl McpBundleSigner {
+    pub fn from_key(key: &VerifyingKey) -> Self {
+        Self {
+            algorithm: SIGNATURE_ALGORITHM.to_string(),
+            public_key: hex::encode(key.to_bytes()),
+        }
+    }
+
+    fn verifying_key(&self) -> Result<VerifyingKey> {
+        if self.algorithm != SIGNATURE_ALGORITHM {
+            return Err(anyhow!("unsupported bundle signature `{}`", self.algorithm));
+        }
+        let bytes: [u8; 32] = hex::decode(&self.public_key)
+            .context("decoding bundle signer key")?
+            .try_into()
+            .map_err(|_| anyhow!("bundle signer key must be 32 bytes"))?;
+        VerifyingKey::from_bytes(&bytes).map_err(|err| anyhow!("invalid bundle signer key: {err}"))
+    }
+
+    /// Short form of the signer key for people to compare out of band: the
+    /// first 16 bytes of its SHA-256, in colon-separated groups.
+    pub fn fingerprint(&self) -> Result<String> {
+        let digest = Sha256::digest(self.verifying_key()?.to_bytes());
+        Ok(digest[..16]
+            .chunks(2)
+            .map(hex::encode)
+            .collect::<Vec<_>>()
+            .join(":"))
+    }
+}
+
+/// Who the importer trusts to have produced a bundle. A bundle is only
+/// opened once its signer matches one of these.
+#[derive(Debug, Clone, Copy)]
+pub enum McpBundleTrust<'a> {
+    /// A pinned, hex-encoded signer key.
+    Signer(&'a str),
+    /// A signer fingerprint the user was shown and explicitly confirmed.
+    Fingerprint(&'a str),
+}
+
+/// On-disk envelope of an encrypted bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpEncryptedBundle {
+    pub format: String,
+    pub version: u8,
+    pub label: String,
+    pub created_at: u64,
+    pub kdf: McpBundleKdf,
+    pub cipher: McpBundleCipher,
+    pub signer: McpBundleSigner,
+    pub ciphertext: String,
+    pub signature: String,
+}
+
+/// Every envelope field except the signature, in a fixed order.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedFields<'a> {
+    format: &'a str,
+    version: u8,
+    label: &'a str,
+    created_at: u64,
+    kdf: &'a McpBundleKdf,
+    cipher: &'a McpBundleCipher,
+    signer: &'a McpBundleSigner,
+    ciphertext: &'a str,
+}
+
+impl McpEncryptedBundle {
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(&SignedFields {
+            format: &self.format,
+            version: self.version,
+            label: &self.label,
+            created_at: self.created_at,
+            kdf: &self.kdf,
+            cipher: &self.cipher,
+            signer: &self.signer,
+            ciphertext: &self.ciphertext,
+        })
+        .context("serialising bundle for signing")
+    }
+
+    /// Checks the envelope signature and that it was produced by a signer
+    /// the importer trusts.
+    pub fn verify(&self, trust: McpBundleTrust<'_>) -> Result<()> {
+        self.verify_signature()?;
+        let trusted = match trust {
+            McpBundleTrust::Signer(expected) => expected
+                .trim()
+                .eq_ignore_ascii_case(&self.signer.public_key),
+            McpBundleTrust::Fingerprint(confirmed) => confirmed
+                .trim()
+                .eq_ignore_ascii_case(&self.signer.fingerprint()?),
+        };
+        if !trusted {
+            return Err(anyhow!(
+                "bundle was signed by {} ({}) rather than the expected signer",
+                self.signer.public_key,
+                self.signer.fingerprint()?
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the envelope is intact and was signed by the key it
+    /// names. That key is not trusted by this alone; see [`Self::verify`].
+    pub fn verify_signature(&self) -> Result<()> {
+        if self.format != BUNDLE_FORMAT {
+            return Err(anyhow!("not an encrypted MCP bundle"));
+        }
+        if self.version != BUNDLE_VERSION {
+            return Err(anyhow!(
+                "unsupported encrypted MCP bundle version {}",
+                self.version
+            ));
+        }
+        let signature_bytes = BASE64
+            .decode(&self.signature)
+            .context("decoding bundle signature")?;
+        let signature = Signature::from_slice(&signature_bytes)
+            .map_err(|err| anyhow!("malformed bundle signature: {err}"))?;
+        self.signer
+            .verifying_key()?
+            .verify(&self.signed_bytes()?, &signature)
+            .map_err(|_| anyhow!("bundle signature verification failed"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpSecretScope {
+    Header,
+    Env,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundledSecret {
+    pub server_id: String,
+    pub scope: McpSecretScope,
+    pub key: String,
+    pub value: String,
+}
+
+impl std::fmt::Debug for McpBundledSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpBundledSecret")
+            .field("server_id", &self.server_id)
+            .field("scope", &self.scope)
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decrypted contents of a bundle. Server manifests are scrubbed of keyring
+/// references; secret values travel separately in `secrets`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundlePayload {
+    pub servers: Vec<McpServerConfig>,
+    #[serde(default)]
+    pub secrets: Vec<McpBundledSecret>,
+}
+
+/// Returns whether raw bundle JSON is an encrypted envelope rather than a
+/// plain profile export.
+pub fn is_encrypted_bundle(raw: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(raw)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("format")
+                .and_then(|format| format.as_str())
+                .map(|format| format == BUNDLE_FORMAT)
+        })
+        .unwrap_or(false)
+}
+
+pub fn seal(
+    label: &str,
+    created_at: u64,
+    payload: &McpBundlePayload,
+    passphrase: &str,
+    signing_key: &SigningKey,
+    params: McpBundleKdfParams,
+) -> Result<McpEncryptedBundle> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(anyhow!(
+            "bundle passphrase must be at least {MIN_PASSPHRASE_CHARS} characters"
+        ));
+    }
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let kdf = McpBundleKdf {
+        algorithm: KDF_ALGORITHM.to_string(),
+        salt: BASE64.encode(salt),
+        memory_kib: params.memory_kib,
+        iterations: params.iterations,
+        parallelism: params.parallelism,
+    };
+    let key = kdf.derive_key(passphrase)?;
+    let plaintext = serde_json::to_vec(payload).context("serialising bundle payload")?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| anyhow!("encrypting bundle payload"))?;
+
+    let mut bundle = McpEncryptedBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        label: label.to_string(),
+        created_at,
+        kdf,
+        cipher: McpBundleCipher {
+            algorithm: CIPHER_ALGORITHM.to_string(),
+            nonce: BASE64.encode(nonce),
+        },
+        signer: McpBundleSigner::from_key(&signing_key.verifying_key()),
+        ciphertext: BASE64.encode(ciphertext),
+        signature: String::new(),
+    };
+    let signature = signing_key.sign(&bundle.signed_bytes()?);
+    bundle.signature = BASE64.encode(signature.to_bytes());
+    Ok(bundle)
+}
+
+/// Verifies the signature first so tampered bundles are rejected before the
+/// comparatively expensive key derivation runs.
+pub fn open(
+    bundle: &McpEncryptedBundle,
+    passphrase: &str,
+    trust: McpBundleTrust<'_>,
+) -> Result<McpBundlePayload> {
+    bundle.verify(trust)?;
+    if bundle.cipher.algorithm != CIPHER_ALGORITHM {
+        return Err(anyhow!(
+            "unsupported bundle cipher `{}`",
+            bundle.cipher.algorithm
+        ));
+    }
+    let nonce = BASE64
+        .decode(&bundle.cipher.nonce)
+        .context("decoding bundle nonce")?;
+    if nonce.len() != NONCE_LEN {
+        return Err(anyhow!("bundle nonce must be {NONCE_LEN} bytes"));
+    }
+    let ciphertext = BASE64
+        .decode(&bundle.ciphertext)
+        .context("decoding bundle ciphertext")?;
+    let key = bundle.kdf.derive_key(passphrase)?;
+    let plaintext = XChaCha20Poly1305::new(&key.into())
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("incorrect passphrase or corrupted bundle"))?;
+    serde_json::from_slice(&plaintext).context("parsing decrypted bundle payload")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: McpBundleKdfParams = McpBundleKdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn payload() -> McpBundlePayload {
+        McpBundlePayload {
+            servers: Vec::new(),
+            secrets: vec![McpBundledSecret {
+                server_id: "search".into(),
+                scope: McpSecretScope::Header,
+                key: "Authorization".into(),
+                value: "Bearer s3cr3t".into(),
+            }],
+        }
+    }
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn sealed_bundle_round_trips_and_hides_secrets() {
+        let key = signing_key(7);
+        let bundle = seal("Team", 42, &payload(), "correct horse", &key, FAST).unwrap();
+        let raw = serde_json::to_string(&bundle).unwrap();
+        assert!(is_encrypted_bundle(&raw));
+        assert!(!raw.contains("s3cr3t"));
+
+        let signer = hex::encode(key.verifying_key().to_bytes());
+        let opened = open(&bundle, "correct horse", McpBundleTrust::Signer(&signer)).unwrap();
+        assert_eq!(opened.secrets.len(), 1);
+        assert_eq!(opened.secrets[0].value, "Bearer s3cr3t");
+    }
+
+    #[test]
+    fn open_rejects_wrong_passphrase_and_unexpected_signer() {
+        let bundle = seal(
+            "Team",
+            42,
+            &payload(),
+            "correct horse",
+            &signing_key(7),
+            FAST,
+        )
+        .unwrap();
+        let signer = hex::encode(signing_key(7).verifying_key().to_bytes());
+        let err = open(&bundle, "battery staple", McpBundleTrust::Signer(&signer)).unwrap_err();
+        assert!(err.to_string().contains("incorrect passphrase"), "{err}");
+
+        let other = hex::encode(signing_key(9).verifying_key().to_bytes());
+        let err = open(&bundle, "correct horse", McpBundleTrust::Signer(&other)).unwrap_err();
+        assert!(err.to_string().contains("expected signer"), "{err}");
+    }
+
+    #[test]
+    fn unpinned_signers_need_a_confirmed_fingerprint() {
+        let bundle = seal(
+            "Team",
+            42,
+            &payload(),
+            "correct horse",
+            &signing_key(7),
+            FAST,
+        )
+        .unwrap();
+        let fingerprint = bundle.signer.fingerprint().unwrap();
+        assert_eq!(fingerprint.len(), 8 * 4 + 7);
+        let opened = open(
+            &bundle,
+            "correct horse",
+            McpBundleTrust::Fingerprint(&fingerprint.to_uppercase()),
+        )
+        .unwrap();
+        assert_eq!(opened.secrets.len(), 1);
+
+        let other = McpBundleSigner::from_key(&signing_key(9).verifying_key())
+            .fingerprint()
+            .unwrap();
+        let err = open(
+            &bundle,
+            "correct horse",
+            McpBundleTrust::Fingerprint(&other),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains(&fingerprint), "{err}");
+    }
+
+    #[test]
+    fn tampering_breaks_the_signature() {
+        let mut bundle = seal(
+            "Team",
+            42,
+            &payload(),
+            "correct horse",
+            &signing_key(7),
+            FAST,
+        )
+        .unwrap();
+        bundle.label = "Evil".into();
+        let original = hex::encode(signing_key(7).verifying_key().to_bytes());
+        let err = open(&bundle, "correct horse", McpBundleTrust::Signer(&original)).unwrap_err();
+        assert!(err.to_string().contains("signature"), "{err}");
+
+        // Re-signing with a different key swaps the signer, which a pinned
+        // import must notice.
+        let forged = seal(
+            "Team",
+            42,
+            &payload(),
+            "correct horse",
+            &signing_key(8),
+            FAST,
+        )
+        .unwrap();
+        assert!(forged.verify_signature().is_ok());
+        assert!(forged.verify(McpBundleTrust::Signer(&original)).is_err());
+    }
+
+    #[test]
+    fn seal_requires_a_reasonable_passphrase() {
+        assert!(seal("Team", 0, &payload(), "short", &signing_key(1), FAST).is_err());
+        assert!(!is_encrypted_bundle(r#"{"version":1,"servers":[]}"#));
+    }
+}
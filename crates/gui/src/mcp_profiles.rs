@@ -1,5 +1,10 @@
 use crate::agent::{McpConfigValue, McpResolvedServerConfig, McpServerConfig, McpTransportKind};
+use crate::mcp_bundle::{
+    self, McpBundleKdfParams, McpBundlePayload, McpBundleSigner, McpBundleTrust, McpBundledSecret,
+    McpEncryptedBundle, McpSecretScope,
+};
 use anyhow::{anyhow, Context, Result};
+use ed25519_dalek::SigningKey;
 use indexmap::IndexMap;
 use keyring::Entry;
 use serde::{Deserialize, Serialize};
@@ -14,6 +19,7 @@ const LEGACY_MANIFEST: &str = "configs/mcp_servers.json";
 const PROFILE_INDEX_FILE: &str = "profiles.json";
 const EXPORT_VERSION: u8 = 1;
 const KEYRING_SERVICE: &str = "advatar-browser-mcp";
+const BUNDLE_SIGNING_KEY_ID: &str = "mcp-bundle-signing-key";
 
 fn now_millis() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -46,6 +52,15 @@ impl McpSecretStore {
             .map_err(|err| anyhow!("failed to read secret `{}`: {}", secret_id, err))
     }
 
+    /// Like [`read`](Self::read), but reports a missing entry as `None`.
+    pub fn try_read(&self, secret_id: &str) -> Result<Option<String>> {
+        match self.entry(secret_id)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(anyhow!("failed to read secret `{}`: {}", secret_id, err)),
+        }
+    }
+
     pub fn store(&self, secret_id: Option<String>, value: &str) -> Result<String> {
         let id = secret_id.unwrap_or_else(|| Uuid::new_v4().to_string());
         self.entry(&id)?
@@ -132,6 +147,28 @@ pub struct McpProfileBundle {
     pub servers: Vec<McpServerConfig>,
 }
 
+/// What the user is shown about an encrypted bundle before deciding whether
+/// to trust its signer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundleInspection {
+    pub label: String,
+    pub created_at: u64,
+    pub signer: McpBundleSigner,
+    pub fingerprint: String,
+    /// Signed with this machine's own bundle key.
+    pub own_key: bool,
+}
+
+/// Outcome of importing an encrypted bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpBundleImport {
+    pub profile: McpProfileRecord,
+    pub signer: McpBundleSigner,
+    pub secrets_restored: usize,
+}
+
 pub struct McpConfigService {
     base_dir: PathBuf,
     index_path: PathBuf,
@@ -343,6 +380,9 @@ impl McpConfigService {
             program: config.program.clone(),
             args: config.args.clone(),
             env: self.resolve_map(profile_id, &config.id, "env", &config.env)?,
+            sandbox: config.sandbox.clone(),
+            circuit_breaker: config.circuit_breaker.clone(),
+            oauth: config.oauth.clone(),
         })
     }
 
@@ -371,6 +411,9 @@ impl McpConfigService {
                 .or_else(|| (!config.endpoint.trim().is_empty()).then(|| config.endpoint.clone())),
             args: config.args.clone(),
             env: Self::resolve_inline_map(&self.secret_store, config, "env", &config.env)?,
+            sandbox: config.sandbox.clone(),
+            circuit_breaker: config.circuit_breaker.clone(),
+            oauth: config.oauth.clone(),
         })
     }
 
@@ -392,11 +435,14 @@ impl McpConfigService {
             if server.enabled {
                 let transport_label = match server.transport {
                     McpTransportKind::Http => "http",
+                    McpTransportKind::StreamableHttp => "streamable-http",
                     McpTransportKind::Websocket => "websocket",
                     McpTransportKind::Stdio => "stdio",
                 };
                 match server.transport {
-                    McpTransportKind::Http | McpTransportKind::Websocket => {
+                    McpTransportKind::Http
+                    | McpTransportKind::StreamableHttp
+                    | McpTransportKind::Websocket => {
                         if server.endpoint.trim().is_empty() {
                             return Err(anyhow!(
                                 "MCP server `{}` requires an endpoint for {} transport",
@@ -417,6 +463,40 @@ impl McpConfigService {
                 }
             }
 
+            if server.sandbox.is_some() && server.transport != McpTransportKind::Stdio {
+                return Err(anyhow!(
+                    "MCP server `{}` declares a sandbox, which only applies to stdio servers",
+                    label
+                ));
+            }
+
+            if server.oauth.is_some()
+                && !matches!(
+                    server.transport,
+                    McpTransportKind::Http | McpTransportKind::StreamableHttp
+                )
+            {
+                return Err(anyhow!(
+                    "MCP server `{}` declares OAuth, which only applies to HTTP transports",
+                    label
+                ));
+            }
+
+            if let Some(breaker) = &server.circuit_breaker {
+                if !(breaker.error_rate_threshold > 0.0 && breaker.error_rate_threshold <= 1.0) {
+                    return Err(anyhow!(
+                        "MCP server `{}` circuit breaker errorRateThreshold must be within (0, 1]",
+                        label
+                    ));
+                }
+                if breaker.window_size == 0 {
+                    return Err(anyhow!(
+                        "MCP server `{}` circuit breaker windowSize must be positive",
+                        label
+                    ));
+                }
+            }
+
             Self::validate_secret_map(&server.headers, &server.id, "headers")?;
             Self::validate_secret_map(&server.env, &server.id, "env")?;
         }
@@ -690,17 +770,7 @@ impl McpConfigService {
     pub fn export_profile(&self, profile_id: &str, target: &Path) -> Result<()> {
         self.ensure_profile_exists(profile_id)?;
         let configs = self.read_manifest(profile_id)?;
-        let index = self
-            .state
-            .lock()
-            .map_err(|_| anyhow!("profile index poisoned"))?;
-        let label = index
-            .profiles
-            .iter()
-            .find(|p| p.id == profile_id)
-            .map(|p| p.label.clone())
-            .unwrap_or_else(|| profile_id.to_string());
-        drop(index);
+        let label = self.profile_label(profile_id)?;
         let mut scrubbed = configs.clone();
         for server in scrubbed.iter_mut() {
             Self::scrub_map(&mut server.headers);
@@ -729,6 +799,12 @@ impl McpConfigService {
     ) -> Result<McpProfileRecord> {
         let raw = fs::read_to_string(bundle_path)
             .with_context(|| format!("reading MCP bundle at {}", bundle_path.display()))?;
+        if mcp_bundle::is_encrypted_bundle(&raw) {
+            return Err(anyhow!(
+                "MCP bundle at {} is encrypted; a passphrase is required to import it",
+                bundle_path.display()
+            ));
+        }
         let mut bundle: McpProfileBundle = serde_json::from_str(&raw)
             .with_context(|| format!("parsing MCP bundle at {}", bundle_path.display()))?;
         if bundle.version != EXPORT_VERSION {
@@ -747,6 +823,224 @@ impl McpConfigService {
         Ok(record)
     }
 
+    /// Writes the profile as an encrypted bundle signed with this machine's
+    /// bundle key. Secret values are read from the keyring when requested.
+    pub fn export_encrypted_profile(
+        &self,
+        profile_id: &str,
+        target: &Path,
+        passphrase: &str,
+        include_secrets: bool,
+    ) -> Result<McpBundleSigner> {
+        self.ensure_profile_exists(profile_id)?;
+        let mut servers = self.read_manifest(profile_id)?;
+        let label = self.profile_label(profile_id)?;
+        let mut secrets = Vec::new();
+        for server in servers.iter_mut() {
+            if include_secrets {
+                self.collect_secrets(
+                    &server.id,
+                    McpSecretScope::Header,
+                    &server.headers,
+                    &mut secrets,
+                )?;
+                self.collect_secrets(&server.id, McpSecretScope::Env, &server.env, &mut secrets)?;
+            }
+            Self::scrub_map(&mut server.headers);
+            Self::scrub_map(&mut server.env);
+        }
+        let payload = McpBundlePayload { servers, secrets };
+        let signing_key = self.bundle_signing_key()?;
+        let bundle = mcp_bundle::seal(
+            &label,
+            now_millis(),
+            &payload,
+            passphrase,
+            &signing_key,
+            McpBundleKdfParams::default(),
+        )?;
+        let raw =
+            serde_json::to_string_pretty(&bundle).context("serialising encrypted MCP bundle")?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating export dir at {}", parent.display()))?;
+        }
+        fs::write(target, raw)
+            .with_context(|| format!("writing MCP export to {}", target.display()))?;
+        Ok(bundle.signer)
+    }
+
+    /// Checks a bundle's signature and describes its signer, without
+    /// decrypting it, so the user can decide whether to trust the signer.
+    pub fn inspect_encrypted_bundle(&self, bundle_path: &Path) -> Result<McpBundleInspection> {
+        let bundle = Self::read_encrypted_bundle(bundle_path)?;
+        bundle.verify_signature()?;
+        Ok(McpBundleInspection {
+            fingerprint: bundle.signer.fingerprint()?,
+            own_key: bundle.signer == self.bundle_signer()?,
+            label: bundle.label,
+            created_at: bundle.created_at,
+            signer: bundle.signer,
+        })
+    }
+
+    /// Verifies and decrypts a bundle, stores its secrets in the local
+    /// keyring under fresh ids and creates a profile from it. The signer has
+    /// to be pinned with `expected_signer`, confirmed by fingerprint, or be
+    /// this machine's own bundle key.
+    pub fn import_encrypted_profile_from_path(
+        &self,
+        bundle_path: &Path,
+        passphrase: &str,
+        expected_signer: Option<&str>,
+        confirmed_fingerprint: Option<&str>,
+        label_override: Option<&str>,
+        make_active: bool,
+    ) -> Result<McpBundleImport> {
+        let bundle = Self::read_encrypted_bundle(bundle_path)?;
+        let own_signer = self.bundle_signer()?;
+        let trust = match (expected_signer, confirmed_fingerprint) {
+            (Some(signer), _) => McpBundleTrust::Signer(signer),
+            (None, Some(fingerprint)) => McpBundleTrust::Fingerprint(fingerprint),
+            (None, None) if bundle.signer == own_signer => {
+                McpBundleTrust::Signer(&own_signer.public_key)
+            }
+            (None, None) => {
+                return Err(anyhow!(
+                    "MCP bundle is signed by an untrusted key with fingerprint {}; confirm the fingerprint or pin the signer to import it",
+                    bundle.signer.fingerprint()?
+                ));
+            }
+        };
+        let payload = mcp_bundle::open(&bundle, passphrase, trust)?;
+        let mut servers = payload.servers;
+        for server in servers.iter_mut() {
+            Self::scrub_map(&mut server.headers);
+            Self::scrub_map(&mut server.env);
+        }
+        let secrets_restored = match self.rehome_secrets(&mut servers, &payload.secrets) {
+            Ok(count) => count,
+            Err(err) => {
+                for server in &servers {
+                    let _ = self.revoke_all_secrets(server);
+                }
+                return Err(err);
+            }
+        };
+        let label = label_override
+            .map(|s| s.to_string())
+            .unwrap_or(bundle.label);
+        let record = match self.create_profile(&label, make_active) {
+            Ok(record) => record,
+            Err(err) => {
+                for server in &servers {
+                    let _ = self.revoke_all_secrets(server);
+                }
+                return Err(err);
+            }
+        };
+        self.write_profile_manifest(&record.id, &servers)?;
+        self.touch_profile(&record.id)?;
+        Ok(McpBundleImport {
+            profile: record,
+            signer: bundle.signer,
+            secrets_restored,
+        })
+    }
+
+    fn read_encrypted_bundle(bundle_path: &Path) -> Result<McpEncryptedBundle> {
+        let raw = fs::read_to_string(bundle_path)
+            .with_context(|| format!("reading MCP bundle at {}", bundle_path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("parsing MCP bundle at {}", bundle_path.display()))
+    }
+
+    /// Public half of the key used to sign exported bundles, so teammates can
+    /// pin it when importing.
+    pub fn bundle_signer(&self) -> Result<McpBundleSigner> {
+        Ok(McpBundleSigner::from_key(
+            &self.bundle_signing_key()?.verifying_key(),
+        ))
+    }
+
+    fn bundle_signing_key(&self) -> Result<SigningKey> {
+        if let Some(encoded) = self.secret_store.try_read(BUNDLE_SIGNING_KEY_ID)? {
+            let bytes: [u8; 32] = hex::decode(encoded.trim())
+                .context("decoding MCP bundle signing key")?
+                .try_into()
+                .map_err(|_| anyhow!("MCP bundle signing key must be 32 bytes"))?;
+            return Ok(SigningKey::from_bytes(&bytes));
+        }
+        let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        self.secret_store.store(
+            Some(BUNDLE_SIGNING_KEY_ID.to_string()),
+            &hex::encode(key.to_bytes()),
+        )?;
+        Ok(key)
+    }
+
+    fn profile_label(&self, profile_id: &str) -> Result<String> {
+        let index = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("profile index poisoned"))?;
+        Ok(index
+            .profiles
+            .iter()
+            .find(|p| p.id == profile_id)
+            .map(|p| p.label.clone())
+            .unwrap_or_else(|| profile_id.to_string()))
+    }
+
+    fn collect_secrets(
+        &self,
+        server_id: &str,
+        scope: McpSecretScope,
+        map: &IndexMap<String, McpConfigValue>,
+        out: &mut Vec<McpBundledSecret>,
+    ) -> Result<()> {
+        for (key, value) in map {
+            let McpConfigValue::Secret(secret) = value else {
+                continue;
+            };
+            let Some(secret_id) = &secret.secret_id else {
+                continue;
+            };
+            out.push(McpBundledSecret {
+                server_id: server_id.to_string(),
+                scope,
+                key: key.clone(),
+                value: self.secret_store.read(secret_id)?,
+            });
+        }
+        Ok(())
+    }
+
+    fn rehome_secrets(
+        &self,
+        servers: &mut [McpServerConfig],
+        secrets: &[McpBundledSecret],
+    ) -> Result<usize> {
+        let mut restored = 0;
+        for bundled in secrets {
+            let Some(server) = servers.iter_mut().find(|s| s.id == bundled.server_id) else {
+                continue;
+            };
+            let map = match bundled.scope {
+                McpSecretScope::Header => &mut server.headers,
+                McpSecretScope::Env => &mut server.env,
+            };
+            let Some(McpConfigValue::Secret(secret)) = map.get_mut(&bundled.key) else {
+                continue;
+            };
+            let id = self.secret_store.store(None, &bundled.value)?;
+            secret.secret_id = Some(id);
+            secret.preview = Some(McpSecretStore::preview(&bundled.value));
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
     fn scrub_map(map: &mut IndexMap<String, McpConfigValue>) {
         for value in map.values_mut() {
             if let McpConfigValue::Secret(secret) = value {
@@ -765,7 +1059,7 @@ impl McpConfigService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::agent::McpSecretValue;
+    use crate::agent::{McpCircuitBreakerConfig, McpSandboxConfig, McpSecretValue};
     use indexmap::indexmap;
 
     fn test_service() -> McpConfigService {
@@ -793,6 +1087,9 @@ mod tests {
             program: None,
             args: Vec::new(),
             env: IndexMap::new(),
+            sandbox: None,
+            circuit_breaker: None,
+            oauth: None,
         }
     }
 
@@ -839,6 +1136,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate_rejects_sandbox_for_network_transports() {
+        let service = test_service();
+        let server = McpServerConfig {
+            sandbox: Some(McpSandboxConfig::default()),
+            ..base_server("sandboxed-http")
+        };
+        let err = service
+            .validate_servers(&[server])
+            .expect_err("should fail");
+        assert!(
+            err.to_string().contains("only applies to stdio"),
+            "unexpected error: {err}"
+        );
+    }
+
     #[test]
     fn validate_secrets_require_values() {
         let service = test_service();
@@ -860,6 +1173,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate_rejects_out_of_range_error_budget() {
+        let service = test_service();
+        let server = McpServerConfig {
+            circuit_breaker: Some(McpCircuitBreakerConfig {
+                error_rate_threshold: 1.5,
+                ..McpCircuitBreakerConfig::default()
+            }),
+            ..base_server("over-budget")
+        };
+        let err = service
+            .validate_servers(&[server])
+            .expect_err("should fail");
+        assert!(
+            err.to_string().contains("errorRateThreshold"),
+            "unexpected error: {err}"
+        );
+    }
+
     #[test]
     fn resolve_inline_uses_endpoint_as_stdio_program() {
         let service = test_service();
@@ -11,6 +11,8 @@ use url::Url;
 
 const DEFAULT_IPFS_GATEWAY: &str = "builtin://ipfs";
 const LOCAL_IPFS_TIMEOUT: Duration = Duration::from_secs(20);
+/// Recursive pins fetch the whole DAG, so they get far longer than reads.
+const LOCAL_IPFS_PIN_TIMEOUT: Duration = Duration::from_secs(300);
 
 #[derive(Clone)]
 pub struct ProtocolHandler {
@@ -209,6 +211,42 @@ impl ProtocolHandler {
         self.load_decentralized_content(&resource).await
     }
 
+    /// Recursively pins the root CID of an `ipfs://` URL (or a bare CID) in
+    /// the embedded node and returns it.
+    pub async fn pin_ipfs_content(&self, url: &str) -> Result<String> {
+        let candidate = url.trim();
+        let resource = if candidate.contains("://") {
+            DecentralizedUrl::parse(candidate, DecentralizedScheme::Ipfs)?
+        } else {
+            DecentralizedUrl {
+                scheme: DecentralizedScheme::Ipfs,
+                root: candidate.trim_matches('/').to_string(),
+                path: String::new(),
+            }
+        };
+        if resource.scheme != DecentralizedScheme::Ipfs {
+            return Err(anyhow!("only ipfs:// content can be pinned"));
+        }
+        resource.validate(self)?;
+
+        let node = self.local_ipfs().await?;
+        let root = format!("/ipfs/{}", resource.root)
+            .parse::<IpfsPath>()
+            .with_context(|| format!("invalid IPFS CID: {}", resource.root))?;
+        let cid = *root
+            .root()
+            .cid()
+            .ok_or_else(|| anyhow!("invalid IPFS CID: {}", resource.root))?;
+        tokio::time::timeout(
+            LOCAL_IPFS_PIN_TIMEOUT,
+            node.ipfs.insert_pin(&cid).recursive(),
+        )
+        .await
+        .context("timed out pinning IPFS content")?
+        .context("failed to pin IPFS content")?;
+        Ok(cid.to_string())
+    }
+
     pub async fn handle_ens(&self, name: &str) -> Result<String> {
         {
             let cache = self.ens_cache.read().await;
@@ -0,0 +1,628 @@
+use ai_agent::{cosine_similarity, EmbeddingClient, HashingEmbeddingClient};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::browser_engine::{Bookmark, BrowserEngine, HistoryEntry};
+use crate::commands::preferred_data_root;
+
+const INDEX_FILE: &str = "semantic-index.json";
+const MAX_DOCUMENT_CHARS: usize = 4_000;
+const MAX_SNAPSHOT_DOCUMENTS: usize = 500;
+const SNIPPET_CHARS: usize = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SemanticSourceKind {
+    History,
+    Bookmark,
+    Snapshot,
+}
+
+#[derive(Debug, Clone)]
+pub struct SemanticDocumentDraft {
+    pub kind: SemanticSourceKind,
+    pub source_id: String,
+    pub url: String,
+    pub title: String,
+    pub text: String,
+}
+
+impl SemanticDocumentDraft {
+    pub fn from_history(entry: &HistoryEntry) -> Self {
+        let mut text = entry.title.clone();
+        if let Some(summary) = &entry.summary {
+            text.push('\n');
+            text.push_str(summary);
+        }
+        if !entry.keywords.is_empty() {
+            text.push('\n');
+            text.push_str(&entry.keywords.join(" "));
+        }
+        Self {
+            kind: SemanticSourceKind::History,
+            source_id: entry.url.clone(),
+            url: entry.url.clone(),
+            title: entry.title.clone(),
+            text,
+        }
+    }
+
+    pub fn from_bookmark(bookmark: &Bookmark) -> Self {
+        let mut text = bookmark.title.clone();
+        if let Some(folder) = &bookmark.folder {
+            text.push('\n');
+            text.push_str(folder);
+        }
+        if !bookmark.tags.is_empty() {
+            text.push('\n');
+            text.push_str(&bookmark.tags.join(" "));
+        }
+        text.push('\n');
+        text.push_str(&bookmark.url);
+        Self {
+            kind: SemanticSourceKind::Bookmark,
+            source_id: bookmark.id.clone(),
+            url: bookmark.url.clone(),
+            title: bookmark.title.clone(),
+            text,
+        }
+    }
+
+    /// Builds a draft from the payload returned by `snapshot_page`.
+    pub fn from_snapshot(snapshot: &Value) -> Option<Self> {
+        let url = snapshot.get("url").and_then(Value::as_str)?.trim();
+        if url.is_empty() {
+            return None;
+        }
+        let title = snapshot
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or(url)
+            .to_string();
+        let mut parts = vec![title.clone()];
+        if let Some(headings) = snapshot.get("headings").and_then(Value::as_array) {
+            parts.extend(
+                headings
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string),
+            );
+        }
+        if let Some(main_text) = snapshot.get("mainText").and_then(Value::as_str) {
+            parts.push(main_text.to_string());
+        }
+        Some(Self {
+            kind: SemanticSourceKind::Snapshot,
+            source_id: url.to_string(),
+            url: url.to_string(),
+            title,
+            text: parts.join("\n"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SemanticDocument {
+    kind: SemanticSourceKind,
+    source_id: String,
+    url: String,
+    title: String,
+    text: String,
+    content_hash: String,
+    updated_at: u64,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SemanticIndexManifest {
+    model_id: String,
+    dimensions: usize,
+    #[serde(default)]
+    documents: Vec<SemanticDocument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticSearchHit {
+    pub kind: SemanticSourceKind,
+    pub source_id: String,
+    pub url: String,
+    pub title: String,
+    pub snippet: String,
+    pub score: f32,
+}
+
+pub struct SemanticIndex {
+    embedder: Arc<dyn EmbeddingClient>,
+    documents: Arc<RwLock<Vec<SemanticDocument>>>,
+    index_path: Option<PathBuf>,
+    /// [`BrowserEngine::browsing_data_revision`] last mirrored by
+    /// [`Self::sync_browser_data`].
+    synced_revision: AsyncMutex<Option<u64>>,
+}
+
+impl SemanticIndex {
+    /// Loads the index stored next to the browser settings and downloads.
+    pub fn load_default() -> Result<Self> {
+        let data_root = preferred_data_root().map_err(|err| anyhow!(err))?;
+        Self::from_path(
+            data_root.join(INDEX_FILE),
+            Arc::new(HashingEmbeddingClient::default()),
+        )
+    }
+
+    pub fn from_path(path: PathBuf, embedder: Arc<dyn EmbeddingClient>) -> Result<Self> {
+        let documents = Self::read_manifest(&path, embedder.as_ref())?;
+        Ok(Self {
+            embedder,
+            documents: Arc::new(RwLock::new(documents)),
+            index_path: Some(path),
+            synced_revision: AsyncMutex::new(None),
+        })
+    }
+
+    /// Index that lives only in memory; used when the data directory is
+    /// unavailable.
+    pub fn in_memory(embedder: Arc<dyn EmbeddingClient>) -> Self {
+        Self {
+            embedder,
+            documents: Arc::new(RwLock::new(Vec::new())),
+            index_path: None,
+            synced_revision: AsyncMutex::new(None),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.read().map(|docs| docs.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Embeds and stores new or changed drafts. Returns the number of
+    /// documents that were (re)embedded.
+    pub async fn upsert(&self, drafts: Vec<SemanticDocumentDraft>) -> Result<usize> {
+        let changed = self.changed_drafts(drafts)?;
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        let embedded = self.embed_drafts(changed).await?;
+        let count = embedded.len();
+        {
+            let mut documents = self
+                .documents
+                .write()
+                .map_err(|_| anyhow!("semantic index lock poisoned"))?;
+            for document in embedded {
+                documents.retain(|existing| {
+                    !(existing.kind == document.kind && existing.source_id == document.source_id)
+                });
+                documents.push(document);
+            }
+            trim_snapshots(&mut documents);
+        }
+        self.persist()?;
+        Ok(count)
+    }
+
+    /// Makes the documents of `kind` mirror `drafts`, dropping any source that
+    /// is no longer present (e.g. cleared history or removed bookmarks).
+    pub async fn replace_kind(
+        &self,
+        kind: SemanticSourceKind,
+        drafts: Vec<SemanticDocumentDraft>,
+    ) -> Result<usize> {
+        let keep: HashSet<String> = drafts.iter().map(|draft| draft.source_id.clone()).collect();
+        let removed = {
+            let mut documents = self
+                .documents
+                .write()
+                .map_err(|_| anyhow!("semantic index lock poisoned"))?;
+            let before = documents.len();
+            documents
+                .retain(|document| document.kind != kind || keep.contains(&document.source_id));
+            before - documents.len()
+        };
+        let updated = self.upsert(drafts).await?;
+        if removed > 0 && updated == 0 {
+            self.persist()?;
+        }
+        Ok(updated)
+    }
+
+    pub fn remove(&self, kind: SemanticSourceKind, source_id: &str) -> Result<bool> {
+        let removed = {
+            let mut documents = self
+                .documents
+                .write()
+                .map_err(|_| anyhow!("semantic index lock poisoned"))?;
+            let before = documents.len();
+            documents
+                .retain(|document| !(document.kind == kind && document.source_id == source_id));
+            documents.len() != before
+        };
+        if removed {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Refreshes history and bookmark documents from the browser engine so
+    /// results reflect the current state before searching. Does nothing when
+    /// neither changed since the last sync, so searches stay read-only.
+    pub async fn sync_browser_data(&self, browser_engine: &BrowserEngine) -> Result<()> {
+        let mut synced = self.synced_revision.lock().await;
+        // Read before the data, so a change made during the sync is picked up
+        // by the next one.
+        let revision = browser_engine.browsing_data_revision();
+        if *synced == Some(revision) {
+            return Ok(());
+        }
+        let history = browser_engine.get_history()?;
+        self.replace_kind(
+            SemanticSourceKind::History,
+            history
+                .iter()
+                .map(SemanticDocumentDraft::from_history)
+                .collect(),
+        )
+        .await?;
+        let bookmarks = browser_engine.get_bookmarks()?;
+        self.replace_kind(
+            SemanticSourceKind::Bookmark,
+            bookmarks
+                .iter()
+                .map(SemanticDocumentDraft::from_bookmark)
+                .collect(),
+        )
+        .await?;
+        *synced = Some(revision);
+        Ok(())
+    }
+
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        kinds: &[SemanticSourceKind],
+    ) -> Result<Vec<SemanticSearchHit>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let limit = limit.clamp(1, 50);
+        let query_vector = self
+            .embedder
+            .embed(&[query.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("embedder returned no vector for query"))?;
+
+        let documents = self
+            .documents
+            .read()
+            .map_err(|_| anyhow!("semantic index lock poisoned"))?;
+        let mut hits: Vec<SemanticSearchHit> = documents
+            .iter()
+            .filter(|document| kinds.is_empty() || kinds.contains(&document.kind))
+            .map(|document| (document, cosine_similarity(&query_vector, &document.vector)))
+            .filter(|(_, score)| *score > 0.0)
+            .map(|(document, score)| SemanticSearchHit {
+                kind: document.kind,
+                source_id: document.source_id.clone(),
+                url: document.url.clone(),
+                title: document.title.clone(),
+                snippet: document.text.chars().take(SNIPPET_CHARS).collect(),
+                score,
+            })
+            .collect();
+        hits.sort_by(|left, right| {
+            right
+                .score
+                .partial_cmp(&left.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    fn changed_drafts(
+        &self,
+        drafts: Vec<SemanticDocumentDraft>,
+    ) -> Result<Vec<(SemanticDocumentDraft, String)>> {
+        let documents = self
+            .documents
+            .read()
+            .map_err(|_| anyhow!("semantic index lock poisoned"))?;
+        Ok(drafts
+            .into_iter()
+            .filter_map(|mut draft| {
+                draft.text = draft.text.chars().take(MAX_DOCUMENT_CHARS).collect();
+                let hash = content_hash(&draft);
+                let unchanged = documents.iter().any(|document| {
+                    document.kind == draft.kind
+                        && document.source_id == draft.source_id
+                        && document.content_hash == hash
+                });
+                (!unchanged).then_some((draft, hash))
+            })
+            .collect())
+    }
+
+    async fn embed_drafts(
+        &self,
+        drafts: Vec<(SemanticDocumentDraft, String)>,
+    ) -> Result<Vec<SemanticDocument>> {
+        let texts: Vec<String> = drafts.iter().map(|(draft, _)| draft.text.clone()).collect();
+        let vectors = self.embedder.embed(&texts).await?;
+        if vectors.len() != drafts.len() {
+            return Err(anyhow!(
+                "embedder returned {} vectors for {} documents",
+                vectors.len(),
+                drafts.len()
+            ));
+        }
+        let now = now_secs();
+        Ok(drafts
+            .into_iter()
+            .zip(vectors)
+            .map(|((draft, content_hash), vector)| SemanticDocument {
+                kind: draft.kind,
+                source_id: draft.source_id,
+                url: draft.url,
+                title: draft.title,
+                text: draft.text,
+                content_hash,
+                updated_at: now,
+                vector,
+            })
+            .collect())
+    }
+
+    fn read_manifest(path: &Path, embedder: &dyn EmbeddingClient) -> Result<Vec<SemanticDocument>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read semantic index {}", path.display()))?;
+        let manifest: SemanticIndexManifest = serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse semantic index {}", path.display()))?;
+        // Vectors from a different embedder are not comparable; start over and
+        // let the next sync re-embed everything.
+        if manifest.model_id != embedder.model_id() || manifest.dimensions != embedder.dimensions()
+        {
+            return Ok(Vec::new());
+        }
+        Ok(manifest.documents)
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.index_path else {
+            return Ok(());
+        };
+        let documents = self
+            .documents
+            .read()
+            .map_err(|_| anyhow!("semantic index lock poisoned"))?
+            .clone();
+        let manifest = SemanticIndexManifest {
+            model_id: self.embedder.model_id().to_string(),
+            dimensions: self.embedder.dimensions(),
+            documents,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create semantic index directory {}",
+                    parent.display()
+                )
+            })?;
+        }
+        let payload = serde_json::to_string(&manifest)?;
+        fs::write(path, payload)
+            .with_context(|| format!("failed to write semantic index {}", path.display()))
+    }
+}
+
+fn trim_snapshots(documents: &mut Vec<SemanticDocument>) {
+    let snapshot_count = documents
+        .iter()
+        .filter(|document| document.kind == SemanticSourceKind::Snapshot)
+        .count();
+    if snapshot_count <= MAX_SNAPSHOT_DOCUMENTS {
+        return;
+    }
+    let mut snapshot_times: Vec<u64> = documents
+        .iter()
+        .filter(|document| document.kind == SemanticSourceKind::Snapshot)
+        .map(|document| document.updated_at)
+        .collect();
+    snapshot_times.sort_unstable_by(|left, right| right.cmp(left));
+    let cutoff = snapshot_times[MAX_SNAPSHOT_DOCUMENTS - 1];
+    let mut kept = 0usize;
+    documents.retain(|document| {
+        if document.kind != SemanticSourceKind::Snapshot {
+            return true;
+        }
+        if document.updated_at >= cutoff && kept < MAX_SNAPSHOT_DOCUMENTS {
+            kept += 1;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+fn content_hash(draft: &SemanticDocumentDraft) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(draft.url.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(draft.title.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(draft.text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_index_path() -> PathBuf {
+        std::env::temp_dir()
+            .join("dbrowser-semantic-index-tests")
+            .join(Uuid::new_v4().to_string())
+            .join(INDEX_FILE)
+    }
+
+    fn draft(kind: SemanticSourceKind, id: &str, title: &str, text: &str) -> SemanticDocumentDraft {
+        SemanticDocumentDraft {
+            kind,
+            source_id: id.to_string(),
+            url: format!("https://{id}"),
+            title: title.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn search_ranks_related_documents_first_and_persists() {
+        let path = temp_index_path();
+        let index =
+            SemanticIndex::from_path(path.clone(), Arc::new(HashingEmbeddingClient::default()))
+                .expect("index should load");
+        index
+            .upsert(vec![
+                draft(
+                    SemanticSourceKind::History,
+                    "rust-lang.org/async",
+                    "Async Rust book",
+                    "futures executors and the tokio runtime",
+                ),
+                draft(
+                    SemanticSourceKind::Bookmark,
+                    "bakery.example",
+                    "Sourdough guide",
+                    "flour water salt starter baking bread",
+                ),
+            ])
+            .await
+            .expect("upsert should succeed");
+
+        let hits = index
+            .search("tokio runtime futures", 5, &[])
+            .await
+            .expect("search should succeed");
+        assert_eq!(hits[0].source_id, "rust-lang.org/async");
+
+        let reloaded =
+            SemanticIndex::from_path(path, Arc::new(HashingEmbeddingClient::default())).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        let bookmark_hits = reloaded
+            .search("bread baking", 5, &[SemanticSourceKind::Bookmark])
+            .await
+            .unwrap();
+        assert_eq!(bookmark_hits.len(), 1);
+        assert_eq!(bookmark_hits[0].kind, SemanticSourceKind::Bookmark);
+    }
+
+    #[tokio::test]
+    async fn replace_kind_drops_missing_sources_and_skips_unchanged() {
+        let index = SemanticIndex::in_memory(Arc::new(HashingEmbeddingClient::default()));
+        let first = draft(SemanticSourceKind::History, "a.example", "A", "alpha");
+        let second = draft(SemanticSourceKind::History, "b.example", "B", "beta");
+        let snapshot = draft(SemanticSourceKind::Snapshot, "a.example", "A", "alpha page");
+        index.upsert(vec![snapshot]).await.unwrap();
+
+        let embedded = index
+            .replace_kind(
+                SemanticSourceKind::History,
+                vec![first.clone(), second.clone()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(embedded, 2);
+
+        let embedded = index
+            .replace_kind(SemanticSourceKind::History, vec![first])
+            .await
+            .unwrap();
+        assert_eq!(embedded, 0);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn browser_data_is_only_resynced_after_it_changes() {
+        let path = temp_index_path();
+        let index =
+            SemanticIndex::from_path(path.clone(), Arc::new(HashingEmbeddingClient::default()))
+                .unwrap();
+        let engine = BrowserEngine::new();
+        engine
+            .add_to_history("https://docs.rs/tokio".into(), "Tokio docs".into())
+            .unwrap();
+        index.sync_browser_data(&engine).await.unwrap();
+        assert_eq!(index.len(), 1);
+
+        // Nothing changed, so syncing neither re-reads the engine nor
+        // rewrites the index.
+        index
+            .remove(SemanticSourceKind::History, "https://docs.rs/tokio")
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        index.sync_browser_data(&engine).await.unwrap();
+        assert!(index.is_empty());
+        assert!(!path.exists());
+        index.search("tokio", 5, &[]).await.unwrap();
+        assert!(!path.exists());
+
+        engine
+            .add_bookmark(
+                "Sourdough guide".into(),
+                "https://bakery.example".into(),
+                None,
+                Vec::new(),
+            )
+            .unwrap();
+        index.sync_browser_data(&engine).await.unwrap();
+        assert_eq!(index.len(), 2);
+        assert!(path.exists());
+
+        engine.clear_history().unwrap();
+        index.sync_browser_data(&engine).await.unwrap();
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn snapshot_draft_reads_page_snapshot_payload() {
+        let draft = SemanticDocumentDraft::from_snapshot(&serde_json::json!({
+            "url": "https://example.com/docs",
+            "title": "Docs",
+            "headings": ["Getting started"],
+            "mainText": "Install the CLI and run it."
+        }))
+        .expect("snapshot should produce a draft");
+        assert_eq!(draft.kind, SemanticSourceKind::Snapshot);
+        assert!(draft.text.contains("Getting started"));
+        assert!(draft.text.contains("Install the CLI"));
+        assert!(SemanticDocumentDraft::from_snapshot(&serde_json::json!({ "url": "" })).is_none());
+    }
+}
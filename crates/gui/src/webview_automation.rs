@@ -7,8 +7,9 @@ use tauri::{AppHandle, Manager, Wry};
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 
-use crate::app_state::AppState;
+use crate::app_state::{AppState, RunTabSurface};
 use crate::commands::emit_history_updated;
+use crate::semantic_index::SemanticDocumentDraft;
 
 const AUTOMATION_TIMEOUT: Duration = Duration::from_secs(8);
 const DEFAULT_QUERY_LIMIT: usize = 20;
@@ -27,35 +28,73 @@ pub fn active_tab_id(app_handle: &AppHandle<Wry>) -> Result<Option<String>, Stri
         .map(|active| active.clone())
 }
 
+/// The tab an automation call acts on: whatever the user has focused, or a
+/// specific tab such as the one owned by an agent run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TabTarget {
+    #[default]
+    Active,
+    Tab(String),
+}
+
+impl TabTarget {
+    fn describe(&self) -> String {
+        match self {
+            TabTarget::Active => "active tab".to_string(),
+            TabTarget::Tab(tab_id) => format!("tab `{tab_id}`"),
+        }
+    }
+}
+
+/// Creates and tears down the webviews behind tabs that agent runs open for
+/// themselves. Implemented by the binary, which owns webview creation.
+pub trait TabWebviewHost: Send + Sync {
+    fn open_tab(&self, tab_id: &str, url: &str, surface: RunTabSurface) -> Result<(), String>;
+    fn close_tab(&self, tab_id: &str) -> Result<(), String>;
+    /// Releases a run-owned tab to the user as an ordinary, focused tab.
+    fn hand_over_tab(&self, tab_id: &str) -> Result<(), String>;
+}
+
 pub fn active_tab_webview(
     app_handle: &AppHandle<Wry>,
+) -> Result<tauri::webview::Webview<Wry>, String> {
+    tab_webview(app_handle, &TabTarget::Active)
+}
+
+pub fn tab_webview(
+    app_handle: &AppHandle<Wry>,
+    target: &TabTarget,
 ) -> Result<tauri::webview::Webview<Wry>, String> {
     let state = app_handle
         .try_state::<AppState>()
         .ok_or_else(|| "application state unavailable".to_string())?;
 
-    let active_tab_id = state
-        .active_content_tab
-        .lock()
-        .map_err(|_| "active tab mutex poisoned".to_string())?
-        .clone()
-        .ok_or_else(|| "active tab webview is not available".to_string())?;
+    let tab_id = match target {
+        TabTarget::Active => state
+            .active_content_tab
+            .lock()
+            .map_err(|_| "active tab mutex poisoned".to_string())?
+            .clone()
+            .ok_or_else(|| "active tab webview is not available".to_string())?,
+        TabTarget::Tab(tab_id) => tab_id.clone(),
+    };
 
     let label = state
         .content_tab_webviews
         .lock()
         .map_err(|_| "content webview map mutex poisoned".to_string())?
-        .get(&active_tab_id)
+        .get(&tab_id)
         .cloned()
-        .ok_or_else(|| "active tab webview is not registered".to_string())?;
+        .ok_or_else(|| format!("{} webview is not registered", target.describe()))?;
 
     app_handle
         .get_webview(&label)
-        .ok_or_else(|| "active tab webview is not available".to_string())
+        .ok_or_else(|| format!("{} webview is not available", target.describe()))
 }
 
-pub async fn query_active_dom(
+pub async fn query_dom(
     app_handle: &AppHandle<Wry>,
+    target: &TabTarget,
     selector: &str,
     limit: Option<usize>,
 ) -> Result<Value, String> {
@@ -108,12 +147,15 @@ pub async fn query_active_dom(
         limit = limit
     );
 
-    let result = evaluate_active_json(app_handle, &script).await?;
-    sync_active_tab_metadata(app_handle, &result)?;
+    let result = evaluate_target_json(app_handle, target, &script).await?;
+    sync_tab_metadata(app_handle, target, &result)?;
     Ok(result)
 }
 
-pub async fn snapshot_active_page(app_handle: &AppHandle<Wry>) -> Result<Value, String> {
+pub async fn snapshot_page(
+    app_handle: &AppHandle<Wry>,
+    target: &TabTarget,
+) -> Result<Value, String> {
     let script = format!(
         r#"(() => {{
             const itemLimit = {item_limit};
@@ -221,18 +263,35 @@ pub async fn snapshot_active_page(app_handle: &AppHandle<Wry>) -> Result<Value,
         text_limit = DEFAULT_MAIN_TEXT_LIMIT
     );
 
-    let result = evaluate_active_json(app_handle, &script).await?;
-    sync_active_tab_metadata(app_handle, &result)?;
+    let result = evaluate_target_json(app_handle, target, &script).await?;
+    sync_tab_metadata(app_handle, target, &result)?;
+    index_page_snapshot(app_handle, &result);
     Ok(result)
 }
 
-pub async fn execute_active_dom_action(
+fn index_page_snapshot(app_handle: &AppHandle<Wry>, payload: &Value) {
+    let Some(draft) = SemanticDocumentDraft::from_snapshot(payload) else {
+        return;
+    };
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return;
+    };
+    let index = state.semantic_index.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = index.upsert(vec![draft]).await {
+            tracing::warn!(error = %err, "failed to index page snapshot");
+        }
+    });
+}
+
+pub async fn execute_dom_action(
     app_handle: &AppHandle<Wry>,
+    target: &TabTarget,
     action: &DomAction,
 ) -> Result<DomExecutionResult, String> {
     match action {
         DomAction::Navigate { url } => {
-            let webview = active_tab_webview(app_handle)?;
+            let webview = tab_webview(app_handle, target)?;
             let target = url::Url::parse(url.trim())
                 .or_else(|_| url::Url::parse(&format!("https://{}", url.trim())))
                 .map_err(|err| format!("invalid url: {err}"))?;
@@ -277,7 +336,7 @@ pub async fn execute_active_dom_action(
             action_result(
                 "clicked",
                 selector,
-                evaluate_active_json(app_handle, &script).await?,
+                evaluate_target_json(app_handle, target, &script).await?,
             )
         }
         DomAction::Type { selector, text } => {
@@ -315,7 +374,7 @@ pub async fn execute_active_dom_action(
             action_result(
                 "typed into",
                 selector,
-                evaluate_active_json(app_handle, &script).await?,
+                evaluate_target_json(app_handle, target, &script).await?,
             )
         }
         DomAction::Scroll { dx, dy } => {
@@ -333,7 +392,7 @@ pub async fn execute_active_dom_action(
                 dx = dx,
                 dy = dy
             );
-            let result = evaluate_active_json(app_handle, &script).await?;
+            let result = evaluate_target_json(app_handle, target, &script).await?;
             ensure_action_ok(&result)?;
             Ok(DomExecutionResult::with_details(
                 format!("scrolled by dx={}, dy={}", dx, dy),
@@ -343,11 +402,12 @@ pub async fn execute_active_dom_action(
     }
 }
 
-pub async fn evaluate_active_json(
+pub async fn evaluate_target_json(
     app_handle: &AppHandle<Wry>,
+    target: &TabTarget,
     script: &str,
 ) -> Result<Value, String> {
-    let webview = active_tab_webview(app_handle)?;
+    let webview = tab_webview(app_handle, target)?;
     evaluate_json(&webview, script).await
 }
 
@@ -449,7 +509,11 @@ fn ensure_action_ok(result: &Value) -> Result<(), String> {
     }
 }
 
-fn sync_active_tab_metadata(app_handle: &AppHandle<Wry>, payload: &Value) -> Result<(), String> {
+fn sync_tab_metadata(
+    app_handle: &AppHandle<Wry>,
+    target: &TabTarget,
+    payload: &Value,
+) -> Result<(), String> {
     let title = payload
         .get("title")
         .and_then(Value::as_str)
@@ -468,13 +532,16 @@ fn sync_active_tab_metadata(app_handle: &AppHandle<Wry>, payload: &Value) -> Res
     let state = app_handle
         .try_state::<AppState>()
         .ok_or_else(|| "application state unavailable".to_string())?;
-    let active_tab_id = state
-        .active_content_tab
-        .lock()
-        .map_err(|_| "active tab mutex poisoned".to_string())?
-        .clone();
-
-    if let Some(tab_id) = active_tab_id {
+    let tab_id = match target {
+        TabTarget::Active => state
+            .active_content_tab
+            .lock()
+            .map_err(|_| "active tab mutex poisoned".to_string())?
+            .clone(),
+        TabTarget::Tab(tab_id) => Some(tab_id.clone()),
+    };
+
+    if let Some(tab_id) = tab_id {
         let (summary, keywords) = payload_summary_and_keywords(payload);
         state
             .browser_engine
@@ -499,7 +566,8 @@ fn sync_active_tab_metadata(app_handle: &AppHandle<Wry>, payload: &Value) -> Res
         }
     }
 
-    if !url.is_empty() {
+    // Only the focused tab drives the address bar; run-owned tabs must not.
+    if !url.is_empty() && *target == TabTarget::Active {
         if let Ok(mut current_url) = state.current_url.lock() {
             *current_url = url;
         }
@@ -11,6 +11,21 @@ pub enum Provider {
     AppleFoundation,
 }
 
+impl Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::AppleFoundation => "apple_foundation",
+        }
+    }
+
+    /// Model identifier requests to this provider are billed against.
+    pub fn model(&self) -> &'static str {
+        match self {
+            Provider::AppleFoundation => "system_language_model",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingPolicy {
     #[serde(default = "RoutingPolicy::default_prefer_local")]
@@ -56,10 +71,13 @@ impl LlmRouter {
         })
     }
 
-    pub fn route(&self, policy: RoutingPolicy) -> Result<Arc<dyn LanguageModelClient>> {
-        let provider = policy.force_provider.unwrap_or(Provider::AppleFoundation);
+    /// The provider `route` picks for `policy`.
+    pub fn provider_for(&self, policy: &RoutingPolicy) -> Provider {
+        policy.force_provider.unwrap_or(Provider::AppleFoundation)
+    }
 
-        match provider {
+    pub fn route(&self, policy: RoutingPolicy) -> Result<Arc<dyn LanguageModelClient>> {
+        match self.provider_for(&policy) {
             Provider::AppleFoundation => {
                 if policy.no_egress && !self.apple_client.is_available() {
                     return Err(anyhow!(RouterError::ProviderUnavailable(